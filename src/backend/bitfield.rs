@@ -0,0 +1,186 @@
+use anyhow::bail;
+
+use crate::backend::keyspace::KeyType;
+use crate::backend::notify::EventClass;
+use crate::backend::Backend;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+/// A bitfield encoding such as `i8` or `u16`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitfieldType {
+    pub signed: bool,
+    pub bits: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitfieldOverflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BitfieldOp {
+    Get(BitfieldType, u64),
+    Set(BitfieldType, u64, i64),
+    IncrBy(BitfieldType, u64, i64),
+    Overflow(BitfieldOverflow),
+}
+
+impl BitfieldType {
+    fn range(&self) -> (i128, i128) {
+        if self.signed {
+            (-(1i128 << (self.bits - 1)), (1i128 << (self.bits - 1)) - 1)
+        } else {
+            (0, (1i128 << self.bits) - 1)
+        }
+    }
+
+    // bit 0 is the most significant bit of the first byte, missing bytes read as zero.
+    fn read(&self, buf: &[u8], offset: u64) -> i64 {
+        let mut value: u64 = 0;
+        for i in 0..self.bits as u64 {
+            let pos = offset + i;
+            let byte = buf.get((pos >> 3) as usize).copied().unwrap_or(0);
+            let bit = (byte >> (7 - (pos & 7))) & 1;
+            value = (value << 1) | bit as u64;
+        }
+        if self.signed && self.bits < 64 && value & (1 << (self.bits - 1)) != 0 {
+            value |= u64::MAX << self.bits;
+        }
+        value as i64
+    }
+
+    fn write(&self, buf: &mut [u8], offset: u64, value: i64) {
+        let value = value as u64;
+        for i in 0..self.bits as u64 {
+            let pos = offset + i;
+            let mask = 1u8 << (7 - (pos & 7));
+            let byte = &mut buf[(pos >> 3) as usize];
+            if value & (1 << (self.bits as u64 - 1 - i)) != 0 {
+                *byte |= mask;
+            } else {
+                *byte &= !mask;
+            }
+        }
+    }
+
+    /// Adds `incr` to `value`, applying the overflow policy. `None` means the
+    /// operation failed under `OVERFLOW FAIL`.
+    fn add(&self, value: i128, incr: i128, overflow: BitfieldOverflow) -> Option<i64> {
+        let (min, max) = self.range();
+        let result = value + incr;
+        if (min..=max).contains(&result) {
+            return Some(result as i64);
+        }
+        match overflow {
+            BitfieldOverflow::Wrap => Some(self.wrap(result)),
+            BitfieldOverflow::Sat if result > max => Some(max as i64),
+            BitfieldOverflow::Sat => Some(min as i64),
+            BitfieldOverflow::Fail => None,
+        }
+    }
+
+    fn wrap(&self, value: i128) -> i64 {
+        let mask = (1u128 << self.bits) - 1;
+        let low = value as u128 & mask;
+        if self.signed && low & (1 << (self.bits - 1)) != 0 {
+            (low | !mask) as i64
+        } else {
+            low as i64
+        }
+    }
+}
+
+impl BitfieldOp {
+    /// Number of bits the string must hold for this op to write, if it writes at all.
+    fn write_end(&self) -> Option<u64> {
+        match self {
+            BitfieldOp::Set(ty, offset, _) | BitfieldOp::IncrBy(ty, offset, _) => {
+                Some(offset + ty.bits as u64)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Backend {
+    /// Runs the ops in order and returns one result per GET/SET/INCRBY.
+    /// The key is only created when at least one op writes.
+    pub fn bitfield(&self, key: &str, ops: &[BitfieldOp]) -> anyhow::Result<Vec<Option<i64>>> {
+        self.check_type(key, KeyType::String)?;
+        let Some(end) = ops.iter().filter_map(|op| op.write_end()).max() else {
+            let entry = self.map.get(key);
+            let buf = match entry.as_ref().map(|v| v.value()) {
+                None => &[][..],
+                Some(RespFrame::BulkString(s)) => s.as_deref().unwrap_or_default(),
+                Some(_) => {
+                    bail!("WRONGTYPE Operation against a key holding the wrong kind of value")
+                }
+            };
+            let ret = ops
+                .iter()
+                .filter_map(|op| match op {
+                    BitfieldOp::Get(ty, offset) => Some(Some(ty.read(buf, *offset))),
+                    _ => None,
+                })
+                .collect();
+            return Ok(ret);
+        };
+
+        let mut entry = self
+            .map
+            .entry(key.to_string())
+            .or_insert_with(|| RespBulkString::new(b"").into());
+        let RespFrame::BulkString(s) = entry.value_mut() else {
+            bail!("WRONGTYPE Operation against a key holding the wrong kind of value");
+        };
+        let buf = s.get_or_insert_with(Vec::new);
+        let len = end.div_ceil(8) as usize;
+        if buf.len() < len {
+            buf.resize(len, 0);
+        }
+
+        let mut overflow = BitfieldOverflow::Wrap;
+        let mut ret = Vec::with_capacity(ops.len());
+        for op in ops {
+            match *op {
+                BitfieldOp::Get(ty, offset) => ret.push(Some(ty.read(buf, offset))),
+                BitfieldOp::Set(ty, offset, value) => {
+                    let old = ty.read(buf, offset);
+                    // unsigned fields take the argument as its two's complement bit pattern
+                    let value = if ty.signed {
+                        value as i128
+                    } else {
+                        value as u64 as i128
+                    };
+                    match ty.add(value, 0, overflow) {
+                        Some(new) => {
+                            ty.write(buf, offset, new);
+                            ret.push(Some(old));
+                        }
+                        None => ret.push(None),
+                    }
+                }
+                BitfieldOp::IncrBy(ty, offset, incr) => {
+                    let old = ty.read(buf, offset);
+                    let old = if ty.signed {
+                        old as i128
+                    } else {
+                        old as u64 as i128
+                    };
+                    let new = ty.add(old, incr as i128, overflow);
+                    if let Some(new) = new {
+                        ty.write(buf, offset, new);
+                    }
+                    ret.push(new);
+                }
+                BitfieldOp::Overflow(o) => overflow = o,
+            }
+        }
+        drop(entry);
+        self.notify(EventClass::String, "setbit", key);
+        Ok(ret)
+    }
+}
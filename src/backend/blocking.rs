@@ -0,0 +1,330 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::backend::Backend;
+use crate::resp::frame::RespFrame;
+use crate::resp::simple_error::RespSimpleError;
+
+/// Tries to serve a blocked client from a key, `None` while the key has
+/// nothing for it.
+pub type Serve = Box<dyn FnMut(&Backend, &str) -> Option<RespFrame> + Send>;
+
+/// Outcome of a blocking command.
+#[derive(Debug)]
+pub enum Blocked {
+    Served(RespFrame),
+    Waiting(Waiting),
+}
+
+/// A parked client. The reply arrives on `rx` once it is served, unblocked
+/// or timed out; `None` timeout waits forever.
+#[derive(Debug)]
+pub struct Waiting {
+    pub rx: oneshot::Receiver<RespFrame>,
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BlockedClients {
+    // ids of the clients blocked on each (db, key), oldest first
+    queues: HashMap<(usize, String), VecDeque<u64>>,
+    waiters: HashMap<u64, Waiter>,
+}
+
+struct Waiter {
+    db: usize,
+    keys: Vec<String>,
+    // where what it's served goes, such as the list `BLMOVE` pushes to
+    destination: Option<String>,
+    serve: Serve,
+    timeout_reply: RespFrame,
+    tx: oneshot::Sender<RespFrame>,
+}
+
+impl fmt::Debug for Waiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Waiter").field("keys", &self.keys).finish()
+    }
+}
+
+impl Waiter {
+    /// The keys serving it from `key` writes.
+    fn writes(&self, key: &str) -> Vec<String> {
+        let mut keys = vec![key.to_string()];
+        keys.extend(self.destination.clone());
+        keys
+    }
+}
+
+impl BlockedClients {
+    fn remove(&mut self, id: u64) -> Option<Waiter> {
+        let waiter = self.waiters.remove(&id)?;
+        for key in &waiter.keys {
+            let key = (waiter.db, key.clone());
+            if let Some(queue) = self.queues.get_mut(&key) {
+                queue.retain(|&other| other != id);
+                if queue.is_empty() {
+                    self.queues.remove(&key);
+                }
+            }
+        }
+        Some(waiter)
+    }
+}
+
+impl Backend {
+    /// Serves the client from the first of `keys` that can, otherwise parks
+    /// it behind the clients already blocked on those keys. `destination`
+    /// is the key serving it also writes to, if any.
+    pub fn block(
+        &self,
+        client_id: u64,
+        keys: Vec<String>,
+        destination: Option<String>,
+        timeout: Option<Duration>,
+        timeout_reply: RespFrame,
+        mut serve: Serve,
+    ) -> Blocked {
+        // held while trying so a push can't slip in before we are queued
+        let mut blocked = self.server.blocked.lock().unwrap();
+        // keys others already wait on are about to be served to them first
+        let reply = keys
+            .iter()
+            .filter(|key| !blocked.queues.contains_key(&(self.index, key.to_string())))
+            .find_map(|key| serve(self, key));
+        if let Some(reply) = reply {
+            return Blocked::Served(reply);
+        }
+        for key in &keys {
+            blocked
+                .queues
+                .entry((self.index, key.clone()))
+                .or_default()
+                .push_back(client_id);
+        }
+        let (tx, rx) = oneshot::channel();
+        let waiter = Waiter {
+            db: self.index,
+            keys,
+            destination,
+            serve,
+            timeout_reply,
+            tx,
+        };
+        blocked.waiters.insert(client_id, waiter);
+        Blocked::Waiting(Waiting { rx, timeout })
+    }
+
+    /// Marks `key` as possibly able to serve blocked clients.
+    pub(crate) fn signal_ready(&self, key: &str) {
+        let mut ready = self.server.ready_keys.lock().unwrap();
+        if !ready.iter().any(|(db, k)| *db == self.index && k == key) {
+            ready.push_back((self.index, key.to_string()));
+        }
+    }
+
+    /// Marks every key clients of database `index` block on as possibly
+    /// able to serve them, for when the whole database changed underneath.
+    pub(crate) fn signal_db_ready(&self, index: usize) {
+        let keys = self
+            .server
+            .blocked
+            .lock()
+            .unwrap()
+            .queues
+            .keys()
+            .filter(|(db, _)| *db == index)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut ready = self.server.ready_keys.lock().unwrap();
+        for key in keys {
+            if !ready.contains(&key) {
+                ready.push_back(key);
+            }
+        }
+    }
+
+    /// Serves the clients blocked on the keys signalled since the last call,
+    /// oldest client first. Runs after every command, so writes done inside
+    /// a transaction only wake clients once the whole transaction is done.
+    pub fn serve_blocked(&self) {
+        // a transaction or script running serves them itself once done
+        let Some(_guard) = self.try_lock_shared() else {
+            return;
+        };
+        loop {
+            let Some(key) = self.server.ready_keys.lock().unwrap().pop_front() else {
+                return;
+            };
+            let backend = self
+                .select(key.0)
+                .expect("blocked clients use valid databases");
+            // everyone is tried: stream readers don't consume what they are
+            // served, so one client coming back empty doesn't stop the next
+            let queue = {
+                let blocked = self.server.blocked.lock().unwrap();
+                blocked.queues.get(&key).cloned().unwrap_or_default()
+            };
+            for id in queue {
+                let Some(writes) = self
+                    .server
+                    .blocked
+                    .lock()
+                    .unwrap()
+                    .waiters
+                    .get(&id)
+                    .map(|waiter| waiter.writes(&key.1))
+                else {
+                    continue;
+                };
+                // locked the way the command itself would have, before the
+                // blocked clients as it does too
+                let _keys = backend.lock_keys(&writes);
+                for key in &writes {
+                    backend.expire_if_due(key);
+                }
+                let mut blocked = self.server.blocked.lock().unwrap();
+                let Some(waiter) = blocked.waiters.get_mut(&id) else {
+                    continue;
+                };
+                if waiter.tx.is_closed() {
+                    blocked.remove(id);
+                    continue;
+                }
+                let Some(reply) = (waiter.serve)(&backend, &key.1) else {
+                    continue;
+                };
+                let waiter = blocked.remove(id).expect("waiter exists");
+                drop(blocked);
+                for key in &writes {
+                    backend.touch(key);
+                }
+                let _ = waiter.tx.send(reply);
+            }
+        }
+    }
+
+    /// Wakes a blocked client as if it timed out, or with an `UNBLOCKED`
+    /// error. Returns false when the client is not blocked.
+    pub fn unblock(&self, client_id: u64, error: bool) -> bool {
+        let Some(waiter) = self.server.blocked.lock().unwrap().remove(client_id) else {
+            return false;
+        };
+        let reply = if error {
+            RespSimpleError::new("UNBLOCKED client unblocked via CLIENT UNBLOCK").into()
+        } else {
+            waiter.timeout_reply
+        };
+        let _ = waiter.tx.send(reply);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::backend::list::ListEnd;
+    use crate::backend::tracking::TrackingOptions;
+    use crate::resp::array::RespArray;
+    use crate::resp::bulkstring::RespBulkString;
+
+    fn blpop(backend: &Backend, client_id: u64) -> Blocked {
+        let serve: Serve = Box::new(|backend, key| {
+            backend
+                .pop(key, ListEnd::Left, 1)
+                .and_then(|mut popped| popped.pop())
+        });
+        backend.block(
+            client_id,
+            vec!["q".to_string()],
+            None,
+            None,
+            RespBulkString::null().into(),
+            serve,
+        )
+    }
+
+    fn waiting(blocked: Blocked) -> oneshot::Receiver<RespFrame> {
+        match blocked {
+            Blocked::Waiting(waiting) => waiting.rx,
+            Blocked::Served(frame) => panic!("unexpectedly served {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn test_serve_touches_destination() {
+        let backend = Backend::default();
+        let (tx, mut pushes) = mpsc::unbounded_channel();
+        backend
+            .track(9, &tx, 3, TrackingOptions::default())
+            .unwrap();
+        backend.track_keys(9, &["dst".to_string()], None);
+        backend.watch(8, vec!["dst".to_string()]);
+
+        let serve: Serve = Box::new(|backend, key| {
+            backend
+                .lmove(key, "dst", ListEnd::Left, ListEnd::Right)
+                .unwrap()
+        });
+        let mut rx = waiting(backend.block(
+            1,
+            vec!["src".to_string()],
+            Some("dst".to_string()),
+            None,
+            RespBulkString::null().into(),
+            serve,
+        ));
+        let value: RespFrame = RespBulkString::new("a").into();
+        backend
+            .push("src", ListEnd::Right, vec![value.clone()], false)
+            .unwrap();
+        backend.serve_blocked();
+        assert_eq!(rx.try_recv(), Ok(value));
+        assert!(backend.unwatch(8));
+        let invalidated = RespArray::new(vec![RespBulkString::new("dst").into()]);
+        assert_eq!(
+            pushes.try_recv(),
+            Ok(vec![
+                RespBulkString::new("invalidate").into(),
+                invalidated.into()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_block_fifo() {
+        let backend = Backend::default();
+        let mut first = waiting(blpop(&backend, 1));
+        let mut second = waiting(blpop(&backend, 2));
+
+        let value: RespFrame = RespBulkString::new("a").into();
+        backend
+            .push("q", ListEnd::Right, vec![value.clone()], false)
+            .unwrap();
+        assert!(first.try_recv().is_err());
+        backend.serve_blocked();
+        assert_eq!(first.try_recv(), Ok(value.clone()));
+        assert!(second.try_recv().is_err());
+
+        // a newcomer queues up behind the client already waiting
+        backend
+            .push("q", ListEnd::Right, vec![value.clone()], false)
+            .unwrap();
+        let mut third = waiting(blpop(&backend, 3));
+        backend.serve_blocked();
+        assert_eq!(second.try_recv(), Ok(value));
+        assert!(third.try_recv().is_err());
+        assert!(backend.unblock(3, true));
+        assert!(third.try_recv().is_ok());
+
+        let mut fourth = waiting(blpop(&backend, 4));
+        assert!(backend.unblock(4, false));
+        assert_eq!(fourth.try_recv(), Ok(RespBulkString::null().into()));
+        assert!(!backend.unblock(4, false));
+    }
+}
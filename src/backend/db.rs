@@ -0,0 +1,168 @@
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::bail;
+use dashmap::DashMap;
+
+use crate::backend::keyspace::KeyType;
+use crate::backend::notify::EventClass;
+use crate::backend::{Backend, Db};
+
+impl Backend {
+    /// A handle on database `index`, as it is now.
+    pub fn select(&self, index: usize) -> anyhow::Result<Backend> {
+        let Some(db) = self.server.dbs.get(index) else {
+            bail!("DB index is out of range");
+        };
+        Ok(Backend {
+            server: self.server.clone(),
+            db: db.read().unwrap().clone(),
+            index,
+        })
+    }
+
+    /// The index of the database this handle is on.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn databases(&self) -> usize {
+        self.server.dbs.len()
+    }
+
+    pub(crate) fn all_dbs(&self) -> impl Iterator<Item = Backend> + '_ {
+        (0..self.databases()).map(|index| self.select(index).expect("index is in range"))
+    }
+
+    /// Swaps the contents of two databases. Clients blocked in either are
+    /// served from what they see now. Commands still holding a handle on
+    /// either would write to the wrong one, so the caller holds them off.
+    pub fn swapdb(&self, a: usize, b: usize) -> anyhow::Result<()> {
+        let len = self.databases();
+        if a >= len || b >= len {
+            bail!("DB index is out of range");
+        }
+        if a != b {
+            // always lock the lower index first
+            let (lo, hi) = (a.min(b), a.max(b));
+            let mut lo = self.server.dbs[lo].write().unwrap();
+            let mut hi = self.server.dbs[hi].write().unwrap();
+            std::mem::swap(&mut *lo, &mut *hi);
+        }
+        self.touch_db(a);
+        self.touch_db(b);
+        self.invalidate_all();
+        self.signal_db_ready(a);
+        self.signal_db_ready(b);
+        Ok(())
+    }
+
+    /// Empties the database of this handle. `lazy` frees the old keys on
+    /// another thread. Like `swapdb`, it runs with every other command held
+    /// off.
+    pub fn flushdb(&self, lazy: bool) {
+        self.flush(self.index, lazy);
+        self.invalidate_all();
+    }
+
+    pub fn flushall(&self, lazy: bool) {
+        for index in 0..self.databases() {
+            self.flush(index, lazy);
+        }
+        self.invalidate_all();
+    }
+
+    fn flush(&self, index: usize, lazy: bool) {
+        let old = std::mem::replace(
+            &mut *self.server.dbs[index].write().unwrap(),
+            Arc::new(Db::default()),
+        );
+        self.touch_db(index);
+        if lazy {
+            thread::spawn(move || drop(old));
+        }
+    }
+
+    /// Moves `key` to database `index`, TTL included, false when it doesn't
+    /// exist here or already exists there. Nothing else runs meanwhile, so
+    /// the destination can't gain the key between the check and the move.
+    pub fn move_key(&self, key: &str, index: usize) -> anyhow::Result<bool> {
+        let dest = self.select(index)?;
+        if index == self.index {
+            bail!("source and destination objects are the same");
+        }
+        let Some(ty) = self.key_type(key) else {
+            return Ok(false);
+        };
+        dest.expire_if_due(key);
+        if dest.key_type(key).is_some() {
+            return Ok(false);
+        }
+        let moved = match ty {
+            KeyType::String => move_entry(&self.map, &dest.map, key),
+            KeyType::Hash => self.hmove(key, &dest),
+            KeyType::Set => move_entry(&self.set, &dest.set, key),
+            KeyType::List => move_entry(&self.list, &dest.list, key),
+            KeyType::ZSet => move_entry(&self.zset, &dest.zset, key),
+            KeyType::Stream => move_entry(&self.stream, &dest.stream, key),
+        };
+        if moved {
+            if let Some(when) = self.clear_expire(key) {
+                dest.set_expire(key, when);
+            }
+            self.notify(EventClass::Generic, "move_from", key);
+            dest.notify(EventClass::Generic, "move_to", key);
+            self.touch(key);
+            dest.touch(key);
+            dest.signal_ready(key);
+        }
+        Ok(moved)
+    }
+}
+
+fn move_entry<V>(from: &DashMap<String, V>, to: &DashMap<String, V>, key: &str) -> bool {
+    match from.remove(key) {
+        Some((key, value)) => {
+            to.insert(key, value);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::list::ListEnd;
+    use crate::resp::bulkstring::RespBulkString;
+    use crate::resp::frame::RespFrame;
+
+    #[test]
+    fn test_databases() -> anyhow::Result<()> {
+        let mut backend = Backend::default();
+        let value: RespFrame = RespBulkString::new("v").into();
+        backend.set("a", value.clone());
+        backend.push("l", ListEnd::Right, vec![value.clone()], false)?;
+        let one = backend.select(1)?;
+        assert_eq!(one.dbsize(), 0);
+        assert!(backend.select(16).is_err());
+
+        assert!(backend.move_key("a", 1)?);
+        assert!(!backend.move_key("a", 1)?);
+        assert!(backend.move_key("l", 0).is_err());
+        assert_eq!(backend.select(1)?.get("a"), Some(value.clone()));
+        assert_eq!(backend.dbsize(), 1);
+
+        // handles see the database as it was when they were made
+        backend.swapdb(0, 1)?;
+        assert_eq!(backend.select(0)?.get("a"), Some(value.clone()));
+        assert_eq!(backend.select(1)?.dbsize(), 1);
+
+        backend.select(0)?.flushdb(false);
+        assert_eq!(backend.select(0)?.dbsize(), 0);
+        assert_eq!(backend.select(1)?.dbsize(), 1);
+        backend.flushall(true);
+        assert_eq!(backend.select(1)?.dbsize(), 0);
+        Ok(())
+    }
+}
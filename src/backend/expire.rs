@@ -0,0 +1,198 @@
+use crate::backend::hmap::ExpireCondition;
+use crate::backend::keyspace::KeyType;
+use crate::backend::notify::EventClass;
+use crate::backend::{now_ms, Backend};
+
+impl Backend {
+    /// Sets the TTL of `key` to `when` (unix time in milliseconds). Replies 0
+    /// when the key does not exist or the condition is not met, 1 otherwise.
+    /// A `when` already in the past deletes the key.
+    pub fn expire(&self, key: &str, when: u64, condition: Option<ExpireCondition>) -> i64 {
+        if self.key_type(key).is_none() {
+            return 0;
+        }
+        let current = self.expires.get(key).map(|v| *v);
+        let allowed = match (condition, current) {
+            (None, _) => true,
+            (Some(ExpireCondition::Nx), current) => current.is_none(),
+            (Some(ExpireCondition::Xx), current) => current.is_some(),
+            // no TTL counts as an infinite one
+            (Some(ExpireCondition::Gt), current) => current.is_some_and(|c| when > c),
+            (Some(ExpireCondition::Lt), current) => current.is_none_or(|c| when < c),
+        };
+        if !allowed {
+            return 0;
+        }
+        if when <= now_ms() {
+            self.clear_expire(key);
+            self.delete_key(key);
+            self.notify(EventClass::Generic, "del", key);
+        } else {
+            self.set_expire(key, when);
+            self.notify(EventClass::Generic, "expire", key);
+        }
+        1
+    }
+
+    /// The unix time in milliseconds at which `key` expires, -1 when it has
+    /// no TTL and -2 when it does not exist.
+    pub fn expiretime(&self, key: &str) -> i64 {
+        match self.expires.get(key) {
+            Some(when) => *when as i64,
+            None if self.key_type(key).is_some() => -1,
+            None => -2,
+        }
+    }
+
+    /// Removes the TTL of `key`, 1 when it had one.
+    pub fn persist(&self, key: &str) -> i64 {
+        if self.clear_expire(key).is_none() {
+            return 0;
+        }
+        self.notify(EventClass::Generic, "persist", key);
+        1
+    }
+
+    /// Lazily expires `key` if its TTL has passed. Every command goes
+    /// through here for its keys first. A TTL left behind by a command that
+    /// deleted the key is dropped too, so a key created again starts
+    /// without one.
+    pub(crate) fn expire_if_due(&self, key: &str) {
+        let Some(when) = self.expires.get(key).map(|v| *v) else {
+            return;
+        };
+        if self.key_type(key).is_none() {
+            self.clear_expire(key);
+        } else if when <= now_ms() {
+            self.clear_expire(key);
+            self.delete_key(key);
+            self.notify(EventClass::Expired, "expired", key);
+            self.touch(key);
+        }
+    }
+
+    /// Whether `key` has a TTL that has passed, for the commands listing
+    /// keys to leave it out before it is expired.
+    pub(crate) fn is_expired(&self, key: &str) -> bool {
+        self.expires.get(key).is_some_and(|when| *when <= now_ms())
+    }
+
+    pub(crate) fn set_expire(&self, key: &str, when: u64) {
+        self.clear_expire(key);
+        self.expires.insert(key.to_string(), when);
+        let mut index = self.expires_index.lock().unwrap();
+        index.insert((when, key.to_string()));
+    }
+
+    // drops the TTL of `key` from wherever it's kept, returning it
+    pub(crate) fn clear_expire(&self, key: &str) -> Option<u64> {
+        let (key, when) = self.expires.remove(key)?;
+        let mut index = self.expires_index.lock().unwrap();
+        index.remove(&(when, key));
+        Some(when)
+    }
+
+    /// Expires the keys that are due, called periodically so keys nobody
+    /// reads again still go away.
+    pub(crate) fn expire_due_keys(&self) {
+        let now = now_ms();
+        let due = {
+            let index = self.expires_index.lock().unwrap();
+            index
+                .iter()
+                .take_while(|(when, _)| *when <= now)
+                .map(|(_, key)| key.clone())
+                .collect::<Vec<_>>()
+        };
+        for key in due {
+            // waits for a command running on the key, as one would for it
+            let _keys = self.lock_keys(std::slice::from_ref(&key));
+            self.expire_if_due(&key);
+        }
+    }
+
+    // removes `key` whatever its type
+    pub(crate) fn delete_key(&self, key: &str) -> bool {
+        let Some(ty) = self.key_type(key) else {
+            return false;
+        };
+        match ty {
+            KeyType::String => self.map.remove(key).is_some(),
+            KeyType::Hash => self.hdrop(key),
+            KeyType::Set => self.set.remove(key).is_some(),
+            KeyType::List => self.list.remove(key).is_some(),
+            KeyType::ZSet => self.zset.remove(key).is_some(),
+            KeyType::Stream => self.stream.remove(key).is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::backend::pubsub::SubscriptionKind;
+    use crate::resp::bulkstring::RespBulkString;
+    use crate::resp::frame::RespFrame;
+
+    #[test]
+    fn test_expire_and_expired_events() -> anyhow::Result<()> {
+        let mut backend = Backend::default();
+        backend.set_notify_keyspace_events("Egx")?;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        backend.subscribe(1, &tx, SubscriptionKind::Pattern, vec!["*".to_string()]);
+
+        let value: RespFrame = RespBulkString::new("v").into();
+        backend.set("a", value.clone());
+        backend.set("b", value.clone());
+        let now = now_ms();
+        assert_eq!(backend.expire("missing", now + 1000, None), 0);
+        assert_eq!(
+            backend.expire("a", now + 1000, Some(ExpireCondition::Xx)),
+            0
+        );
+        assert_eq!(backend.expire("a", now + 1000, None), 1);
+        assert_eq!(backend.expire("a", now + 500, Some(ExpireCondition::Gt)), 0);
+        assert_eq!(backend.expiretime("a"), (now + 1000) as i64);
+        assert_eq!(backend.persist("a"), 1);
+        assert_eq!(backend.expiretime("a"), -1);
+        assert_eq!(backend.expiretime("missing"), -2);
+
+        // a passed TTL goes lazily, and actively for keys nobody asks for
+        backend.set_expire("a", now - 1);
+        backend.set_expire("b", now - 1);
+        assert_eq!(backend.keys("*"), Vec::<String>::new());
+        backend.expire_if_due("a");
+        assert!(backend.get("a").is_none());
+        backend.active_expire_cycle();
+        assert!(backend.get("b").is_none());
+        assert!(backend.expires_index.lock().unwrap().is_empty());
+
+        // SET drops the TTL
+        backend.set("c", value.clone());
+        backend.set_expire("c", now + 1000);
+        backend.set("c", value);
+        assert_eq!(backend.expiretime("c"), -1);
+
+        let events = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|push| (push[2].clone(), push[3].clone()))
+            .collect::<Vec<_>>();
+        let event = |channel: &str, message: &str| -> (RespFrame, RespFrame) {
+            (
+                RespBulkString::new(channel).into(),
+                RespBulkString::new(message).into(),
+            )
+        };
+        assert_eq!(
+            events,
+            [
+                event("__keyevent@0__:expire", "a"),
+                event("__keyevent@0__:persist", "a"),
+                event("__keyevent@0__:expired", "a"),
+                event("__keyevent@0__:expired", "b"),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::anyhow;
+
+use crate::backend::geohash::{self, GeoShape};
+use crate::backend::zset::{ScoreBound, ZAddFlags, ZRangeBy, ZRangeSpec};
+use crate::backend::Backend;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoUnit {
+    M,
+    Km,
+    Ft,
+    Mi,
+}
+
+/// Where `GEOSEARCH` searches from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoFrom {
+    Member(String),
+    LonLat(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoSort {
+    Asc,
+    Desc,
+}
+
+/// What `GEOSEARCH` selects. `shape` is in meters while distances are
+/// reported in `unit`. `count` is `(count, any)`: with `ANY` the search
+/// stops at the first `count` matches instead of returning the nearest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoSearch {
+    pub from: GeoFrom,
+    pub shape: GeoShape,
+    pub unit: GeoUnit,
+    pub sort: Option<GeoSort>,
+    pub count: Option<(usize, bool)>,
+}
+
+/// A member found by `GEOSEARCH`, `dist` is in the unit searched with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: String,
+    pub dist: f64,
+    pub hash: u64,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+impl GeoUnit {
+    pub fn meters(self) -> f64 {
+        match self {
+            GeoUnit::M => 1.0,
+            GeoUnit::Km => 1000.0,
+            GeoUnit::Ft => 0.3048,
+            GeoUnit::Mi => 1609.34,
+        }
+    }
+}
+
+impl Backend {
+    /// Adds `(longitude, latitude, member)` items to the sorted set at `key`,
+    /// scored by their geohash.
+    pub fn geoadd(
+        &self,
+        key: &str,
+        flags: ZAddFlags,
+        items: Vec<(f64, f64, String)>,
+    ) -> anyhow::Result<i64> {
+        let pairs = items
+            .into_iter()
+            .map(|(lon, lat, member)| (geohash::encode(lon, lat) as f64, member))
+            .collect();
+        self.zadd(key, flags, pairs)
+    }
+
+    pub fn geopos(&self, key: &str, members: &[String]) -> Vec<Option<(f64, f64)>> {
+        self.zmscore(key, members)
+            .into_iter()
+            .map(|score| score.map(|score| geohash::decode(score as u64)))
+            .collect()
+    }
+
+    /// Distance in meters between two members, `None` if either is missing.
+    pub fn geodist(&self, key: &str, member1: &str, member2: &str) -> Option<f64> {
+        let members = [member1.to_string(), member2.to_string()];
+        match self.geopos(key, &members)[..] {
+            [Some(from), Some(to)] => Some(geohash::distance(from, to)),
+            _ => None,
+        }
+    }
+
+    pub fn geohash(&self, key: &str, members: &[String]) -> Vec<Option<String>> {
+        self.geopos(key, members)
+            .into_iter()
+            .map(|pos| pos.map(|(lon, lat)| geohash::hash_string(lon, lat)))
+            .collect()
+    }
+
+    pub fn geosearch(&self, key: &str, search: &GeoSearch) -> anyhow::Result<Vec<GeoMatch>> {
+        let Some(zset) = self.zset.get(key) else {
+            return Ok(vec![]);
+        };
+        let center = match &search.from {
+            GeoFrom::LonLat(lon, lat) => (*lon, *lat),
+            GeoFrom::Member(member) => {
+                let score = zset
+                    .score(member)
+                    .ok_or_else(|| anyhow!("could not decode requested zset member"))?;
+                geohash::decode(score as u64)
+            }
+        };
+        let any = search.count.filter(|(_, any)| *any).map(|(count, _)| count);
+
+        let mut matches = vec![];
+        'cells: for (min, max) in search.shape.search_ranges(center) {
+            let spec = ZRangeSpec {
+                by: ZRangeBy::Score(
+                    ScoreBound {
+                        value: min as f64,
+                        exclusive: false,
+                    },
+                    ScoreBound {
+                        value: max as f64,
+                        exclusive: true,
+                    },
+                ),
+                rev: false,
+                limit: None,
+            };
+            for (member, score) in zset.range(&spec) {
+                let hash = score as u64;
+                let (lon, lat) = geohash::decode(hash);
+                let Some(dist) = search.shape.contains(center, (lon, lat)) else {
+                    continue;
+                };
+                matches.push(GeoMatch {
+                    member,
+                    dist: dist / search.unit.meters(),
+                    hash,
+                    lon,
+                    lat,
+                });
+                if any == Some(matches.len()) {
+                    break 'cells;
+                }
+            }
+        }
+
+        // without ANY, COUNT keeps the nearest matches
+        let sort = search
+            .sort
+            .or_else(|| search.count.filter(|(_, any)| !any).map(|_| GeoSort::Asc));
+        match sort {
+            Some(GeoSort::Asc) => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(GeoSort::Desc) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => {}
+        }
+        if let Some((count, _)) = search.count {
+            matches.truncate(count);
+        }
+        Ok(matches)
+    }
+
+    /// Stores the matches in `destination`, scored by their geohash or, with
+    /// `store_dist`, by their distance. Returns how many were stored.
+    pub fn geosearchstore(
+        &self,
+        destination: &str,
+        source: &str,
+        search: &GeoSearch,
+        store_dist: bool,
+    ) -> anyhow::Result<i64> {
+        let matches = self.geosearch(source, search)?;
+        let zset = matches
+            .into_iter()
+            .map(|m| {
+                let score = if store_dist { m.dist } else { m.hash as f64 };
+                (m.member, score)
+            })
+            .collect();
+        Ok(self.zstore(destination, zset, "geosearchstore"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sicily() -> anyhow::Result<Backend> {
+        let backend = Backend::default();
+        let items = vec![
+            (13.361389, 38.115556, "Palermo".to_string()),
+            (15.087269, 37.502669, "Catania".to_string()),
+            (12.758489, 38.788135, "edge1".to_string()),
+            (17.241510, 38.788135, "edge2".to_string()),
+        ];
+        backend.geoadd("Sicily", ZAddFlags::default(), items)?;
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_geosearch() -> anyhow::Result<()> {
+        let backend = sicily()?;
+        let dist = backend.geodist("Sicily", "Palermo", "Catania").unwrap();
+        assert!((dist - 166274.1516).abs() < 0.01);
+
+        let mut search = GeoSearch {
+            from: GeoFrom::LonLat(15.0, 37.0),
+            shape: GeoShape::Radius(200_000.0),
+            unit: GeoUnit::Km,
+            sort: Some(GeoSort::Asc),
+            count: None,
+        };
+        let found = backend.geosearch("Sicily", &search)?;
+        let members: Vec<_> = found.iter().map(|m| m.member.as_str()).collect();
+        assert_eq!(members, ["Catania", "Palermo"]);
+        assert!((found[0].dist - 56.4413).abs() < 1e-4);
+
+        search.shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 400_000.0,
+        };
+        search.sort = Some(GeoSort::Desc);
+        search.count = Some((3, false));
+        let found = backend.geosearch("Sicily", &search)?;
+        let members: Vec<_> = found.iter().map(|m| m.member.as_str()).collect();
+        assert_eq!(members, ["edge1", "edge2", "Palermo"]);
+
+        search.from = GeoFrom::Member("nope".to_string());
+        assert!(backend.geosearch("Sicily", &search).is_err());
+        assert!(backend.geosearch("nope", &search)?.is_empty());
+        Ok(())
+    }
+}
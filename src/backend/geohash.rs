@@ -0,0 +1,268 @@
+/// Bits per coordinate in a stored geohash, 52 bits in total so the hash
+/// fits exactly in the mantissa of a sorted set score.
+const STEP_MAX: u32 = 26;
+
+pub const LON_MIN: f64 = -180.0;
+pub const LON_MAX: f64 = 180.0;
+/// Web Mercator limits, the poles can't be indexed.
+pub const LAT_MIN: f64 = -85.05112878;
+pub const LAT_MAX: f64 = 85.05112878;
+
+const EARTH_RADIUS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+const BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// The area `GEOSEARCH` looks in, with sizes in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+// a geohash cell, as ((lon_min, lon_max), (lat_min, lat_max))
+type Cell = ((f64, f64), (f64, f64));
+
+/// The 52-bit geohash of a `(longitude, latitude)` pair.
+pub fn encode(lon: f64, lat: f64) -> u64 {
+    encode_in(lon, lat, (LAT_MIN, LAT_MAX), STEP_MAX)
+}
+
+/// The center of the cell a 52-bit geohash stands for.
+pub fn decode(bits: u64) -> (f64, f64) {
+    let ((lon_min, lon_max), (lat_min, lat_max)) = cell(bits, (LAT_MIN, LAT_MAX), STEP_MAX);
+    let lon = ((lon_min + lon_max) / 2.0).clamp(LON_MIN, LON_MAX);
+    let lat = ((lat_min + lat_max) / 2.0).clamp(LAT_MIN, LAT_MAX);
+    (lon, lat)
+}
+
+/// The standard 11 character geohash, which covers latitudes -90 to 90
+/// unlike the scores we store.
+pub fn hash_string(lon: f64, lat: f64) -> String {
+    let bits = encode_in(lon, lat, (-90.0, 90.0), STEP_MAX);
+    (0..11)
+        .map(|i| {
+            // 52 bits leave the last character one bit short, it is zero
+            let idx = if i == 10 {
+                0
+            } else {
+                (bits >> (52 - (i + 1) * 5)) & 0x1f
+            };
+            BASE32[idx as usize] as char
+        })
+        .collect()
+}
+
+/// Great-circle distance in meters between two `(longitude, latitude)`
+/// pairs.
+pub fn distance((lon1, lat1): (f64, f64), (lon2, lat2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2.to_radians() - lon1.to_radians()) / 2.0).sin();
+    2.0 * EARTH_RADIUS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+impl GeoShape {
+    /// Distance from `center` to `point` in meters, `None` when the point
+    /// falls outside the shape.
+    pub fn contains(&self, center: (f64, f64), point: (f64, f64)) -> Option<f64> {
+        match *self {
+            GeoShape::Radius(radius) => {
+                let dist = distance(center, point);
+                (dist <= radius).then_some(dist)
+            }
+            GeoShape::Box { width, height } => {
+                let lat_dist = EARTH_RADIUS * (point.1.to_radians() - center.1.to_radians()).abs();
+                if lat_dist > height / 2.0 {
+                    return None;
+                }
+                let lon_dist = distance((center.0, point.1), point);
+                if lon_dist > width / 2.0 {
+                    return None;
+                }
+                Some(distance(center, point))
+            }
+        }
+    }
+
+    /// Score ranges `[min, max)` of the geohash cells that together cover the
+    /// shape around `center`: the cell holding the center and its neighbours,
+    /// at the finest level where a cell is still larger than the shape.
+    pub fn search_ranges(&self, center: (f64, f64)) -> Vec<(u64, u64)> {
+        let (lon, lat) = center;
+        let (half_width, half_height, radius) = match *self {
+            GeoShape::Radius(radius) => (radius, radius, radius),
+            GeoShape::Box { width, height } => {
+                let (w, h) = (width / 2.0, height / 2.0);
+                (w, h, w.hypot(h))
+            }
+        };
+
+        // bounding box of the shape
+        let lat_delta = (half_height / EARTH_RADIUS).to_degrees();
+        let edge_lat = if lat < 0.0 {
+            lat.to_radians() - half_height / EARTH_RADIUS
+        } else {
+            lat.to_radians() + half_height / EARTH_RADIUS
+        };
+        let lon_delta = (half_width / EARTH_RADIUS / edge_lat.cos()).to_degrees();
+        let (min_lon, max_lon) = (lon - lon_delta, lon + lon_delta);
+        let (min_lat, max_lat) = (lat - lat_delta, lat + lat_delta);
+
+        let mut step = estimate_step(radius, lat);
+        let mut bits = encode_in(lon, lat, (LAT_MIN, LAT_MAX), step);
+        let mut area = cell(bits, (LAT_MIN, LAT_MAX), step);
+        // the neighbours may still not reach the edges of the shape
+        let (lon_size, lat_size) = (area.0 .1 - area.0 .0, area.1 .1 - area.1 .0);
+        if step > 1
+            && (area.1 .1 + lat_size < max_lat
+                || area.1 .0 - lat_size > min_lat
+                || area.0 .1 + lon_size < max_lon
+                || area.0 .0 - lon_size > min_lon)
+        {
+            step -= 1;
+            bits = encode_in(lon, lat, (LAT_MIN, LAT_MAX), step);
+            area = cell(bits, (LAT_MIN, LAT_MAX), step);
+        }
+
+        let (lat_idx, lon_idx) = deinterleave(bits);
+        let cells = 1i64 << step;
+        let mut ranges = vec![];
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                // skip the neighbours the shape doesn't reach into
+                if step >= 2
+                    && ((dy < 0 && area.1 .0 < min_lat)
+                        || (dy > 0 && area.1 .1 > max_lat)
+                        || (dx < 0 && area.0 .0 < min_lon)
+                        || (dx > 0 && area.0 .1 > max_lon))
+                {
+                    continue;
+                }
+                let y = lat_idx as i64 + dy;
+                if !(0..cells).contains(&y) {
+                    continue;
+                }
+                let x = (lon_idx as i64 + dx).rem_euclid(cells);
+                let neighbour = interleave(y as u32, x as u32);
+                let shift = 2 * (STEP_MAX - step);
+                ranges.push((neighbour << shift, (neighbour + 1) << shift));
+            }
+        }
+        ranges.sort_unstable();
+        ranges.dedup();
+        ranges
+    }
+}
+
+// the coarsest level whose cells are still larger than `radius`
+fn estimate_step(radius: f64, lat: f64) -> u32 {
+    if radius == 0.0 {
+        return STEP_MAX;
+    }
+    let mut step: i32 = 1;
+    let mut range = radius;
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    // cells shrink towards the poles
+    step -= 2;
+    if !(-66.0..=66.0).contains(&lat) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&lat) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, STEP_MAX as i32) as u32
+}
+
+fn encode_in(lon: f64, lat: f64, (lat_min, lat_max): (f64, f64), step: u32) -> u64 {
+    let cells = (1u64 << step) as f64;
+    let offset = |value: f64, min: f64, max: f64| {
+        (((value - min) / (max - min)) * cells).clamp(0.0, cells - 1.0) as u32
+    };
+    interleave(offset(lat, lat_min, lat_max), offset(lon, LON_MIN, LON_MAX))
+}
+
+fn cell(bits: u64, (lat_min, lat_max): (f64, f64), step: u32) -> Cell {
+    let (lat_idx, lon_idx) = deinterleave(bits);
+    let cells = (1u64 << step) as f64;
+    let bounds = |idx: u32, min: f64, max: f64| {
+        let size = (max - min) / cells;
+        (min + idx as f64 * size, min + (idx as f64 + 1.0) * size)
+    };
+    (
+        bounds(lon_idx, LON_MIN, LON_MAX),
+        bounds(lat_idx, lat_min, lat_max),
+    )
+}
+
+// latitude bits go to the even positions, longitude bits to the odd ones
+fn interleave(lat: u32, lon: u32) -> u64 {
+    spread(lat) | (spread(lon) << 1)
+}
+
+fn deinterleave(bits: u64) -> (u32, u32) {
+    (squash(bits), squash(bits >> 1))
+}
+
+fn spread(x: u32) -> u64 {
+    let mut x = x as u64;
+    x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF;
+    x = (x | (x << 8)) & 0x00FF_00FF_00FF_00FF;
+    x = (x | (x << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    (x | (x << 1)) & 0x5555_5555_5555_5555
+}
+
+fn squash(x: u64) -> u32 {
+    let mut x = x & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x >> 4)) & 0x00FF_00FF_00FF_00FF;
+    x = (x | (x >> 8)) & 0x0000_FFFF_0000_FFFF;
+    ((x | (x >> 16)) & 0x0000_0000_FFFF_FFFF) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALERMO: (f64, f64) = (13.361389, 38.115556);
+    const CATANIA: (f64, f64) = (15.087269, 37.502669);
+
+    #[test]
+    fn test_geohash_round_trip() {
+        let bits = encode(PALERMO.0, PALERMO.1);
+        // the score Redis stores for Palermo
+        assert_eq!(bits, 3479099956230698);
+        let (lon, lat) = decode(bits);
+        assert!((lon - PALERMO.0).abs() < 1e-5 && (lat - PALERMO.1).abs() < 1e-5);
+        assert_eq!(hash_string(lon, lat), "sqc8b49rny0");
+        assert_eq!(deinterleave(interleave(0x3ff_ffff, 7)), (0x3ff_ffff, 7));
+    }
+
+    #[test]
+    fn test_geo_distance_and_shapes() {
+        let dist = distance(PALERMO, CATANIA);
+        assert!((dist - 166274.0).abs() < 1.0);
+        assert!(GeoShape::Radius(200_000.0)
+            .contains(PALERMO, CATANIA)
+            .is_some());
+        assert!(GeoShape::Radius(100_000.0)
+            .contains(PALERMO, CATANIA)
+            .is_none());
+        let shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 200_000.0,
+        };
+        assert!(shape.contains(PALERMO, CATANIA).is_some());
+
+        // the ranges found for a radius cover the points within it
+        let catania = encode(CATANIA.0, CATANIA.1);
+        let ranges = GeoShape::Radius(200_000.0).search_ranges(PALERMO);
+        assert!(ranges
+            .iter()
+            .any(|&(min, max)| (min..max).contains(&catania)));
+    }
+}
@@ -0,0 +1,108 @@
+/// Matches `string` against a Redis glob: `*`, `?`, `[abc]`, `[^a-z]` and
+/// `\` escapes. Follows `stringmatchlen`, so malformed patterns such as an
+/// unterminated `[` behave as they do there.
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    while p < pattern.len() && s < string.len() {
+        match pattern[p] {
+            b'*' => {
+                while pattern.get(p + 1) == Some(&b'*') {
+                    p += 1;
+                }
+                if p + 1 == pattern.len() {
+                    return true;
+                }
+                return (s..string.len()).any(|i| glob_match(&pattern[p + 1..], &string[i..]));
+            }
+            b'?' => s += 1,
+            b'[' => {
+                p += 1;
+                let not = pattern.get(p) == Some(&b'^');
+                if not {
+                    p += 1;
+                }
+                let c = string[s];
+                let mut matched = false;
+                loop {
+                    match pattern.get(p) {
+                        // an unterminated class ends with the pattern
+                        None => {
+                            p -= 1;
+                            break;
+                        }
+                        Some(b'\\') if p + 1 < pattern.len() => {
+                            p += 1;
+                            matched |= pattern[p] == c;
+                        }
+                        Some(b']') => break,
+                        Some(&start) if p + 2 < pattern.len() && pattern[p + 1] == b'-' => {
+                            let end = pattern[p + 2];
+                            let (lo, hi) = if start <= end {
+                                (start, end)
+                            } else {
+                                (end, start)
+                            };
+                            matched |= (lo..=hi).contains(&c);
+                            p += 2;
+                        }
+                        Some(&ch) => matched |= ch == c,
+                    }
+                    p += 1;
+                }
+                if matched == not {
+                    return false;
+                }
+                s += 1;
+            }
+            b'\\' if p + 1 < pattern.len() => {
+                p += 1;
+                if pattern[p] != string[s] {
+                    return false;
+                }
+                s += 1;
+            }
+            ch => {
+                if ch != string[s] {
+                    return false;
+                }
+                s += 1;
+            }
+        }
+        p += 1;
+        if s == string.len() {
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+        }
+    }
+    p == pattern.len() && s == string.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let matches =
+            |pattern: &str, string: &str| glob_match(pattern.as_bytes(), string.as_bytes());
+        // like `stringmatchlen`, `*` needs at least one character
+        assert!(!matches("*", ""));
+        assert!(matches("h?llo", "hello"));
+        assert!(!matches("h?llo", "hllo"));
+        assert!(matches("h*llo", "heeeello"));
+        assert!(matches("h*", "h"));
+        assert!(matches("h[ae]llo", "hallo"));
+        assert!(!matches("h[^e]llo", "hello"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(matches("h[a-b]llo", "hbllo"));
+        assert!(matches("h[b-a]llo", "hallo"));
+        assert!(matches("h\\*llo", "h*llo"));
+        assert!(!matches("h\\*llo", "hello"));
+        assert!(matches("[\\]]", "]"));
+        assert!(matches("a[bc", "ac"));
+        assert!(matches("a\\", "a\\"));
+        assert!(!matches("a*b", "acbd"));
+        assert!(matches("*.*.*", "a.b.c"));
+    }
+}
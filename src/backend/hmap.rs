@@ -0,0 +1,756 @@
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use dashmap::DashMap;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::backend::keyspace::{key_matches, KeyType, ScanTarget};
+use crate::backend::notify::EventClass;
+use crate::backend::{frame_bytes, now_ms, Backend};
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+/// A hash stored in `Db::hmap`. Fields with a TTL also have an entry
+/// in `expires` holding the unix time in milliseconds at which they expire,
+/// and in `deadlines`, which orders them by it.
+#[derive(Debug, Default)]
+pub struct HashValue {
+    fields: DashMap<String, RespFrame>,
+    expires: DashMap<String, u64>,
+    deadlines: Mutex<BTreeSet<(u64, String)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpireCondition {
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+}
+
+/// What to do with the TTL of the fields touched by HGETEX/HSETEX.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldExpiry {
+    Keep,
+    Persist,
+    At(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldCondition {
+    Fnx,
+    Fxx,
+}
+
+impl HashValue {
+    // the fields whose TTL has passed
+    fn due(&self, now: u64) -> Vec<String> {
+        let deadlines = self.deadlines.lock().unwrap();
+        deadlines
+            .iter()
+            .take_while(|(when, _)| *when <= now)
+            .map(|(_, field)| field.clone())
+            .collect()
+    }
+
+    fn has_expired(&self, now: u64) -> bool {
+        let deadlines = self.deadlines.lock().unwrap();
+        deadlines.first().is_some_and(|(when, _)| *when <= now)
+    }
+}
+
+impl Backend {
+    /// Lazily expires the fields of `key` whose TTL has passed, deleting the
+    /// hash when nothing is left. Every hash command goes through here first.
+    fn hexpire_fields(&self, key: &str) {
+        let now = now_ms();
+        let due = match self.hmap.get(key) {
+            Some(inner) => inner.has_expired(now),
+            None => false,
+        };
+        if due {
+            if let Some(inner) = self.hmap.get(key) {
+                for field in inner.due(now) {
+                    self.remove_field(&inner, key, &field);
+                }
+            }
+            self.notify(EventClass::Hash, "hexpired", key);
+            self.drop_empty_hash(key);
+            self.touch(key);
+        }
+    }
+
+    // drops the hash once its last field is gone, deleting the key
+    fn drop_empty_hash(&self, key: &str) {
+        if self
+            .hmap
+            .remove_if(key, |_, v| v.fields.is_empty())
+            .is_some()
+        {
+            self.notify(EventClass::Generic, "del", key);
+        }
+    }
+
+    /// Expires the keys and hash fields that are due in every database,
+    /// called periodically so those nobody reads again still go away.
+    pub fn active_expire_cycle(&self) {
+        // skipped while a transaction or script runs, rather than holding up
+        // a runtime thread until it's done
+        let Some(_guard) = self.try_lock_shared() else {
+            return;
+        };
+        for db in self.all_dbs() {
+            db.expire_due_keys();
+            db.expire_due_fields();
+        }
+    }
+
+    fn expire_due_fields(&self) {
+        let now = now_ms();
+        let due = {
+            let mut index = self.hmap_expires.lock().unwrap();
+            let mut due = Vec::new();
+            while let Some(first) = index.first() {
+                if first.0 > now {
+                    break;
+                }
+                due.push(index.pop_first().expect("index is not empty"));
+            }
+            due
+        };
+        for (when, key, field) in due {
+            let _keys = self.lock_keys(std::slice::from_ref(&key));
+            // the index may be stale if the TTL was changed or removed since
+            let expired = match self.hmap.get(&key) {
+                Some(inner) if inner.expires.get(&field).map(|v| *v) == Some(when) => {
+                    self.remove_field(&inner, &key, &field);
+                    true
+                }
+                _ => false,
+            };
+            if expired {
+                self.notify(EventClass::Hash, "hexpired", &key);
+                self.drop_empty_hash(&key);
+                self.touch(&key);
+            }
+        }
+    }
+
+    /// Moves the hash at `key` to `dest`, TTLs included. False when there is
+    /// no such hash.
+    pub(crate) fn hmove(&self, key: &str, dest: &Backend) -> bool {
+        self.hexpire_fields(key);
+        let Some((key, inner)) = self.hmap.remove(key) else {
+            return false;
+        };
+        {
+            let mut index = self.hmap_expires.lock().unwrap();
+            let mut dest_index = dest.hmap_expires.lock().unwrap();
+            for v in inner.expires.iter() {
+                let entry = (*v.value(), key.clone(), v.key().clone());
+                index.remove(&entry);
+                dest_index.insert(entry);
+            }
+        }
+        dest.hmap.insert(key, inner);
+        true
+    }
+
+    /// Deletes the hash at `key` along with the TTLs of its fields. False
+    /// when there is no such hash.
+    pub(crate) fn hdrop(&self, key: &str) -> bool {
+        let Some((key, inner)) = self.hmap.remove(key) else {
+            return false;
+        };
+        let mut index = self.hmap_expires.lock().unwrap();
+        for v in inner.expires.iter() {
+            index.remove(&(*v.value(), key.clone(), v.key().clone()));
+        }
+        true
+    }
+
+    fn set_field_ttl(&self, inner: &HashValue, key: &str, field: &str, when: u64) {
+        self.clear_field_ttl(inner, key, field);
+        inner.expires.insert(field.to_string(), when);
+        inner
+            .deadlines
+            .lock()
+            .unwrap()
+            .insert((when, field.to_string()));
+        let mut index = self.hmap_expires.lock().unwrap();
+        index.insert((when, key.to_string(), field.to_string()));
+    }
+
+    // drops the TTL of `field` from wherever it's kept, returning whether
+    // it had one
+    fn clear_field_ttl(&self, inner: &HashValue, key: &str, field: &str) -> bool {
+        let Some((_, when)) = inner.expires.remove(field) else {
+            return false;
+        };
+        inner
+            .deadlines
+            .lock()
+            .unwrap()
+            .remove(&(when, field.to_string()));
+        let mut index = self.hmap_expires.lock().unwrap();
+        index.remove(&(when, key.to_string(), field.to_string()));
+        true
+    }
+
+    fn remove_field(&self, inner: &HashValue, key: &str, field: &str) -> Option<RespFrame> {
+        self.clear_field_ttl(inner, key, field);
+        inner.fields.remove(field).map(|(_, v)| v)
+    }
+
+    pub fn hget(&self, key: &str, filed: &str) -> Option<RespFrame> {
+        self.hexpire_fields(key);
+        self.hmap
+            .get(key)
+            .and_then(|v| v.fields.get(filed).map(|v| v.value().clone()))
+    }
+
+    /// Sets every field/value pair, returning the number of newly created fields.
+    /// Overwritten fields lose their TTL.
+    pub fn hset(&self, key: &str, fields: Vec<(String, RespFrame)>) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::Hash)?;
+        self.hexpire_fields(key);
+        let added = {
+            let inner = self.hmap.entry(key.to_string()).or_default();
+            fields
+                .into_iter()
+                .filter(|(field, val)| {
+                    self.clear_field_ttl(&inner, key, field);
+                    inner.fields.insert(field.clone(), val.clone()).is_none()
+                })
+                .count()
+        };
+        self.notify(EventClass::Hash, "hset", key);
+        Ok(added as i64)
+    }
+
+    pub fn hsetnx(&self, key: &str, field: &str, val: RespFrame) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::Hash)?;
+        self.hexpire_fields(key);
+        let mut ret = 0;
+        self.hmap
+            .entry(key.to_string())
+            .or_default()
+            .fields
+            .entry(field.to_string())
+            .or_insert_with(|| {
+                ret = 1;
+                val
+            });
+        if ret == 1 {
+            self.notify(EventClass::Hash, "hset", key);
+        }
+        Ok(ret)
+    }
+
+    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
+        self.hexpire_fields(key);
+        self.hmap.get(key).map(|v| v.fields.clone())
+    }
+
+    pub fn hmget(&self, key: &str, fileds: &[String]) -> Vec<RespFrame> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![RespBulkString::null().into(); fileds.len()];
+        };
+
+        let mut vec = Vec::with_capacity(fileds.len());
+        for field in fileds.iter() {
+            match inner.fields.get(field).map(|v| v.value().clone()) {
+                Some(frame) => vec.push(frame),
+                None => vec.push(RespBulkString::null().into()),
+            }
+        }
+        vec
+    }
+
+    /// Removes the fields and drops the hash once its last field is gone.
+    pub fn hdel(&self, key: &str, fields: &[String]) -> i64 {
+        self.hexpire_fields(key);
+        let removed = match self.hmap.get(key) {
+            Some(inner) => fields
+                .iter()
+                .filter(|f| self.remove_field(&inner, key, f).is_some())
+                .count(),
+            None => return 0,
+        };
+        if removed > 0 {
+            self.notify(EventClass::Hash, "hdel", key);
+        }
+        self.drop_empty_hash(key);
+        removed as i64
+    }
+
+    pub fn hexists(&self, key: &str, field: &str) -> i64 {
+        self.hexpire_fields(key);
+        match self.hmap.get(key) {
+            Some(inner) if inner.fields.contains_key(field) => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn hlen(&self, key: &str) -> i64 {
+        self.hexpire_fields(key);
+        self.hmap
+            .get(key)
+            .map(|v| v.fields.len() as i64)
+            .unwrap_or(0)
+    }
+
+    pub fn hkeys(&self, key: &str) -> Vec<RespFrame> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![];
+        };
+        inner
+            .fields
+            .iter()
+            .map(|v| RespBulkString::new(v.key()).into())
+            .collect()
+    }
+
+    /// One page of `HSCAN`, see [`scan_page`]. Expired fields are skipped.
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> (u64, Vec<(String, RespFrame)>) {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return (0, vec![]);
+        };
+        let target = ScanTarget::Hash(key.to_string());
+        let (cursor, page) = self.scan_names(target, cursor, count, || {
+            inner
+                .fields
+                .iter()
+                .map(|v| v.key().as_bytes().to_vec())
+                .collect()
+        });
+        let page = page
+            .into_iter()
+            .filter(|field| pattern.is_none_or(|p| key_matches(p, field)))
+            .filter_map(|field| {
+                let field = String::from_utf8(field).ok()?;
+                let value = inner.fields.get(&field)?.value().clone();
+                Some((field, value))
+            })
+            .collect();
+        (cursor, page)
+    }
+
+    pub fn hvals(&self, key: &str) -> Vec<RespFrame> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![];
+        };
+        inner.fields.iter().map(|v| v.value().clone()).collect()
+    }
+
+    pub fn hstrlen(&self, key: &str, field: &str) -> i64 {
+        self.hget(key, field)
+            .map(|v| frame_bytes(&v).map(|b| b.len()).unwrap_or(0) as i64)
+            .unwrap_or(0)
+    }
+
+    pub fn hincrby(&self, key: &str, field: &str, incr: i64) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::Hash)?;
+        self.hexpire_fields(key);
+        let inner = self.hmap.entry(key.to_string()).or_default();
+        let mut entry = inner
+            .fields
+            .entry(field.to_string())
+            .or_insert_with(|| RespBulkString::new("0").into());
+        let Some(value) = frame_bytes(entry.value())
+            .and_then(|b| std::str::from_utf8(b).ok())
+            .and_then(|s| s.parse::<i64>().ok())
+        else {
+            bail!("hash value is not an integer");
+        };
+        let Some(value) = value.checked_add(incr) else {
+            bail!("increment or decrement would overflow");
+        };
+        *entry.value_mut() = RespBulkString::new(value.to_string()).into();
+        drop(entry);
+        drop(inner);
+        self.notify(EventClass::Hash, "hincrby", key);
+        Ok(value)
+    }
+
+    pub fn hincrbyfloat(&self, key: &str, field: &str, incr: f64) -> anyhow::Result<String> {
+        self.check_type(key, KeyType::Hash)?;
+        self.hexpire_fields(key);
+        let current = self
+            .hmap
+            .get(key)
+            .and_then(|inner| inner.fields.get(field).map(|v| v.value().clone()));
+        let value = match current {
+            Some(frame) => match frame_bytes(&frame)
+                .and_then(|b| std::str::from_utf8(b).ok())
+                .and_then(|s| s.parse::<f64>().ok())
+            {
+                Some(value) => value,
+                None => bail!("hash value is not a float"),
+            },
+            None => 0.0,
+        };
+        // checked before anything is stored, so a failed increment leaves
+        // no field or hash behind
+        let value = value + incr;
+        if !value.is_finite() {
+            bail!("increment would produce NaN or Infinity");
+        }
+        let value = value.to_string();
+        self.hmap
+            .entry(key.to_string())
+            .or_default()
+            .fields
+            .insert(field.to_string(), RespBulkString::new(&value).into());
+        self.notify(EventClass::Hash, "hincrbyfloat", key);
+        Ok(value)
+    }
+
+    /// Without a count returns at most one field. A positive count returns
+    /// distinct fields, a negative one may repeat fields and returns exactly
+    /// `-count` of them.
+    pub fn hrandfield(&self, key: &str, count: Option<i64>) -> Vec<(String, RespFrame)> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![];
+        };
+        let pair = |v: dashmap::mapref::multiple::RefMulti<String, RespFrame>| {
+            (v.key().clone(), v.value().clone())
+        };
+        let mut rng = rand::thread_rng();
+        match count {
+            None => inner
+                .fields
+                .iter()
+                .choose(&mut rng)
+                .map(pair)
+                .into_iter()
+                .collect(),
+            Some(count) if count >= 0 => inner
+                .fields
+                .iter()
+                .choose_multiple(&mut rng, count as usize)
+                .into_iter()
+                .map(pair)
+                .collect(),
+            Some(count) => {
+                let all = inner.fields.iter().map(pair).collect::<Vec<_>>();
+                (0..count.unsigned_abs())
+                    .map(|_| all[rng.gen_range(0..all.len())].clone())
+                    .collect()
+            }
+        }
+    }
+
+    /// Sets the expiration of each field to `when` (unix time in milliseconds).
+    /// Per field replies -2 when it does not exist, 0 when the condition is not
+    /// met, 1 when the TTL was set and 2 when `when` is already in the past and
+    /// the field got deleted.
+    pub fn hexpire(
+        &self,
+        key: &str,
+        when: u64,
+        condition: Option<ExpireCondition>,
+        fields: &[String],
+    ) -> Vec<i64> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![-2; fields.len()];
+        };
+        let now = now_ms();
+        let ret = fields
+            .iter()
+            .map(|field| {
+                if !inner.fields.contains_key(field) {
+                    return -2;
+                }
+                let current = inner.expires.get(field).map(|v| *v);
+                let allowed = match (condition, current) {
+                    (None, _) => true,
+                    (Some(ExpireCondition::Nx), current) => current.is_none(),
+                    (Some(ExpireCondition::Xx), current) => current.is_some(),
+                    // no TTL counts as an infinite one
+                    (Some(ExpireCondition::Gt), current) => current.is_some_and(|c| when > c),
+                    (Some(ExpireCondition::Lt), current) => current.is_none_or(|c| when < c),
+                };
+                if !allowed {
+                    0
+                } else if when <= now {
+                    self.remove_field(&inner, key, field);
+                    2
+                } else {
+                    self.set_field_ttl(&inner, key, field, when);
+                    1
+                }
+            })
+            .collect::<Vec<_>>();
+        drop(inner);
+        if ret.contains(&1) {
+            self.notify(EventClass::Hash, "hexpire", key);
+        }
+        if ret.contains(&2) {
+            self.notify(EventClass::Hash, "hexpired", key);
+        }
+        self.drop_empty_hash(key);
+        ret
+    }
+
+    /// The unix time in milliseconds at which each field expires, -1 when it
+    /// has no TTL and -2 when it does not exist.
+    pub fn hexpiretime(&self, key: &str, fields: &[String]) -> Vec<i64> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![-2; fields.len()];
+        };
+        fields
+            .iter()
+            .map(|field| match inner.expires.get(field) {
+                Some(when) => *when as i64,
+                None if inner.fields.contains_key(field) => -1,
+                None => -2,
+            })
+            .collect()
+    }
+
+    /// Per field replies -2 when it does not exist, -1 when it has no TTL and
+    /// 1 when the TTL was removed.
+    pub fn hpersist(&self, key: &str, fields: &[String]) -> Vec<i64> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![-2; fields.len()];
+        };
+        let ret = fields
+            .iter()
+            .map(|field| {
+                if self.clear_field_ttl(&inner, key, field) {
+                    1
+                } else if inner.fields.contains_key(field) {
+                    -1
+                } else {
+                    -2
+                }
+            })
+            .collect::<Vec<_>>();
+        drop(inner);
+        if ret.contains(&1) {
+            self.notify(EventClass::Hash, "hpersist", key);
+        }
+        ret
+    }
+
+    /// Returns the values of the fields and then applies `expiry` to the
+    /// existing ones.
+    pub fn hgetex(&self, key: &str, expiry: FieldExpiry, fields: &[String]) -> Vec<RespFrame> {
+        self.hexpire_fields(key);
+        let Some(inner) = self.hmap.get(key) else {
+            return vec![RespBulkString::null().into(); fields.len()];
+        };
+        let now = now_ms();
+        let mut event = None;
+        let ret = fields
+            .iter()
+            .map(|field| {
+                let Some(value) = inner.fields.get(field).map(|v| v.value().clone()) else {
+                    return RespBulkString::null().into();
+                };
+                match expiry {
+                    FieldExpiry::Keep => {}
+                    FieldExpiry::Persist => {
+                        if self.clear_field_ttl(&inner, key, field) {
+                            event = Some("hpersist");
+                        }
+                    }
+                    FieldExpiry::At(when) if when <= now => {
+                        self.remove_field(&inner, key, field);
+                        event = Some("hexpired");
+                    }
+                    FieldExpiry::At(when) => {
+                        self.set_field_ttl(&inner, key, field, when);
+                        event = Some("hexpire");
+                    }
+                }
+                value
+            })
+            .collect();
+        drop(inner);
+        if let Some(event) = event {
+            self.notify(EventClass::Hash, event, key);
+        }
+        self.drop_empty_hash(key);
+        ret
+    }
+
+    /// Sets the fields only if `condition` holds for all of them, returning
+    /// whether anything was set. `FieldExpiry::Persist` discards old TTLs the
+    /// way HSET does, `FieldExpiry::Keep` retains them.
+    pub fn hsetex(
+        &self,
+        key: &str,
+        condition: Option<FieldCondition>,
+        expiry: FieldExpiry,
+        fields: Vec<(String, RespFrame)>,
+    ) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::Hash)?;
+        self.hexpire_fields(key);
+        let inner = self.hmap.entry(key.to_string()).or_default();
+        let allowed = match condition {
+            None => true,
+            Some(FieldCondition::Fnx) => fields.iter().all(|(f, _)| !inner.fields.contains_key(f)),
+            Some(FieldCondition::Fxx) => fields.iter().all(|(f, _)| inner.fields.contains_key(f)),
+        };
+        if !allowed {
+            drop(inner);
+            // nothing was set, so this only drops a hash made just now
+            self.hmap.remove_if(key, |_, v| v.fields.is_empty());
+            return Ok(0);
+        }
+        let now = now_ms();
+        let expired = matches!(expiry, FieldExpiry::At(when) if when <= now);
+        for (field, value) in fields {
+            match expiry {
+                FieldExpiry::At(_) if expired => {
+                    self.remove_field(&inner, key, &field);
+                    continue;
+                }
+                FieldExpiry::At(when) => self.set_field_ttl(&inner, key, &field, when),
+                FieldExpiry::Persist => {
+                    self.clear_field_ttl(&inner, key, &field);
+                }
+                FieldExpiry::Keep => {}
+            }
+            inner.fields.insert(field, value);
+        }
+        drop(inner);
+        self.notify(EventClass::Hash, "hset", key);
+        match expiry {
+            FieldExpiry::At(_) if expired => self.notify(EventClass::Hash, "hexpired", key),
+            FieldExpiry::At(_) => self.notify(EventClass::Hash, "hexpire", key),
+            _ => {}
+        }
+        self.drop_empty_hash(key);
+        Ok(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn hset(backend: &Backend, key: &str, names: &[&str]) {
+        let pairs = names
+            .iter()
+            .map(|f| (f.to_string(), RespBulkString::new(f).into()))
+            .collect();
+        backend.hset(key, pairs).unwrap();
+    }
+
+    #[test]
+    fn test_hexpire_conditions() {
+        let backend = Backend::default();
+        hset(&backend, "k", &["f1", "f2"]);
+        let later = now_ms() + 100_000;
+
+        let ret = backend.hexpire("k", later, None, &fields(&["f1", "nope"]));
+        assert_eq!(ret, vec![1, -2]);
+        let ret = backend.hexpire(
+            "k",
+            later,
+            Some(ExpireCondition::Nx),
+            &fields(&["f1", "f2"]),
+        );
+        assert_eq!(ret, vec![0, 1]);
+        let ret = backend.hexpire("k", later - 1, Some(ExpireCondition::Gt), &fields(&["f1"]));
+        assert_eq!(ret, vec![0]);
+        let ret = backend.hexpire("k", later - 1, Some(ExpireCondition::Lt), &fields(&["f1"]));
+        assert_eq!(ret, vec![1]);
+        assert_eq!(
+            backend.hexpiretime("k", &fields(&["f1", "f2", "nope"])),
+            vec![later as i64 - 1, later as i64, -2]
+        );
+
+        assert_eq!(backend.hpersist("k", &fields(&["f1", "f1"])), vec![1, -1]);
+        hset(&backend, "k", &["f2"]);
+        assert_eq!(backend.hexpiretime("k", &fields(&["f2"])), vec![-1]);
+        // neither leaves its TTL behind in the index
+        assert!(backend.hmap_expires.lock().unwrap().is_empty());
+        backend.hexpire("k", later, None, &fields(&["f1"]));
+        backend.hdel("k", &fields(&["f1"]));
+        assert!(backend.hmap_expires.lock().unwrap().is_empty());
+        assert_eq!(
+            backend.hexpire("nope", later, None, &fields(&["f"])),
+            vec![-2]
+        );
+    }
+
+    #[test]
+    fn test_hexpire_deletes_fields() {
+        let backend = Backend::default();
+        hset(&backend, "k", &["f1", "f2"]);
+        assert_eq!(backend.hexpire("k", 0, None, &fields(&["f1"])), vec![2]);
+        assert_eq!(backend.hlen("k"), 1);
+
+        // lazily expired on access
+        let ret = backend.hexpire("k", now_ms() + 1, None, &fields(&["f2"]));
+        assert_eq!(ret, vec![1]);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(backend.hget("k", "f2"), None);
+        assert!(backend.hmap.get("k").is_none());
+
+        // actively expired without being touched
+        hset(&backend, "k", &["f1"]);
+        backend.hexpire("k", now_ms() + 1, None, &fields(&["f1"]));
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        backend.active_expire_cycle();
+        assert!(backend.hmap.get("k").is_none());
+        assert!(backend.hmap_expires.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hgetex_hsetex() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let later = now_ms() + 100_000;
+        let pairs = vec![("f1".to_string(), RespBulkString::new("v1").into())];
+        let ret = backend.hsetex("k", Some(FieldCondition::Fxx), FieldExpiry::Keep, pairs)?;
+        assert_eq!(ret, 0);
+        assert!(backend.hmap.get("k").is_none());
+
+        let pairs = vec![("f1".to_string(), RespBulkString::new("v1").into())];
+        let ret = backend.hsetex("k", None, FieldExpiry::At(later), pairs)?;
+        assert_eq!(ret, 1);
+        let pairs = vec![("f1".to_string(), RespBulkString::new("v2").into())];
+        backend.hsetex("k", Some(FieldCondition::Fxx), FieldExpiry::Keep, pairs)?;
+        assert_eq!(
+            backend.hexpiretime("k", &fields(&["f1"])),
+            vec![later as i64]
+        );
+
+        let ret = backend.hgetex("k", FieldExpiry::Persist, &fields(&["f1", "f2"]));
+        assert_eq!(
+            ret,
+            vec![
+                RespBulkString::new("v2").into(),
+                RespBulkString::null().into()
+            ]
+        );
+        assert_eq!(backend.hexpiretime("k", &fields(&["f1"])), vec![-1]);
+        backend.hgetex("k", FieldExpiry::At(1), &fields(&["f1"]));
+        assert!(backend.hmap.get("k").is_none());
+        Ok(())
+    }
+}
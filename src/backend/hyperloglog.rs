@@ -0,0 +1,451 @@
+use std::iter;
+
+use anyhow::anyhow;
+
+use crate::backend::keyspace::KeyType;
+use crate::backend::notify::EventClass;
+use crate::backend::{frame_bytes, Backend};
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+// HyperLogLogs are strings laid out like Redis's: a 16 byte header ("HYLL",
+// encoding, 3 unused bytes, 8 bytes of cached cardinality) followed by either
+// 16384 packed 6 bit registers (dense) or run-length opcodes (sparse).
+
+/// Bits of the hash picking the register, the rest counts the zeros.
+const HLL_P: u32 = 14;
+const HLL_Q: u32 = 64 - HLL_P;
+const REGISTERS: usize = 1 << HLL_P;
+const BITS: usize = 6;
+const REGISTER_MAX: u16 = (1 << BITS) - 1;
+const HDR_SIZE: usize = 16;
+const DENSE_SIZE: usize = HDR_SIZE + (REGISTERS * BITS).div_ceil(8);
+const DENSE: u8 = 0;
+const SPARSE: u8 = 1;
+/// `hll-sparse-max-bytes`, sparse values growing past it turn dense.
+const SPARSE_MAX_BYTES: usize = 3000;
+const SPARSE_VAL_MAX_VALUE: u8 = 32;
+const SPARSE_VAL_MAX_LEN: usize = 4;
+const SPARSE_ZERO_MAX_LEN: usize = 64;
+const SPARSE_XZERO_MAX_LEN: usize = 16384;
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const SEED: u64 = 0xadc8_3b19;
+
+impl Backend {
+    /// Adds `elements` to the HyperLogLog at `key`. Returns true when the
+    /// key was created or a register changed.
+    pub fn pfadd(&self, key: &str, elements: Vec<RespFrame>) -> anyhow::Result<bool> {
+        self.check_type(key, KeyType::String)?;
+        let mut created = false;
+        let mut entry = self.map.entry(key.to_string()).or_insert_with(|| {
+            created = true;
+            RespBulkString::new(empty()).into()
+        });
+        let bytes = hll_bytes_mut(entry.value_mut())?;
+        let changed = if bytes[4] == DENSE {
+            dense_add(bytes, &elements)
+        } else {
+            sparse_add(bytes, &elements)?
+        };
+        drop(entry);
+        if created || changed {
+            self.notify(EventClass::String, "pfadd", key);
+        }
+        Ok(created || changed)
+    }
+
+    /// The estimated cardinality of the union of the HyperLogLogs at `keys`.
+    /// A single key caches the estimate in its header.
+    pub fn pfcount(&self, keys: &[String]) -> anyhow::Result<u64> {
+        if let [key] = keys {
+            let Some(mut entry) = self.map.get_mut(key) else {
+                return Ok(0);
+            };
+            let bytes = hll_bytes_mut(entry.value_mut())?;
+            if bytes[15] & 0x80 == 0 {
+                let card = bytes[8..HDR_SIZE].try_into().expect("8 bytes");
+                return Ok(u64::from_le_bytes(card));
+            }
+            let card = estimate(&decode(bytes)?);
+            bytes[8..HDR_SIZE].copy_from_slice(&card.to_le_bytes());
+            return Ok(card);
+        }
+
+        let mut union = vec![0; REGISTERS];
+        for key in keys {
+            if let Some((regs, _)) = self.hll_registers(key)? {
+                merge(&mut union, &regs);
+            }
+        }
+        Ok(estimate(&union))
+    }
+
+    /// Merges `sources` into `destination`, which turns dense when any of
+    /// the inputs is dense.
+    pub fn pfmerge(&self, destination: &str, sources: &[String]) -> anyhow::Result<()> {
+        self.check_type(destination, KeyType::String)?;
+        let mut union = vec![0; REGISTERS];
+        let mut dense = false;
+        for key in iter::once(destination).chain(sources.iter().map(String::as_str)) {
+            if let Some((regs, is_dense)) = self.hll_registers(key)? {
+                merge(&mut union, &regs);
+                dense |= is_dense;
+            }
+        }
+        let mut entry = self
+            .map
+            .entry(destination.to_string())
+            .or_insert_with(|| RespBulkString::new(empty()).into());
+        let bytes = hll_bytes_mut(entry.value_mut())?;
+        *bytes = encode(&union, !dense, invalidated_card(bytes));
+        drop(entry);
+        self.notify(EventClass::String, "pfadd", destination);
+        Ok(())
+    }
+
+    // the registers at `key` and whether they are stored dense
+    fn hll_registers(&self, key: &str) -> anyhow::Result<Option<(Vec<u8>, bool)>> {
+        let Some(entry) = self.map.get(key) else {
+            return Ok(None);
+        };
+        let bytes = match entry.value() {
+            RespFrame::BulkString(s) => s.as_deref().ok_or_else(not_hll)?,
+            _ => return Err(not_hll()),
+        };
+        let regs = decode(bytes)?;
+        Ok(Some((regs, bytes[4] == DENSE)))
+    }
+}
+
+// dense registers change where they are, only the header is touched besides
+fn dense_add(bytes: &mut [u8], elements: &[RespFrame]) -> bool {
+    let mut changed = false;
+    for element in elements {
+        let (index, count) = pattern(frame_bytes(element).unwrap_or_default());
+        if count > dense_get(&bytes[HDR_SIZE..], index) {
+            dense_set(&mut bytes[HDR_SIZE..], index, count);
+            changed = true;
+        }
+    }
+    if changed {
+        let card = invalidated_card(bytes);
+        bytes[8..HDR_SIZE].copy_from_slice(&card);
+    }
+    changed
+}
+
+// sparse registers are rewritten, turning dense once they no longer fit
+fn sparse_add(bytes: &mut Vec<u8>, elements: &[RespFrame]) -> anyhow::Result<bool> {
+    let mut regs = decode(bytes)?;
+    let mut changed = false;
+    for element in elements {
+        let (index, count) = pattern(frame_bytes(element).unwrap_or_default());
+        if count > regs[index] {
+            regs[index] = count;
+            changed = true;
+        }
+    }
+    if changed {
+        *bytes = encode(&regs, true, invalidated_card(bytes));
+    }
+    Ok(changed)
+}
+
+fn hll_bytes_mut(frame: &mut RespFrame) -> anyhow::Result<&mut Vec<u8>> {
+    let bytes = match frame {
+        RespFrame::BulkString(s) => s.as_mut().ok_or_else(not_hll)?,
+        _ => return Err(not_hll()),
+    };
+    check_header(bytes)?;
+    Ok(bytes)
+}
+
+// what Redis checks before using a string as a HyperLogLog
+fn check_header(bytes: &[u8]) -> anyhow::Result<()> {
+    let valid = bytes.len() >= HDR_SIZE
+        && &bytes[..4] == b"HYLL"
+        && (bytes[4] == SPARSE || bytes[4] == DENSE && bytes.len() == DENSE_SIZE);
+    if !valid {
+        return Err(not_hll());
+    }
+    Ok(())
+}
+
+fn not_hll() -> anyhow::Error {
+    anyhow!("WRONGTYPE Key is not a valid HyperLogLog string value.")
+}
+
+fn corrupted() -> anyhow::Error {
+    anyhow!("INVALIDOBJ Corrupted HLL object detected")
+}
+
+/// A new HyperLogLog: sparse, every register zero and a cached count of 0.
+fn empty() -> Vec<u8> {
+    encode(&[0; REGISTERS], true, [0; 8])
+}
+
+// the cached cardinality with its "needs recomputing" bit set
+fn invalidated_card(bytes: &[u8]) -> [u8; 8] {
+    let mut card: [u8; 8] = bytes[8..HDR_SIZE].try_into().expect("8 bytes");
+    card[7] |= 0x80;
+    card
+}
+
+/// One byte per register, from either encoding.
+fn decode(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    check_header(bytes)?;
+    let data = &bytes[HDR_SIZE..];
+    if bytes[4] == DENSE {
+        return Ok((0..REGISTERS).map(|i| dense_get(data, i)).collect());
+    }
+
+    let mut regs = Vec::with_capacity(REGISTERS);
+    let mut ops = data.iter();
+    while let Some(&op) = ops.next() {
+        match op >> 6 {
+            // ZERO: 00xxxxxx
+            0 => regs.resize(regs.len() + (op & 0x3f) as usize + 1, 0),
+            // XZERO: 01xxxxxx yyyyyyyy
+            1 => {
+                let low = *ops.next().ok_or_else(corrupted)? as usize;
+                let len = (((op & 0x3f) as usize) << 8 | low) + 1;
+                regs.resize(regs.len() + len, 0);
+            }
+            // VAL: 1vvvvvxx
+            _ => {
+                let value = ((op >> 2) & 0x1f) + 1;
+                regs.resize(regs.len() + (op & 0x3) as usize + 1, value);
+            }
+        }
+        if regs.len() > REGISTERS {
+            return Err(corrupted());
+        }
+    }
+    if regs.len() != REGISTERS {
+        return Err(corrupted());
+    }
+    Ok(regs)
+}
+
+/// Encodes `regs` sparse when `sparse` allows it and they fit, dense otherwise.
+fn encode(regs: &[u8], sparse: bool, card: [u8; 8]) -> Vec<u8> {
+    let sparse = sparse
+        .then(|| sparse_encode(regs))
+        .flatten()
+        .filter(|data| data.len() <= SPARSE_MAX_BYTES);
+    let mut bytes = Vec::with_capacity(DENSE_SIZE);
+    bytes.extend_from_slice(b"HYLL");
+    bytes.push(if sparse.is_some() { SPARSE } else { DENSE });
+    bytes.extend_from_slice(&[0; 3]);
+    bytes.extend_from_slice(&card);
+    match sparse {
+        Some(data) => bytes.extend(data),
+        None => {
+            let mut data = vec![0; DENSE_SIZE - HDR_SIZE];
+            for (i, &value) in regs.iter().enumerate() {
+                dense_set(&mut data, i, value);
+            }
+            bytes.extend(data);
+        }
+    }
+    bytes
+}
+
+// `None` when a register is too large for a VAL opcode
+fn sparse_encode(regs: &[u8]) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    let mut i = 0;
+    while i < regs.len() {
+        let value = regs[i];
+        let mut run = regs[i..].iter().take_while(|&&v| v == value).count();
+        i += run;
+        if value > SPARSE_VAL_MAX_VALUE {
+            return None;
+        }
+        while run > 0 {
+            if value > 0 {
+                let len = run.min(SPARSE_VAL_MAX_LEN);
+                data.push(0x80 | (value - 1) << 2 | (len - 1) as u8);
+                run -= len;
+            } else if run > SPARSE_ZERO_MAX_LEN {
+                let len = run.min(SPARSE_XZERO_MAX_LEN) - 1;
+                data.extend([0x40 | (len >> 8) as u8, (len & 0xff) as u8]);
+                run -= len + 1;
+            } else {
+                data.push((run - 1) as u8);
+                run = 0;
+            }
+        }
+    }
+    Some(data)
+}
+
+// registers are packed little endian, the first in the low bits of byte 0
+fn dense_get(data: &[u8], i: usize) -> u8 {
+    let (byte, shift) = (i * BITS / 8, i * BITS % 8);
+    let b0 = data[byte] as u16;
+    let b1 = data.get(byte + 1).copied().unwrap_or_default() as u16;
+    (((b0 | b1 << 8) >> shift) & REGISTER_MAX) as u8
+}
+
+fn dense_set(data: &mut [u8], i: usize, value: u8) {
+    let (byte, shift) = (i * BITS / 8, i * BITS % 8);
+    let (mask, value) = (REGISTER_MAX << shift, (value as u16) << shift);
+    data[byte] = data[byte] & !(mask as u8) | value as u8;
+    if mask > 0xff {
+        data[byte + 1] = data[byte + 1] & !((mask >> 8) as u8) | (value >> 8) as u8;
+    }
+}
+
+fn merge(union: &mut [u8], regs: &[u8]) {
+    for (max, &value) in union.iter_mut().zip(regs) {
+        *max = (*max).max(value);
+    }
+}
+
+/// The register `element` falls in and the position of the first set bit
+/// in the rest of its hash.
+fn pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmurhash64a(element, SEED);
+    let index = (hash & (REGISTERS as u64 - 1)) as usize;
+    let hash = hash >> HLL_P | 1 << HLL_Q;
+    (index, hash.trailing_zeros() as u8 + 1)
+}
+
+fn murmurhash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, &b) in rest.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^ h >> R
+}
+
+/// Ertl's improved raw estimator, as used by Redis.
+fn estimate(regs: &[u8]) -> u64 {
+    let m = REGISTERS as f64;
+    let q = HLL_Q as usize;
+    let mut histo = [0u32; 64];
+    for &value in regs {
+        histo[value as usize] += 1;
+    }
+    let mut z = m * tau((m - histo[q + 1] as f64) / m);
+    for &count in histo[1..=q].iter().rev() {
+        z += count as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histo[0] as f64 / m);
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let prev = z;
+        z += x * y;
+        y += y;
+        if prev == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if prev == z {
+            return z / 3.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(range: std::ops::Range<u32>) -> Vec<RespFrame> {
+        range
+            .map(|i| RespBulkString::new(format!("element:{}", i)).into())
+            .collect()
+    }
+
+    fn encoding(backend: &Backend, key: &str) -> u8 {
+        match backend.get(key) {
+            Some(RespFrame::BulkString(s)) => s.as_deref().unwrap()[4],
+            _ => panic!("expected a string"),
+        }
+    }
+
+    #[test]
+    fn test_pfadd_pfcount() -> anyhow::Result<()> {
+        let mut backend = Backend::default();
+        assert!(backend.pfadd("hll", vec![])?);
+        assert!(!backend.pfadd("hll", vec![])?);
+        assert_eq!(backend.pfcount(&["hll".to_string()])?, 0);
+        assert!(backend.pfadd("hll", elements(0..7))?);
+        assert!(!backend.pfadd("hll", elements(0..7))?);
+        assert_eq!(backend.pfcount(&["hll".to_string()])?, 7);
+        assert_eq!(encoding(&backend, "hll"), SPARSE);
+
+        backend.pfadd("hll", elements(0..100_000))?;
+        assert_eq!(encoding(&backend, "hll"), DENSE);
+        let count = backend.pfcount(&["hll".to_string()])? as f64;
+        // well within a few standard errors of 0.81%
+        assert!((count - 100_000.0).abs() / 100_000.0 < 0.03);
+        // dense registers change in place, invalidating the cached count
+        assert!(!backend.pfadd("hll", elements(0..1))?);
+        assert!(backend.pfadd("hll", elements(100_000..110_000))?);
+        assert!(backend.pfcount(&["hll".to_string()])? as f64 > count);
+
+        backend.set("str", RespBulkString::new("HYLL").into());
+        assert!(backend.pfadd("str", elements(0..1)).is_err());
+        assert!(backend.pfcount(&["str".to_string()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pfmerge() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.pfadd("a", elements(0..1000))?;
+        backend.pfadd("b", elements(500..1500))?;
+        let keys = ["a".to_string(), "b".to_string()];
+        backend.pfmerge("c", &keys)?;
+        let merged = backend.pfcount(&["c".to_string()])?;
+        assert_eq!(merged, backend.pfcount(&keys)?);
+        assert!((merged as f64 - 1500.0).abs() < 1500.0 * 0.03);
+
+        let regs = decode(&encode(&[7; REGISTERS], false, [0; 8]))?;
+        assert!(regs.iter().all(|&r| r == 7));
+        let regs = decode(&empty())?;
+        assert!(regs.iter().all(|&r| r == 0));
+        assert!(decode(b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x80").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+
+use crate::backend::glob::glob_match;
+use crate::backend::Backend;
+
+/// The type of a key, as `SCAN TYPE` names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyType {
+    String,
+    Hash,
+    Set,
+    List,
+    ZSet,
+    Stream,
+}
+
+/// What a scan walks: the keys of a database, of one type or all, or the
+/// names inside one collection.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ScanTarget {
+    Keys(Option<KeyType>),
+    Hash(String),
+    Set(String),
+    ZSet(String),
+}
+
+// (hash, name) of everything a scan walks, in hash order
+type SortedNames = Arc<Vec<(u64, Vec<u8>)>>;
+
+/// Most scans whose names are kept at once, see [`ScanCache`].
+const MAX_SCANS: usize = 64;
+
+/// The names scans walk, sorted by their hash, kept from the first page of
+/// a scan to its last so a page costs no more than its length. Names added
+/// meanwhile are missed, which a scan may do; a later scan of the same
+/// target starting over takes a fresh copy, and since any copy is in hash
+/// order it serves cursors handed out from an older one just as well.
+#[derive(Debug, Default)]
+pub(crate) struct ScanCache {
+    names: Mutex<HashMap<ScanTarget, SortedNames>>,
+}
+
+impl KeyType {
+    const ALL: [KeyType; 6] = [
+        KeyType::String,
+        KeyType::Hash,
+        KeyType::Set,
+        KeyType::List,
+        KeyType::ZSet,
+        KeyType::Stream,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        KeyType::ALL
+            .into_iter()
+            .find(|ty| ty.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyType::String => "string",
+            KeyType::Hash => "hash",
+            KeyType::Set => "set",
+            KeyType::List => "list",
+            KeyType::ZSet => "zset",
+            KeyType::Stream => "stream",
+        }
+    }
+}
+
+impl Backend {
+    /// Every key matching `pattern`, sorted.
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
+        let mut keys = BTreeSet::new();
+        for ty in KeyType::ALL {
+            self.collect_keys(ty, |key| {
+                if key_matches(pattern, key.as_bytes()) && !self.is_expired(key) {
+                    keys.insert(key.to_string());
+                }
+            });
+        }
+        keys.into_iter().collect()
+    }
+
+    /// One page of `SCAN`, see [`scan_page`]. `pattern` filters the page
+    /// after it was picked, so a page may come back empty.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+        ty: Option<KeyType>,
+    ) -> (u64, Vec<String>) {
+        let (cursor, page) = self.scan_names(ScanTarget::Keys(ty), cursor, count, || {
+            let mut keys = HashSet::new();
+            for ty in KeyType::ALL
+                .into_iter()
+                .filter(|t| ty.is_none_or(|ty| ty == *t))
+            {
+                self.collect_keys(ty, |key| {
+                    keys.insert(key.as_bytes().to_vec());
+                });
+            }
+            keys.into_iter().collect()
+        });
+        let page = page
+            .into_iter()
+            .filter(|key| pattern.is_none_or(|p| key_matches(p, key)))
+            .filter_map(|key| String::from_utf8(key).ok())
+            // keys deleted since the scan started are left out
+            .filter(|key| {
+                self.key_type(key)
+                    .is_some_and(|t| ty.is_none_or(|ty| ty == t))
+                    && !self.is_expired(key)
+            })
+            .collect();
+        (cursor, page)
+    }
+
+    /// One page of the names of `target`, see [`scan_page`]. `names` lists
+    /// them all, which is only asked for when a scan starts over.
+    pub(crate) fn scan_names(
+        &self,
+        target: ScanTarget,
+        cursor: u64,
+        count: usize,
+        names: impl FnOnce() -> Vec<Vec<u8>>,
+    ) -> (u64, Vec<Vec<u8>>) {
+        let cached = self.scans.names.lock().unwrap().get(&target).cloned();
+        let sorted = match cached {
+            Some(sorted) if cursor != 0 => sorted,
+            _ => {
+                let mut sorted = names()
+                    .into_iter()
+                    .map(|name| (scan_hash(&name), name))
+                    .collect::<Vec<_>>();
+                sorted.sort_unstable_by_key(|(hash, _)| *hash);
+                let sorted = Arc::new(sorted);
+                let mut scans = self.scans.names.lock().unwrap();
+                if scans.len() >= MAX_SCANS && !scans.contains_key(&target) {
+                    // a scan whose copy is dropped only pays for a new one
+                    let evicted = scans.keys().next().cloned();
+                    if let Some(evicted) = evicted {
+                        scans.remove(&evicted);
+                    }
+                }
+                scans.insert(target.clone(), sorted.clone());
+                sorted
+            }
+        };
+        let (next, page) = scan_page(&sorted, cursor, count);
+        let page = page.iter().map(|(_, name)| name.clone()).collect();
+        if next == 0 {
+            let mut scans = self.scans.names.lock().unwrap();
+            if scans.get(&target).is_some_and(|s| Arc::ptr_eq(s, &sorted)) {
+                scans.remove(&target);
+            }
+        }
+        (next, page)
+    }
+
+    /// The type of `key`, `None` when it doesn't exist.
+    pub fn key_type(&self, key: &str) -> Option<KeyType> {
+        KeyType::ALL.into_iter().find(|ty| match ty {
+            KeyType::String => self.map.contains_key(key),
+            KeyType::Hash => self.hmap.contains_key(key),
+            KeyType::Set => self.set.contains_key(key),
+            KeyType::List => self.list.contains_key(key),
+            KeyType::ZSet => self.zset.contains_key(key),
+            KeyType::Stream => self.stream.contains_key(key),
+        })
+    }
+
+    /// Fails with `WRONGTYPE` when `key` holds something other than a `ty`,
+    /// for commands about to add to it.
+    pub(crate) fn check_type(&self, key: &str, ty: KeyType) -> anyhow::Result<()> {
+        match self.key_type(key) {
+            Some(t) if t != ty => {
+                bail!("WRONGTYPE Operation against a key holding the wrong kind of value")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes `key`, TTL included, when it holds something other than a
+    /// `ty`, for commands that replace whatever it holds.
+    pub(crate) fn clear_other_type(&self, key: &str, ty: KeyType) {
+        if self.key_type(key).is_some_and(|t| t != ty) {
+            self.clear_expire(key);
+            self.delete_key(key);
+        }
+    }
+
+    /// The number of keys in the database.
+    pub fn dbsize(&self) -> usize {
+        let mut keys = HashSet::new();
+        for ty in KeyType::ALL {
+            self.collect_keys(ty, |key| {
+                keys.insert(key.to_string());
+            });
+        }
+        keys.len()
+    }
+
+    fn collect_keys(&self, ty: KeyType, mut f: impl FnMut(&str)) {
+        match ty {
+            KeyType::String => self.map.iter().for_each(|e| f(e.key())),
+            KeyType::Hash => self.hmap.iter().for_each(|e| f(e.key())),
+            KeyType::Set => self.set.iter().for_each(|e| f(e.key())),
+            KeyType::List => self.list.iter().for_each(|e| f(e.key())),
+            KeyType::ZSet => self.zset.iter().for_each(|e| f(e.key())),
+            KeyType::Stream => self.stream.iter().for_each(|e| f(e.key())),
+        }
+    }
+}
+
+/// Whether a scanned name matches `pattern`. `*` takes every name, the
+/// empty one included.
+pub(crate) fn key_matches(pattern: &str, name: &[u8]) -> bool {
+    pattern == "*" || glob_match(pattern.as_bytes(), name)
+}
+
+/// The position of an item in a scan: scans walk items in the order of the
+/// hash of their name, which doesn't depend on how the `DashMap` holding
+/// them is laid out, so a resize between two calls can't hide anything.
+pub(crate) fn scan_hash(name: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Picks, out of `items` sorted by hash, the `count` items with the lowest
+/// hash at or after `cursor`, plus any sharing the last hash, and the cursor
+/// to continue from, 0 once nothing is left. Every item present for a whole
+/// scan is returned at least once since pages cover consecutive hash ranges.
+pub(crate) fn scan_page<T>(items: &[(u64, T)], cursor: u64, count: usize) -> (u64, &[(u64, T)]) {
+    let start = items.partition_point(|(hash, _)| *hash < cursor);
+    let mut end = (start + count.max(1)).min(items.len());
+    if end == items.len() {
+        return (0, &items[start..]);
+    }
+    let last = items[end - 1].0;
+    end += items[end..].partition_point(|(hash, _)| *hash == last);
+    if end == items.len() {
+        return (0, &items[start..]);
+    }
+    // something past `last` means `last + 1` can't overflow
+    (last + 1, &items[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::bulkstring::RespBulkString;
+    use crate::resp::frame::RespFrame;
+
+    #[test]
+    fn test_scan_page() {
+        let items = [1u64, 3, 5, 5, 7, 9].map(|h| (h, h));
+        let page = |cursor, count| {
+            let (next, page) = scan_page(&items, cursor, count);
+            (next, page.iter().map(|(h, _)| *h).collect::<Vec<_>>())
+        };
+        assert_eq!(page(0, 2), (4, vec![1, 3]));
+        // items sharing the last hash come together
+        assert_eq!(page(4, 1), (6, vec![5, 5]));
+        assert_eq!(page(6, 10), (0, vec![7, 9]));
+        assert_eq!(page(10, 10), (0, vec![]));
+    }
+
+    #[test]
+    fn test_keys_and_scan() {
+        let mut backend = Backend::default();
+        for i in 0..100 {
+            backend.set(&format!("key:{}", i), RespBulkString::new("v").into());
+        }
+        backend
+            .sadd("set", vec![RespBulkString::new("m").into()])
+            .unwrap();
+        assert_eq!(backend.keys("key:1?").len(), 10);
+        assert_eq!(backend.keys("s*"), ["set"]);
+
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let (next, keys) = backend.scan(cursor, None, 7, None);
+            // writes in between don't make the scan miss existing keys
+            backend.set(&format!("new:{}", cursor), RespBulkString::new("v").into());
+            seen.extend(keys);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert!((0..100).all(|i| seen.contains(&format!("key:{}", i))));
+        assert!(seen.contains("set"));
+
+        let (_, keys) = backend.scan(0, Some("s*"), 1000, Some(KeyType::Set));
+        assert_eq!(keys, ["set"]);
+        let (_, keys) = backend.scan(0, None, 1000, Some(KeyType::Stream));
+        assert!(keys.is_empty());
+
+        // `MATCH *` keeps an empty field name, which no glob matches
+        let v: RespFrame = RespBulkString::new("v").into();
+        backend
+            .hset("h", vec![(String::new(), v.clone()), ("a".to_string(), v)])
+            .unwrap();
+        let (_, fields) = backend.hscan("h", 0, Some("*"), 10);
+        assert_eq!(fields.len(), 2);
+    }
+}
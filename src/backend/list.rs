@@ -0,0 +1,462 @@
+use std::collections::VecDeque;
+
+use anyhow::bail;
+
+use crate::backend::keyspace::KeyType;
+use crate::backend::notify::EventClass;
+use crate::backend::Backend;
+use crate::resp::frame::RespFrame;
+
+/// A list stored in `Db::list`, O(1) push and pop at both ends.
+pub type ListValue = VecDeque<RespFrame>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    fn push(self, list: &mut ListValue, value: RespFrame) {
+        match self {
+            ListEnd::Left => list.push_front(value),
+            ListEnd::Right => list.push_back(value),
+        }
+    }
+
+    fn pop(self, list: &mut ListValue) -> Option<RespFrame> {
+        match self {
+            ListEnd::Left => list.pop_front(),
+            ListEnd::Right => list.pop_back(),
+        }
+    }
+
+    fn push_event(self) -> &'static str {
+        match self {
+            ListEnd::Left => "lpush",
+            ListEnd::Right => "rpush",
+        }
+    }
+
+    fn pop_event(self) -> &'static str {
+        match self {
+            ListEnd::Left => "lpop",
+            ListEnd::Right => "rpop",
+        }
+    }
+}
+
+/// Options of `LPOS`. `count` of 0 means every match, `maxlen` of 0 means
+/// the whole list is scanned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LposOptions {
+    pub rank: i64,
+    pub count: usize,
+    pub maxlen: usize,
+}
+
+impl Default for LposOptions {
+    fn default() -> Self {
+        Self {
+            rank: 1,
+            count: 1,
+            maxlen: 0,
+        }
+    }
+}
+
+impl Backend {
+    /// Pushes `values` one by one at `end` and returns the new length. With
+    /// `only_existing` nothing happens unless the list already exists.
+    /// Clients blocked on the list are served after the current command.
+    pub fn push(
+        &self,
+        key: &str,
+        end: ListEnd,
+        values: Vec<RespFrame>,
+        only_existing: bool,
+    ) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::List)?;
+        let len = {
+            let mut list = if only_existing {
+                match self.list.get_mut(key) {
+                    Some(list) => list,
+                    None => return Ok(0),
+                }
+            } else {
+                self.list.entry(key.to_string()).or_default()
+            };
+            for value in values {
+                end.push(&mut list, value);
+            }
+            list.len()
+        };
+        self.notify(EventClass::List, end.push_event(), key);
+        self.signal_ready(key);
+        Ok(len as i64)
+    }
+
+    /// Pops up to `count` elements from `end`, `None` if the list does not
+    /// exist. The list is dropped once its last element is gone.
+    pub fn pop(&self, key: &str, end: ListEnd, count: usize) -> Option<Vec<RespFrame>> {
+        let popped = {
+            let mut list = self.list.get_mut(key)?;
+            let count = count.min(list.len());
+            (0..count)
+                .filter_map(|_| end.pop(&mut list))
+                .collect::<Vec<_>>()
+        };
+        if !popped.is_empty() {
+            self.notify(EventClass::List, end.pop_event(), key);
+        }
+        self.drop_empty_list(key);
+        Some(popped)
+    }
+
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Vec<RespFrame> {
+        let Some(list) = self.list.get(key) else {
+            return vec![];
+        };
+        match normalize_range(list.len(), start, stop) {
+            Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn lindex(&self, key: &str, index: i64) -> Option<RespFrame> {
+        let list = self.list.get(key)?;
+        let index = normalize_index(list.len(), index)?;
+        list.get(index).cloned()
+    }
+
+    pub fn lset(&self, key: &str, index: i64, value: RespFrame) -> anyhow::Result<()> {
+        let Some(mut list) = self.list.get_mut(key) else {
+            bail!("no such key");
+        };
+        match normalize_index(list.len(), index) {
+            Some(index) => {
+                list[index] = value;
+                drop(list);
+                self.notify(EventClass::List, "lset", key);
+                Ok(())
+            }
+            None => bail!("index out of range"),
+        }
+    }
+
+    pub fn llen(&self, key: &str) -> i64 {
+        self.list.get(key).map_or(0, |list| list.len() as i64)
+    }
+
+    /// Removes up to `|count|` occurrences of `value`, from the head when
+    /// `count` is positive, from the tail when negative, all of them when 0.
+    pub fn lrem(&self, key: &str, count: i64, value: &RespFrame) -> i64 {
+        let removed = {
+            let Some(mut list) = self.list.get_mut(key) else {
+                return 0;
+            };
+            let limit = match count {
+                0 => usize::MAX,
+                n => n.unsigned_abs() as usize,
+            };
+            // from the tail, the matches before the last `limit` ones stay
+            let mut keep = match count {
+                n if n < 0 => list
+                    .iter()
+                    .filter(|v| *v == value)
+                    .count()
+                    .saturating_sub(limit),
+                _ => 0,
+            };
+            let mut removed = 0;
+            list.retain(|v| {
+                if v != value || removed == limit {
+                    return true;
+                }
+                if keep > 0 {
+                    keep -= 1;
+                    return true;
+                }
+                removed += 1;
+                false
+            });
+            removed
+        };
+        if removed > 0 {
+            self.notify(EventClass::List, "lrem", key);
+        }
+        self.drop_empty_list(key);
+        removed as i64
+    }
+
+    pub fn ltrim(&self, key: &str, start: i64, stop: i64) {
+        if let Some(mut list) = self.list.get_mut(key) {
+            match normalize_range(list.len(), start, stop) {
+                Some((start, stop)) => {
+                    list.truncate(stop + 1);
+                    list.drain(..start);
+                }
+                None => list.clear(),
+            }
+        } else {
+            return;
+        }
+        self.notify(EventClass::List, "ltrim", key);
+        self.drop_empty_list(key);
+    }
+
+    /// Inserts `value` next to the first `pivot` and returns the new length,
+    /// -1 when `pivot` is not found and 0 when the list does not exist.
+    pub fn linsert(&self, key: &str, before: bool, pivot: &RespFrame, value: RespFrame) -> i64 {
+        let Some(mut list) = self.list.get_mut(key) else {
+            return 0;
+        };
+        let Some(pos) = list.iter().position(|v| v == pivot) else {
+            return -1;
+        };
+        let pos = if before { pos } else { pos + 1 };
+        list.insert(pos, value);
+        let len = list.len() as i64;
+        drop(list);
+        self.notify(EventClass::List, "linsert", key);
+        len
+    }
+
+    /// Positions of the elements equal to `value`. A negative rank scans
+    /// from the tail, but positions are always counted from the head.
+    pub fn lpos(&self, key: &str, value: &RespFrame, opts: LposOptions) -> Vec<i64> {
+        let Some(list) = self.list.get(key) else {
+            return vec![];
+        };
+        let maxlen = match opts.maxlen {
+            0 => list.len(),
+            n => n,
+        };
+        let count = match opts.count {
+            0 => usize::MAX,
+            n => n,
+        };
+        let skip = (opts.rank.unsigned_abs() - 1) as usize;
+        let positions = list.iter().enumerate();
+        let matches: Box<dyn Iterator<Item = (usize, &RespFrame)>> = if opts.rank > 0 {
+            Box::new(positions.take(maxlen))
+        } else {
+            Box::new(positions.rev().take(maxlen))
+        };
+        matches
+            .filter(|(_, v)| *v == value)
+            .skip(skip)
+            .take(count)
+            .map(|(i, _)| i as i64)
+            .collect()
+    }
+
+    /// Pops from `source` at `from` and pushes to `destination` at `to`.
+    pub fn lmove(
+        &self,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> anyhow::Result<Option<RespFrame>> {
+        if !self.list.contains_key(source) {
+            return Ok(None);
+        }
+        self.check_type(destination, KeyType::List)?;
+        if source == destination {
+            let value = {
+                let Some(mut list) = self.list.get_mut(source) else {
+                    return Ok(None);
+                };
+                let Some(value) = from.pop(&mut list) else {
+                    return Ok(None);
+                };
+                to.push(&mut list, value.clone());
+                value
+            };
+            self.notify(EventClass::List, from.pop_event(), source);
+            self.notify(EventClass::List, to.push_event(), source);
+            return Ok(Some(value));
+        }
+        // never hold two entries at once, they may share a shard
+        let Some(value) = self.pop(source, from, 1).and_then(|mut v| v.pop()) else {
+            return Ok(None);
+        };
+        self.push(destination, to, vec![value.clone()], false)?;
+        Ok(Some(value))
+    }
+
+    // drops the list once its last element is gone, deleting the key
+    fn drop_empty_list(&self, key: &str) {
+        if self.list.remove_if(key, |_, v| v.is_empty()).is_some() {
+            self.notify(EventClass::Generic, "del", key);
+        }
+    }
+
+    /// Pops up to `count` elements from the first non-empty list in `keys`.
+    pub fn lmpop(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+        count: usize,
+    ) -> Option<(String, Vec<RespFrame>)> {
+        keys.iter().find_map(|key| {
+            self.pop(key, end, count)
+                .filter(|popped| !popped.is_empty())
+                .map(|popped| (key.clone(), popped))
+        })
+    }
+}
+
+fn normalize_index(len: usize, index: i64) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+// inclusive [start, stop] clamped to the list, `None` when it is empty
+fn normalize_range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::bulkstring::RespBulkString;
+
+    fn values(vals: &[&str]) -> Vec<RespFrame> {
+        vals.iter()
+            .map(|v| RespBulkString::new(*v).into())
+            .collect()
+    }
+
+    #[test]
+    fn test_push_pop() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        assert_eq!(backend.push("k", ListEnd::Left, values(&["a"]), true)?, 0);
+        assert_eq!(
+            backend.push("k", ListEnd::Left, values(&["b", "a"]), false)?,
+            2
+        );
+        assert_eq!(backend.push("k", ListEnd::Right, values(&["c"]), true)?, 3);
+        assert_eq!(backend.lrange("k", 0, -1), values(&["a", "b", "c"]));
+
+        assert_eq!(
+            backend.pop("k", ListEnd::Right, 2),
+            Some(values(&["c", "b"]))
+        );
+        assert_eq!(backend.pop("k", ListEnd::Left, 5), Some(values(&["a"])));
+        assert_eq!(backend.pop("k", ListEnd::Left, 1), None);
+        assert_eq!(backend.llen("k"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_trim_rem() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.push(
+            "k",
+            ListEnd::Right,
+            values(&["a", "b", "a", "c", "a"]),
+            false,
+        )?;
+        assert_eq!(backend.lrange("k", -100, 1), values(&["a", "b"]));
+        assert_eq!(backend.lrange("k", 3, 100), values(&["c", "a"]));
+        assert!(backend.lrange("k", 3, 2).is_empty());
+        assert_eq!(backend.lindex("k", -2), Some(values(&["c"])[0].clone()));
+
+        assert_eq!(backend.lrem("k", -2, &values(&["a"])[0]), 2);
+        assert_eq!(backend.lrange("k", 0, -1), values(&["a", "b", "c"]));
+        let x = &values(&["x"])[0];
+        backend.push("r", ListEnd::Right, values(&["x", "a", "x", "x"]), false)?;
+        assert_eq!(backend.lrem("r", 1, x), 1);
+        assert_eq!(backend.lrange("r", 0, -1), values(&["a", "x", "x"]));
+        assert_eq!(backend.lrem("r", 0, x), 2);
+        assert_eq!(backend.lrange("r", 0, -1), values(&["a"]));
+        backend.ltrim("k", 1, -1);
+        assert_eq!(backend.lrange("k", 0, -1), values(&["b", "c"]));
+        backend.ltrim("k", 5, 10);
+        assert_eq!(backend.llen("k"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.push(
+            "k",
+            ListEnd::Right,
+            values(&["a", "b", "c", "1", "2", "3", "c", "c"]),
+            false,
+        )?;
+        let c = &values(&["c"])[0];
+        let lpos = |rank, count, maxlen| {
+            backend.lpos(
+                "k",
+                c,
+                LposOptions {
+                    rank,
+                    count,
+                    maxlen,
+                },
+            )
+        };
+        assert_eq!(lpos(1, 1, 0), vec![2]);
+        assert_eq!(lpos(2, 1, 0), vec![6]);
+        assert_eq!(lpos(-1, 2, 0), vec![7, 6]);
+        assert_eq!(lpos(1, 0, 0), vec![2, 6, 7]);
+        assert_eq!(lpos(1, 0, 3), vec![2]);
+        assert!(lpos(4, 1, 0).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.push("a", ListEnd::Right, values(&["1", "2", "3"]), false)?;
+        let moved = backend.lmove("a", "a", ListEnd::Left, ListEnd::Right)?;
+        assert_eq!(moved, Some(values(&["1"])[0].clone()));
+        assert_eq!(backend.lrange("a", 0, -1), values(&["2", "3", "1"]));
+
+        backend.lmove("a", "b", ListEnd::Right, ListEnd::Left)?;
+        assert_eq!(backend.lrange("b", 0, -1), values(&["1"]));
+        let keys = ["x".to_string(), "b".to_string(), "a".to_string()];
+        assert_eq!(
+            backend.lmpop(&keys, ListEnd::Left, 10),
+            Some(("b".to_string(), values(&["1"])))
+        );
+        assert_eq!(backend.llen("b"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_type() -> anyhow::Result<()> {
+        let mut backend = Backend::default();
+        backend.set("s", RespBulkString::new("v").into());
+        assert!(backend
+            .push("s", ListEnd::Left, values(&["a"]), false)
+            .is_err());
+        assert_eq!(backend.key_type("s"), Some(KeyType::String));
+
+        backend.push("a", ListEnd::Right, values(&["1"]), false)?;
+        assert!(backend
+            .lmove("a", "s", ListEnd::Left, ListEnd::Left)
+            .is_err());
+        assert_eq!(backend.llen("a"), 1);
+        // SET replaces the list rather than shadow it
+        backend.set("a", RespBulkString::new("v").into());
+        assert_eq!(backend.key_type("a"), Some(KeyType::String));
+        assert_eq!(backend.llen("a"), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeSet;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::backend::keyspace::scan_hash;
+use crate::backend::Backend;
+
+/// Number of locks keys are spread over. Keys sharing one only cost some
+/// concurrency, never correctness.
+const STRIPES: usize = 1024;
+
+#[derive(Debug)]
+pub(crate) struct KeyLocks {
+    stripes: Vec<Mutex<()>>,
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self {
+            stripes: (0..STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+}
+
+/// Held while a command runs over its keys, see [`Backend::lock_keys`].
+#[derive(Debug)]
+pub struct KeysGuard<'a> {
+    _guards: Vec<MutexGuard<'a, ()>>,
+}
+
+impl Backend {
+    /// Locks `keys` of this database so a command spanning them neither
+    /// sees nor leaves a half-done state of another one, and a command on
+    /// one of them waits for it. Locks are taken in a fixed order, so two
+    /// commands can't wait on each other.
+    pub fn lock_keys(&self, keys: &[String]) -> KeysGuard<'_> {
+        let stripes = keys
+            .iter()
+            .map(|key| self.stripe(key))
+            .collect::<BTreeSet<_>>();
+        // the set iterates in order, which is what makes this deadlock free
+        let guards = stripes
+            .into_iter()
+            .map(|i| self.server.key_locks.stripes[i].lock().unwrap())
+            .collect();
+        KeysGuard { _guards: guards }
+    }
+
+    fn stripe(&self, key: &str) -> usize {
+        let hash = scan_hash(key.as_bytes()) ^ (self.index as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        (hash % STRIPES as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::cmd::frame_to_i64;
+    use crate::resp::bulkstring::RespBulkString;
+    use crate::resp::frame::RespFrame;
+
+    fn get(backend: &Backend, key: &str) -> i64 {
+        frame_to_i64(backend.get(key).expect("key exists")).expect("value is an integer")
+    }
+
+    fn set(backend: &mut Backend, key: &str, value: i64) {
+        let value: RespFrame = RespBulkString::new(value.to_string()).into();
+        backend.set(key, value);
+    }
+
+    #[test]
+    fn test_lock_keys() {
+        let mut backend = Backend::default();
+        set(&mut backend, "a", 100);
+        set(&mut backend, "b", 0);
+        let keys = ["a".to_string(), "b".to_string()];
+        let movers = (0..4)
+            .map(|_| {
+                let (locker, mut backend) = (backend.clone(), backend.clone());
+                let keys = keys.clone();
+                thread::spawn(move || {
+                    for _ in 0..500 {
+                        let _guard = locker.lock_keys(&keys);
+                        let (a, b) = (get(&backend, "a"), get(&backend, "b"));
+                        set(&mut backend, "a", a - 1);
+                        set(&mut backend, "b", b + 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        // locking in the other order doesn't deadlock and sees no half move
+        let reversed = [keys[1].clone(), keys[0].clone()];
+        for _ in 0..500 {
+            let _guard = backend.lock_keys(&reversed);
+            assert_eq!(get(&backend, "a") + get(&backend, "b"), 100);
+        }
+        for mover in movers {
+            mover.join().unwrap();
+        }
+        assert_eq!(get(&backend, "b"), 2000);
+    }
+
+    #[test]
+    fn test_lock_single_key() {
+        let mut backend = Backend::default();
+        set(&mut backend, "a", 0);
+        // `ZUNIONSTORE a a x` names one key twice, `ZADD a` names it once,
+        // either way a read-modify-write of `a` isn't interleaved
+        let writers = [
+            vec!["a".to_string(), "a".to_string()],
+            vec!["a".to_string()],
+        ]
+        .map(|keys| {
+            let (locker, mut backend) = (backend.clone(), backend.clone());
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    let _guard = locker.lock_keys(&keys);
+                    let a = get(&backend, "a");
+                    set(&mut backend, "a", a + 1);
+                }
+            })
+        });
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(get(&backend, "a"), 2000);
+    }
+}
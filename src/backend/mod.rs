@@ -1,103 +1,215 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, VecDeque};
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use dashmap::DashMap;
 
-use crate::resp::bulkstring::RespBulkString;
+use crate::backend::blocking::BlockedClients;
+use crate::backend::hmap::HashValue;
+use crate::backend::keyspace::{KeyType, ScanCache};
+use crate::backend::list::ListValue;
+use crate::backend::lock::KeyLocks;
+use crate::backend::notify::EventClass;
+use crate::backend::pubsub::PubSub;
+use crate::backend::script::{Scripts, DEFAULT_BUSY_REPLY_THRESHOLD};
+use crate::backend::set::SetValue;
+use crate::backend::stream::StreamValue;
+use crate::backend::tracking::Tracking;
+use crate::backend::watch::WatchedKeys;
+use crate::backend::zset::ZSetValue;
 use crate::resp::frame::RespFrame;
 
+pub mod bitfield;
+pub mod blocking;
+pub mod db;
+pub mod expire;
+pub mod geo;
+pub mod geohash;
+pub mod glob;
+pub mod hmap;
+pub mod hyperloglog;
+pub mod keyspace;
+pub mod list;
+pub mod lock;
+pub mod notify;
+pub mod pubsub;
+pub mod script;
+pub mod set;
+pub mod skiplist;
+pub mod slot;
+pub mod stream;
+pub mod tracking;
+pub mod watch;
+pub mod zset;
+
+/// Number of databases a server has unless told otherwise.
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// A handle on one database of the server. Connections get a fresh one for
+/// every command, so `SWAPDB` and `FLUSHDB` are seen by the next command.
 #[derive(Debug, Clone)]
-pub struct Backend(Arc<BackendInner>);
+pub struct Backend {
+    server: Arc<Server>,
+    db: Arc<Db>,
+    index: usize,
+}
 
+/// The keys of one database.
 #[derive(Debug)]
-pub struct BackendInner {
+pub struct Db {
     map: DashMap<String, RespFrame>,
-    hmap: DashMap<String, DashMap<String, RespFrame>>,
-    set: DashMap<String, BTreeSet<RespFrame>>,
+    hmap: DashMap<String, HashValue>,
+    set: DashMap<String, SetValue>,
+    list: DashMap<String, ListValue>,
+    zset: DashMap<String, ZSetValue>,
+    stream: DashMap<String, StreamValue>,
+    // unix time in milliseconds at which each key with a TTL expires
+    expires: DashMap<String, u64>,
+    // (when, key) of every key with a TTL, ordered by expiry
+    expires_index: Mutex<BTreeSet<(u64, String)>>,
+    // (when, key, field) of every hash field with a TTL, ordered by expiry
+    hmap_expires: Mutex<BTreeSet<(u64, String, String)>>,
+    scans: ScanCache,
+}
+
+/// What every database shares.
+#[derive(Debug)]
+struct Server {
+    dbs: Vec<RwLock<Arc<Db>>>,
+    blocked: Mutex<BlockedClients>,
+    // (db, key) written since blocked clients were last served
+    ready_keys: Mutex<VecDeque<(usize, String)>>,
+    next_client_id: AtomicU64,
+    // shared by every command, taken exclusively by transactions
+    // fair, so a transaction waiting on it isn't starved by a steady stream
+    // of commands sharing it
+    exec_lock: tokio::sync::RwLock<()>,
+    key_locks: KeyLocks,
+    watched: Mutex<WatchedKeys>,
+    // whether any client watches a key at all
+    watching: AtomicBool,
+    pubsub: Mutex<PubSub>,
+    // the classes of keyspace events published, see `notify-keyspace-events`
+    notify_flags: AtomicU32,
+    tracking: Mutex<Tracking>,
+    // whether any client has tracking on
+    tracking_on: AtomicBool,
+    scripts: Mutex<Scripts>,
+    // see `busy-reply-threshold`
+    busy_reply_threshold: AtomicU64,
 }
 
 impl Deref for Backend {
-    type Target = BackendInner;
+    type Target = Db;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.db
     }
 }
 
 impl Default for Backend {
     fn default() -> Self {
-        Self(Arc::new(BackendInner::default()))
+        Self::new(DEFAULT_DATABASES)
     }
 }
 
-impl Default for BackendInner {
+impl Default for Db {
     fn default() -> Self {
         Self {
             map: DashMap::new(),
             hmap: DashMap::new(),
             set: DashMap::new(),
+            list: DashMap::new(),
+            zset: DashMap::new(),
+            stream: DashMap::new(),
+            expires: DashMap::new(),
+            expires_index: Mutex::new(BTreeSet::new()),
+            hmap_expires: Mutex::new(BTreeSet::new()),
+            scans: ScanCache::default(),
         }
     }
 }
 
 impl Backend {
+    /// A server with `databases` empty databases, the handle is on the first.
+    pub fn new(databases: usize) -> Self {
+        assert!(databases > 0, "a server has at least one database");
+        let server = Server {
+            dbs: (0..databases)
+                .map(|_| RwLock::new(Arc::new(Db::default())))
+                .collect(),
+            blocked: Mutex::new(BlockedClients::default()),
+            ready_keys: Mutex::new(VecDeque::new()),
+            next_client_id: AtomicU64::new(1),
+            exec_lock: tokio::sync::RwLock::new(()),
+            key_locks: KeyLocks::default(),
+            watched: Mutex::new(WatchedKeys::default()),
+            watching: AtomicBool::new(false),
+            pubsub: Mutex::new(PubSub::default()),
+            notify_flags: AtomicU32::new(0),
+            tracking: Mutex::new(Tracking::default()),
+            tracking_on: AtomicBool::new(false),
+            scripts: Mutex::new(Scripts::default()),
+            busy_reply_threshold: AtomicU64::new(DEFAULT_BUSY_REPLY_THRESHOLD),
+        };
+        let db = server.dbs[0].read().unwrap().clone();
+        Self {
+            server: Arc::new(server),
+            db,
+            index: 0,
+        }
+    }
+
     pub fn get(&self, key: &str) -> Option<RespFrame> {
         self.map.get(key).map(|v| v.value().clone())
     }
 
+    /// Sets `key` to `val`, whatever it held, dropping any TTL it had.
     pub fn set(&mut self, key: &str, val: RespFrame) {
+        self.clear_other_type(key, KeyType::String);
         self.map.insert(key.to_string(), val);
+        self.clear_expire(key);
+        self.notify(EventClass::String, "set", key);
     }
 
-    pub fn hget(&self, key: &str, filed: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(filed).map(|v| v.value().clone()))
+    /// Held by a command while it runs, so no transaction or script runs
+    /// meanwhile. Waits behind a transaction or script already waiting.
+    pub async fn lock_shared(&self) -> tokio::sync::RwLockReadGuard<'_, ()> {
+        self.server.exec_lock.read().await
     }
 
-    pub fn hset(&mut self, key: &str, field: &str, val: RespFrame) {
-        self.hmap
-            .entry(key.to_string())
-            .or_default()
-            .insert(field.to_string(), val);
+    /// Held by a transaction or script while it runs, so nothing else does.
+    pub async fn lock_exclusive(&self) -> tokio::sync::RwLockWriteGuard<'_, ()> {
+        self.server.exec_lock.write().await
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.value().clone())
+    /// Like `lock_shared`, `None` rather than waiting, for housekeeping that
+    /// can as well run later.
+    pub fn try_lock_shared(&self) -> Option<tokio::sync::RwLockReadGuard<'_, ()>> {
+        self.server.exec_lock.try_read().ok()
     }
 
-    pub fn hmget(&self, key: &str, fileds: &[String]) -> Vec<RespFrame> {
-        let Some(inner) = self.hmap.get(key) else {
-            return vec![RespBulkString::null().into(); fileds.len()];
-        };
-
-        let mut vec = Vec::with_capacity(fileds.len());
-        for field in fileds.iter() {
-            match inner.get(field).map(|v| v.value().clone()) {
-                Some(frame) => vec.push(frame),
-                None => vec.push(RespBulkString::null().into()),
-            }
-        }
-        vec
+    /// Ids handed out to connections, unique for the server's lifetime.
+    pub fn next_client_id(&self) -> u64 {
+        self.server.next_client_id.fetch_add(1, Ordering::Relaxed)
     }
+}
 
-    pub fn sadd(&self, key: &str, member: RespFrame) -> i64 {
-        if self.sismember(key, &member) == 1 {
-            return 0;
-        }
-        self.set.entry(key.to_string()).or_default().insert(member);
-        1
+/// The raw bytes of a string-like frame.
+pub(crate) fn frame_bytes(frame: &RespFrame) -> Option<&[u8]> {
+    match frame {
+        RespFrame::BulkString(s) => s.as_deref(),
+        RespFrame::SimpleString(s) => Some(s.as_bytes()),
+        _ => None,
     }
+}
 
-    pub fn sismember(&self, key: &str, member: &RespFrame) -> i64 {
-        let Some(inner) = self.set.get(key) else {
-            return 0;
-        };
-        if inner.contains(member) {
-            1
-        } else {
-            0
-        }
-    }
+/// Current unix time in milliseconds.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_millis() as u64
 }
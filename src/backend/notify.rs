@@ -0,0 +1,184 @@
+use std::sync::atomic::Ordering;
+
+use crate::backend::Backend;
+use crate::resp::bulkstring::RespBulkString;
+
+// where events go: `__keyspace@<db>__:<key>` and `__keyevent@<db>__:<event>`
+const KEYSPACE: u32 = 1 << 0;
+const KEYEVENT: u32 = 1 << 1;
+
+/// What an event is about, the classes `notify-keyspace-events` filters on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventClass {
+    Generic,
+    String,
+    List,
+    Set,
+    Hash,
+    ZSet,
+    Expired,
+    /// Keys are never evicted, so nothing is published under it. The letter
+    /// is still taken, so settings written for Redis such as `Kea` work.
+    Evicted,
+    Stream,
+}
+
+// the letters of `notify-keyspace-events`, in the order Redis prints them
+const CLASSES: [(u8, EventClass); 9] = [
+    (b'g', EventClass::Generic),
+    (b'$', EventClass::String),
+    (b'l', EventClass::List),
+    (b's', EventClass::Set),
+    (b'h', EventClass::Hash),
+    (b'z', EventClass::ZSet),
+    (b'x', EventClass::Expired),
+    (b'e', EventClass::Evicted),
+    (b't', EventClass::Stream),
+];
+
+impl EventClass {
+    fn flag(self) -> u32 {
+        let i = CLASSES
+            .iter()
+            .position(|(_, class)| *class == self)
+            .expect("every class has a letter");
+        1 << (i + 2)
+    }
+}
+
+// every class, what `A` stands for
+fn all_classes() -> u32 {
+    CLASSES
+        .iter()
+        .fold(0, |flags, (_, class)| flags | class.flag())
+}
+
+/// Parses `notify-keyspace-events`, `None` for a letter it doesn't know.
+fn parse_flags(s: &str) -> Option<u32> {
+    s.bytes().try_fold(0, |flags, c| {
+        let flag = match c {
+            // `a` too, as settings such as `Kea` spell it
+            b'A' | b'a' => all_classes(),
+            b'K' => KEYSPACE,
+            b'E' => KEYEVENT,
+            c => CLASSES
+                .iter()
+                .find(|(letter, _)| *letter == c)
+                .map(|(_, class)| class.flag())?,
+        };
+        Some(flags | flag)
+    })
+}
+
+fn format_flags(flags: u32) -> String {
+    let mut s = String::new();
+    if flags & all_classes() == all_classes() {
+        s.push('A');
+    } else {
+        for (letter, class) in CLASSES {
+            if flags & class.flag() != 0 {
+                s.push(letter as char);
+            }
+        }
+    }
+    if flags & KEYSPACE != 0 {
+        s.push('K');
+    }
+    if flags & KEYEVENT != 0 {
+        s.push('E');
+    }
+    s
+}
+
+impl Backend {
+    /// The `notify-keyspace-events` setting, as `CONFIG GET` shows it.
+    pub fn notify_keyspace_events(&self) -> String {
+        format_flags(self.server.notify_flags.load(Ordering::Relaxed))
+    }
+
+    pub fn set_notify_keyspace_events(&self, s: &str) -> anyhow::Result<()> {
+        let Some(flags) = parse_flags(s) else {
+            anyhow::bail!(
+                "Invalid argument '{}' for CONFIG SET 'notify-keyspace-events'",
+                s
+            );
+        };
+        self.server.notify_flags.store(flags, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Publishes that `event` happened to `key` of this database, if events
+    /// of `class` are asked for.
+    pub(crate) fn notify(&self, class: EventClass, event: &str, key: &str) {
+        let flags = self.server.notify_flags.load(Ordering::Relaxed);
+        if flags & class.flag() == 0 {
+            return;
+        }
+        if flags & KEYSPACE != 0 {
+            let channel = format!("__keyspace@{}__:{}", self.index, key);
+            self.publish(&channel, RespBulkString::new(event).into());
+        }
+        if flags & KEYEVENT != 0 {
+            let channel = format!("__keyevent@{}__:{}", self.index, event);
+            self.publish(&channel, RespBulkString::new(key).into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::backend::list::ListEnd;
+    use crate::backend::pubsub::SubscriptionKind;
+    use crate::resp::frame::RespFrame;
+
+    #[test]
+    fn test_notify_keyspace_events() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        assert_eq!(backend.notify_keyspace_events(), "");
+        backend.set_notify_keyspace_events("KEA")?;
+        assert_eq!(backend.notify_keyspace_events(), "AKE");
+        assert!(backend.set_notify_keyspace_events("Kq").is_err());
+        assert_eq!(backend.notify_keyspace_events(), "AKE");
+        // `e` is part of `A` even though nothing is ever evicted
+        backend.set_notify_keyspace_events("Kea")?;
+        assert_eq!(backend.notify_keyspace_events(), "AK");
+        backend.set_notify_keyspace_events("Ee")?;
+        assert_eq!(backend.notify_keyspace_events(), "eE");
+
+        backend.set_notify_keyspace_events("Esh")?;
+        assert_eq!(backend.notify_keyspace_events(), "shE");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        backend.subscribe(
+            1,
+            &tx,
+            SubscriptionKind::Pattern,
+            vec!["__key*__:*".to_string()],
+        );
+        let db = backend.select(2)?;
+        db.sadd("k", vec![RespBulkString::new("m").into()])?;
+        db.srem("k", &[RespBulkString::new("m").into()]);
+        let value = RespBulkString::new("v").into();
+        db.push("l", ListEnd::Left, vec![value], false)?;
+        let events = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|push| (push[2].clone(), push[3].clone()))
+            .collect::<Vec<_>>();
+        let event = |channel: &str, message: &str| -> (RespFrame, RespFrame) {
+            (
+                RespBulkString::new(channel).into(),
+                RespBulkString::new(message).into(),
+            )
+        };
+        // lists aren't asked for, and the set going away is a generic event
+        assert_eq!(
+            events,
+            [
+                event("__keyevent@2__:sadd", "k"),
+                event("__keyevent@2__:srem", "k"),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use tokio::sync::mpsc;
+
+use crate::backend::glob::glob_match;
+use crate::backend::Backend;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+/// Where a connection receives what it didn't ask for, such as pub/sub
+/// messages. Each item is the content of one push.
+pub type PushSender = mpsc::UnboundedSender<Vec<RespFrame>>;
+
+/// What a subscription is to: a channel, the channels matching a pattern,
+/// or a shard channel. Shard channels are a namespace of their own, hashed
+/// to slots like keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubscriptionKind {
+    Channel,
+    Pattern,
+    Shard,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct PubSub {
+    senders: HashMap<u64, PushSender>,
+    // clients subscribed to each channel or shard channel
+    subscribers: HashMap<(SubscriptionKind, String), HashSet<u64>>,
+    // clients subscribed to each pattern, kept apart so a publish only
+    // walks the patterns
+    patterns: HashMap<(SubscriptionKind, String), HashSet<u64>>,
+    // what each client is subscribed to
+    clients: HashMap<u64, HashSet<(SubscriptionKind, String)>>,
+}
+
+impl PubSub {
+    fn subscribers(
+        &self,
+        kind: SubscriptionKind,
+    ) -> &HashMap<(SubscriptionKind, String), HashSet<u64>> {
+        match kind {
+            SubscriptionKind::Pattern => &self.patterns,
+            _ => &self.subscribers,
+        }
+    }
+
+    fn subscribers_mut(
+        &mut self,
+        kind: SubscriptionKind,
+    ) -> &mut HashMap<(SubscriptionKind, String), HashSet<u64>> {
+        match kind {
+            SubscriptionKind::Pattern => &mut self.patterns,
+            _ => &mut self.subscribers,
+        }
+    }
+
+    // the count SUBSCRIBE and friends reply with: channels and patterns
+    // together, shard channels on their own
+    fn count(&self, client_id: u64, kind: SubscriptionKind) -> usize {
+        let shard = kind == SubscriptionKind::Shard;
+        self.clients.get(&client_id).map_or(0, |subs| {
+            subs.iter()
+                .filter(|(k, _)| (*k == SubscriptionKind::Shard) == shard)
+                .count()
+        })
+    }
+
+    fn remove(&mut self, client_id: u64, sub: &(SubscriptionKind, String)) -> bool {
+        let Some(subs) = self.clients.get_mut(&client_id) else {
+            return false;
+        };
+        if !subs.remove(sub) {
+            return false;
+        }
+        if subs.is_empty() {
+            self.clients.remove(&client_id);
+            self.senders.remove(&client_id);
+        }
+        let subscribers = self.subscribers_mut(sub.0);
+        if let Some(clients) = subscribers.get_mut(sub) {
+            clients.remove(&client_id);
+            if clients.is_empty() {
+                subscribers.remove(sub);
+            }
+        }
+        true
+    }
+
+    fn send(&self, client_id: u64, items: Vec<RespFrame>) {
+        if let Some(sender) = self.senders.get(&client_id) {
+            // a closed connection is cleaned up when its session ends
+            let _ = sender.send(items);
+        }
+    }
+}
+
+impl Backend {
+    /// Subscribes the client to `names`, returning each with the number of
+    /// subscriptions the client has after it.
+    pub fn subscribe(
+        &self,
+        client_id: u64,
+        sender: &PushSender,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+    ) -> Vec<(String, usize)> {
+        let mut pubsub = self.server.pubsub.lock().unwrap();
+        pubsub
+            .senders
+            .entry(client_id)
+            .or_insert_with(|| sender.clone());
+        names
+            .into_iter()
+            .map(|name| {
+                let sub = (kind, name.clone());
+                pubsub
+                    .subscribers_mut(kind)
+                    .entry(sub.clone())
+                    .or_default()
+                    .insert(client_id);
+                pubsub.clients.entry(client_id).or_default().insert(sub);
+                (name, pubsub.count(client_id, kind))
+            })
+            .collect()
+    }
+
+    /// Unsubscribes the client from `names`, or from everything of `kind`
+    /// when empty. Each name comes back with the number of subscriptions
+    /// left, `None` when there was nothing to unsubscribe from.
+    pub fn unsubscribe(
+        &self,
+        client_id: u64,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+    ) -> Vec<(Option<String>, usize)> {
+        let mut pubsub = self.server.pubsub.lock().unwrap();
+        let names = if names.is_empty() {
+            let subs = pubsub.clients.get(&client_id);
+            subs.into_iter()
+                .flatten()
+                .filter(|(k, _)| *k == kind)
+                .map(|(_, name)| name.clone())
+                .collect()
+        } else {
+            names
+        };
+        if names.is_empty() {
+            return vec![(None, pubsub.count(client_id, kind))];
+        }
+        names
+            .into_iter()
+            .map(|name| {
+                pubsub.remove(client_id, &(kind, name.clone()));
+                (Some(name), pubsub.count(client_id, kind))
+            })
+            .collect()
+    }
+
+    /// Drops every subscription of a client that went away.
+    pub fn unsubscribe_all(&self, client_id: u64) {
+        let mut pubsub = self.server.pubsub.lock().unwrap();
+        let subs = pubsub.clients.get(&client_id).cloned().unwrap_or_default();
+        for sub in subs {
+            pubsub.remove(client_id, &sub);
+        }
+    }
+
+    /// Number of channels, patterns and shard channels the client is
+    /// subscribed to.
+    pub fn subscriptions(&self, client_id: u64) -> usize {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        pubsub.clients.get(&client_id).map_or(0, |subs| subs.len())
+    }
+
+    /// Sends `message` to the subscribers of `channel` and of the patterns
+    /// matching it, returning how many got it.
+    pub fn publish(&self, channel: &str, message: RespFrame) -> usize {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        let mut receivers = 0;
+        let name: RespFrame = RespBulkString::new(channel).into();
+        if let Some(clients) = pubsub
+            .subscribers
+            .get(&(SubscriptionKind::Channel, channel.to_string()))
+        {
+            for &client_id in clients {
+                let kind = RespBulkString::new("message").into();
+                pubsub.send(client_id, vec![kind, name.clone(), message.clone()]);
+                receivers += 1;
+            }
+        }
+        for ((_, pattern), clients) in &pubsub.patterns {
+            if !glob_match(pattern.as_bytes(), channel.as_bytes()) {
+                continue;
+            }
+            for &client_id in clients {
+                let kind = RespBulkString::new("pmessage").into();
+                let pattern = RespBulkString::new(pattern.as_str()).into();
+                pubsub.send(
+                    client_id,
+                    vec![kind, pattern, name.clone(), message.clone()],
+                );
+                receivers += 1;
+            }
+        }
+        receivers
+    }
+
+    /// Sends `message` to the subscribers of shard channel `channel`. Every
+    /// slot is served by this node, so that's every subscriber there is.
+    pub fn spublish(&self, channel: &str, message: RespFrame) -> usize {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        let Some(clients) = pubsub
+            .subscribers
+            .get(&(SubscriptionKind::Shard, channel.to_string()))
+        else {
+            return 0;
+        };
+        for &client_id in clients {
+            let items = vec![
+                RespBulkString::new("smessage").into(),
+                RespBulkString::new(channel).into(),
+                message.clone(),
+            ];
+            pubsub.send(client_id, items);
+        }
+        clients.len()
+    }
+
+    /// Sends `message` on `channel` to the client alone, if it's subscribed
+    /// to it. Returns whether it was.
+    pub(crate) fn send_message(&self, client_id: u64, channel: &str, message: RespFrame) -> bool {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        let subscribed = pubsub
+            .subscribers
+            .get(&(SubscriptionKind::Channel, channel.to_string()))
+            .is_some_and(|clients| clients.contains(&client_id));
+        if subscribed {
+            let items = vec![
+                RespBulkString::new("message").into(),
+                RespBulkString::new(channel).into(),
+                message,
+            ];
+            pubsub.send(client_id, items);
+        }
+        subscribed
+    }
+
+    /// The channels of `kind` with subscribers, only those matching
+    /// `pattern` if any.
+    pub fn pubsub_channels(&self, kind: SubscriptionKind, pattern: Option<&str>) -> Vec<String> {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        pubsub
+            .subscribers(kind)
+            .keys()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, name)| name)
+            .filter(|name| pattern.is_none_or(|p| glob_match(p.as_bytes(), name.as_bytes())))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// The number of subscribers of each channel of `kind`.
+    pub fn pubsub_numsub(
+        &self,
+        kind: SubscriptionKind,
+        channels: Vec<String>,
+    ) -> Vec<(String, usize)> {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        channels
+            .into_iter()
+            .map(|channel| {
+                let n = pubsub
+                    .subscribers(kind)
+                    .get(&(kind, channel.clone()))
+                    .map_or(0, |clients| clients.len());
+                (channel, n)
+            })
+            .collect()
+    }
+
+    /// The number of patterns with subscribers.
+    pub fn pubsub_numpat(&self) -> usize {
+        let pubsub = self.server.pubsub.lock().unwrap();
+        pubsub.patterns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish() {
+        let backend = Backend::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let subscribed = backend.subscribe(
+            1,
+            &tx,
+            SubscriptionKind::Channel,
+            vec!["news".to_string(), "sport".to_string()],
+        );
+        assert_eq!(
+            subscribed,
+            [("news".to_string(), 1), ("sport".to_string(), 2)]
+        );
+        backend.subscribe(1, &tx, SubscriptionKind::Pattern, vec!["n*".to_string()]);
+        assert_eq!(backend.pubsub_numpat(), 1);
+        assert_eq!(
+            backend.pubsub_channels(SubscriptionKind::Channel, Some("s*")),
+            ["sport"]
+        );
+
+        let message: RespFrame = RespBulkString::new("hi").into();
+        assert_eq!(backend.publish("news", message.clone()), 2);
+        assert_eq!(
+            rx.try_recv().unwrap()[0],
+            RespBulkString::new("message").into()
+        );
+        assert_eq!(rx.try_recv().unwrap()[1], RespBulkString::new("n*").into());
+        assert_eq!(backend.publish("weather", message.clone()), 0);
+
+        let left = backend.unsubscribe(1, SubscriptionKind::Channel, vec![]);
+        assert_eq!(left.len(), 2);
+        assert_eq!(backend.subscriptions(1), 1);
+        backend.unsubscribe_all(1);
+        assert_eq!(backend.subscriptions(1), 0);
+        assert_eq!(
+            backend.unsubscribe(1, SubscriptionKind::Pattern, vec![]),
+            [(None, 0)]
+        );
+        assert_eq!(backend.publish("news", message), 0);
+    }
+
+    #[test]
+    fn test_spublish() {
+        let backend = Backend::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        backend.subscribe(1, &tx, SubscriptionKind::Channel, vec!["a".to_string()]);
+        // shard channels are counted apart from the others
+        assert_eq!(
+            backend.subscribe(1, &tx, SubscriptionKind::Shard, vec!["a".to_string()]),
+            [("a".to_string(), 1)]
+        );
+        assert_eq!(backend.subscriptions(1), 2);
+
+        let message: RespFrame = RespBulkString::new("hi").into();
+        assert_eq!(backend.spublish("a", message.clone()), 1);
+        assert_eq!(
+            rx.try_recv().unwrap()[0],
+            RespBulkString::new("smessage").into()
+        );
+        assert!(rx.try_recv().is_err());
+        assert_eq!(
+            backend.pubsub_numsub(SubscriptionKind::Shard, vec!["a".to_string()]),
+            [("a".to_string(), 1)]
+        );
+        assert_eq!(
+            backend.unsubscribe(1, SubscriptionKind::Shard, vec![]),
+            [(Some("a".to_string()), 0)]
+        );
+        assert_eq!(backend.spublish("a", message), 0);
+        assert_eq!(backend.subscriptions(1), 1);
+    }
+}
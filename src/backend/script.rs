@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+
+use crate::backend::Backend;
+
+/// Milliseconds a script runs before other clients are told the server is
+/// busy, unless `busy-reply-threshold` says otherwise.
+pub const DEFAULT_BUSY_REPLY_THRESHOLD: u64 = 5000;
+
+#[derive(Debug, Default)]
+pub(crate) struct Scripts {
+    // script bodies by their SHA1, see `SCRIPT LOAD`
+    cache: HashMap<String, String>,
+    running: Option<RunningScript>,
+}
+
+#[derive(Debug)]
+struct RunningScript {
+    started: Instant,
+    // a script that wrote can't be killed, it would leave half its writes
+    wrote: bool,
+    killed: bool,
+}
+
+/// Held while a script runs, see [`Backend::start_script`].
+#[derive(Debug)]
+pub struct ScriptGuard<'a> {
+    backend: &'a Backend,
+}
+
+impl Drop for ScriptGuard<'_> {
+    fn drop(&mut self) {
+        self.backend.server.scripts.lock().unwrap().running = None;
+    }
+}
+
+/// The SHA1 scripts go by, in lowercase hex.
+pub fn sha1_hex(body: &[u8]) -> String {
+    sha1_smol::Sha1::from(body).digest().to_string()
+}
+
+impl Backend {
+    /// Caches a script body, returning the SHA1 it goes by.
+    pub fn script_load(&self, body: String) -> String {
+        let sha = sha1_hex(body.as_bytes());
+        let mut scripts = self.server.scripts.lock().unwrap();
+        scripts.cache.entry(sha.clone()).or_insert(body);
+        sha
+    }
+
+    pub fn script_body(&self, sha: &str) -> Option<String> {
+        let scripts = self.server.scripts.lock().unwrap();
+        scripts.cache.get(&sha.to_ascii_lowercase()).cloned()
+    }
+
+    pub fn script_exists(&self, shas: &[String]) -> Vec<bool> {
+        let scripts = self.server.scripts.lock().unwrap();
+        shas.iter()
+            .map(|sha| scripts.cache.contains_key(&sha.to_ascii_lowercase()))
+            .collect()
+    }
+
+    pub fn script_flush(&self) {
+        self.server.scripts.lock().unwrap().cache.clear();
+    }
+
+    /// Marks a script as running until the guard is dropped.
+    pub fn start_script(&self) -> ScriptGuard<'_> {
+        self.server.scripts.lock().unwrap().running = Some(RunningScript {
+            started: Instant::now(),
+            wrote: false,
+            killed: false,
+        });
+        ScriptGuard { backend: self }
+    }
+
+    /// Notes the running script is about to write.
+    pub fn script_wrote(&self) {
+        if let Some(running) = &mut self.server.scripts.lock().unwrap().running {
+            running.wrote = true;
+        }
+    }
+
+    /// Whether `SCRIPT KILL` asked the running script to stop.
+    pub fn script_killed(&self) -> bool {
+        let scripts = self.server.scripts.lock().unwrap();
+        scripts
+            .running
+            .as_ref()
+            .is_some_and(|running| running.killed)
+    }
+
+    /// Asks the running script to stop, which only one that didn't write
+    /// yet may be.
+    pub fn kill_script(&self) -> anyhow::Result<()> {
+        let mut scripts = self.server.scripts.lock().unwrap();
+        let Some(running) = &mut scripts.running else {
+            bail!("NOTBUSY No scripts in execution right now.");
+        };
+        if running.wrote {
+            bail!("UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.");
+        }
+        running.killed = true;
+        Ok(())
+    }
+
+    /// Whether a script has run for longer than `busy-reply-threshold`, so
+    /// other clients are turned away rather than kept waiting.
+    pub fn script_busy(&self) -> bool {
+        let threshold = Duration::from_millis(self.busy_reply_threshold());
+        let scripts = self.server.scripts.lock().unwrap();
+        scripts
+            .running
+            .as_ref()
+            .is_some_and(|running| running.started.elapsed() > threshold)
+    }
+
+    /// The `busy-reply-threshold` setting, in milliseconds.
+    pub fn busy_reply_threshold(&self) -> u64 {
+        self.server.busy_reply_threshold.load(Ordering::Relaxed)
+    }
+
+    pub fn set_busy_reply_threshold(&self, ms: u64) {
+        self.server
+            .busy_reply_threshold
+            .store(ms, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts() {
+        let backend = Backend::default();
+        let sha = backend.script_load("return 1".to_string());
+        assert_eq!(sha, "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        assert_eq!(
+            backend.script_exists(&[sha.to_ascii_uppercase(), "x".to_string()]),
+            [true, false]
+        );
+
+        assert!(backend.kill_script().is_err());
+        {
+            let _running = backend.start_script();
+            assert!(!backend.script_killed());
+            backend.kill_script().unwrap();
+            assert!(backend.script_killed());
+        }
+        {
+            let _running = backend.start_script();
+            backend.script_wrote();
+            assert!(backend.kill_script().is_err());
+            backend.set_busy_reply_threshold(0);
+            std::thread::sleep(Duration::from_millis(2));
+            assert!(backend.script_busy());
+        }
+        assert!(!backend.script_busy());
+        backend.script_flush();
+        assert_eq!(backend.script_body(&sha), None);
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+
+use dashmap::mapref::one::Ref;
+use rand::seq::index;
+use rand::Rng;
+
+use crate::backend::keyspace::{key_matches, KeyType, ScanTarget};
+use crate::backend::notify::EventClass;
+use crate::backend::{frame_bytes, Backend};
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl SetOp {
+    fn store_event(self) -> &'static str {
+        match self {
+            SetOp::Inter => "sinterstore",
+            SetOp::Union => "sunionstore",
+            SetOp::Diff => "sdiffstore",
+        }
+    }
+}
+
+/// A set stored in `Db::set`. Members live in a vector so a uniform
+/// random one can be picked in O(1), and `index` maps each member to its
+/// position for O(1) lookups. Removal swaps the last member into the hole.
+#[derive(Debug, Default, Clone)]
+pub struct SetValue {
+    members: Vec<RespFrame>,
+    index: HashMap<RespFrame, usize>,
+}
+
+impl SetValue {
+    pub fn insert(&mut self, member: RespFrame) -> bool {
+        if self.index.contains_key(&member) {
+            return false;
+        }
+        self.index.insert(member.clone(), self.members.len());
+        self.members.push(member);
+        true
+    }
+
+    pub fn remove(&mut self, member: &RespFrame) -> bool {
+        let Some(pos) = self.index.remove(member) else {
+            return false;
+        };
+        self.members.swap_remove(pos);
+        if let Some(moved) = self.members.get(pos) {
+            self.index.insert(moved.clone(), pos);
+        }
+        true
+    }
+
+    pub fn contains(&self, member: &RespFrame) -> bool {
+        self.index.contains_key(member)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RespFrame> {
+        self.members.iter()
+    }
+
+    pub fn random(&self) -> Option<&RespFrame> {
+        if self.is_empty() {
+            return None;
+        }
+        let pos = rand::thread_rng().gen_range(0..self.len());
+        Some(&self.members[pos])
+    }
+
+    /// `count` distinct random members, or all of them if there are fewer.
+    pub fn random_distinct(&self, count: usize) -> Vec<RespFrame> {
+        if count >= self.len() {
+            return self.members.clone();
+        }
+        index::sample(&mut rand::thread_rng(), self.len(), count)
+            .into_iter()
+            .map(|pos| self.members[pos].clone())
+            .collect()
+    }
+
+    pub fn pop_random(&mut self) -> Option<RespFrame> {
+        let member = self.random()?.clone();
+        self.remove(&member);
+        Some(member)
+    }
+}
+
+impl FromIterator<RespFrame> for SetValue {
+    fn from_iter<T: IntoIterator<Item = RespFrame>>(iter: T) -> Self {
+        let mut set = SetValue::default();
+        for member in iter {
+            set.insert(member);
+        }
+        set
+    }
+}
+
+impl Backend {
+    pub fn sadd(&self, key: &str, members: Vec<RespFrame>) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::Set)?;
+        let added = {
+            let mut inner = self.set.entry(key.to_string()).or_default();
+            members
+                .into_iter()
+                .filter(|m| inner.insert(m.clone()))
+                .count()
+        };
+        if added > 0 {
+            self.notify(EventClass::Set, "sadd", key);
+        }
+        Ok(added as i64)
+    }
+
+    /// Removes the members and drops the set once its last member is gone.
+    pub fn srem(&self, key: &str, members: &[RespFrame]) -> i64 {
+        let removed = match self.set.get_mut(key) {
+            Some(mut inner) => members.iter().filter(|m| inner.remove(m)).count(),
+            None => return 0,
+        };
+        if removed > 0 {
+            self.notify(EventClass::Set, "srem", key);
+        }
+        self.drop_empty_set(key);
+        removed as i64
+    }
+
+    pub fn sismember(&self, key: &str, member: &RespFrame) -> i64 {
+        let Some(inner) = self.set.get(key) else {
+            return 0;
+        };
+        if inner.contains(member) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn smismember(&self, key: &str, members: &[RespFrame]) -> Vec<i64> {
+        let Some(inner) = self.set.get(key) else {
+            return vec![0; members.len()];
+        };
+        members.iter().map(|m| inner.contains(m) as i64).collect()
+    }
+
+    pub fn smembers(&self, key: &str) -> Vec<RespFrame> {
+        self.set
+            .get(key)
+            .map(|v| v.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// One page of `SSCAN`, see [`scan_page`].
+    pub fn sscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> (u64, Vec<RespFrame>) {
+        let Some(inner) = self.set.get(key) else {
+            return (0, vec![]);
+        };
+        let target = ScanTarget::Set(key.to_string());
+        let (cursor, page) = self.scan_names(target, cursor, count, || {
+            inner
+                .iter()
+                .map(|member| frame_bytes(member).unwrap_or_default().to_vec())
+                .collect()
+        });
+        let page = page
+            .into_iter()
+            .filter(|member| pattern.is_none_or(|p| key_matches(p, member)))
+            .map(|member| RespBulkString::new(member).into())
+            .filter(|member| inner.contains(member))
+            .collect();
+        (cursor, page)
+    }
+
+    pub fn scard(&self, key: &str) -> i64 {
+        self.set.get(key).map(|v| v.len() as i64).unwrap_or(0)
+    }
+
+    /// Removes and returns up to `count` random members.
+    pub fn spop(&self, key: &str, count: usize) -> Vec<RespFrame> {
+        let popped = match self.set.get_mut(key) {
+            Some(mut inner) => (0..count)
+                .map_while(|_| inner.pop_random())
+                .collect::<Vec<_>>(),
+            None => return vec![],
+        };
+        if !popped.is_empty() {
+            self.notify(EventClass::Set, "spop", key);
+        }
+        self.drop_empty_set(key);
+        popped
+    }
+
+    /// Without a count returns at most one member. A positive count returns
+    /// distinct members, a negative one may repeat members and returns
+    /// exactly `-count` of them.
+    pub fn srandmember(&self, key: &str, count: Option<i64>) -> Vec<RespFrame> {
+        let Some(inner) = self.set.get(key) else {
+            return vec![];
+        };
+        match count {
+            None => inner.random().cloned().into_iter().collect(),
+            Some(count) if count >= 0 => inner.random_distinct(count as usize),
+            Some(count) => (0..count.unsigned_abs())
+                .filter_map(|_| inner.random().cloned())
+                .collect(),
+        }
+    }
+
+    pub fn smove(&self, source: &str, destination: &str, member: RespFrame) -> anyhow::Result<i64> {
+        if !self.set.contains_key(source) {
+            return Ok(0);
+        }
+        self.check_type(destination, KeyType::Set)?;
+        if source == destination {
+            return Ok(self.sismember(source, &member));
+        }
+        // never hold two entries at once, they may share a shard
+        let removed = match self.set.get_mut(source) {
+            Some(mut inner) => inner.remove(&member),
+            None => false,
+        };
+        if !removed {
+            return Ok(0);
+        }
+        self.notify(EventClass::Set, "srem", source);
+        self.drop_empty_set(source);
+        let added = self
+            .set
+            .entry(destination.to_string())
+            .or_default()
+            .insert(member);
+        if added {
+            self.notify(EventClass::Set, "sadd", destination);
+        }
+        Ok(1)
+    }
+
+    pub fn sinter(&self, keys: &[String]) -> Vec<RespFrame> {
+        self.set_algebra(SetOp::Inter, keys).members
+    }
+
+    pub fn sunion(&self, keys: &[String]) -> Vec<RespFrame> {
+        self.set_algebra(SetOp::Union, keys).members
+    }
+
+    pub fn sdiff(&self, keys: &[String]) -> Vec<RespFrame> {
+        self.set_algebra(SetOp::Diff, keys).members
+    }
+
+    /// Stores the result of `op` over `keys` in `destination`, replacing
+    /// whatever it held, and returns its cardinality. An empty result
+    /// deletes `destination`.
+    pub fn sstore(&self, op: SetOp, destination: &str, keys: &[String]) -> i64 {
+        let result = self.set_algebra(op, keys);
+        let len = result.len() as i64;
+        self.clear_other_type(destination, KeyType::Set);
+        if result.is_empty() {
+            if self.set.remove(destination).is_some() {
+                self.notify(EventClass::Generic, "del", destination);
+            }
+        } else {
+            self.set.insert(destination.to_string(), result);
+            self.notify(EventClass::Set, op.store_event(), destination);
+        }
+        len
+    }
+
+    // drops the set once its last member is gone, deleting the key
+    fn drop_empty_set(&self, key: &str) {
+        if self.set.remove_if(key, |_, v| v.is_empty()).is_some() {
+            self.notify(EventClass::Generic, "del", key);
+        }
+    }
+
+    /// Cardinality of the intersection, stopping early once `limit` is
+    /// reached. A `limit` of 0 means no limit.
+    pub fn sintercard(&self, keys: &[String], limit: usize) -> i64 {
+        let Some(sets) = self.sets_smallest_first(keys) else {
+            return 0;
+        };
+        let (first, rest) = sets.split_first().expect("keys is not empty");
+        let matches = first.iter().filter(|m| rest.iter().all(|s| s.contains(m)));
+        let count = if limit == 0 {
+            matches.count()
+        } else {
+            matches.take(limit).count()
+        };
+        count as i64
+    }
+
+    // callers hold the locks of `keys`, so no set changes underneath
+    fn set_algebra(&self, op: SetOp, keys: &[String]) -> SetValue {
+        match op {
+            SetOp::Inter => {
+                let Some(sets) = self.sets_smallest_first(keys) else {
+                    return SetValue::default();
+                };
+                let (first, rest) = sets.split_first().expect("keys is not empty");
+                first
+                    .iter()
+                    .filter(|m| rest.iter().all(|s| s.contains(m)))
+                    .cloned()
+                    .collect()
+            }
+            SetOp::Union => keys
+                .iter()
+                .filter_map(|k| self.set.get(k))
+                .flat_map(|s| s.iter().cloned().collect::<Vec<_>>())
+                .collect(),
+            SetOp::Diff => {
+                let Some(first) = self.set.get(&keys[0]) else {
+                    return SetValue::default();
+                };
+                let rest = keys[1..]
+                    .iter()
+                    .filter_map(|k| self.set.get(k))
+                    .collect::<Vec<_>>();
+                first
+                    .iter()
+                    .filter(|m| !rest.iter().any(|s| s.contains(m)))
+                    .cloned()
+                    .collect()
+            }
+        }
+    }
+
+    // `None` when any of the sets is missing, so the intersection is empty
+    fn sets_smallest_first(&self, keys: &[String]) -> Option<Vec<Ref<'_, String, SetValue>>> {
+        let mut sets = keys
+            .iter()
+            .map(|k| self.set.get(k))
+            .collect::<Option<Vec<_>>>()?;
+        sets.sort_by_key(|s| s.len());
+        Some(sets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resp::bulkstring::RespBulkString;
+
+    use super::*;
+
+    fn members(names: &[&str]) -> Vec<RespFrame> {
+        names
+            .iter()
+            .map(|s| RespBulkString::new(s).into())
+            .collect()
+    }
+
+    #[test]
+    fn test_set_value_swap_remove() {
+        let mut set = members(&["a", "b", "c"]).into_iter().collect::<SetValue>();
+        assert!(set.remove(&RespBulkString::new("a").into()));
+        assert!(!set.remove(&RespBulkString::new("a").into()));
+        assert!(set.contains(&RespBulkString::new("c").into()));
+        assert!(set.remove(&RespBulkString::new("c").into()));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&members(&["b"])[0]]);
+        assert_eq!(set.random_distinct(5).len(), 1);
+    }
+
+    #[test]
+    fn test_spop_srandmember() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        assert_eq!(backend.sadd("k", members(&["a", "b", "c", "a"]))?, 3);
+        assert_eq!(backend.srandmember("k", Some(2)).len(), 2);
+        assert_eq!(backend.srandmember("k", Some(10)).len(), 3);
+        assert_eq!(backend.srandmember("k", Some(-10)).len(), 10);
+        assert_eq!(backend.srandmember("nope", Some(-10)).len(), 0);
+
+        let popped = backend.spop("k", 2);
+        assert_eq!(popped.len(), 2);
+        assert_eq!(backend.smismember("k", &popped), vec![0, 0]);
+        assert_eq!(backend.spop("k", 2).len(), 1);
+        assert!(backend.set.get("k").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.sadd("src", members(&["a"]))?;
+        let a = members(&["a"]).remove(0);
+        assert_eq!(backend.smove("src", "src", a.clone())?, 1);
+        assert_eq!(backend.smove("src", "dst", a.clone())?, 1);
+        assert_eq!(backend.smove("src", "dst", a.clone())?, 0);
+        assert_eq!(backend.scard("src"), 0);
+        assert_eq!(backend.smembers("dst"), vec![a.clone()]);
+
+        let mut backend = Backend::default();
+        backend.sadd("src", members(&["a"]))?;
+        backend.set("dst", RespBulkString::new("v").into());
+        assert!(backend.smove("src", "dst", a.clone()).is_err());
+        assert!(backend.sadd("dst", vec![a]).is_err());
+        assert_eq!(backend.scard("src"), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_algebra() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.sadd("a", members(&["1", "2", "3", "4"]))?;
+        backend.sadd("b", members(&["2", "3", "5"]))?;
+        backend.sadd("c", members(&["3"]))?;
+        let keys = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let sorted = |mut v: Vec<RespFrame>| {
+            v.sort();
+            v
+        };
+
+        assert_eq!(backend.sinter(&keys(&["a", "b", "c"])), members(&["3"]));
+        assert!(backend.sinter(&keys(&["a", "nope"])).is_empty());
+        assert_eq!(
+            sorted(backend.sunion(&keys(&["b", "c", "nope"]))),
+            members(&["2", "3", "5"])
+        );
+        assert_eq!(
+            sorted(backend.sdiff(&keys(&["a", "b", "nope"]))),
+            members(&["1", "4"])
+        );
+        assert_eq!(backend.sintercard(&keys(&["a", "b"]), 0), 2);
+        assert_eq!(backend.sintercard(&keys(&["a", "b"]), 1), 1);
+
+        assert_eq!(backend.sstore(SetOp::Inter, "c", &keys(&["a", "b"])), 2);
+        assert_eq!(sorted(backend.smembers("c")), members(&["2", "3"]));
+        assert_eq!(backend.sstore(SetOp::Diff, "c", &keys(&["c", "a"])), 0);
+        assert!(backend.set.get("c").is_none());
+        Ok(())
+    }
+}
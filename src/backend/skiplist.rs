@@ -0,0 +1,347 @@
+use rand::Rng;
+
+const MAX_LEVEL: usize = 32;
+const HEAD: usize = 0;
+
+/// A skiplist ordered by `(score, member)`, the index of a sorted set. Every
+/// link records how many nodes it skips, so ranks are found in O(log n).
+/// Nodes live in an arena and are referred to by index.
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    level: usize,
+    len: usize,
+    tail: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: String,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Level>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Level {
+    forward: Option<usize>,
+    span: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: String::new(),
+            score: 0.0,
+            backward: None,
+            levels: vec![Level::default(); MAX_LEVEL],
+        };
+        Self {
+            nodes: vec![head],
+            free: vec![],
+            level: 1,
+            len: 0,
+            tail: None,
+        }
+    }
+}
+
+impl SkipList {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts a member that is not in the list yet.
+    pub fn insert(&mut self, score: f64, member: String) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0usize; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if !self.before(next, score, &member) {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let x = self.alloc(Node {
+            member,
+            score,
+            backward: (update[0] != HEAD).then_some(update[0]),
+            levels: vec![Level::default(); level],
+        });
+        for i in 0..level {
+            let prev = self.nodes[update[i]].levels[i];
+            self.nodes[x].levels[i] = Level {
+                forward: prev.forward,
+                span: prev.span - (rank[0] - rank[i]),
+            };
+            self.nodes[update[i]].levels[i] = Level {
+                forward: Some(x),
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        for (i, &prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[prev].levels[i].span += 1;
+        }
+        match self.nodes[x].levels[0].forward {
+            Some(next) => self.nodes[next].backward = Some(x),
+            None => self.tail = Some(x),
+        }
+        self.len += 1;
+    }
+
+    /// Removes the node holding exactly `(score, member)`.
+    pub fn remove(&mut self, score: f64, member: &str) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if !self.before(next, score, member) {
+                    break;
+                }
+                x = next;
+            }
+            update[i] = x;
+        }
+        let Some(x) = self.nodes[x].levels[0].forward else {
+            return false;
+        };
+        if self.nodes[x].score != score || self.nodes[x].member != member {
+            return false;
+        }
+
+        for (i, &prev) in update.iter().enumerate().take(self.level) {
+            let level = self.nodes[prev].levels[i];
+            if level.forward == Some(x) {
+                let removed = self.nodes[x].levels[i];
+                self.nodes[prev].levels[i] = Level {
+                    forward: removed.forward,
+                    span: level.span + removed.span - 1,
+                };
+            } else {
+                self.nodes[prev].levels[i].span -= 1;
+            }
+        }
+        match self.nodes[x].levels[0].forward {
+            Some(next) => self.nodes[next].backward = self.nodes[x].backward,
+            None => self.tail = self.nodes[x].backward,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+        self.nodes[x].member = String::new();
+        self.nodes[x].levels = vec![];
+        self.free.push(x);
+        true
+    }
+
+    /// 0-based rank of `(score, member)`.
+    pub fn rank(&self, score: f64, member: &str) -> Option<usize> {
+        let mut x = HEAD;
+        let mut rank = 0;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                let node = &self.nodes[next];
+                if node.score > score || (node.score == score && node.member.as_str() > member) {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if x != HEAD && self.nodes[x].score == score && self.nodes[x].member == member {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    /// The node at 0-based `rank`.
+    pub fn by_rank(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut x = HEAD;
+        let mut traversed = 0;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    /// The first node, with its rank, for which `pred` holds. `pred` must be
+    /// false for a prefix of the list and true for the rest.
+    pub fn first_where(&self, pred: impl Fn(f64, &str) -> bool) -> Option<(usize, usize)> {
+        let mut x = HEAD;
+        let mut traversed = 0;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if pred(self.nodes[next].score, &self.nodes[next].member) {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+        }
+        self.nodes[x].levels[0]
+            .forward
+            .map(|next| (traversed, next))
+    }
+
+    /// The last node, with its rank, for which `pred` holds. `pred` must be
+    /// true for a prefix of the list and false for the rest.
+    pub fn last_where(&self, pred: impl Fn(f64, &str) -> bool) -> Option<(usize, usize)> {
+        let mut x = HEAD;
+        let mut traversed = 0;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].forward {
+                if !pred(self.nodes[next].score, &self.nodes[next].member) {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+        }
+        (x != HEAD).then(|| (traversed - 1, x))
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.nodes[HEAD].levels[0].forward
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.tail
+    }
+
+    pub fn next(&self, node: usize) -> Option<usize> {
+        self.nodes[node].levels[0].forward
+    }
+
+    pub fn prev(&self, node: usize) -> Option<usize> {
+        self.nodes[node].backward
+    }
+
+    pub fn get(&self, node: usize) -> (&str, f64) {
+        (&self.nodes[node].member, self.nodes[node].score)
+    }
+
+    // whether `node` sorts strictly before `(score, member)`
+    fn before(&self, node: usize, score: f64, member: &str) -> bool {
+        let node = &self.nodes[node];
+        node.score < score || (node.score == score && node.member.as_str() < member)
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+}
+
+fn random_level() -> usize {
+    let mut rng = rand::thread_rng();
+    let mut level = 1;
+    while level < MAX_LEVEL && rng.gen::<f64>() < 0.25 {
+        level += 1;
+    }
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(list: &SkipList) -> Vec<(String, f64)> {
+        let mut ret = vec![];
+        let mut node = list.first();
+        while let Some(x) = node {
+            let (member, score) = list.get(x);
+            ret.push((member.to_string(), score));
+            node = list.next(x);
+        }
+        ret
+    }
+
+    #[test]
+    fn test_skiplist_order_and_rank() {
+        let mut list = SkipList::default();
+        for i in (0..200).rev() {
+            list.insert((i / 2) as f64, format!("m{:03}", i));
+        }
+        assert_eq!(list.len(), 200);
+        let all = members(&list);
+        assert!(all
+            .windows(2)
+            .all(|w| (w[0].1, &w[0].0) < (w[1].1, &w[1].0)));
+        for (rank, (member, score)) in all.iter().enumerate() {
+            assert_eq!(list.rank(*score, member), Some(rank));
+            assert_eq!(
+                list.get(list.by_rank(rank).unwrap()),
+                (member.as_str(), *score)
+            );
+        }
+        assert_eq!(list.rank(3.0, "nope"), None);
+        assert_eq!(list.by_rank(200), None);
+
+        for i in (0..200).step_by(3) {
+            assert!(list.remove((i / 2) as f64, &format!("m{:03}", i)));
+        }
+        assert!(!list.remove(0.0, "m000"));
+        let all = members(&list);
+        assert_eq!(list.len(), all.len());
+        for (rank, (member, score)) in all.iter().enumerate() {
+            assert_eq!(list.rank(*score, member), Some(rank));
+        }
+        let (last, score) = list.get(list.last().unwrap());
+        assert_eq!((last, score), ("m199", 99.0));
+        assert_eq!(list.get(list.prev(list.last().unwrap()).unwrap()).0, "m197");
+    }
+
+    #[test]
+    fn test_skiplist_first_last_where() {
+        let mut list = SkipList::default();
+        for i in 0..10 {
+            list.insert(i as f64, i.to_string());
+        }
+        let (rank, node) = list.first_where(|score, _| score >= 3.5).unwrap();
+        assert_eq!((rank, list.get(node).1), (4, 4.0));
+        let (rank, node) = list.last_where(|score, _| score <= 3.5).unwrap();
+        assert_eq!((rank, list.get(node).1), (3, 3.0));
+        assert!(list.first_where(|score, _| score > 9.0).is_none());
+        assert!(list.last_where(|score, _| score < 0.0).is_none());
+    }
+}
@@ -0,0 +1,44 @@
+/// Number of hash slots the keyspace is split into, as in Redis Cluster.
+pub const SLOTS: u16 = 16384;
+
+/// The slot a key, or a shard channel, belongs to. Only the part between
+/// the first `{` and the next `}` is hashed when it is not empty, so keys
+/// sharing such a hash tag land in the same slot.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let tagged = key.iter().position(|&b| b == b'{').and_then(|start| {
+        let len = key[start + 1..].iter().position(|&b| b == b'}')?;
+        (len > 0).then(|| &key[start + 1..start + 1 + len])
+    });
+    crc16(tagged.unwrap_or(key)) % SLOTS
+}
+
+// CRC16-CCITT (XMODEM), the one Redis Cluster uses
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |crc, &b| {
+        (0..8).fold(crc ^ ((b as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(
+            key_slot(b"{user1000}.following"),
+            key_slot(b"{user1000}.followers")
+        );
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        // an empty tag doesn't count, the whole key is hashed
+        assert_eq!(key_slot(b"{}foo"), crc16(b"{}foo") % SLOTS);
+    }
+}
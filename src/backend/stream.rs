@@ -0,0 +1,1091 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Bound;
+
+use anyhow::{anyhow, bail};
+
+use crate::backend::keyspace::KeyType;
+use crate::backend::notify::EventClass;
+use crate::backend::{now_ms, Backend};
+use crate::resp::frame::RespFrame;
+
+/// Entries per node of a Redis stream; `~` trimming only drops whole nodes,
+/// which we emulate by trimming in chunks of this many entries.
+const NODE_ENTRIES: usize = 100;
+
+/// A stream entry id, `<ms>-<seq>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// The id `XADD` was asked to use: `*`, `<ms>-*` or an explicit one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XAddId {
+    Auto,
+    Partial(u64),
+    Explicit(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrimBy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+/// `MAXLEN|MINID [=|~] threshold [LIMIT count]`. An `approx` trim only drops
+/// whole nodes and at most `limit` entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamTrim {
+    pub by: TrimBy,
+    pub approx: bool,
+    pub limit: Option<usize>,
+}
+
+/// Where `XREAD` reads from: after an explicit id, after the current top of
+/// the stream (`$`) or from its last entry (`+`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XReadId {
+    After(StreamId),
+    New,
+    Last,
+}
+
+/// A stream stored in `Db::stream`. Each entry is a flat list of
+/// field/value pairs.
+#[derive(Debug, Default)]
+pub struct StreamValue {
+    entries: BTreeMap<StreamId, Vec<RespFrame>>,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+/// A consumer group: the last entry delivered to it and the entries it was
+/// delivered but hasn't acknowledged yet, its pending entries list.
+#[derive(Debug, Default)]
+struct ConsumerGroup {
+    last_id: StreamId,
+    // entries the group has read, `None` once deletions make it unknowable
+    entries_read: Option<u64>,
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PendingEntry {
+    consumer: String,
+    delivered_at: u64,
+    deliveries: u64,
+}
+
+#[derive(Debug)]
+struct Consumer {
+    seen_at: u64,
+    active_at: Option<u64>,
+    pending: BTreeSet<StreamId>,
+}
+
+/// A stream entry with its field/value pairs.
+pub type StreamEntry = (StreamId, Vec<RespFrame>);
+
+/// An entry returned to a consumer group, `None` fields when it was deleted
+/// from the stream while still pending.
+pub type GroupEntry = (StreamId, Option<Vec<RespFrame>>);
+
+/// `XCLAIM` options. `delivered_at` comes from `IDLE` or `TIME`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct XClaimOptions {
+    pub delivered_at: Option<u64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub just_id: bool,
+    pub last_id: Option<StreamId>,
+}
+
+/// The `XPENDING` summary form.
+#[derive(Debug, Default, PartialEq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub min: Option<StreamId>,
+    pub max: Option<StreamId>,
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// One entry of the extended `XPENDING` form.
+#[derive(Debug, PartialEq)]
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: String,
+    pub idle: u64,
+    pub deliveries: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_id: StreamId,
+    pub max_deleted_id: StreamId,
+    pub entries_added: u64,
+    pub first_id: StreamId,
+    pub groups: usize,
+    pub first_entry: Option<(StreamId, Vec<RespFrame>)>,
+    pub last_entry: Option<(StreamId, Vec<RespFrame>)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_id: StreamId,
+    pub entries_read: Option<u64>,
+    pub lag: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    pub idle: u64,
+    pub inactive: Option<u64>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// Parses `<ms>-<seq>`, or a bare `<ms>` with `missing_seq`.
+    pub fn parse(s: &str, missing_seq: u64) -> Option<Self> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(Self::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(Self::new(s.parse().ok()?, missing_seq)),
+        }
+    }
+
+    /// The smallest id greater than this one.
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// The greatest id smaller than this one.
+    pub fn prev(self) -> Option<Self> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl StreamValue {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn first_entry(&self) -> Option<(StreamId, &Vec<RespFrame>)> {
+        self.entries.first_key_value().map(|(id, f)| (*id, f))
+    }
+
+    pub fn last_entry(&self) -> Option<(StreamId, &Vec<RespFrame>)> {
+        self.entries.last_key_value().map(|(id, f)| (*id, f))
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&Vec<RespFrame>> {
+        self.entries.get(id)
+    }
+
+    /// Appends an entry, picking its id as `XADD` does.
+    pub fn add(&mut self, id: XAddId, fields: Vec<RespFrame>) -> anyhow::Result<StreamId> {
+        let last = self.last_id;
+        let id = match id {
+            XAddId::Auto => {
+                let ms = now_ms();
+                if ms > last.ms {
+                    StreamId::new(ms, 0)
+                } else {
+                    match last.next() {
+                        Some(id) => id,
+                        None => bail!(
+                            "The stream has exhausted the last possible ID, unable to add more items"
+                        ),
+                    }
+                }
+            }
+            XAddId::Partial(ms) if ms == last.ms => match last.seq.checked_add(1) {
+                Some(seq) => StreamId::new(ms, seq),
+                None => bail!(
+                    "The ID specified in XADD is equal or smaller than the target stream top item"
+                ),
+            },
+            XAddId::Partial(ms) => StreamId::new(ms, 0),
+            XAddId::Explicit(id) => id,
+        };
+        if id == StreamId::MIN {
+            bail!("The ID specified in XADD must be greater than 0-0");
+        }
+        if id <= last {
+            bail!("The ID specified in XADD is equal or smaller than the target stream top item");
+        }
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    /// Entries with ids in `[start, end]`, newest first when `rev`.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, Vec<RespFrame>)> {
+        if start > end {
+            return vec![];
+        }
+        let range = self
+            .entries
+            .range((Bound::Included(start), Bound::Included(end)));
+        let take = count.unwrap_or(usize::MAX);
+        let clone = |(id, fields): (&StreamId, &Vec<RespFrame>)| (*id, fields.clone());
+        if rev {
+            range.rev().take(take).map(clone).collect()
+        } else {
+            range.take(take).map(clone).collect()
+        }
+    }
+
+    pub fn delete(&mut self, id: &StreamId) -> bool {
+        if self.entries.remove(id).is_none() {
+            return false;
+        }
+        self.max_deleted_id = self.max_deleted_id.max(*id);
+        true
+    }
+
+    /// Drops the oldest entries as `trim` asks, returning how many went.
+    pub fn trim(&mut self, trim: StreamTrim) -> usize {
+        let excess = match trim.by {
+            TrimBy::MaxLen(maxlen) => self.len().saturating_sub(maxlen),
+            TrimBy::MinId(minid) => self.entries.range(..minid).count(),
+        };
+        let mut count = excess;
+        if trim.approx {
+            count -= count % NODE_ENTRIES;
+        }
+        if let Some(limit) = trim.limit.filter(|&limit| limit > 0) {
+            count = count.min(limit);
+        }
+        for _ in 0..count {
+            if let Some((id, _)) = self.entries.pop_first() {
+                self.max_deleted_id = self.max_deleted_id.max(id);
+            }
+        }
+        count
+    }
+
+    /// Whether entries at or after `from` were deleted, which makes read
+    /// counters past that point unreliable.
+    fn has_tombstones(&self, from: StreamId) -> bool {
+        !self.entries.is_empty()
+            && self.max_deleted_id != StreamId::MIN
+            && from <= self.max_deleted_id
+    }
+
+    /// How many entries were ever added up to and including `id`, when that
+    /// can still be told.
+    fn entries_up_to(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if id >= self.last_id || self.entries.is_empty() {
+            return (id <= self.last_id).then_some(self.entries_added);
+        }
+        let (first, _) = self.first_entry()?;
+        // without deletions past the first entry, everything before it was trimmed
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first {
+            let before_first = self.entries_added - self.len() as u64;
+            if id < first {
+                return Some(before_first);
+            } else if id == first {
+                return Some(before_first + 1);
+            }
+        }
+        None
+    }
+
+    fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        let read = match group.entries_read {
+            Some(read) if !self.has_tombstones(group.last_id) => Some(read),
+            _ => self.entries_up_to(group.last_id),
+        };
+        read.map(|read| self.entries_added.saturating_sub(read))
+    }
+
+    fn group(&self, key: &str, name: &str) -> anyhow::Result<&ConsumerGroup> {
+        self.groups.get(name).ok_or_else(|| no_group(key, name))
+    }
+
+    fn group_mut(&mut self, key: &str, name: &str) -> anyhow::Result<&mut ConsumerGroup> {
+        self.groups.get_mut(name).ok_or_else(|| no_group(key, name))
+    }
+
+    /// Delivers up to `count` entries never delivered to the group before,
+    /// recording them as pending for `consumer` unless `noack`.
+    fn read_new(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        count: Option<usize>,
+        noack: bool,
+    ) -> anyhow::Result<Vec<(StreamId, Vec<RespFrame>)>> {
+        let last_id = self.group(key, group)?.last_id;
+        let entries: Vec<_> = self
+            .entries
+            .range((Bound::Excluded(last_id), Bound::Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect();
+        let now = now_ms();
+        for (id, _) in &entries {
+            let entries_read = match self.group(key, group)?.entries_read {
+                Some(read) if !self.has_tombstones(*id) => Some(read + 1),
+                _ => self.entries_up_to(*id),
+            };
+            let group = self.group_mut(key, group)?;
+            group.last_id = *id;
+            group.entries_read = entries_read;
+            if !noack {
+                group.assign(*id, consumer, now, 1);
+            }
+        }
+        let consumer = self.group_mut(key, group)?.consumer(consumer, now);
+        if !entries.is_empty() {
+            consumer.active_at = Some(now);
+        }
+        Ok(entries)
+    }
+
+    /// Entries pending for `consumer` with ids after `after`.
+    fn read_pending(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        after: StreamId,
+        count: Option<usize>,
+    ) -> anyhow::Result<Vec<GroupEntry>> {
+        let now = now_ms();
+        let group = self
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| no_group(key, group))?;
+        let consumer = group.consumer(consumer, now);
+        let ids: Vec<StreamId> = consumer
+            .pending
+            .range((Bound::Excluded(after), Bound::Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .copied()
+            .collect();
+        let mut ret = Vec::with_capacity(ids.len());
+        for id in ids {
+            let entry = group
+                .pending
+                .get_mut(&id)
+                .expect("pending ids are in the group");
+            entry.delivered_at = now;
+            entry.deliveries += 1;
+            ret.push((id, self.entries.get(&id).cloned()));
+        }
+        Ok(ret)
+    }
+}
+
+impl ConsumerGroup {
+    fn new(last_id: StreamId, entries_read: Option<u64>) -> Self {
+        Self {
+            last_id,
+            entries_read,
+            ..Default::default()
+        }
+    }
+
+    // the consumer, created if needed, marked as just seen
+    fn consumer(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_string())
+            .or_insert_with(|| Consumer {
+                seen_at: now,
+                active_at: None,
+                pending: BTreeSet::new(),
+            });
+        consumer.seen_at = now;
+        consumer
+    }
+
+    // (re)assigns a pending entry to `consumer`
+    fn assign(&mut self, id: StreamId, consumer: &str, delivered_at: u64, deliveries: u64) {
+        self.unassign(id);
+        self.consumer(consumer, now_ms()).pending.insert(id);
+        let entry = PendingEntry {
+            consumer: consumer.to_string(),
+            delivered_at,
+            deliveries,
+        };
+        self.pending.insert(id, entry);
+    }
+
+    fn unassign(&mut self, id: StreamId) -> Option<PendingEntry> {
+        let entry = self.pending.remove(&id)?;
+        if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+            consumer.pending.remove(&id);
+        }
+        Some(entry)
+    }
+}
+
+impl Backend {
+    /// Appends an entry and trims the stream, `None` when the stream doesn't
+    /// exist and `nomkstream` is set.
+    pub fn xadd(
+        &self,
+        key: &str,
+        id: XAddId,
+        fields: Vec<RespFrame>,
+        nomkstream: bool,
+        trim: Option<StreamTrim>,
+    ) -> anyhow::Result<Option<StreamId>> {
+        self.check_type(key, KeyType::Stream)?;
+        let mut created = false;
+        let mut trimmed = 0;
+        let id = {
+            let mut stream = match self.stream.get_mut(key) {
+                Some(stream) => stream,
+                None if nomkstream => return Ok(None),
+                None => {
+                    created = true;
+                    self.stream.entry(key.to_string()).or_default()
+                }
+            };
+            let id = stream.add(id, fields);
+            if let (Ok(_), Some(trim)) = (&id, trim) {
+                trimmed = stream.trim(trim);
+            }
+            id
+        };
+        // a failed add must not leave an empty stream behind
+        if id.is_err() && created {
+            self.stream.remove(key);
+        }
+        let id = id?;
+        self.notify(EventClass::Stream, "xadd", key);
+        if trimmed > 0 {
+            self.notify(EventClass::Stream, "xtrim", key);
+        }
+        self.signal_ready(key);
+        Ok(Some(id))
+    }
+
+    pub fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, Vec<RespFrame>)> {
+        self.stream
+            .get(key)
+            .map_or_else(Vec::new, |stream| stream.range(start, end, count, rev))
+    }
+
+    pub fn xlen(&self, key: &str) -> i64 {
+        self.stream.get(key).map_or(0, |stream| stream.len() as i64)
+    }
+
+    pub fn xdel(&self, key: &str, ids: &[StreamId]) -> i64 {
+        let deleted = match self.stream.get_mut(key) {
+            Some(mut stream) => ids.iter().filter(|id| stream.delete(id)).count(),
+            None => 0,
+        };
+        if deleted > 0 {
+            self.notify(EventClass::Stream, "xdel", key);
+        }
+        deleted as i64
+    }
+
+    pub fn xtrim(&self, key: &str, trim: StreamTrim) -> i64 {
+        let trimmed = self
+            .stream
+            .get_mut(key)
+            .map_or(0, |mut stream| stream.trim(trim));
+        if trimmed > 0 {
+            self.notify(EventClass::Stream, "xtrim", key);
+        }
+        trimmed as i64
+    }
+
+    /// Resolves `id` to the id `XREAD` returns entries after, so that later
+    /// reads of a blocked client see the same position.
+    pub fn xread_after(&self, key: &str, id: XReadId) -> StreamId {
+        if let XReadId::After(id) = id {
+            return id;
+        }
+        let Some(stream) = self.stream.get(key) else {
+            return StreamId::MIN;
+        };
+        match stream.last_entry() {
+            Some((last, _)) if id == XReadId::Last => last.prev().unwrap_or(stream.last_id),
+            // an empty stream has no last entry, so `+` waits like `$`
+            _ => stream.last_id,
+        }
+    }
+
+    /// Entries strictly after `after`.
+    pub fn xread(&self, key: &str, after: StreamId, count: Option<usize>) -> Vec<StreamEntry> {
+        match after.next() {
+            Some(start) => self.xrange(key, start, StreamId::MAX, count, false),
+            None => vec![],
+        }
+    }
+
+    /// Creates a group starting after `id`, `None` standing for the last
+    /// entry of the stream.
+    pub fn xgroup_create(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> anyhow::Result<()> {
+        self.check_type(key, KeyType::Stream)?;
+        let mut stream = match self.stream.get_mut(key) {
+            Some(stream) => stream,
+            None if mkstream => self.stream.entry(key.to_string()).or_default(),
+            None => bail!(
+                "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically."
+            ),
+        };
+        if stream.groups.contains_key(group) {
+            bail!("BUSYGROUP Consumer Group name already exists");
+        }
+        let id = id.unwrap_or(stream.last_id);
+        stream
+            .groups
+            .insert(group.to_string(), ConsumerGroup::new(id, entries_read));
+        drop(stream);
+        self.notify(EventClass::Stream, "xgroup-create", key);
+        Ok(())
+    }
+
+    pub fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let mut stream = self.existing_stream(key)?;
+        let id = id.unwrap_or(stream.last_id);
+        let group = stream.group_mut(key, group)?;
+        group.last_id = id;
+        group.entries_read = entries_read;
+        drop(stream);
+        self.notify(EventClass::Stream, "xgroup-setid", key);
+        Ok(())
+    }
+
+    pub fn xgroup_destroy(&self, key: &str, group: &str) -> anyhow::Result<bool> {
+        let destroyed = self.existing_stream(key)?.groups.remove(group).is_some();
+        if destroyed {
+            self.notify(EventClass::Stream, "xgroup-destroy", key);
+        }
+        Ok(destroyed)
+    }
+
+    pub fn xgroup_createconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> anyhow::Result<bool> {
+        let mut stream = self.existing_stream(key)?;
+        let group = stream.group_mut(key, group)?;
+        if group.consumers.contains_key(consumer) {
+            return Ok(false);
+        }
+        group.consumer(consumer, now_ms());
+        drop(stream);
+        self.notify(EventClass::Stream, "xgroup-createconsumer", key);
+        Ok(true)
+    }
+
+    /// Deletes a consumer along with its pending entries, returning how
+    /// many it had.
+    pub fn xgroup_delconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> anyhow::Result<usize> {
+        let mut stream = self.existing_stream(key)?;
+        let group = stream.group_mut(key, group)?;
+        let Some(removed) = group.consumers.remove(consumer) else {
+            return Ok(0);
+        };
+        for id in &removed.pending {
+            group.pending.remove(id);
+        }
+        drop(stream);
+        self.notify(EventClass::Stream, "xgroup-delconsumer", key);
+        Ok(removed.pending.len())
+    }
+
+    /// Reads for `consumer` of `group`: entries never delivered to the group
+    /// when `after` is `None` (`>`), otherwise the consumer's own pending
+    /// entries after that id.
+    pub fn xreadgroup(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+    ) -> anyhow::Result<Vec<GroupEntry>> {
+        let mut stream = self.stream.get_mut(key).ok_or_else(|| {
+            anyhow!(
+                "NOGROUP No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                key,
+                group
+            )
+        })?;
+        match after {
+            None => Ok(stream
+                .read_new(key, group, consumer, count, noack)?
+                .into_iter()
+                .map(|(id, fields)| (id, Some(fields)))
+                .collect()),
+            Some(after) => stream.read_pending(key, group, consumer, after, count),
+        }
+    }
+
+    pub fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> i64 {
+        let Some(mut stream) = self.stream.get_mut(key) else {
+            return 0;
+        };
+        let Some(group) = stream.groups.get_mut(group) else {
+            return 0;
+        };
+        ids.iter()
+            .filter(|id| group.unassign(**id).is_some())
+            .count() as i64
+    }
+
+    pub fn xpending_summary(&self, key: &str, group: &str) -> anyhow::Result<PendingSummary> {
+        let stream = self.stream.get(key).ok_or_else(|| no_group(key, group))?;
+        let group = stream.group(key, group)?;
+        let consumers = group
+            .consumers
+            .iter()
+            .filter(|(_, consumer)| !consumer.pending.is_empty())
+            .map(|(name, consumer)| (name.clone(), consumer.pending.len()))
+            .collect();
+        Ok(PendingSummary {
+            count: group.pending.len(),
+            min: group.pending.keys().next().copied(),
+            max: group.pending.keys().next_back().copied(),
+            consumers,
+        })
+    }
+
+    /// Pending entries in `[start, end]` idle for at least `min_idle` ms,
+    /// optionally only those of one consumer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xpending(
+        &self,
+        key: &str,
+        group: &str,
+        min_idle: u64,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        consumer: Option<&str>,
+    ) -> anyhow::Result<Vec<PendingInfo>> {
+        let stream = self.stream.get(key).ok_or_else(|| no_group(key, group))?;
+        let group = stream.group(key, group)?;
+        if start > end {
+            return Ok(vec![]);
+        }
+        let now = now_ms();
+        Ok(group
+            .pending
+            .range(start..=end)
+            .filter(|(_, entry)| consumer.is_none_or(|c| c == entry.consumer))
+            .map(|(id, entry)| PendingInfo {
+                id: *id,
+                consumer: entry.consumer.clone(),
+                idle: now.saturating_sub(entry.delivered_at),
+                deliveries: entry.deliveries,
+            })
+            .filter(|info| info.idle >= min_idle)
+            .take(count)
+            .collect())
+    }
+
+    /// Hands the pending entries idle for at least `min_idle` ms over to
+    /// `consumer`. Entries deleted from the stream are dropped instead.
+    pub fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        opts: XClaimOptions,
+    ) -> anyhow::Result<Vec<(StreamId, Vec<RespFrame>)>> {
+        let mut stream = self
+            .stream
+            .get_mut(key)
+            .ok_or_else(|| no_group(key, group))?;
+        let stream = &mut *stream;
+        let group = stream
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| no_group(key, group))?;
+        let now = now_ms();
+        if let Some(last_id) = opts.last_id.filter(|&id| id > group.last_id) {
+            group.last_id = last_id;
+        }
+        let delivered_at = opts.delivered_at.unwrap_or(now).min(now);
+
+        let mut claimed = vec![];
+        for &id in ids {
+            let pending = match group.pending.get(&id) {
+                Some(pending) => Some(pending.clone()),
+                None if opts.force && stream.entries.contains_key(&id) => None,
+                None => continue,
+            };
+            let Some(fields) = stream.entries.get(&id) else {
+                group.unassign(id);
+                continue;
+            };
+            let deliveries = match &pending {
+                Some(pending) if now.saturating_sub(pending.delivered_at) < min_idle => continue,
+                Some(pending) => pending.deliveries,
+                None => 0,
+            };
+            let deliveries = match opts.retry_count {
+                Some(retry_count) => retry_count,
+                None if opts.just_id => deliveries,
+                None => deliveries + 1,
+            };
+            group.assign(id, consumer, delivered_at, deliveries);
+            claimed.push((id, fields.clone()));
+        }
+        let consumer = group.consumer(consumer, now);
+        if !claimed.is_empty() {
+            consumer.active_at = Some(now);
+        }
+        Ok(claimed)
+    }
+
+    /// Scans the pending entries from `start` and claims up to `count` idle
+    /// for at least `min_idle` ms. Returns the id to continue from (`0-0`
+    /// when the scan is complete), the claimed entries and the ids of those
+    /// that were deleted from the stream.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> anyhow::Result<(StreamId, Vec<StreamEntry>, Vec<StreamId>)> {
+        let mut stream = self
+            .stream
+            .get_mut(key)
+            .ok_or_else(|| no_group(key, group))?;
+        let stream = &mut *stream;
+        let group = stream
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| no_group(key, group))?;
+        let now = now_ms();
+        // bounds the work done for sparse matches, as Redis does
+        let mut attempts = count.saturating_mul(10);
+        let (mut claimed, mut deleted) = (vec![], vec![]);
+        let mut cursor = group.pending.range(start..).map(|(id, _)| *id).next();
+        while let Some(id) = cursor {
+            if attempts == 0 || claimed.len() == count {
+                break;
+            }
+            attempts -= 1;
+            cursor = group
+                .pending
+                .range((Bound::Excluded(id), Bound::Unbounded))
+                .map(|(id, _)| *id)
+                .next();
+            let Some(fields) = stream.entries.get(&id) else {
+                group.unassign(id);
+                deleted.push(id);
+                continue;
+            };
+            let pending = &group.pending[&id];
+            if now.saturating_sub(pending.delivered_at) < min_idle {
+                continue;
+            }
+            let deliveries = pending.deliveries + (!just_id) as u64;
+            group.assign(id, consumer, now, deliveries);
+            claimed.push((id, fields.clone()));
+        }
+        let consumer = group.consumer(consumer, now);
+        if !claimed.is_empty() {
+            consumer.active_at = Some(now);
+        }
+        Ok((cursor.unwrap_or(StreamId::MIN), claimed, deleted))
+    }
+
+    pub fn xinfo_stream(&self, key: &str) -> anyhow::Result<StreamInfo> {
+        let stream = self.existing_stream_ref(key)?;
+        let owned = |(id, fields): (StreamId, &Vec<RespFrame>)| (id, fields.clone());
+        Ok(StreamInfo {
+            length: stream.len(),
+            last_id: stream.last_id,
+            max_deleted_id: stream.max_deleted_id,
+            entries_added: stream.entries_added,
+            first_id: stream.first_entry().map_or(StreamId::MIN, |(id, _)| id),
+            groups: stream.groups.len(),
+            first_entry: stream.first_entry().map(owned),
+            last_entry: stream.last_entry().map(owned),
+        })
+    }
+
+    pub fn xinfo_groups(&self, key: &str) -> anyhow::Result<Vec<GroupInfo>> {
+        let stream = self.existing_stream_ref(key)?;
+        Ok(stream
+            .groups
+            .iter()
+            .map(|(name, group)| GroupInfo {
+                name: name.clone(),
+                consumers: group.consumers.len(),
+                pending: group.pending.len(),
+                last_id: group.last_id,
+                entries_read: group.entries_read,
+                lag: stream.lag(group),
+            })
+            .collect())
+    }
+
+    pub fn xinfo_consumers(&self, key: &str, group: &str) -> anyhow::Result<Vec<ConsumerInfo>> {
+        let stream = self.existing_stream_ref(key)?;
+        let group = stream.group(key, group)?;
+        let now = now_ms();
+        Ok(group
+            .consumers
+            .iter()
+            .map(|(name, consumer)| ConsumerInfo {
+                name: name.clone(),
+                pending: consumer.pending.len(),
+                idle: now.saturating_sub(consumer.seen_at),
+                inactive: consumer.active_at.map(|at| now.saturating_sub(at)),
+            })
+            .collect())
+    }
+
+    fn existing_stream(
+        &self,
+        key: &str,
+    ) -> anyhow::Result<dashmap::mapref::one::RefMut<'_, String, StreamValue>> {
+        self.stream.get_mut(key).ok_or_else(no_key)
+    }
+
+    fn existing_stream_ref(
+        &self,
+        key: &str,
+    ) -> anyhow::Result<dashmap::mapref::one::Ref<'_, String, StreamValue>> {
+        self.stream.get(key).ok_or_else(no_key)
+    }
+}
+
+fn no_key() -> anyhow::Error {
+    anyhow!("no such key")
+}
+
+fn no_group(key: &str, group: &str) -> anyhow::Error {
+    anyhow!(
+        "NOGROUP No such key '{}' or consumer group '{}'",
+        key,
+        group
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::bulkstring::RespBulkString;
+
+    fn fields() -> Vec<RespFrame> {
+        vec![
+            RespBulkString::new("f").into(),
+            RespBulkString::new("v").into(),
+        ]
+    }
+
+    #[test]
+    fn test_stream_ids() -> anyhow::Result<()> {
+        let mut stream = StreamValue::default();
+        assert!(stream
+            .add(XAddId::Explicit(StreamId::MIN), fields())
+            .is_err());
+        assert_eq!(
+            stream.add(XAddId::Partial(0), fields())?,
+            StreamId::new(0, 1)
+        );
+        assert_eq!(
+            stream.add(XAddId::Partial(0), fields())?,
+            StreamId::new(0, 2)
+        );
+        assert_eq!(
+            stream.add(XAddId::Partial(5), fields())?,
+            StreamId::new(5, 0)
+        );
+        assert!(stream
+            .add(XAddId::Explicit(StreamId::new(5, 0)), fields())
+            .is_err());
+        assert!(stream.add(XAddId::Partial(4), fields()).is_err());
+        let id = stream.add(XAddId::Auto, fields())?;
+        assert!(id > StreamId::new(5, 0));
+        assert_eq!(stream.len(), 4);
+
+        assert_eq!(
+            StreamId::parse("7", u64::MAX),
+            Some(StreamId::new(7, u64::MAX))
+        );
+        assert_eq!(StreamId::parse("7-x", 0), None);
+        assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+        assert_eq!(StreamId::MIN.prev(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_range_and_trim() -> anyhow::Result<()> {
+        let mut stream = StreamValue::default();
+        for ms in 1..=250 {
+            stream.add(XAddId::Explicit(StreamId::new(ms, 0)), fields())?;
+        }
+        let ids = |entries: Vec<(StreamId, Vec<RespFrame>)>| {
+            entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>()
+        };
+        let range = stream.range(StreamId::new(3, 0), StreamId::new(5, 0), None, false);
+        assert_eq!(ids(range), [3, 4, 5]);
+        let range = stream.range(StreamId::MIN, StreamId::MAX, Some(2), true);
+        assert_eq!(ids(range), [250, 249]);
+
+        let approx = |by| StreamTrim {
+            by,
+            approx: true,
+            limit: None,
+        };
+        // only whole nodes go with `~`
+        assert_eq!(stream.trim(approx(TrimBy::MaxLen(100))), 100);
+        assert_eq!(stream.trim(approx(TrimBy::MinId(StreamId::new(200, 0)))), 0);
+        let exact = StreamTrim {
+            by: TrimBy::MinId(StreamId::new(200, 0)),
+            approx: false,
+            limit: None,
+        };
+        assert_eq!(stream.trim(exact), 99);
+        assert_eq!(stream.first_entry().map(|(id, _)| id.ms), Some(200));
+        assert!(stream.delete(&StreamId::new(250, 0)));
+        assert_eq!(stream.max_deleted_id, StreamId::new(250, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_groups() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        assert!(backend.xgroup_create("s", "g", None, false, None).is_err());
+        backend.xgroup_create("s", "g", None, true, None)?;
+        assert!(backend.xgroup_create("s", "g", None, false, None).is_err());
+        for ms in 1..=3 {
+            let id = XAddId::Explicit(StreamId::new(ms, 0));
+            backend.xadd("s", id, fields(), false, None)?;
+        }
+
+        let read = backend.xreadgroup("s", "g", "alice", None, Some(2), false)?;
+        let ids: Vec<_> = read.iter().map(|(id, _)| id.ms).collect();
+        assert_eq!(ids, [1, 2]);
+        let read = backend.xreadgroup("s", "g", "bob", None, None, false)?;
+        assert_eq!(read.len(), 1);
+        // history only has what alice was delivered
+        let read = backend.xreadgroup("s", "g", "alice", Some(StreamId::MIN), None, false)?;
+        assert_eq!(read.len(), 2);
+
+        assert_eq!(backend.xack("s", "g", &[StreamId::new(1, 0)]), 1);
+        assert_eq!(backend.xack("s", "g", &[StreamId::new(1, 0)]), 0);
+        let summary = backend.xpending_summary("s", "g")?;
+        assert_eq!(summary.count, 2);
+        assert_eq!(
+            summary.consumers,
+            [("alice".to_string(), 1), ("bob".to_string(), 1)]
+        );
+
+        let claimed = backend.xclaim(
+            "s",
+            "g",
+            "bob",
+            0,
+            &[StreamId::new(2, 0)],
+            XClaimOptions::default(),
+        )?;
+        assert_eq!(claimed.len(), 1);
+        let pending =
+            backend.xpending("s", "g", 0, StreamId::MIN, StreamId::MAX, 10, Some("bob"))?;
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].deliveries, 3);
+
+        backend.xdel("s", &[StreamId::new(3, 0)]);
+        let (next, claimed, deleted) =
+            backend.xautoclaim("s", "g", "alice", 0, StreamId::MIN, 10, false)?;
+        assert_eq!((next, claimed.len()), (StreamId::MIN, 1));
+        assert_eq!(deleted, [StreamId::new(3, 0)]);
+        assert_eq!(backend.xinfo_groups("s")?[0].pending, 1);
+        Ok(())
+    }
+}
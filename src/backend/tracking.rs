@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+
+use anyhow::bail;
+
+use crate::backend::pubsub::PushSender;
+use crate::backend::Backend;
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+/// Where a `REDIRECT`ed client hears of invalidations.
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// How a client asked to be told of changes, see `CLIENT TRACKING`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackingOptions {
+    pub redirect: Option<u64>,
+    pub bcast: bool,
+    pub prefixes: Vec<String>,
+    pub optin: bool,
+    pub optout: bool,
+    pub noloop: bool,
+}
+
+#[derive(Debug)]
+struct TrackingClient {
+    options: TrackingOptions,
+    sender: PushSender,
+    // the RESP version spoken when tracking was turned on
+    protocol: u8,
+    // what `CLIENT CACHING` said about the next command
+    caching: Option<bool>,
+    // the client redirected to went away
+    redirect_broken: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Tracking {
+    clients: HashMap<u64, TrackingClient>,
+    // clients that read each key since it last changed, whatever the database
+    keys: HashMap<String, HashSet<u64>>,
+}
+
+/// What `CLIENT TRACKINGINFO` tells about a client.
+#[derive(Debug, PartialEq)]
+pub struct TrackingInfo {
+    pub flags: Vec<&'static str>,
+    // -1 when off, 0 when not redirecting
+    pub redirect: i64,
+    pub prefixes: Vec<String>,
+}
+
+impl Backend {
+    /// Turns tracking on for the client, replacing what it asked for before.
+    /// Invalidations go to `sender`, unless redirected to another client.
+    pub fn track(
+        &self,
+        client_id: u64,
+        sender: &PushSender,
+        protocol: u8,
+        mut options: TrackingOptions,
+    ) -> anyhow::Result<()> {
+        if !options.bcast && !options.prefixes.is_empty() {
+            bail!("PREFIX option requires BCAST mode to be enabled");
+        }
+        if options.bcast && (options.optin || options.optout) {
+            bail!("OPTIN and OPTOUT are not compatible with BCAST");
+        }
+        if options.optin && options.optout {
+            bail!("You can't use both OPTIN and OPTOUT");
+        }
+        if let Some(id) = options.redirect {
+            if id == client_id {
+                options.redirect = None;
+            } else if id == 0 || id >= self.server.next_client_id.load(Ordering::Relaxed) {
+                bail!("The client ID you want redirect to does not exist");
+            }
+        }
+        // BCAST without a prefix is told of every key
+        if options.bcast && options.prefixes.is_empty() {
+            options.prefixes.push(String::new());
+        }
+        let mut tracking = self.server.tracking.lock().unwrap();
+        tracking.clients.insert(
+            client_id,
+            TrackingClient {
+                options,
+                sender: sender.clone(),
+                protocol,
+                caching: None,
+                redirect_broken: false,
+            },
+        );
+        self.server.tracking_on.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Turns tracking off for the client, also when it goes away.
+    pub fn untrack(&self, client_id: u64) {
+        if !self.server.tracking_on.load(Ordering::Acquire) {
+            return;
+        }
+        let mut tracking = self.server.tracking.lock().unwrap();
+        // the keys it read are forgotten as they change
+        tracking.clients.remove(&client_id);
+        self.server
+            .tracking_on
+            .store(!tracking.clients.is_empty(), Ordering::Release);
+    }
+
+    /// Says whether the next command of an `OPTIN` or `OPTOUT` client has
+    /// its keys tracked.
+    pub fn set_caching(&self, client_id: u64, yes: bool) -> anyhow::Result<()> {
+        let mut tracking = self.server.tracking.lock().unwrap();
+        let client = tracking.clients.get_mut(&client_id);
+        let Some(client) = client.filter(|c| c.options.optin || c.options.optout) else {
+            bail!("CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled");
+        };
+        if yes && !client.options.optin {
+            bail!("CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode.");
+        }
+        if !yes && !client.options.optout {
+            bail!("CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode.");
+        }
+        client.caching = Some(yes);
+        Ok(())
+    }
+
+    /// What `CLIENT CACHING` said for the command about to run, which it
+    /// only ever says for one.
+    pub fn take_caching(&self, client_id: u64) -> Option<bool> {
+        if !self.server.tracking_on.load(Ordering::Acquire) {
+            return None;
+        }
+        let mut tracking = self.server.tracking.lock().unwrap();
+        tracking.clients.get_mut(&client_id)?.caching.take()
+    }
+
+    /// Remembers the client read `keys`, so it's told when they change.
+    /// `caching` is what `CLIENT CACHING` said for this command.
+    pub fn track_keys(&self, client_id: u64, keys: &[String], caching: Option<bool>) {
+        if keys.is_empty() || !self.server.tracking_on.load(Ordering::Acquire) {
+            return;
+        }
+        let mut tracking = self.server.tracking.lock().unwrap();
+        let Some(client) = tracking.clients.get(&client_id) else {
+            return;
+        };
+        let options = &client.options;
+        let tracked = if options.optin {
+            caching == Some(true)
+        } else if options.optout {
+            caching != Some(false)
+        } else {
+            !options.bcast
+        };
+        if !tracked {
+            return;
+        }
+        for key in keys {
+            tracking
+                .keys
+                .entry(key.clone())
+                .or_default()
+                .insert(client_id);
+        }
+    }
+
+    pub fn tracking_info(&self, client_id: u64) -> TrackingInfo {
+        let tracking = self.server.tracking.lock().unwrap();
+        let Some(client) = tracking.clients.get(&client_id) else {
+            return TrackingInfo {
+                flags: vec!["off"],
+                redirect: -1,
+                prefixes: vec![],
+            };
+        };
+        let options = &client.options;
+        let mut flags = vec!["on"];
+        for (set, flag) in [
+            (options.bcast, "bcast"),
+            (options.optin, "optin"),
+            (options.optout, "optout"),
+            (client.caching == Some(true), "caching-yes"),
+            (client.caching == Some(false), "caching-no"),
+            (options.noloop, "noloop"),
+            (client.redirect_broken, "broken_redirect"),
+        ] {
+            if set {
+                flags.push(flag);
+            }
+        }
+        TrackingInfo {
+            flags,
+            redirect: options.redirect.map_or(0, |id| id as i64),
+            prefixes: if options.bcast {
+                options.prefixes.clone()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    /// Tells the clients that read `key`, or whose prefixes match it, that
+    /// it changed. `writer` is the client that changed it, if any.
+    pub(crate) fn invalidate(&self, key: &str, writer: Option<u64>) {
+        if !self.server.tracking_on.load(Ordering::Acquire) {
+            return;
+        }
+        let mut tracking = self.server.tracking.lock().unwrap();
+        let readers = tracking.keys.remove(key).unwrap_or_default();
+        let keys: RespFrame = RespArray::new(vec![RespBulkString::new(key).into()]).into();
+        for (&id, client) in tracking.clients.iter_mut() {
+            let told = if client.options.bcast {
+                client.options.prefixes.iter().any(|p| key.starts_with(p))
+            } else {
+                readers.contains(&id)
+            };
+            if told && !(client.options.noloop && writer == Some(id)) {
+                self.send_invalidation(client, keys.clone());
+            }
+        }
+    }
+
+    /// Tells every tracking client that all it read may have changed, for
+    /// when a whole database did.
+    pub(crate) fn invalidate_all(&self) {
+        if !self.server.tracking_on.load(Ordering::Acquire) {
+            return;
+        }
+        let mut tracking = self.server.tracking.lock().unwrap();
+        tracking.keys.clear();
+        for client in tracking.clients.values_mut() {
+            self.send_invalidation(client, RespArray::null().into());
+        }
+    }
+
+    // invalidations only reach a RESP2 client through the channel of the
+    // client it redirects to
+    fn send_invalidation(&self, client: &mut TrackingClient, keys: RespFrame) {
+        let Some(id) = client.options.redirect else {
+            if client.protocol > 2 {
+                let items = vec![RespBulkString::new("invalidate").into(), keys];
+                let _ = client.sender.send(items);
+            }
+            return;
+        };
+        if !self.send_message(id, INVALIDATE_CHANNEL, keys) {
+            client.redirect_broken = true;
+            if client.protocol > 2 {
+                let items = vec![
+                    RespBulkString::new("tracking-redir-broken").into(),
+                    RespFrame::Integer(id as i64),
+                ];
+                let _ = client.sender.send(items);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn invalidated(key: &str) -> Vec<RespFrame> {
+        vec![
+            RespBulkString::new("invalidate").into(),
+            RespArray::new(vec![RespBulkString::new(key).into()]).into(),
+        ]
+    }
+
+    #[test]
+    fn test_tracking() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let id = backend.next_client_id();
+        backend.track(id, &tx, 3, TrackingOptions::default())?;
+        backend.track_keys(id, &["a".to_string()], None);
+        backend.invalidate("b", None);
+        backend.invalidate("a", Some(id + 1));
+        assert_eq!(rx.try_recv()?, invalidated("a"));
+        // told once, until read again
+        backend.invalidate("a", None);
+        assert!(rx.try_recv().is_err());
+
+        let options = TrackingOptions {
+            optin: true,
+            noloop: true,
+            ..Default::default()
+        };
+        backend.track(id, &tx, 3, options)?;
+        assert!(backend.set_caching(id, false).is_err());
+        backend.track_keys(id, &["a".to_string()], None);
+        backend.set_caching(id, true)?;
+        assert_eq!(
+            backend.tracking_info(id).flags,
+            ["on", "optin", "caching-yes", "noloop"]
+        );
+        let caching = backend.take_caching(id);
+        backend.track_keys(id, &["b".to_string()], caching);
+        backend.invalidate("a", None);
+        backend.invalidate("b", Some(id));
+        assert!(rx.try_recv().is_err());
+
+        let options = TrackingOptions {
+            bcast: true,
+            prefixes: vec!["user:".to_string()],
+            ..Default::default()
+        };
+        backend.track(id, &tx, 3, options)?;
+        backend.invalidate("user:1", None);
+        backend.invalidate("post:1", None);
+        assert_eq!(rx.try_recv()?, invalidated("user:1"));
+        assert!(rx.try_recv().is_err());
+
+        backend.untrack(id);
+        assert_eq!(backend.tracking_info(id).redirect, -1);
+        assert!(backend
+            .track(
+                id,
+                &tx,
+                3,
+                TrackingOptions {
+                    prefixes: vec!["a".to_string()],
+                    ..Default::default()
+                }
+            )
+            .is_err());
+        Ok(())
+    }
+}
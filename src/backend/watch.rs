@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+
+use crate::backend::Backend;
+
+#[derive(Debug, Default)]
+pub(crate) struct WatchedKeys {
+    // clients watching each (db, key)
+    keys: HashMap<(usize, String), HashSet<u64>>,
+    // what each client watches
+    clients: HashMap<u64, Vec<(usize, String)>>,
+    // clients that saw one of their keys change since they watched it
+    dirty: HashSet<u64>,
+}
+
+impl Backend {
+    /// Watches `keys` of this database for the client, until `unwatch`.
+    pub fn watch(&self, client_id: u64, keys: Vec<String>) {
+        let mut watched = self.server.watched.lock().unwrap();
+        for key in keys {
+            let key = (self.index, key);
+            if watched
+                .keys
+                .entry(key.clone())
+                .or_default()
+                .insert(client_id)
+            {
+                watched.clients.entry(client_id).or_default().push(key);
+            }
+        }
+        self.server
+            .watching
+            .store(!watched.keys.is_empty(), Ordering::Release);
+    }
+
+    /// Forgets every key the client watches. Returns true when one of them
+    /// changed meanwhile.
+    pub fn unwatch(&self, client_id: u64) -> bool {
+        let mut watched = self.server.watched.lock().unwrap();
+        for key in watched.clients.remove(&client_id).unwrap_or_default() {
+            if let Some(clients) = watched.keys.get_mut(&key) {
+                clients.remove(&client_id);
+                if clients.is_empty() {
+                    watched.keys.remove(&key);
+                }
+            }
+        }
+        self.server
+            .watching
+            .store(!watched.keys.is_empty(), Ordering::Release);
+        watched.dirty.remove(&client_id)
+    }
+
+    /// Marks the clients watching `key` as dirty, called whenever it changes.
+    pub(crate) fn touch(&self, key: &str) {
+        self.touched_by(key, None);
+    }
+
+    /// Like `touch`, `writer` being the client whose command changed `key`.
+    pub(crate) fn touched_by(&self, key: &str, writer: Option<u64>) {
+        self.invalidate(key, writer);
+        // skips the lock while nobody watches anything
+        if !self.server.watching.load(Ordering::Acquire) {
+            return;
+        }
+        let mut watched = self.server.watched.lock().unwrap();
+        let WatchedKeys { keys, dirty, .. } = &mut *watched;
+        if let Some(clients) = keys.get(&(self.index, key.to_string())) {
+            dirty.extend(clients);
+        }
+    }
+
+    /// Marks every client watching a key of database `index` as dirty, for
+    /// when the whole database changed.
+    pub(crate) fn touch_db(&self, index: usize) {
+        if !self.server.watching.load(Ordering::Acquire) {
+            return;
+        }
+        let mut watched = self.server.watched.lock().unwrap();
+        let WatchedKeys { keys, dirty, .. } = &mut *watched;
+        for ((db, _), clients) in keys.iter() {
+            if *db == index {
+                dirty.extend(clients);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::bulkstring::RespBulkString;
+
+    #[test]
+    fn test_watch() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.watch(1, vec!["a".to_string(), "b".to_string()]);
+        backend.watch(2, vec!["b".to_string()]);
+        backend.touch("a");
+        // the same key in another database is another key
+        backend.select(1)?.touch("b");
+        assert!(backend.unwatch(1));
+        assert!(!backend.unwatch(2));
+        assert!(!backend.unwatch(1));
+
+        backend.watch(3, vec!["a".to_string()]);
+        backend.select(1)?.flushdb(false);
+        assert!(!backend.unwatch(3));
+        backend.watch(3, vec!["a".to_string()]);
+        backend.hset(
+            "a",
+            vec![("f".to_string(), RespBulkString::new("v").into())],
+        )?;
+        backend.flushdb(false);
+        assert!(backend.unwatch(3));
+        Ok(())
+    }
+}
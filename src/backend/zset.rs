@@ -0,0 +1,926 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use rand::seq::index;
+use rand::Rng;
+
+use crate::backend::keyspace::{key_matches, KeyType, ScanTarget};
+use crate::backend::notify::EventClass;
+use crate::backend::skiplist::SkipList;
+use crate::backend::{frame_bytes, Backend};
+
+/// A sorted set stored in `Db::zset`: `scores` answers member
+/// lookups in O(1), `list` keeps members ordered by `(score, member)`.
+#[derive(Debug, Default, Clone)]
+pub struct ZSetValue {
+    scores: HashMap<String, f64>,
+    list: SkipList,
+}
+
+/// One end of a score range, `(1.5` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+/// One end of a lex range: `-`, `+`, `[member` or `(member`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+impl ZRangeBy {
+    fn remove_event(&self) -> &'static str {
+        match self {
+            ZRangeBy::Rank(..) => "zremrangebyrank",
+            ZRangeBy::Score(..) => "zremrangebyscore",
+            ZRangeBy::Lex(..) => "zremrangebylex",
+        }
+    }
+}
+
+/// What `ZRANGE` selects. Bounds are always `(min, max)`, `rev` only flips
+/// the order in which they are walked. `limit` is `(offset, count)`, where
+/// a negative count means all remaining elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRangeSpec {
+    pub by: ZRangeBy,
+    pub rev: bool,
+    pub limit: Option<(i64, i64)>,
+}
+
+/// Which end of a sorted set `ZPOPMIN`/`ZPOPMAX` take from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZSetEnd {
+    Min,
+    Max,
+}
+
+impl ZSetEnd {
+    fn pop_event(self) -> &'static str {
+        match self {
+            ZSetEnd::Min => "zpopmin",
+            ZSetEnd::Max => "zpopmax",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZSetOp {
+    Union,
+    Inter,
+    Diff,
+}
+
+/// How `ZUNION`/`ZINTER` combine the scores of a member found in several
+/// inputs.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ZAddFlags {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+    pub ch: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ZAddOutcome {
+    Added(f64),
+    Updated(f64),
+    Unchanged(f64),
+    Skipped,
+}
+
+impl ScoreBound {
+    pub fn above_min(&self, score: f64) -> bool {
+        if self.exclusive {
+            score > self.value
+        } else {
+            score >= self.value
+        }
+    }
+
+    pub fn below_max(&self, score: f64) -> bool {
+        if self.exclusive {
+            score < self.value
+        } else {
+            score <= self.value
+        }
+    }
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf + -inf
+            Aggregate::Sum => Some(a + b).filter(|s| !s.is_nan()).unwrap_or(0.0),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+impl LexBound {
+    pub fn above_min(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(m) => member >= m.as_str(),
+            LexBound::Exclusive(m) => member > m.as_str(),
+        }
+    }
+
+    pub fn below_max(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(m) => member <= m.as_str(),
+            LexBound::Exclusive(m) => member < m.as_str(),
+        }
+    }
+}
+
+impl ZSetValue {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds `member` or moves it to `score`, true if it was not there yet.
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(old) if old == score => false,
+            Some(old) => {
+                self.list.remove(old, &member);
+                self.list.insert(score, member);
+                false
+            }
+            None => {
+                self.list.insert(score, member);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.list.remove(score, member);
+        Some(score)
+    }
+
+    /// Removes and returns the member with the lowest or highest score.
+    pub fn pop(&mut self, end: ZSetEnd) -> Option<(String, f64)> {
+        let node = match end {
+            ZSetEnd::Min => self.list.first()?,
+            ZSetEnd::Max => self.list.last()?,
+        };
+        let member = self.list.get(node).0.to_string();
+        let score = self.remove(&member)?;
+        Some((member, score))
+    }
+
+    /// 0-based rank, counted from the highest score when `rev`.
+    pub fn rank(&self, member: &str, rev: bool) -> Option<(usize, f64)> {
+        let score = self.score(member)?;
+        let rank = self.list.rank(score, member)?;
+        let rank = if rev { self.len() - 1 - rank } else { rank };
+        Some((rank, score))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.scores.iter().map(|(m, s)| (m.as_str(), *s))
+    }
+
+    /// Number of members with a score within `[min, max]`.
+    pub fn count_by_score(&self, min: ScoreBound, max: ScoreBound) -> usize {
+        let first = self.list.first_where(|score, _| min.above_min(score));
+        let last = self.list.last_where(|score, _| max.below_max(score));
+        match (first, last) {
+            (Some((first, _)), Some((last, _))) if last >= first => last - first + 1,
+            _ => 0,
+        }
+    }
+
+    /// Number of members within `[min, max]`, all scores being equal.
+    pub fn count_by_lex(&self, min: &LexBound, max: &LexBound) -> usize {
+        let first = self.list.first_where(|_, member| min.above_min(member));
+        let last = self.list.last_where(|_, member| max.below_max(member));
+        match (first, last) {
+            (Some((first, _)), Some((last, _))) if last >= first => last - first + 1,
+            _ => 0,
+        }
+    }
+
+    /// A uniformly random member, picked by rank.
+    pub fn random(&self) -> Option<(String, f64)> {
+        if self.is_empty() {
+            return None;
+        }
+        let rank = rand::thread_rng().gen_range(0..self.len());
+        self.by_rank(rank)
+    }
+
+    /// `count` distinct random members, or all of them if there are fewer.
+    pub fn random_distinct(&self, count: usize) -> Vec<(String, f64)> {
+        let count = count.min(self.len());
+        index::sample(&mut rand::thread_rng(), self.len(), count)
+            .into_iter()
+            .filter_map(|rank| self.by_rank(rank))
+            .collect()
+    }
+
+    fn by_rank(&self, rank: usize) -> Option<(String, f64)> {
+        let (member, score) = self.list.get(self.list.by_rank(rank)?);
+        Some((member.to_string(), score))
+    }
+
+    pub fn range(&self, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        let (offset, count) = match spec.limit {
+            Some((offset, _)) if offset < 0 => return vec![],
+            Some((offset, count)) if count >= 0 => (offset as usize, count as usize),
+            Some((offset, _)) => (offset as usize, usize::MAX),
+            None => (0, usize::MAX),
+        };
+        let walk = |start: Option<usize>, take: usize, keep: &dyn Fn(&str, f64) -> bool| {
+            let mut ret = vec![];
+            let mut node = start;
+            while let Some(x) = node {
+                let (member, score) = self.list.get(x);
+                if !keep(member, score) || ret.len() == take {
+                    break;
+                }
+                ret.push((member.to_string(), score));
+                node = if spec.rev {
+                    self.list.prev(x)
+                } else {
+                    self.list.next(x)
+                };
+            }
+            ret
+        };
+        // the node `offset` places past the rank-th one, found through the
+        // spans rather than walked to
+        let skip = |start: Option<(usize, usize)>| {
+            let (rank, x) = start?;
+            if offset == 0 {
+                return Some(x);
+            }
+            let rank = if spec.rev {
+                rank.checked_sub(offset)?
+            } else {
+                rank.checked_add(offset)?
+            };
+            self.list.by_rank(rank)
+        };
+
+        match &spec.by {
+            ZRangeBy::Rank(start, stop) => {
+                let len = self.len() as i64;
+                let start = if *start < 0 {
+                    (len + start).max(0)
+                } else {
+                    *start
+                };
+                let stop = if *stop < 0 {
+                    len + stop
+                } else {
+                    (*stop).min(len - 1)
+                };
+                if start > stop || start >= len {
+                    return vec![];
+                }
+                let first = if spec.rev { len - 1 - start } else { start };
+                let take = (stop - start + 1) as usize;
+                walk(self.list.by_rank(first as usize), take, &|_, _| true)
+            }
+            ZRangeBy::Score(min, max) => {
+                let start = if spec.rev {
+                    self.list.last_where(|score, _| max.below_max(score))
+                } else {
+                    self.list.first_where(|score, _| min.above_min(score))
+                };
+                let start = skip(start);
+                walk(start, count, &|_, score| {
+                    min.above_min(score) && max.below_max(score)
+                })
+            }
+            ZRangeBy::Lex(min, max) => {
+                let start = if spec.rev {
+                    self.list.last_where(|_, member| max.below_max(member))
+                } else {
+                    self.list.first_where(|_, member| min.above_min(member))
+                };
+                let start = skip(start);
+                walk(start, count, &|member, _| {
+                    min.above_min(member) && max.below_max(member)
+                })
+            }
+        }
+    }
+
+    fn add(
+        &mut self,
+        flags: ZAddFlags,
+        score: f64,
+        member: String,
+        incr: bool,
+    ) -> anyhow::Result<ZAddOutcome> {
+        let Some(current) = self.score(&member) else {
+            if flags.xx {
+                return Ok(ZAddOutcome::Skipped);
+            }
+            self.insert(member, score);
+            return Ok(ZAddOutcome::Added(score));
+        };
+        if flags.nx {
+            return Ok(ZAddOutcome::Skipped);
+        }
+        let score = if incr { current + score } else { score };
+        if score.is_nan() {
+            bail!("resulting score is not a number (NaN)");
+        }
+        if (flags.gt && score <= current) || (flags.lt && score >= current) {
+            return Ok(ZAddOutcome::Skipped);
+        }
+        if score == current {
+            return Ok(ZAddOutcome::Unchanged(score));
+        }
+        self.insert(member, score);
+        Ok(ZAddOutcome::Updated(score))
+    }
+}
+
+impl FromIterator<(String, f64)> for ZSetValue {
+    fn from_iter<T: IntoIterator<Item = (String, f64)>>(iter: T) -> Self {
+        let mut zset = ZSetValue::default();
+        for (member, score) in iter {
+            zset.insert(member, score);
+        }
+        zset
+    }
+}
+
+impl Backend {
+    /// Returns the number of new members, plus the updated ones with `CH`.
+    pub fn zadd(
+        &self,
+        key: &str,
+        flags: ZAddFlags,
+        pairs: Vec<(f64, String)>,
+    ) -> anyhow::Result<i64> {
+        self.check_type(key, KeyType::ZSet)?;
+        let ret = {
+            let mut zset = self.zset.entry(key.to_string()).or_default();
+            let (mut added, mut updated) = (0, 0);
+            for (score, member) in pairs {
+                match zset.add(flags, score, member, false)? {
+                    ZAddOutcome::Added(_) => added += 1,
+                    ZAddOutcome::Updated(_) => updated += 1,
+                    _ => {}
+                }
+            }
+            if added + updated > 0 {
+                drop(zset);
+                self.notify(EventClass::ZSet, "zadd", key);
+            }
+            if flags.ch {
+                added + updated
+            } else {
+                added
+            }
+        };
+        // only a sorted set made just now can be empty, nothing to tell
+        self.zset.remove_if(key, |_, v| v.is_empty());
+        self.signal_ready(key);
+        Ok(ret)
+    }
+
+    /// Increments the score of `member`, `None` when the flags prevented it.
+    pub fn zincrby(
+        &self,
+        key: &str,
+        flags: ZAddFlags,
+        incr: f64,
+        member: String,
+    ) -> anyhow::Result<Option<f64>> {
+        self.check_type(key, KeyType::ZSet)?;
+        let ret = self
+            .zset
+            .entry(key.to_string())
+            .or_default()
+            .add(flags, incr, member, true);
+        self.zset.remove_if(key, |_, v| v.is_empty());
+        self.signal_ready(key);
+        match ret? {
+            ZAddOutcome::Added(score) | ZAddOutcome::Updated(score) => {
+                self.notify(EventClass::ZSet, "zincr", key);
+                Ok(Some(score))
+            }
+            ZAddOutcome::Unchanged(score) => Ok(Some(score)),
+            ZAddOutcome::Skipped => Ok(None),
+        }
+    }
+
+    pub fn zrem(&self, key: &str, members: &[String]) -> i64 {
+        let removed = match self.zset.get_mut(key) {
+            Some(mut zset) => members.iter().filter(|m| zset.remove(m).is_some()).count(),
+            None => return 0,
+        };
+        if removed > 0 {
+            self.notify(EventClass::ZSet, "zrem", key);
+        }
+        self.drop_empty_zset(key);
+        removed as i64
+    }
+
+    /// Pops up to `count` members from `end`, in popping order.
+    pub fn zpop(&self, key: &str, end: ZSetEnd, count: usize) -> Vec<(String, f64)> {
+        let popped = match self.zset.get_mut(key) {
+            Some(mut zset) => (0..count).map_while(|_| zset.pop(end)).collect::<Vec<_>>(),
+            None => return vec![],
+        };
+        if !popped.is_empty() {
+            self.notify(EventClass::ZSet, end.pop_event(), key);
+        }
+        self.drop_empty_zset(key);
+        popped
+    }
+
+    /// Pops from the first of `keys` holding a non-empty sorted set.
+    pub fn zmpop(
+        &self,
+        keys: &[String],
+        end: ZSetEnd,
+        count: usize,
+    ) -> Option<(String, Vec<(String, f64)>)> {
+        keys.iter().find_map(|key| {
+            let popped = self.zpop(key, end, count);
+            (!popped.is_empty()).then(|| (key.clone(), popped))
+        })
+    }
+
+    pub fn zscore(&self, key: &str, member: &str) -> Option<f64> {
+        self.zset.get(key)?.score(member)
+    }
+
+    pub fn zmscore(&self, key: &str, members: &[String]) -> Vec<Option<f64>> {
+        match self.zset.get(key) {
+            Some(zset) => members.iter().map(|m| zset.score(m)).collect(),
+            None => vec![None; members.len()],
+        }
+    }
+
+    /// One page of `ZSCAN`, see [`scan_page`].
+    pub fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> (u64, Vec<(String, f64)>) {
+        let Some(zset) = self.zset.get(key) else {
+            return (0, vec![]);
+        };
+        let target = ScanTarget::ZSet(key.to_string());
+        let (cursor, page) = self.scan_names(target, cursor, count, || {
+            zset.iter()
+                .map(|(member, _)| member.as_bytes().to_vec())
+                .collect()
+        });
+        let page = page
+            .into_iter()
+            .filter(|member| pattern.is_none_or(|p| key_matches(p, member)))
+            .filter_map(|member| {
+                let member = String::from_utf8(member).ok()?;
+                let score = zset.score(&member)?;
+                Some((member, score))
+            })
+            .collect();
+        (cursor, page)
+    }
+
+    pub fn zcard(&self, key: &str) -> i64 {
+        self.zset.get(key).map_or(0, |zset| zset.len() as i64)
+    }
+
+    pub fn zcount(&self, key: &str, min: ScoreBound, max: ScoreBound) -> i64 {
+        self.zset
+            .get(key)
+            .map_or(0, |zset| zset.count_by_score(min, max) as i64)
+    }
+
+    pub fn zrank(&self, key: &str, member: &str, rev: bool) -> Option<(usize, f64)> {
+        self.zset.get(key)?.rank(member, rev)
+    }
+
+    pub fn zrange(&self, key: &str, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        self.zset
+            .get(key)
+            .map_or_else(Vec::new, |zset| zset.range(spec))
+    }
+
+    /// Stores the selected range of `source` in `destination`, replacing it,
+    /// and returns its cardinality.
+    pub fn zrangestore(&self, destination: &str, source: &str, spec: &ZRangeSpec) -> i64 {
+        let range = self.zrange(source, spec);
+        self.zstore(destination, range.into_iter().collect(), "zrangestore")
+    }
+
+    pub fn zlexcount(&self, key: &str, min: &LexBound, max: &LexBound) -> i64 {
+        self.zset
+            .get(key)
+            .map_or(0, |zset| zset.count_by_lex(min, max) as i64)
+    }
+
+    /// Removes the members selected by `by` and returns how many they were.
+    pub fn zremrange(&self, key: &str, by: ZRangeBy) -> i64 {
+        let event = by.remove_event();
+        let removed = {
+            let Some(mut zset) = self.zset.get_mut(key) else {
+                return 0;
+            };
+            let spec = ZRangeSpec {
+                by,
+                rev: false,
+                limit: None,
+            };
+            let range = zset.range(&spec);
+            for (member, _) in &range {
+                zset.remove(member);
+            }
+            range.len()
+        };
+        if removed > 0 {
+            self.notify(EventClass::ZSet, event, key);
+        }
+        self.drop_empty_zset(key);
+        removed as i64
+    }
+
+    /// Same count semantics as `SRANDMEMBER`: distinct members when
+    /// positive, possibly repeated ones when negative.
+    pub fn zrandmember(&self, key: &str, count: Option<i64>) -> Vec<(String, f64)> {
+        let Some(zset) = self.zset.get(key) else {
+            return vec![];
+        };
+        match count {
+            None => zset.random().into_iter().collect(),
+            Some(count) if count >= 0 => zset.random_distinct(count as usize),
+            Some(count) => (0..count.unsigned_abs())
+                .filter_map(|_| zset.random())
+                .collect(),
+        }
+    }
+
+    /// Combines the sorted sets, or plain sets scored 1, at `keys`. Scores
+    /// are multiplied by `weights` first. The result is ordered by score.
+    pub fn zsetop(
+        &self,
+        op: ZSetOp,
+        keys: &[String],
+        weights: Option<&[f64]>,
+        aggregate: Aggregate,
+    ) -> Vec<(String, f64)> {
+        let mut inputs = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let weight = weights.map_or(1.0, |w| w[i]);
+                let mut input = self.zset_input(key);
+                for (_, score) in input.iter_mut() {
+                    // 0 * inf
+                    *score = Some(*score * weight).filter(|s| !s.is_nan()).unwrap_or(0.0);
+                }
+                input
+            })
+            .collect::<Vec<_>>();
+
+        let result = match op {
+            ZSetOp::Union => {
+                let mut result = HashMap::new();
+                for input in inputs {
+                    for (member, score) in input {
+                        result
+                            .entry(member)
+                            .and_modify(|s| *s = aggregate.apply(*s, score))
+                            .or_insert(score);
+                    }
+                }
+                result
+            }
+            ZSetOp::Inter => {
+                inputs.sort_by_key(|input| input.len());
+                let (first, rest) = inputs.split_first().expect("keys is not empty");
+                first
+                    .iter()
+                    .filter_map(|(member, score)| {
+                        rest.iter()
+                            .try_fold(*score, |acc, input| {
+                                input.get(member).map(|s| aggregate.apply(acc, *s))
+                            })
+                            .map(|score| (member.clone(), score))
+                    })
+                    .collect()
+            }
+            ZSetOp::Diff => {
+                let (first, rest) = inputs.split_first().expect("keys is not empty");
+                first
+                    .iter()
+                    .filter(|(member, _)| !rest.iter().any(|input| input.contains_key(*member)))
+                    .map(|(member, score)| (member.clone(), *score))
+                    .collect()
+            }
+        };
+        let mut result = result.into_iter().collect::<Vec<_>>();
+        result.sort_by(|(m1, s1), (m2, s2)| s1.total_cmp(s2).then_with(|| m1.cmp(m2)));
+        result
+    }
+
+    /// Stores the result of `op` in `destination`, replacing it, and returns
+    /// its cardinality.
+    pub fn zsetop_store(
+        &self,
+        destination: &str,
+        op: ZSetOp,
+        keys: &[String],
+        weights: Option<&[f64]>,
+        aggregate: Aggregate,
+    ) -> i64 {
+        let result = self.zsetop(op, keys, weights, aggregate);
+        let event = match op {
+            ZSetOp::Union => "zunionstore",
+            ZSetOp::Inter => "zinterstore",
+            ZSetOp::Diff => "zdiffstore",
+        };
+        self.zstore(destination, result.into_iter().collect(), event)
+    }
+
+    // replaces `key` whatever it held, or deletes it when `zset` is empty;
+    // `event` is the storing command
+    pub(crate) fn zstore(&self, key: &str, zset: ZSetValue, event: &str) -> i64 {
+        let len = zset.len() as i64;
+        self.clear_other_type(key, KeyType::ZSet);
+        if zset.is_empty() {
+            if self.zset.remove(key).is_some() {
+                self.notify(EventClass::Generic, "del", key);
+            }
+        } else {
+            self.zset.insert(key.to_string(), zset);
+            self.notify(EventClass::ZSet, event, key);
+            self.signal_ready(key);
+        }
+        len
+    }
+
+    // drops the sorted set once its last member is gone, deleting the key
+    fn drop_empty_zset(&self, key: &str) {
+        if self.zset.remove_if(key, |_, v| v.is_empty()).is_some() {
+            self.notify(EventClass::Generic, "del", key);
+        }
+    }
+
+    // a sorted set, or a plain set whose members all score 1
+    fn zset_input(&self, key: &str) -> HashMap<String, f64> {
+        if let Some(zset) = self.zset.get(key) {
+            return zset.scores.clone();
+        }
+        match self.set.get(key) {
+            Some(set) => set
+                .iter()
+                .map(|m| {
+                    let member = String::from_utf8_lossy(frame_bytes(m).unwrap_or_default());
+                    (member.into_owned(), 1.0)
+                })
+                .collect(),
+            None => HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(vals: &[(f64, &str)]) -> Vec<(f64, String)> {
+        vals.iter().map(|(s, m)| (*s, m.to_string())).collect()
+    }
+
+    fn keys(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn inclusive(value: f64) -> ScoreBound {
+        ScoreBound {
+            value,
+            exclusive: false,
+        }
+    }
+
+    #[test]
+    fn test_zadd_flags() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let flags = ZAddFlags::default();
+        assert_eq!(
+            backend.zadd("z", flags, pairs(&[(1.0, "a"), (2.0, "b")]))?,
+            2
+        );
+
+        let xx = ZAddFlags {
+            xx: true,
+            ch: true,
+            ..flags
+        };
+        assert_eq!(backend.zadd("z", xx, pairs(&[(5.0, "a"), (1.0, "c")]))?, 1);
+        assert_eq!(backend.zscore("z", "c"), None);
+        assert_eq!(backend.zadd("none", xx, pairs(&[(1.0, "a")]))?, 0);
+        assert_eq!(backend.zcard("none"), 0);
+
+        let gt = ZAddFlags { gt: true, ..flags };
+        assert_eq!(backend.zincrby("z", gt, -1.0, "a".to_string())?, None);
+        assert_eq!(backend.zincrby("z", gt, 1.5, "a".to_string())?, Some(6.5));
+        backend.zadd("z", flags, pairs(&[(f64::INFINITY, "inf")]))?;
+        assert!(backend
+            .zincrby("z", flags, f64::NEG_INFINITY, "inf".to_string())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let vals = pairs(&[(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d"), (4.0, "e")]);
+        backend.zadd("z", ZAddFlags::default(), vals)?;
+        let members = |spec: ZRangeSpec| {
+            backend
+                .zrange("z", &spec)
+                .into_iter()
+                .map(|(m, _)| m)
+                .collect::<Vec<_>>()
+        };
+
+        let by_rank = |start, stop, rev| ZRangeSpec {
+            by: ZRangeBy::Rank(start, stop),
+            rev,
+            limit: None,
+        };
+        assert_eq!(members(by_rank(1, 2, false)), ["b", "c"]);
+        assert_eq!(members(by_rank(0, 1, true)), ["e", "d"]);
+        assert_eq!(members(by_rank(-2, 100, false)), ["d", "e"]);
+
+        let open = ScoreBound {
+            value: 1.0,
+            exclusive: true,
+        };
+        let by_score = ZRangeSpec {
+            by: ZRangeBy::Score(open, inclusive(3.0)),
+            rev: true,
+            limit: Some((1, 2)),
+        };
+        assert_eq!(members(by_score), ["c", "b"]);
+        let past_end = ZRangeSpec {
+            by: ZRangeBy::Score(inclusive(f64::NEG_INFINITY), inclusive(f64::INFINITY)),
+            rev: false,
+            limit: Some((4_000_000_000, 1)),
+        };
+        assert!(members(past_end).is_empty());
+        assert_eq!(backend.zcount("z", open, inclusive(3.0)), 3);
+        assert_eq!(backend.zcount("z", inclusive(5.0), inclusive(9.0)), 0);
+
+        assert_eq!(backend.zrank("z", "d", false), Some((3, 3.0)));
+        assert_eq!(backend.zrank("z", "d", true), Some((1, 3.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_bylex() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let vals = pairs(&[(0.0, "a"), (0.0, "b"), (0.0, "c"), (0.0, "d")]);
+        backend.zadd("z", ZAddFlags::default(), vals)?;
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Lex(LexBound::Exclusive("a".to_string()), LexBound::Max),
+            rev: false,
+            limit: Some((0, 2)),
+        };
+        let members = |spec: &ZRangeSpec| {
+            backend
+                .zrange("z", spec)
+                .into_iter()
+                .map(|(m, _)| m)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(members(&spec), ["b", "c"]);
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Lex(LexBound::Min, LexBound::Inclusive("b".to_string())),
+            rev: true,
+            limit: None,
+        };
+        assert_eq!(members(&spec), ["b", "a"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zsetop() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let flags = ZAddFlags::default();
+        backend.zadd("z1", flags, pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]))?;
+        backend.zadd("z2", flags, pairs(&[(10.0, "b"), (20.0, "c"), (30.0, "d")]))?;
+        backend.sadd(
+            "s",
+            vec![crate::resp::bulkstring::RespBulkString::new("c").into()],
+        )?;
+        let ret = backend.zsetop(
+            ZSetOp::Union,
+            &keys(&["z1", "z2"]),
+            Some(&[2.0, 1.0]),
+            Aggregate::Sum,
+        );
+        assert_eq!(
+            ret,
+            vec![
+                ("a".to_string(), 2.0),
+                ("b".to_string(), 14.0),
+                ("c".to_string(), 26.0),
+                ("d".to_string(), 30.0)
+            ]
+        );
+        let ret = backend.zsetop(
+            ZSetOp::Inter,
+            &keys(&["z1", "z2", "s"]),
+            None,
+            Aggregate::Max,
+        );
+        assert_eq!(ret, vec![("c".to_string(), 20.0)]);
+        let ret = backend.zsetop(ZSetOp::Diff, &keys(&["z1", "z2"]), None, Aggregate::Sum);
+        assert_eq!(ret, vec![("a".to_string(), 1.0)]);
+
+        let stored = backend.zsetop_store(
+            "z1",
+            ZSetOp::Inter,
+            &keys(&["z1", "nope"]),
+            None,
+            Aggregate::Sum,
+        );
+        assert_eq!(stored, 0);
+        assert_eq!(backend.zcard("z1"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let vals = pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c"), (4.0, "d")]);
+        backend.zadd("z", ZAddFlags::default(), vals)?;
+        assert_eq!(backend.zremrange("z", ZRangeBy::Rank(-1, -1)), 1);
+        let by_score = ZRangeBy::Score(inclusive(0.0), inclusive(2.0));
+        assert_eq!(backend.zremrange("z", by_score), 2);
+        assert_eq!(backend.zrank("z", "c", false), Some((0, 3.0)));
+        assert_eq!(
+            backend.zremrange("z", ZRangeBy::Lex(LexBound::Min, LexBound::Max)),
+            1
+        );
+        assert_eq!(backend.zcard("z"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zpop() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let vals = pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]);
+        backend.zadd("z", ZAddFlags::default(), vals)?;
+        assert_eq!(
+            backend.zpop("z", ZSetEnd::Max, 2),
+            vec![("c".to_string(), 3.0), ("b".to_string(), 2.0)]
+        );
+        let ret = backend.zmpop(&keys(&["nope", "z"]), ZSetEnd::Min, 5);
+        assert_eq!(ret, Some(("z".to_string(), vec![("a".to_string(), 1.0)])));
+        assert_eq!(backend.zcard("z"), 0);
+        assert_eq!(backend.zmpop(&keys(&["z"]), ZSetEnd::Min, 1), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use crate::backend::bitfield::{BitfieldOp, BitfieldOverflow, BitfieldType};
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
+use crate::cmd::{frame_to_i64, frame_to_string, into_args, CommandExecutor, ExecuteError};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+// offsets are limited to a 512MB string, like `proto-max-bulk-len`
+const MAX_BIT_OFFSET: u64 = 512 * 1024 * 1024 * 8;
+
+// BitField: "*5\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nget\r\n$2\r\nu8\r\n$1\r\n0\r\n"
+#[derive(Debug, PartialEq)]
+pub struct BitFieldCommand {
+    key: String,
+    ops: Vec<BitfieldOp>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BitFieldRoCommand {
+    key: String,
+    ops: Vec<BitfieldOp>,
+}
+
+impl CommandExecutor for BitFieldCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.bitfield(&self.key, &self.ops)?;
+        Ok(results_to_frame(ret))
+    }
+}
+
+impl CommandExecutor for BitFieldRoCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.bitfield(&self.key, &self.ops)?;
+        Ok(results_to_frame(ret))
+    }
+}
+
+impl TryFrom<RespArray> for BitFieldCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, ops) = parse_bitfield(arr)?;
+        Ok(BitFieldCommand { key, ops })
+    }
+}
+
+impl TryFrom<RespArray> for BitFieldRoCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, ops) = parse_bitfield(arr)?;
+        if ops.iter().any(|op| !matches!(op, BitfieldOp::Get(..))) {
+            return Err(InvalidCommand(
+                "BITFIELD_RO only supports the GET subcommand".to_string(),
+            ));
+        }
+        Ok(BitFieldRoCommand { key, ops })
+    }
+}
+
+fn results_to_frame(ret: Vec<Option<i64>>) -> RespFrame {
+    let vec = ret
+        .into_iter()
+        .map(|v| match v {
+            Some(v) => v.into(),
+            None => RespBulkString::null().into(),
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(vec).into()
+}
+
+fn parse_bitfield(arr: RespArray) -> Result<(String, Vec<BitfieldOp>), ExecuteError> {
+    let mut args = into_args(arr)?.into_iter();
+    let Some(key) = args.next() else {
+        return Err(InvalidArgument("expected at least 1, got 0".to_string()));
+    };
+    let key = frame_to_string(key)?;
+
+    let mut ops = Vec::new();
+    while let Some(sub) = args.next() {
+        let sub = frame_to_string(sub)?.to_ascii_lowercase();
+        let op = match sub.as_str() {
+            "get" => {
+                let ty = parse_type(args.next())?;
+                let offset = parse_offset(args.next(), ty)?;
+                BitfieldOp::Get(ty, offset)
+            }
+            "set" | "incrby" => {
+                let ty = parse_type(args.next())?;
+                let offset = parse_offset(args.next(), ty)?;
+                let Some(value) = args.next() else {
+                    return Err(InvalidArgument("syntax error".to_string()));
+                };
+                let value = frame_to_i64(value)?;
+                if sub == "set" {
+                    BitfieldOp::Set(ty, offset, value)
+                } else {
+                    BitfieldOp::IncrBy(ty, offset, value)
+                }
+            }
+            "overflow" => {
+                let Some(overflow) = args.next() else {
+                    return Err(InvalidArgument("syntax error".to_string()));
+                };
+                let overflow = match frame_to_string(overflow)?.to_ascii_lowercase().as_str() {
+                    "wrap" => BitfieldOverflow::Wrap,
+                    "sat" => BitfieldOverflow::Sat,
+                    "fail" => BitfieldOverflow::Fail,
+                    _ => {
+                        return Err(InvalidArgument(
+                            "Invalid OVERFLOW type specified".to_string(),
+                        ))
+                    }
+                };
+                BitfieldOp::Overflow(overflow)
+            }
+            _ => return Err(InvalidArgument("syntax error".to_string())),
+        };
+        ops.push(op);
+    }
+    Ok((key, ops))
+}
+
+// i1..i64 or u1..u63
+fn parse_type(frame: Option<RespFrame>) -> Result<BitfieldType, ExecuteError> {
+    let err = || {
+        InvalidArgument(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string(),
+        )
+    };
+    let s = frame_to_string(frame.ok_or_else(err)?)?.to_ascii_lowercase();
+    let signed = match s.chars().next() {
+        Some('i') => true,
+        Some('u') => false,
+        _ => return Err(err()),
+    };
+    let bits = s[1..].parse::<u8>().map_err(|_| err())?;
+    let max = if signed { 64 } else { 63 };
+    if bits == 0 || bits > max {
+        return Err(err());
+    }
+    Ok(BitfieldType { signed, bits })
+}
+
+// either an absolute bit offset or `#N`, which means the N-th field of this type
+fn parse_offset(frame: Option<RespFrame>, ty: BitfieldType) -> Result<u64, ExecuteError> {
+    let err = || InvalidArgument("bit offset is not an integer or out of range".to_string());
+    let s = frame_to_string(frame.ok_or_else(err)?)?;
+    let offset = match s.strip_prefix('#') {
+        Some(n) => n
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(ty.bits as u64)),
+        None => s.parse::<u64>().ok(),
+    };
+    match offset {
+        Some(offset) if offset < MAX_BIT_OFFSET => Ok(offset),
+        _ => Err(err()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitfield(backend: &Backend, args: &[&str]) -> anyhow::Result<RespFrame> {
+        let mut vec: Vec<RespFrame> = vec![RespBulkString::new("bitfield").into()];
+        vec.extend(args.iter().map(|s| RespBulkString::new(s).into()));
+        BitFieldCommand::try_from(RespArray::new(vec))?.execute(backend.clone())
+    }
+
+    fn ints(vals: &[Option<i64>]) -> RespFrame {
+        results_to_frame(vals.to_vec())
+    }
+
+    #[test]
+    fn test_bitfield_try_from() -> anyhow::Result<()> {
+        let arr = RespArray::new(vec![
+            RespBulkString::new("bitfield").into(),
+            RespBulkString::new("k").into(),
+            RespBulkString::new("OVERFLOW").into(),
+            RespBulkString::new("sat").into(),
+            RespBulkString::new("incrby").into(),
+            RespBulkString::new("u4").into(),
+            RespBulkString::new("#2").into(),
+            RespBulkString::new("-3").into(),
+        ]);
+        let cmd = BitFieldCommand::try_from(arr)?;
+        let u4 = BitfieldType {
+            signed: false,
+            bits: 4,
+        };
+        assert_eq!(
+            cmd.ops,
+            vec![
+                BitfieldOp::Overflow(BitfieldOverflow::Sat),
+                BitfieldOp::IncrBy(u4, 8, -3)
+            ]
+        );
+
+        let arr = RespArray::new(vec![
+            RespBulkString::new("bitfield_ro").into(),
+            RespBulkString::new("k").into(),
+            RespBulkString::new("set").into(),
+            RespBulkString::new("u4").into(),
+            RespBulkString::new("0").into(),
+            RespBulkString::new("1").into(),
+        ]);
+        assert!(BitFieldRoCommand::try_from(arr).is_err());
+
+        for ty in ["u64", "i65", "x8", "i0"] {
+            assert!(bitfield(&Backend::default(), &["k", "get", ty, "0"]).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_get_set() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let ret = bitfield(&backend, &["k", "get", "u8", "0"])?;
+        assert_eq!(ret, ints(&[Some(0)]));
+        assert_eq!(backend.get("k"), None);
+
+        let ret = bitfield(&backend, &["k", "set", "u8", "#1", "255", "get", "u4", "8"])?;
+        assert_eq!(ret, ints(&[Some(0), Some(15)]));
+        assert_eq!(
+            backend.get("k"),
+            Some(RespBulkString::new([0u8, 255]).into())
+        );
+
+        let ret = bitfield(&backend, &["k", "get", "i8", "8", "get", "i5", "6"])?;
+        assert_eq!(ret, ints(&[Some(-1), Some(7)]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_overflow() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let ret = bitfield(
+            &backend,
+            &[
+                "k", "incrby", "u2", "100", "1", "overflow", "sat", "incrby", "u2", "102", "1",
+            ],
+        )?;
+        assert_eq!(ret, ints(&[Some(1), Some(1)]));
+
+        let ret = bitfield(
+            &backend,
+            &[
+                "k", "incrby", "u2", "100", "3", "overflow", "sat", "incrby", "u2", "102", "5",
+            ],
+        )?;
+        assert_eq!(ret, ints(&[Some(0), Some(3)]));
+
+        let ret = bitfield(
+            &backend,
+            &[
+                "k", "overflow", "fail", "incrby", "u2", "102", "1", "get", "u2", "102",
+            ],
+        )?;
+        assert_eq!(ret, ints(&[None, Some(3)]));
+
+        let ret = bitfield(
+            &backend,
+            &[
+                "k", "set", "i8", "0", "127", "incrby", "i8", "0", "1", "get", "i8", "0",
+            ],
+        )?;
+        assert_eq!(ret, ints(&[Some(0), Some(-128), Some(-128)]));
+
+        let ret = bitfield(
+            &backend,
+            &[
+                "k", "overflow", "sat", "set", "u8", "0", "-1", "incrby", "i64", "128", "-1",
+            ],
+        )?;
+        assert_eq!(ret, ints(&[Some(128), Some(-1)]));
+        let ret = bitfield(
+            &backend,
+            &[
+                "k",
+                "overflow",
+                "sat",
+                "incrby",
+                "i64",
+                "128",
+                &i64::MIN.to_string(),
+            ],
+        )?;
+        assert_eq!(ret, ints(&[Some(i64::MIN)]));
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use crate::backend::pubsub::PushSender;
+use crate::backend::tracking::TrackingOptions;
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{check_min_nargs, frame_to_i64, frame_to_string, into_args, ExecuteError, RET_OK};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+use crate::resp::map::RespMap;
+
+// Client: "*3\r\n$6\r\nclient\r\n$7\r\nunblock\r\n$1\r\n5\r\n"
+// Tracking: ON|OFF [REDIRECT id] [PREFIX prefix ...] [BCAST] [OPTIN] [OPTOUT] [NOLOOP]
+// Caching: YES|NO
+#[derive(Debug, PartialEq)]
+pub enum ClientCommand {
+    Id,
+    Unblock { id: u64, error: bool },
+    Tracking(Option<TrackingOptions>),
+    Caching(bool),
+    TrackingInfo,
+}
+
+// Hello: [protover]
+#[derive(Debug, PartialEq)]
+pub struct HelloCommand {
+    protover: Option<i64>,
+}
+
+// a map for RESP3, the same pairs in a row for RESP2
+fn map_reply(pairs: Vec<(&str, RespFrame)>, protocol: u8) -> RespFrame {
+    if protocol == 3 {
+        let mut map = RespMap::new();
+        for (key, value) in pairs {
+            map.insert(RespBulkString::new(key).into(), value);
+        }
+        return map.into();
+    }
+    let flat = pairs
+        .into_iter()
+        .flat_map(|(key, value)| [RespBulkString::new(key).into(), value])
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(flat).into()
+}
+
+fn strings(items: Vec<impl Into<String>>) -> RespFrame {
+    let items = items
+        .into_iter()
+        .map(|item| RespBulkString::new(item.into()).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(items).into()
+}
+
+impl ClientCommand {
+    /// `client_id` is the id of the connection running the command, `sender`
+    /// where its pushes go and `protocol` the RESP version it speaks.
+    pub fn execute(
+        self,
+        backend: Backend,
+        client_id: u64,
+        sender: &PushSender,
+        protocol: u8,
+    ) -> anyhow::Result<RespFrame> {
+        match self {
+            ClientCommand::Id => Ok(RespFrame::Integer(client_id as i64)),
+            ClientCommand::Unblock { id, error } => {
+                Ok(RespFrame::Integer(backend.unblock(id, error) as i64))
+            }
+            ClientCommand::Tracking(Some(options)) => {
+                backend.track(client_id, sender, protocol, options)?;
+                Ok(RET_OK.clone())
+            }
+            ClientCommand::Tracking(None) => {
+                backend.untrack(client_id);
+                Ok(RET_OK.clone())
+            }
+            ClientCommand::Caching(yes) => {
+                backend.set_caching(client_id, yes)?;
+                Ok(RET_OK.clone())
+            }
+            ClientCommand::TrackingInfo => {
+                let info = backend.tracking_info(client_id);
+                let pairs = vec![
+                    ("flags", strings(info.flags)),
+                    ("redirect", RespFrame::Integer(info.redirect)),
+                    ("prefixes", strings(info.prefixes)),
+                ];
+                Ok(map_reply(pairs, protocol))
+            }
+        }
+    }
+}
+
+impl HelloCommand {
+    /// Switches the connection to the protocol asked for, `protocol` being
+    /// the one it speaks. Replies with what the server is about.
+    pub fn execute(
+        self,
+        _backend: Backend,
+        client_id: u64,
+        protocol: &mut u8,
+    ) -> anyhow::Result<RespFrame> {
+        match self.protover {
+            None => {}
+            Some(v @ (2 | 3)) => *protocol = v as u8,
+            Some(_) => anyhow::bail!("NOPROTO unsupported protocol version"),
+        }
+        let info: Vec<(&str, RespFrame)> = vec![
+            ("server", RespBulkString::new("redis").into()),
+            (
+                "version",
+                RespBulkString::new(env!("CARGO_PKG_VERSION")).into(),
+            ),
+            ("proto", RespFrame::Integer(*protocol as i64)),
+            ("id", RespFrame::Integer(client_id as i64)),
+            ("mode", RespBulkString::new("standalone").into()),
+            ("role", RespBulkString::new("master").into()),
+            ("modules", RespArray::new(vec![]).into()),
+        ];
+        Ok(map_reply(info, *protocol))
+    }
+}
+
+impl TryFrom<RespArray> for HelloCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        if args.len() > 1 {
+            return Err(InvalidArgument(
+                "only HELLO [protover] is supported".to_string(),
+            ));
+        }
+        let protover = args
+            .into_iter()
+            .next()
+            .map(|v| {
+                frame_to_i64(v).map_err(|_| {
+                    InvalidArgument(
+                        "Protocol version is not an integer or out of range".to_string(),
+                    )
+                })
+            })
+            .transpose()?;
+        Ok(HelloCommand { protover })
+    }
+}
+
+impl TryFrom<RespArray> for ClientCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let sub = frame_to_string(args.next().expect("subcommand has to exist"))?;
+        let args = args.collect::<Vec<_>>();
+        match (sub.to_ascii_lowercase().as_str(), args.len()) {
+            ("id", 0) => Ok(ClientCommand::Id),
+            ("unblock", 1 | 2) => {
+                let mut args = args.into_iter();
+                let id = frame_to_i64(args.next().expect("client id has to exist"))?;
+                let error = match args.next().map(frame_to_string).transpose()? {
+                    None => false,
+                    Some(s) if s.eq_ignore_ascii_case("timeout") => false,
+                    Some(s) if s.eq_ignore_ascii_case("error") => true,
+                    Some(_) => {
+                        return Err(InvalidArgument(
+                            "CLIENT UNBLOCK reason should be TIMEOUT or ERROR".to_string(),
+                        ))
+                    }
+                };
+                Ok(ClientCommand::Unblock {
+                    id: id.max(0) as u64,
+                    error,
+                })
+            }
+            ("tracking", 1..) => parse_tracking(args),
+            ("caching", 1) => {
+                let yes = frame_to_string(args.into_iter().next().expect("mode has to exist"))?;
+                match yes.to_ascii_lowercase().as_str() {
+                    "yes" => Ok(ClientCommand::Caching(true)),
+                    "no" => Ok(ClientCommand::Caching(false)),
+                    _ => Err(InvalidArgument("syntax error".to_string())),
+                }
+            }
+            ("trackinginfo", 0) => Ok(ClientCommand::TrackingInfo),
+            _ => Err(InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+        }
+    }
+}
+
+fn parse_tracking(args: Vec<RespFrame>) -> Result<ClientCommand, ExecuteError> {
+    let mut args = args.into_iter();
+    let on = match frame_to_string(args.next().expect("on or off has to exist"))?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "on" => true,
+        "off" => false,
+        _ => return Err(InvalidArgument("syntax error".to_string())),
+    };
+    let mut options = TrackingOptions::default();
+    while let Some(arg) = args.next() {
+        match frame_to_string(arg)?.to_ascii_lowercase().as_str() {
+            "redirect" => {
+                let id = args
+                    .next()
+                    .ok_or_else(|| InvalidArgument("syntax error".to_string()))?;
+                options.redirect = Some(frame_to_i64(id)?.max(0) as u64);
+            }
+            "prefix" => {
+                let prefix = args
+                    .next()
+                    .ok_or_else(|| InvalidArgument("syntax error".to_string()))?;
+                options.prefixes.push(frame_to_string(prefix)?);
+            }
+            "bcast" => options.bcast = true,
+            "optin" => options.optin = true,
+            "optout" => options.optout = true,
+            "noloop" => options.noloop = true,
+            _ => return Err(InvalidArgument("syntax error".to_string())),
+        }
+    }
+    Ok(ClientCommand::Tracking(on.then_some(options)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_client_try_from() -> anyhow::Result<()> {
+        assert_eq!(
+            ClientCommand::try_from(cmd(&["client", "UNBLOCK", "7", "error"]))?,
+            ClientCommand::Unblock { id: 7, error: true }
+        );
+        assert_eq!(
+            ClientCommand::try_from(cmd(&["client", "id"]))?,
+            ClientCommand::Id
+        );
+        assert!(ClientCommand::try_from(cmd(&["client", "unblock", "7", "later"])).is_err());
+        assert!(ClientCommand::try_from(cmd(&["client", "nope"])).is_err());
+        assert_eq!(
+            ClientCommand::try_from(cmd(&[
+                "client", "tracking", "on", "bcast", "prefix", "a:", "NOLOOP"
+            ]))?,
+            ClientCommand::Tracking(Some(TrackingOptions {
+                bcast: true,
+                prefixes: vec!["a:".to_string()],
+                noloop: true,
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            ClientCommand::try_from(cmd(&["client", "tracking", "off"]))?,
+            ClientCommand::Tracking(None)
+        );
+        assert!(ClientCommand::try_from(cmd(&["client", "tracking", "on", "redirect"])).is_err());
+        assert_eq!(
+            ClientCommand::try_from(cmd(&["client", "caching", "YES"]))?,
+            ClientCommand::Caching(true)
+        );
+
+        let mut protocol = 2;
+        HelloCommand::try_from(cmd(&["hello", "3"]))?.execute(
+            Backend::default(),
+            1,
+            &mut protocol,
+        )?;
+        assert_eq!(protocol, 3);
+        assert!(HelloCommand::try_from(cmd(&["hello", "4"]))?
+            .execute(Backend::default(), 1, &mut protocol)
+            .is_err());
+        assert_eq!(protocol, 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use crate::backend::glob::glob_match;
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, frame_to_string, into_args, CommandExecutor, ExecuteError, RET_OK,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+// Config: GET parameter [parameter ...] | SET parameter value [parameter value ...]
+#[derive(Debug, PartialEq)]
+pub enum ConfigCommand {
+    Get { patterns: Vec<String> },
+    Set { params: Vec<(String, String)> },
+}
+
+// the parameters there are, and whether CONFIG SET may change them
+const PARAMETERS: [(&str, bool); 4] = [
+    ("busy-reply-threshold", true),
+    ("databases", false),
+    // what `busy-reply-threshold` used to be called
+    ("lua-time-limit", true),
+    ("notify-keyspace-events", true),
+];
+
+fn get_parameter(backend: &Backend, name: &str) -> String {
+    match name {
+        "busy-reply-threshold" | "lua-time-limit" => backend.busy_reply_threshold().to_string(),
+        "databases" => backend.databases().to_string(),
+        "notify-keyspace-events" => backend.notify_keyspace_events(),
+        _ => unreachable!("only known parameters are read"),
+    }
+}
+
+fn set_parameter(backend: &Backend, name: &str, value: &str) -> anyhow::Result<()> {
+    match name {
+        "busy-reply-threshold" | "lua-time-limit" => {
+            let Ok(ms) = value.parse::<u64>() else {
+                anyhow::bail!("CONFIG SET failed (possibly related to argument '{}') - argument couldn't be parsed into an integer", name);
+            };
+            backend.set_busy_reply_threshold(ms);
+        }
+        "notify-keyspace-events" => backend.set_notify_keyspace_events(value)?,
+        _ => unreachable!("only settable parameters are set"),
+    }
+    Ok(())
+}
+
+impl CommandExecutor for ConfigCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        match self {
+            ConfigCommand::Get { patterns } => {
+                let pairs = PARAMETERS
+                    .iter()
+                    .filter(|(name, _)| {
+                        patterns
+                            .iter()
+                            .any(|p| glob_match(p.to_ascii_lowercase().as_bytes(), name.as_bytes()))
+                    })
+                    .flat_map(|(name, _)| {
+                        [
+                            RespBulkString::new(name).into(),
+                            RespBulkString::new(get_parameter(&backend, name)).into(),
+                        ]
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(pairs).into())
+            }
+            ConfigCommand::Set { params } => {
+                for (name, _) in &params {
+                    match PARAMETERS.iter().find(|(known, _)| known == name) {
+                        Some((_, true)) => {}
+                        Some((_, false)) => {
+                            anyhow::bail!("CONFIG SET failed (possibly related to argument '{}') - can't set immutable config", name)
+                        }
+                        None => {
+                            anyhow::bail!(
+                                "Unknown option or number of arguments for CONFIG SET - '{}'",
+                                name
+                            )
+                        }
+                    }
+                }
+                for (name, value) in params {
+                    set_parameter(&backend, &name, &value)?;
+                }
+                Ok(RET_OK.clone())
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ConfigCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let sub = frame_to_string(args.next().expect("subcommand has to exist"))?;
+        let args = args.map(frame_to_string).collect::<Result<Vec<_>, _>>()?;
+        match sub.to_ascii_lowercase().as_str() {
+            "get" if !args.is_empty() => Ok(ConfigCommand::Get { patterns: args }),
+            "set" if !args.is_empty() && args.len() % 2 == 0 => {
+                let params = args
+                    .chunks(2)
+                    .map(|pair| (pair[0].to_ascii_lowercase(), pair[1].clone()))
+                    .collect();
+                Ok(ConfigCommand::Set { params })
+            }
+            _ => Err(InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_config() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let set = ConfigCommand::try_from(cmd(&["config", "SET", "Notify-Keyspace-Events", "Ex"]))?;
+        assert_eq!(set.execute(backend.clone())?, RET_OK.clone());
+        let get = ConfigCommand::try_from(cmd(&["config", "get", "notify-*"]))?;
+        assert_eq!(
+            get.execute(backend.clone())?,
+            cmd(&["notify-keyspace-events", "xE"]).into()
+        );
+        let set = ConfigCommand::try_from(cmd(&["config", "set", "databases", "4"]))?;
+        assert!(set.execute(backend.clone()).is_err());
+        let set = ConfigCommand::try_from(cmd(&["config", "set", "notify-keyspace-events", "?"]))?;
+        assert!(set.execute(backend.clone()).is_err());
+        assert!(ConfigCommand::try_from(cmd(&["config", "set", "databases"])).is_err());
+        let set = ConfigCommand::try_from(cmd(&["config", "set", "lua-time-limit", "100"]))?;
+        set.execute(backend.clone())?;
+        assert_eq!(backend.busy_reply_threshold(), 100);
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{check_nargs, frame_to_string, into_args, CommandExecutor, ExecuteError, RET_OK};
+use crate::resp::array::RespArray;
+use crate::resp::frame::RespFrame;
+
+// Select: "*2\r\n$6\r\nselect\r\n$1\r\n1\r\n"
+#[derive(Debug, PartialEq)]
+pub struct SelectCommand {
+    index: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SwapDbCommand {
+    a: usize,
+    b: usize,
+}
+
+// Move: key db
+#[derive(Debug, PartialEq)]
+pub struct MoveCommand {
+    key: String,
+    index: usize,
+}
+
+#[derive(Debug)]
+pub struct DbSizeCommand;
+
+// FlushDb: [ASYNC|SYNC]
+#[derive(Debug, PartialEq)]
+pub struct FlushDbCommand {
+    lazy: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FlushAllCommand {
+    lazy: bool,
+}
+
+impl SelectCommand {
+    /// `db` is the database of the connection running the command.
+    pub fn execute(self, backend: Backend, db: &mut usize) -> anyhow::Result<RespFrame> {
+        *db = backend.select(self.index)?.index();
+        Ok(RET_OK.clone())
+    }
+}
+
+impl CommandExecutor for SwapDbCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        backend.swapdb(self.a, self.b)?;
+        Ok(RET_OK.clone())
+    }
+}
+
+impl CommandExecutor for MoveCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespFrame::Integer(
+            backend.move_key(&self.key, self.index)? as i64
+        ))
+    }
+}
+
+impl CommandExecutor for DbSizeCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespFrame::Integer(backend.dbsize() as i64))
+    }
+}
+
+impl CommandExecutor for FlushDbCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        backend.flushdb(self.lazy);
+        Ok(RET_OK.clone())
+    }
+}
+
+impl CommandExecutor for FlushAllCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        backend.flushall(self.lazy);
+        Ok(RET_OK.clone())
+    }
+}
+
+impl TryFrom<RespArray> for SelectCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 1)?;
+        let index = parse_index(args.into_iter().next().expect("index has to exist"))?;
+        Ok(SelectCommand { index })
+    }
+}
+
+impl TryFrom<RespArray> for SwapDbCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let a = parse_index(args.next().expect("index has to exist"))?;
+        let b = parse_index(args.next().expect("index has to exist"))?;
+        Ok(SwapDbCommand { a, b })
+    }
+}
+
+impl TryFrom<RespArray> for MoveCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let index = parse_index(args.next().expect("index has to exist"))?;
+        Ok(MoveCommand { key, index })
+    }
+}
+
+impl TryFrom<RespArray> for DbSizeCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        check_nargs(&into_args(arr)?, 0)?;
+        Ok(DbSizeCommand)
+    }
+}
+
+impl TryFrom<RespArray> for FlushDbCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(FlushDbCommand {
+            lazy: parse_flush_mode(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FlushAllCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(FlushAllCommand {
+            lazy: parse_flush_mode(arr)?,
+        })
+    }
+}
+
+fn parse_index(frame: RespFrame) -> Result<usize, ExecuteError> {
+    let index = frame_to_string(frame)?
+        .parse::<i64>()
+        .map_err(|_| InvalidArgument("invalid DB index".to_string()))?;
+    usize::try_from(index).map_err(|_| InvalidArgument("DB index is out of range".to_string()))
+}
+
+// [ASYNC|SYNC], true for ASYNC
+fn parse_flush_mode(arr: RespArray) -> Result<bool, ExecuteError> {
+    let args = into_args(arr)?;
+    match args.len() {
+        0 => Ok(false),
+        1 => {
+            let mode = frame_to_string(args.into_iter().next().expect("mode has to exist"))?;
+            match mode.to_ascii_lowercase().as_str() {
+                "async" => Ok(true),
+                "sync" => Ok(false),
+                _ => Err(InvalidArgument("syntax error".to_string())),
+            }
+        }
+        _ => Err(InvalidArgument("syntax error".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_db_try_from() -> anyhow::Result<()> {
+        assert_eq!(
+            SelectCommand::try_from(cmd(&["select", "3"]))?,
+            SelectCommand { index: 3 }
+        );
+        assert!(SelectCommand::try_from(cmd(&["select", "-1"])).is_err());
+        assert!(SelectCommand::try_from(cmd(&["select", "one"])).is_err());
+        assert_eq!(
+            MoveCommand::try_from(cmd(&["move", "key", "2"]))?,
+            MoveCommand {
+                key: "key".to_string(),
+                index: 2
+            }
+        );
+        assert_eq!(
+            FlushDbCommand::try_from(cmd(&["flushdb", "ASYNC"]))?,
+            FlushDbCommand { lazy: true }
+        );
+        assert_eq!(
+            FlushAllCommand::try_from(cmd(&["flushall"]))?,
+            FlushAllCommand { lazy: false }
+        );
+        assert!(FlushAllCommand::try_from(cmd(&["flushall", "later"])).is_err());
+
+        let backend = Backend::default();
+        let mut db = 0;
+        SelectCommand { index: 15 }.execute(backend.clone(), &mut db)?;
+        assert_eq!(db, 15);
+        assert!(SelectCommand { index: 16 }
+            .execute(backend, &mut db)
+            .is_err());
+        assert_eq!(db, 15);
+        Ok(())
+    }
+}
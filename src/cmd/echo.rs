@@ -1,8 +1,10 @@
 use crate::backend::Backend;
-use crate::cmd::ExecuteError::InvalidCommand;
-use crate::cmd::{CommandExecutor, ExecuteError};
+use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
+use crate::cmd::{into_args, CommandExecutor, ExecuteError};
 use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
 use crate::resp::frame::RespFrame;
+use crate::resp::simple_string::RespSimpleString;
 
 #[derive(Debug)]
 pub struct ECHOCommand {
@@ -28,3 +30,41 @@ impl TryFrom<RespArray> for ECHOCommand {
         })
     }
 }
+
+// Ping: [message]
+#[derive(Debug)]
+pub struct PingCommand {
+    message: Option<RespFrame>,
+}
+
+impl PingCommand {
+    /// A RESP2 client in subscriber mode only reads arrays, so `subscribed`
+    /// turns the reply into one.
+    pub fn execute(self, _backend: Backend, subscribed: bool) -> anyhow::Result<RespFrame> {
+        if subscribed {
+            let message = self
+                .message
+                .unwrap_or_else(|| RespBulkString::new("").into());
+            return Ok(RespArray::new(vec![RespBulkString::new("pong").into(), message]).into());
+        }
+        Ok(self
+            .message
+            .unwrap_or_else(|| RespSimpleString::new("PONG").into()))
+    }
+}
+
+impl TryFrom<RespArray> for PingCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        if args.len() > 1 {
+            return Err(InvalidArgument(
+                "wrong number of arguments for 'ping' command".to_string(),
+            ));
+        }
+        Ok(PingCommand {
+            message: args.into_iter().next(),
+        })
+    }
+}
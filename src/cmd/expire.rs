@@ -0,0 +1,168 @@
+use crate::backend::hmap::ExpireCondition;
+use crate::backend::{now_ms, Backend};
+use crate::cmd::hexpire::ExpireTime;
+use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
+use crate::cmd::{
+    check_min_nargs, check_nargs, command_name, frame_to_i64, frame_to_string, into_args,
+    CommandExecutor, ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::frame::RespFrame;
+
+/// EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT.
+// Expire: "*3\r\n$6\r\nexpire\r\n$3\r\nkey\r\n$2\r\n10\r\n"
+#[derive(Debug, PartialEq)]
+pub struct ExpireCommand {
+    key: String,
+    time: ExpireTime,
+    condition: Option<ExpireCondition>,
+}
+
+/// TTL, PTTL, EXPIRETIME and PEXPIRETIME.
+#[derive(Debug, PartialEq)]
+pub struct TtlCommand {
+    key: String,
+    millis: bool,
+    absolute: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PersistCommand {
+    key: String,
+}
+
+impl CommandExecutor for ExpireCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.expire(&self.key, self.time.at(), self.condition);
+        Ok(RespFrame::Integer(ret))
+    }
+}
+
+impl CommandExecutor for TtlCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let now = now_ms() as i64;
+        let ret = match backend.expiretime(&self.key) {
+            when @ (-2 | -1) => when,
+            when if self.absolute && self.millis => when,
+            when if self.absolute => when / 1000,
+            when if self.millis => (when - now).max(0),
+            // round up so a key with a TTL never reports 0 seconds left
+            when => ((when - now).max(0) + 999) / 1000,
+        };
+        Ok(RespFrame::Integer(ret))
+    }
+}
+
+impl CommandExecutor for PersistCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespFrame::Integer(backend.persist(&self.key)))
+    }
+}
+
+impl TryFrom<RespArray> for ExpireCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&arr);
+        let (millis, absolute) = match name.as_str() {
+            "expire" => (false, false),
+            "pexpire" => (true, false),
+            "expireat" => (false, true),
+            "pexpireat" => (true, true),
+            _ => return Err(InvalidCommand(name)),
+        };
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        if args.len() > 3 {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let time = frame_to_i64(args.next().expect("time has to exist"))?;
+        let time = ExpireTime::new(time, millis, absolute)?;
+        let condition = match args.next().map(frame_to_string).transpose()? {
+            None => None,
+            Some(option) => Some(match option.to_ascii_lowercase().as_str() {
+                "nx" => ExpireCondition::Nx,
+                "xx" => ExpireCondition::Xx,
+                "gt" => ExpireCondition::Gt,
+                "lt" => ExpireCondition::Lt,
+                _ => return Err(InvalidArgument(format!("Unsupported option {}", option))),
+            }),
+        };
+        Ok(ExpireCommand {
+            key,
+            time,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TtlCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&arr);
+        let (millis, absolute) = match name.as_str() {
+            "ttl" => (false, false),
+            "pttl" => (true, false),
+            "expiretime" => (false, true),
+            "pexpiretime" => (true, true),
+            _ => return Err(InvalidCommand(name)),
+        };
+        let args = into_args(arr)?;
+        check_nargs(&args, 1)?;
+        let key = frame_to_string(args.into_iter().next().expect("key has to exist"))?;
+        Ok(TtlCommand {
+            key,
+            millis,
+            absolute,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PersistCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 1)?;
+        let key = frame_to_string(args.into_iter().next().expect("key has to exist"))?;
+        Ok(PersistCommand { key })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::cmd;
+    use crate::resp::bulkstring::RespBulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_expire_ttl_persist() -> anyhow::Result<()> {
+        let mut backend = Backend::default();
+        backend.set("k", RespBulkString::new("v").into());
+        assert_eq!(
+            ExpireCommand::try_from(cmd(&["pexpireat", "k", "1000", "NX"]))?,
+            ExpireCommand {
+                key: "k".to_string(),
+                time: ExpireTime::Absolute(1000),
+                condition: Some(ExpireCondition::Nx),
+            }
+        );
+        assert!(ExpireCommand::try_from(cmd(&["expire", "k", "10", "YY"])).is_err());
+
+        let expire = ExpireCommand::try_from(cmd(&["expire", "k", "100"]))?;
+        assert_eq!(expire.execute(backend.clone())?, RespFrame::Integer(1));
+        let ttl = TtlCommand::try_from(cmd(&["ttl", "k"]))?;
+        assert_eq!(ttl.execute(backend.clone())?, RespFrame::Integer(100));
+        let persist = PersistCommand::try_from(cmd(&["persist", "k"]))?;
+        assert_eq!(persist.execute(backend.clone())?, RespFrame::Integer(1));
+        let pttl = TtlCommand::try_from(cmd(&["pttl", "k"]))?;
+        assert_eq!(pttl.execute(backend.clone())?, RespFrame::Integer(-1));
+        let pttl = TtlCommand::try_from(cmd(&["pttl", "missing"]))?;
+        assert_eq!(pttl.execute(backend)?, RespFrame::Integer(-2));
+        Ok(())
+    }
+}
@@ -0,0 +1,493 @@
+use crate::backend::geo::{GeoFrom, GeoMatch, GeoSearch, GeoSort, GeoUnit};
+use crate::backend::geohash::{GeoShape, LAT_MAX, LAT_MIN, LON_MAX, LON_MIN};
+use crate::backend::zset::ZAddFlags;
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, frame_to_f64, frame_to_i64, frame_to_string, into_args, parse_key_names,
+    CommandExecutor, ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+// GeoAdd: key [NX|XX] [CH] longitude latitude member [longitude latitude member ...]
+#[derive(Debug, PartialEq)]
+pub struct GeoAddCommand {
+    key: String,
+    flags: ZAddFlags,
+    items: Vec<(f64, f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct GeoPosCommand {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoDistCommand {
+    key: String,
+    member1: String,
+    member2: String,
+    unit: GeoUnit,
+}
+
+#[derive(Debug)]
+pub struct GeoHashCommand {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GeoSearchCommand {
+    key: String,
+    search: GeoSearch,
+    with: GeoWith,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GeoSearchStoreCommand {
+    destination: String,
+    source: String,
+    search: GeoSearch,
+    store_dist: bool,
+}
+
+/// What each `GEOSEARCH` match is replied with besides its name.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct GeoWith {
+    coord: bool,
+    dist: bool,
+    hash: bool,
+}
+
+impl CommandExecutor for GeoAddCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.geoadd(&self.key, self.flags, self.items)?.into())
+    }
+}
+
+impl CommandExecutor for GeoPosCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend
+            .geopos(&self.key, &self.members)
+            .into_iter()
+            .map(|pos| match pos {
+                Some((lon, lat)) => coord_frame(lon, lat),
+                None => RespArray::null().into(),
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for GeoDistCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.geodist(&self.key, &self.member1, &self.member2);
+        Ok(match ret {
+            Some(dist) => dist_frame(dist / self.unit.meters()),
+            None => RespBulkString::null().into(),
+        })
+    }
+}
+
+impl CommandExecutor for GeoHashCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend
+            .geohash(&self.key, &self.members)
+            .into_iter()
+            .map(|hash| match hash {
+                Some(hash) => RespBulkString::new(hash).into(),
+                None => RespBulkString::null().into(),
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for GeoSearchCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend
+            .geosearch(&self.key, &self.search)?
+            .into_iter()
+            .map(|m| match_frame(m, self.with))
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for GeoSearchStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.geosearchstore(
+            &self.destination,
+            &self.source,
+            &self.search,
+            self.store_dist,
+        )?;
+        Ok(ret.into())
+    }
+}
+
+impl TryFrom<RespArray> for GeoAddCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 4)?;
+        let mut args = args.into_iter().peekable();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+
+        let mut flags = ZAddFlags::default();
+        while let Some(frame) = args.peek() {
+            let opt = match frame {
+                RespFrame::BulkString(s) => s.as_deref().unwrap_or_default().to_ascii_lowercase(),
+                _ => break,
+            };
+            match opt.as_slice() {
+                b"nx" => flags.nx = true,
+                b"xx" => flags.xx = true,
+                b"ch" => flags.ch = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if flags.nx && flags.xx {
+            return Err(InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let args: Vec<RespFrame> = args.collect();
+        if args.is_empty() || !args.len().is_multiple_of(3) {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        let mut items = Vec::with_capacity(args.len() / 3);
+        let mut args = args.into_iter();
+        while let (Some(lon), Some(lat), Some(member)) = (args.next(), args.next(), args.next()) {
+            let (lon, lat) = parse_lon_lat(lon, lat)?;
+            items.push((lon, lat, frame_to_string(member)?));
+        }
+        Ok(GeoAddCommand { key, flags, items })
+    }
+}
+
+impl TryFrom<RespArray> for GeoPosCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_names(arr, 0)?;
+        Ok(GeoPosCommand { key, members })
+    }
+}
+
+// GeoDist: key member1 member2 [M|KM|FT|MI]
+impl TryFrom<RespArray> for GeoDistCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        if args.len() > 4 {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let member1 = frame_to_string(args.next().expect("member1 has to exist"))?;
+        let member2 = frame_to_string(args.next().expect("member2 has to exist"))?;
+        let unit = match args.next() {
+            Some(unit) => parse_unit(unit)?,
+            None => GeoUnit::M,
+        };
+        Ok(GeoDistCommand {
+            key,
+            member1,
+            member2,
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoHashCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_names(arr, 0)?;
+        Ok(GeoHashCommand { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let (search, with, _) = parse_geosearch(args, false)?;
+        Ok(GeoSearchCommand { key, search, with })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let destination = frame_to_string(args.next().expect("destination has to exist"))?;
+        let source = frame_to_string(args.next().expect("source has to exist"))?;
+        let (search, _, store_dist) = parse_geosearch(args, true)?;
+        Ok(GeoSearchStoreCommand {
+            destination,
+            source,
+            search,
+            store_dist,
+        })
+    }
+}
+
+/// Parses `FROMMEMBER member | FROMLONLAT lon lat`, `BYRADIUS radius unit |
+/// BYBOX width height unit` and the options that may follow: `ASC|DESC`,
+/// `COUNT count [ANY]`, then `WITHCOORD`, `WITHDIST` and `WITHHASH` for
+/// `GEOSEARCH` or `STOREDIST` for `GEOSEARCHSTORE`.
+fn parse_geosearch(
+    mut args: impl Iterator<Item = RespFrame>,
+    store: bool,
+) -> Result<(GeoSearch, GeoWith, bool), ExecuteError> {
+    let syntax_error = || InvalidArgument("syntax error".to_string());
+    let (mut from, mut shape, mut unit, mut sort, mut count) = (None, None, GeoUnit::M, None, None);
+    let (mut with, mut store_dist, mut any) = (GeoWith::default(), false, false);
+    while let Some(opt) = args.next() {
+        let mut next = || args.next().ok_or_else(syntax_error);
+        match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+            "frommember" if from.is_none() => {
+                from = Some(GeoFrom::Member(frame_to_string(next()?)?));
+            }
+            "fromlonlat" if from.is_none() => {
+                let (lon, lat) = (next()?, next()?);
+                let (lon, lat) = parse_lon_lat(lon, lat)?;
+                from = Some(GeoFrom::LonLat(lon, lat));
+            }
+            "frommember" | "fromlonlat" => {
+                return Err(InvalidArgument(
+                    "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"
+                        .to_string(),
+                ))
+            }
+            "byradius" if shape.is_none() => {
+                let radius = frame_to_f64(next()?)?;
+                if radius < 0.0 {
+                    return Err(InvalidArgument("radius cannot be negative".to_string()));
+                }
+                unit = parse_unit(next()?)?;
+                shape = Some(GeoShape::Radius(radius * unit.meters()));
+            }
+            "bybox" if shape.is_none() => {
+                let (width, height) = (frame_to_f64(next()?)?, frame_to_f64(next()?)?);
+                if width < 0.0 || height < 0.0 {
+                    return Err(InvalidArgument(
+                        "height or width cannot be negative".to_string(),
+                    ));
+                }
+                unit = parse_unit(next()?)?;
+                shape = Some(GeoShape::Box {
+                    width: width * unit.meters(),
+                    height: height * unit.meters(),
+                });
+            }
+            "byradius" | "bybox" => {
+                return Err(InvalidArgument(
+                    "exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH".to_string(),
+                ))
+            }
+            "asc" => sort = Some(GeoSort::Asc),
+            "desc" => sort = Some(GeoSort::Desc),
+            "count" => {
+                let n = frame_to_i64(next()?)?;
+                if n <= 0 {
+                    return Err(InvalidArgument("COUNT must be > 0".to_string()));
+                }
+                count = Some(n as usize);
+            }
+            "any" => any = true,
+            "withcoord" if !store => with.coord = true,
+            "withdist" if !store => with.dist = true,
+            "withhash" if !store => with.hash = true,
+            "storedist" if store => store_dist = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let Some(from) = from else {
+        return Err(InvalidArgument(
+            "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH".to_string(),
+        ));
+    };
+    let Some(shape) = shape else {
+        return Err(InvalidArgument(
+            "exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH".to_string(),
+        ));
+    };
+    if any && count.is_none() {
+        return Err(InvalidArgument(
+            "the ANY argument requires COUNT argument".to_string(),
+        ));
+    }
+    let search = GeoSearch {
+        from,
+        shape,
+        unit,
+        sort,
+        count: count.map(|count| (count, any)),
+    };
+    Ok((search, with, store_dist))
+}
+
+fn parse_lon_lat(lon: RespFrame, lat: RespFrame) -> Result<(f64, f64), ExecuteError> {
+    let (lon, lat) = (frame_to_f64(lon)?, frame_to_f64(lat)?);
+    if !(LON_MIN..=LON_MAX).contains(&lon) || !(LAT_MIN..=LAT_MAX).contains(&lat) {
+        return Err(InvalidArgument(format!(
+            "invalid longitude,latitude pair {:.6},{:.6}",
+            lon, lat
+        )));
+    }
+    Ok((lon, lat))
+}
+
+fn parse_unit(frame: RespFrame) -> Result<GeoUnit, ExecuteError> {
+    match frame_to_string(frame)?.to_ascii_lowercase().as_str() {
+        "m" => Ok(GeoUnit::M),
+        "km" => Ok(GeoUnit::Km),
+        "ft" => Ok(GeoUnit::Ft),
+        "mi" => Ok(GeoUnit::Mi),
+        _ => Err(InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )),
+    }
+}
+
+/// Distances are replied as strings with four decimals.
+fn dist_frame(dist: f64) -> RespFrame {
+    RespBulkString::new(format!("{:.4}", dist)).into()
+}
+
+fn coord_frame(lon: f64, lat: f64) -> RespFrame {
+    RespArray::new(vec![
+        RespBulkString::new(lon.to_string()).into(),
+        RespBulkString::new(lat.to_string()).into(),
+    ])
+    .into()
+}
+
+// the bare member, or `[member, dist, hash, [lon, lat]]` with what was asked
+fn match_frame(m: GeoMatch, with: GeoWith) -> RespFrame {
+    if with == GeoWith::default() {
+        return RespBulkString::new(m.member).into();
+    }
+    let mut ret = vec![RespBulkString::new(m.member).into()];
+    if with.dist {
+        ret.push(dist_frame(m.dist));
+    }
+    if with.hash {
+        ret.push(RespFrame::Integer(m.hash as i64));
+    }
+    if with.coord {
+        ret.push(coord_frame(m.lon, m.lat));
+    }
+    RespArray::new(ret).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_geoadd_try_from() -> anyhow::Result<()> {
+        let geoadd = GeoAddCommand::try_from(cmd(&["geoadd", "g", "ch", "13.5", "38", "p"]))?;
+        assert_eq!(
+            geoadd,
+            GeoAddCommand {
+                key: "g".to_string(),
+                flags: ZAddFlags {
+                    ch: true,
+                    ..Default::default()
+                },
+                items: vec![(13.5, 38.0, "p".to_string())],
+            }
+        );
+        assert!(GeoAddCommand::try_from(cmd(&["geoadd", "g", "0", "86", "p"])).is_err());
+        assert!(GeoAddCommand::try_from(cmd(&["geoadd", "g", "nx", "xx", "0", "0", "p"])).is_err());
+        assert!(GeoAddCommand::try_from(cmd(&["geoadd", "g", "0", "0"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_try_from() -> anyhow::Result<()> {
+        let geosearch = GeoSearchCommand::try_from(cmd(&[
+            "geosearch",
+            "g",
+            "FROMLONLAT",
+            "15",
+            "37",
+            "BYBOX",
+            "2",
+            "1",
+            "km",
+            "COUNT",
+            "3",
+            "ANY",
+            "WITHDIST",
+        ]))?;
+        assert_eq!(
+            geosearch.search,
+            GeoSearch {
+                from: GeoFrom::LonLat(15.0, 37.0),
+                shape: GeoShape::Box {
+                    width: 2000.0,
+                    height: 1000.0,
+                },
+                unit: GeoUnit::Km,
+                sort: None,
+                count: Some((3, true)),
+            }
+        );
+        assert!(geosearch.with.dist && !geosearch.with.coord);
+
+        let search = |args: &[&str]| {
+            let mut full = vec!["geosearch", "g"];
+            full.extend_from_slice(args);
+            GeoSearchCommand::try_from(cmd(&full))
+        };
+        assert!(search(&["FROMMEMBER", "a", "BYRADIUS", "1", "yd"]).is_err());
+        assert!(search(&[
+            "FROMMEMBER",
+            "a",
+            "FROMLONLAT",
+            "1",
+            "2",
+            "BYRADIUS",
+            "1",
+            "m"
+        ])
+        .is_err());
+        assert!(search(&["BYRADIUS", "1", "m"]).is_err());
+        assert!(search(&["FROMMEMBER", "a", "BYRADIUS", "1", "m", "ANY"]).is_err());
+        let store = cmd(&[
+            "geosearchstore",
+            "d",
+            "g",
+            "FROMMEMBER",
+            "a",
+            "BYRADIUS",
+            "1",
+            "m",
+            "WITHDIST",
+        ]);
+        assert!(GeoSearchStoreCommand::try_from(store).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,451 @@
+use crate::backend::hmap::{ExpireCondition, FieldCondition, FieldExpiry};
+use crate::backend::{now_ms, Backend};
+use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
+use crate::cmd::{
+    check_min_nargs, command_name, frame_to_i64, frame_to_string, into_args, CommandExecutor,
+    ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::frame::RespFrame;
+
+/// HEXPIRE, HPEXPIRE, HEXPIREAT and HPEXPIREAT.
+// HExpire: "*6\r\n$7\r\nhexpire\r\n$3\r\nmap\r\n$2\r\n10\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nhello\r\n"
+#[derive(Debug, PartialEq)]
+pub struct HExpireCommand {
+    key: String,
+    time: ExpireTime,
+    condition: Option<ExpireCondition>,
+    fields: Vec<String>,
+}
+
+/// HTTL, HPTTL, HEXPIRETIME and HPEXPIRETIME.
+#[derive(Debug, PartialEq)]
+pub struct HTtlCommand {
+    key: String,
+    millis: bool,
+    absolute: bool,
+    fields: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HPersistCommand {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HGetExCommand {
+    key: String,
+    expiry: Option<ExpireTime>,
+    persist: bool,
+    fields: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HSetExCommand {
+    key: String,
+    condition: Option<FieldCondition>,
+    expiry: Option<ExpireTime>,
+    keep_ttl: bool,
+    fields: Vec<(String, RespFrame)>,
+}
+
+/// An expiration as given on the command line, resolved against the clock
+/// only when the command executes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ExpireTime {
+    /// milliseconds from now
+    Relative(i64),
+    /// unix time in milliseconds
+    Absolute(i64),
+}
+
+impl ExpireTime {
+    pub(crate) fn at(&self) -> u64 {
+        match *self {
+            ExpireTime::Relative(ms) => (now_ms() as i64).saturating_add(ms).max(0) as u64,
+            ExpireTime::Absolute(ms) => ms.max(0) as u64,
+        }
+    }
+
+    // `EX`/`PX`/`EXAT`/`PXAT`
+    pub(crate) fn parse(
+        option: &str,
+        value: Option<RespFrame>,
+    ) -> Result<Option<Self>, ExecuteError> {
+        let (millis, absolute) = match option {
+            "ex" => (false, false),
+            "px" => (true, false),
+            "exat" => (false, true),
+            "pxat" => (true, true),
+            _ => return Ok(None),
+        };
+        let value = value.ok_or_else(|| InvalidArgument("syntax error".to_string()))?;
+        Self::new(frame_to_i64(value)?, millis, absolute).map(Some)
+    }
+
+    pub(crate) fn new(value: i64, millis: bool, absolute: bool) -> Result<Self, ExecuteError> {
+        let err = || InvalidArgument("invalid expire time".to_string());
+        if value < 0 {
+            return Err(err());
+        }
+        let ms = if millis {
+            value
+        } else {
+            value.checked_mul(1000).ok_or_else(err)?
+        };
+        Ok(if absolute {
+            ExpireTime::Absolute(ms)
+        } else {
+            ExpireTime::Relative(ms)
+        })
+    }
+}
+
+impl CommandExecutor for HExpireCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.hexpire(&self.key, self.time.at(), self.condition, &self.fields);
+        Ok(integers(ret))
+    }
+}
+
+impl CommandExecutor for HTtlCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let now = now_ms() as i64;
+        let ret = backend
+            .hexpiretime(&self.key, &self.fields)
+            .into_iter()
+            .map(|when| match when {
+                -2 | -1 => when,
+                when if self.absolute && self.millis => when,
+                when if self.absolute => when / 1000,
+                when if self.millis => (when - now).max(0),
+                // round up so a field with a TTL never reports 0 seconds left
+                when => ((when - now).max(0) + 999) / 1000,
+            })
+            .collect();
+        Ok(integers(ret))
+    }
+}
+
+impl CommandExecutor for HPersistCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(integers(backend.hpersist(&self.key, &self.fields)))
+    }
+}
+
+impl CommandExecutor for HGetExCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let expiry = match (self.expiry, self.persist) {
+            (Some(time), _) => FieldExpiry::At(time.at()),
+            (None, true) => FieldExpiry::Persist,
+            (None, false) => FieldExpiry::Keep,
+        };
+        let ret = backend.hgetex(&self.key, expiry, &self.fields);
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for HSetExCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let expiry = match (self.expiry, self.keep_ttl) {
+            (Some(time), _) => FieldExpiry::At(time.at()),
+            (None, true) => FieldExpiry::Keep,
+            (None, false) => FieldExpiry::Persist,
+        };
+        let ret = backend.hsetex(&self.key, self.condition, expiry, self.fields)?;
+        Ok(ret.into())
+    }
+}
+
+impl TryFrom<RespArray> for HExpireCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&arr);
+        let (millis, absolute) = match name.as_str() {
+            "hexpire" => (false, false),
+            "hpexpire" => (true, false),
+            "hexpireat" => (false, true),
+            "hpexpireat" => (true, true),
+            _ => return Err(InvalidCommand(name)),
+        };
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 5)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let time = frame_to_i64(args.next().expect("time has to exist"))?;
+        let time = ExpireTime::new(time, millis, absolute)?;
+
+        let mut rest = args.collect::<Vec<_>>();
+        let condition = match frame_to_string(rest[0].clone())?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "nx" => Some(ExpireCondition::Nx),
+            "xx" => Some(ExpireCondition::Xx),
+            "gt" => Some(ExpireCondition::Gt),
+            "lt" => Some(ExpireCondition::Lt),
+            _ => None,
+        };
+        if condition.is_some() {
+            rest.remove(0);
+        }
+        let fields = parse_fields(rest)?;
+        Ok(HExpireCommand {
+            key,
+            time,
+            condition,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HTtlCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&arr);
+        let (millis, absolute) = match name.as_str() {
+            "httl" => (false, false),
+            "hpttl" => (true, false),
+            "hexpiretime" => (false, true),
+            "hpexpiretime" => (true, true),
+            _ => return Err(InvalidCommand(name)),
+        };
+        let (key, fields) = parse_key_fields(arr)?;
+        Ok(HTtlCommand {
+            key,
+            millis,
+            absolute,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPersistCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = parse_key_fields(arr)?;
+        Ok(HPersistCommand { key, fields })
+    }
+}
+
+// HGetEx: "*6\r\n$6\r\nhgetex\r\n$3\r\nmap\r\n$2\r\nex\r\n$2\r\n10\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nhello\r\n"
+impl TryFrom<RespArray> for HGetExCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 4)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let mut expiry = None;
+        let mut persist = false;
+        let mut rest = args.collect::<Vec<_>>();
+        loop {
+            let option = frame_to_string(rest[0].clone())?.to_ascii_lowercase();
+            if option == "fields" {
+                break;
+            }
+            if expiry.is_some() || persist {
+                return Err(InvalidArgument("syntax error".to_string()));
+            }
+            if option == "persist" {
+                persist = true;
+                rest.remove(0);
+            } else {
+                expiry = ExpireTime::parse(&option, rest.get(1).cloned())?;
+                if expiry.is_none() {
+                    return Err(InvalidArgument("syntax error".to_string()));
+                }
+                rest.drain(..2);
+            }
+            if rest.is_empty() {
+                return Err(InvalidArgument("syntax error".to_string()));
+            }
+        }
+        let fields = parse_fields(rest)?;
+        Ok(HGetExCommand {
+            key,
+            expiry,
+            persist,
+            fields,
+        })
+    }
+}
+
+// HSetEx: "*7\r\n$6\r\nhsetex\r\n$3\r\nmap\r\n$2\r\nex\r\n$2\r\n10\r\n$6\r\nfields\r\n$1\r\n1\r\n$5\r\nhello\r\n$5\r\nworld\r\n"
+impl TryFrom<RespArray> for HSetExCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 5)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let mut condition = None;
+        let mut expiry = None;
+        let mut keep_ttl = false;
+        let mut rest = args.collect::<Vec<_>>();
+        loop {
+            let option = frame_to_string(rest[0].clone())?.to_ascii_lowercase();
+            match option.as_str() {
+                "fields" => break,
+                "fnx" | "fxx" if condition.is_none() => {
+                    condition = Some(if option == "fnx" {
+                        FieldCondition::Fnx
+                    } else {
+                        FieldCondition::Fxx
+                    });
+                    rest.remove(0);
+                }
+                "keepttl" if expiry.is_none() && !keep_ttl => {
+                    keep_ttl = true;
+                    rest.remove(0);
+                }
+                _ if expiry.is_none() && !keep_ttl => {
+                    expiry = ExpireTime::parse(&option, rest.get(1).cloned())?;
+                    if expiry.is_none() {
+                        return Err(InvalidArgument("syntax error".to_string()));
+                    }
+                    rest.drain(..2);
+                }
+                _ => return Err(InvalidArgument("syntax error".to_string())),
+            }
+            if rest.is_empty() {
+                return Err(InvalidArgument("syntax error".to_string()));
+            }
+        }
+
+        let numfields = parse_numfields(&rest)?;
+        // the count is the client's, doubling it could overflow
+        if rest.len() % 2 != 0 || (rest.len() - 2) / 2 != numfields {
+            return Err(InvalidArgument(
+                "wrong number of arguments for 'hsetex' command".to_string(),
+            ));
+        }
+        let mut args = rest.into_iter().skip(2);
+        let mut fields = Vec::with_capacity(numfields);
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((frame_to_string(field)?, value));
+        }
+        Ok(HSetExCommand {
+            key,
+            condition,
+            expiry,
+            keep_ttl,
+            fields,
+        })
+    }
+}
+
+fn integers(vals: Vec<i64>) -> RespFrame {
+    let vec = vals.into_iter().map(RespFrame::Integer).collect::<Vec<_>>();
+    RespArray::new(vec).into()
+}
+
+// key FIELDS numfields field [field ...]
+fn parse_key_fields(arr: RespArray) -> Result<(String, Vec<String>), ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, 4)?;
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let fields = parse_fields(args.collect())?;
+    Ok((key, fields))
+}
+
+// FIELDS numfields field [field ...]
+fn parse_fields(rest: Vec<RespFrame>) -> Result<Vec<String>, ExecuteError> {
+    let numfields = parse_numfields(&rest)?;
+    if rest.len() != numfields + 2 {
+        return Err(InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    rest.into_iter().skip(2).map(frame_to_string).collect()
+}
+
+fn parse_numfields(rest: &[RespFrame]) -> Result<usize, ExecuteError> {
+    match rest.first().cloned().map(frame_to_string).transpose()? {
+        Some(s) if s.eq_ignore_ascii_case("fields") => {}
+        _ => {
+            return Err(InvalidArgument(
+                "Mandatory argument FIELDS is missing or not at the right position".to_string(),
+            ))
+        }
+    }
+    let numfields = rest.get(1).cloned().map(frame_to_i64).transpose()?;
+    match numfields {
+        Some(n) if n > 0 => Ok(n as usize),
+        _ => Err(InvalidArgument(
+            "Parameter `numFields` should be greater than 0".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::cmd;
+    use crate::resp::bulkstring::RespBulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_hexpire_try_from() -> anyhow::Result<()> {
+        let hexpire = HExpireCommand::try_from(cmd(&[
+            "hpexpireat",
+            "k",
+            "1000",
+            "GT",
+            "FIELDS",
+            "2",
+            "f1",
+            "f2",
+        ]))?;
+        assert_eq!(
+            hexpire,
+            HExpireCommand {
+                key: "k".to_string(),
+                time: ExpireTime::Absolute(1000),
+                condition: Some(ExpireCondition::Gt),
+                fields: vec!["f1".to_string(), "f2".to_string()],
+            }
+        );
+        assert!(HExpireCommand::try_from(cmd(&["hexpire", "k", "1", "fields", "2", "f"])).is_err());
+        assert!(
+            HExpireCommand::try_from(cmd(&["hexpire", "k", "-1", "fields", "1", "f"])).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hsetex_httl() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let hsetex = HSetExCommand::try_from(cmd(&[
+            "hsetex", "k", "FNX", "EX", "100", "FIELDS", "2", "f1", "v1", "f2", "v2",
+        ]))?;
+        assert_eq!(hsetex.execute(backend.clone())?, RespFrame::Integer(1));
+        let huge = i64::MAX.to_string();
+        assert!(HSetExCommand::try_from(cmd(&["hsetex", "k", "FIELDS", &huge, "f", "v"])).is_err());
+
+        let httl = HTtlCommand::try_from(cmd(&["httl", "k", "fields", "2", "f1", "f3"]))?;
+        assert_eq!(httl.execute(backend.clone())?, integers(vec![100, -2]));
+
+        let hgetex =
+            HGetExCommand::try_from(cmd(&["hgetex", "k", "PERSIST", "FIELDS", "1", "f1"]))?;
+        assert_eq!(
+            hgetex.execute(backend.clone())?,
+            RespArray::new(vec![RespBulkString::new("v1").into()]).into()
+        );
+        let httl = HTtlCommand::try_from(cmd(&["hpttl", "k", "fields", "2", "f1", "f2"]))?;
+        let RespFrame::Array(ttls) = httl.execute(backend.clone())? else {
+            panic!("expected an array");
+        };
+        let ttls = ttls.0.expect("array is not null");
+        assert_eq!(ttls[0], RespFrame::Integer(-1));
+        assert!(matches!(ttls[1], RespFrame::Integer(ms) if ms > 99_000));
+        Ok(())
+    }
+}
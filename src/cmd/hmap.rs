@@ -1,7 +1,12 @@
 use crate::backend::Backend;
+use crate::cmd::keyspace::{parse_key_scan, scan_reply, ScanOptions};
 use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
-use crate::cmd::{into_args_iter, CommandExecutor, ExecuteError, RET_OK};
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_f64, frame_to_i64, frame_to_string, into_args,
+    into_args_iter, parse_key, CommandExecutor, ExecuteError,
+};
 use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
 use crate::resp::frame::RespFrame;
 use crate::resp::null::RespNull;
 
@@ -14,8 +19,7 @@ pub struct HGetCommand {
 #[derive(Debug)]
 pub struct HSetCommand {
     key: String,
-    field: String,
-    value: RespFrame,
+    fields: Vec<(String, RespFrame)>,
 }
 
 #[derive(Debug)]
@@ -29,6 +33,75 @@ pub struct HmgetCommand {
     fields: Vec<String>,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct HDelCommand {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HExistsCommand {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HLenCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HKeysCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HValsCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HIncrByCommand {
+    key: String,
+    field: String,
+    incr: i64,
+}
+
+#[derive(Debug)]
+pub struct HIncrByFloatCommand {
+    key: String,
+    field: String,
+    incr: f64,
+}
+
+#[derive(Debug)]
+pub struct HSetNxCommand {
+    key: String,
+    field: String,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HStrLenCommand {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct HRandFieldCommand {
+    key: String,
+    count: Option<i64>,
+    with_values: bool,
+}
+
+// HScan: key cursor [MATCH pattern] [COUNT count] [NOVALUES]
+#[derive(Debug, PartialEq)]
+pub struct HScanCommand {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+}
+
 impl CommandExecutor for HGetCommand {
     fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
         match backend.hget(&self.key, &self.field) {
@@ -39,9 +112,8 @@ impl CommandExecutor for HGetCommand {
 }
 
 impl CommandExecutor for HSetCommand {
-    fn execute(self, mut backend: Backend) -> anyhow::Result<RespFrame> {
-        backend.hset(&self.key, &self.field, self.value);
-        Ok(RET_OK.clone())
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hset(&self.key, self.fields)?.into())
     }
 }
 
@@ -64,6 +136,97 @@ impl CommandExecutor for HmgetCommand {
     }
 }
 
+impl CommandExecutor for HDelCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hdel(&self.key, &self.fields).into())
+    }
+}
+
+impl CommandExecutor for HExistsCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hexists(&self.key, &self.field).into())
+    }
+}
+
+impl CommandExecutor for HLenCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hlen(&self.key).into())
+    }
+}
+
+impl CommandExecutor for HKeysCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespArray::new(backend.hkeys(&self.key)).into())
+    }
+}
+
+impl CommandExecutor for HValsCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespArray::new(backend.hvals(&self.key)).into())
+    }
+}
+
+impl CommandExecutor for HIncrByCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hincrby(&self.key, &self.field, self.incr)?.into())
+    }
+}
+
+impl CommandExecutor for HIncrByFloatCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.hincrbyfloat(&self.key, &self.field, self.incr)?;
+        Ok(RespBulkString::new(ret).into())
+    }
+}
+
+impl CommandExecutor for HSetNxCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hsetnx(&self.key, &self.field, self.value)?.into())
+    }
+}
+
+impl CommandExecutor for HStrLenCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.hstrlen(&self.key, &self.field).into())
+    }
+}
+
+impl CommandExecutor for HRandFieldCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let pairs = backend.hrandfield(&self.key, self.count);
+        if self.count.is_none() {
+            return Ok(match pairs.into_iter().next() {
+                Some((field, _)) => RespBulkString::new(field).into(),
+                None => RespBulkString::null().into(),
+            });
+        }
+        let mut vec = Vec::with_capacity(pairs.len() * 2);
+        for (field, value) in pairs {
+            vec.push(RespBulkString::new(field).into());
+            if self.with_values {
+                vec.push(value);
+            }
+        }
+        Ok(RespArray::new(vec).into())
+    }
+}
+
+impl CommandExecutor for HScanCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let opts = self.opts;
+        let (cursor, fields) =
+            backend.hscan(&self.key, self.cursor, opts.pattern.as_deref(), opts.count);
+        let mut items = Vec::with_capacity(fields.len() * 2);
+        for (field, value) in fields {
+            items.push(RespBulkString::new(field).into());
+            if !opts.novalues {
+                items.push(value);
+            }
+        }
+        Ok(scan_reply(cursor, items))
+    }
+}
+
 // HGet: "*3\r\n$4\r\nhget\r\n$3\r\nmap\r\n$5\r\nhello\r\n"
 // HSet: "*4\r\n$4\r\nhset\r\n$3\r\nmap\r\n$5\r\nhello\r\n$5\r\nworld\r\n"
 // HGetAll: "*2\r\n$7\r\nhgetall\r\n$3\r\nmap\r\n"
@@ -101,30 +264,20 @@ impl TryFrom<RespArray> for HSetCommand {
     type Error = ExecuteError;
 
     fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
-        let Some(arr) = arr.0 else {
-            return Err(InvalidCommand("command exists".to_string()));
-        };
-
-        let len = arr.len();
-        if len != 4 {
-            return Err(InvalidArgument(format!("expected 3, got {}", len - 1)));
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        if args.len() % 2 == 0 {
+            return Err(InvalidArgument(
+                "wrong number of arguments for 'hset' command".to_string(),
+            ));
         }
-        let mut args = into_args_iter(arr, 1);
-        match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(field)), Some(RespFrame::BulkString(key)), Some(frame)) => {
-                let field =
-                    String::from_utf8(field.as_ref().expect("field has to exist").to_vec())?;
-                let key = String::from_utf8(key.as_ref().expect("key has to exist").to_vec())?;
-                Ok(HSetCommand {
-                    key: field,
-                    field: key,
-                    value: frame.clone(),
-                })
-            }
-            _ => Err(InvalidCommand(
-                "hset field and key should be bulkstring".to_string(),
-            )),
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let mut fields = Vec::with_capacity(args.len() / 2);
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((frame_to_string(field)?, value));
         }
+        Ok(HSetCommand { key, fields })
     }
 }
 
@@ -188,11 +341,185 @@ impl TryFrom<RespArray> for HmgetCommand {
     }
 }
 
+impl TryFrom<RespArray> for HDelCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let fields = args.map(frame_to_string).collect::<Result<_, _>>()?;
+        Ok(HDelCommand { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HExistsCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, field) = parse_key_field(arr)?;
+        Ok(HExistsCommand { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HLenCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(HLenCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HKeysCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(HKeysCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HValsCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(HValsCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrByCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(incr)) => Ok(HIncrByCommand {
+                key: frame_to_string(key)?,
+                field: frame_to_string(field)?,
+                incr: frame_to_i64(incr)?,
+            }),
+            _ => Err(InvalidCommand("hincrby key field increment".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HIncrByFloatCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(incr)) => {
+                let incr = frame_to_f64(incr)?;
+                if !incr.is_finite() {
+                    return Err(InvalidArgument(
+                        "increment would produce NaN or Infinity".to_string(),
+                    ));
+                }
+                Ok(HIncrByFloatCommand {
+                    key: frame_to_string(key)?,
+                    field: frame_to_string(field)?,
+                    incr,
+                })
+            }
+            _ => Err(InvalidCommand(
+                "hincrbyfloat key field increment".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HSetNxCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(key), Some(field), Some(value)) => Ok(HSetNxCommand {
+                key: frame_to_string(key)?,
+                field: frame_to_string(field)?,
+                value,
+            }),
+            _ => Err(InvalidCommand("hsetnx key field value".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HStrLenCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, field) = parse_key_field(arr)?;
+        Ok(HStrLenCommand { key, field })
+    }
+}
+
+// HRandField: "*4\r\n$10\r\nhrandfield\r\n$3\r\nmap\r\n$2\r\n-5\r\n$10\r\nwithvalues\r\n"
+impl TryFrom<RespArray> for HRandFieldCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        if args.len() > 3 {
+            return Err(InvalidArgument(format!(
+                "expected at most 3, got {}",
+                args.len()
+            )));
+        }
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let count = args.next().map(frame_to_i64).transpose()?;
+        let with_values = match args.next().map(frame_to_string).transpose()? {
+            Some(s) if s.eq_ignore_ascii_case("withvalues") => true,
+            Some(_) => return Err(InvalidArgument("syntax error".to_string())),
+            None => false,
+        };
+        Ok(HRandFieldCommand {
+            key,
+            count,
+            with_values,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HScanCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, cursor, opts) = parse_key_scan(arr, true)?;
+        Ok(HScanCommand { key, cursor, opts })
+    }
+}
+
+fn parse_key_field(arr: RespArray) -> Result<(String, String), ExecuteError> {
+    let args = into_args(arr)?;
+    check_nargs(&args, 2)?;
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (Some(key), Some(field)) => Ok((frame_to_string(key)?, frame_to_string(field)?)),
+        _ => Err(InvalidCommand(
+            "key and field should be bulkstring".to_string(),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::resp::bulkstring::RespBulkString;
-
     use super::*;
+    use crate::cmd::cmd;
 
     #[test]
     fn test_hmget_try_from() -> anyhow::Result<()> {
@@ -211,4 +538,72 @@ mod tests {
         assert_eq!(hmget, transformed);
         Ok(())
     }
+
+    #[test]
+    fn test_hset_hdel() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let hset = HSetCommand::try_from(cmd(&["hset", "k", "f1", "v1", "f2", "v2"]))?;
+        assert_eq!(hset.execute(backend.clone())?, RespFrame::Integer(2));
+        let hset = HSetCommand::try_from(cmd(&["hset", "k", "f2", "v3", "f3", "v3"]))?;
+        assert_eq!(hset.execute(backend.clone())?, RespFrame::Integer(1));
+        assert!(HSetCommand::try_from(cmd(&["hset", "k", "f1"])).is_err());
+
+        let hdel = HDelCommand::try_from(cmd(&["hdel", "k", "f1", "f2", "nope"]))?;
+        assert_eq!(hdel.execute(backend.clone())?, RespFrame::Integer(2));
+        assert_eq!(backend.hlen("k"), 1);
+        let hdel = HDelCommand::try_from(cmd(&["hdel", "k", "f3"]))?;
+        assert_eq!(hdel.execute(backend.clone())?, RespFrame::Integer(1));
+        assert!(backend.hgetall("k").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincrby() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let incr = HIncrByCommand::try_from(cmd(&["hincrby", "k", "f", "5"]))?;
+        assert_eq!(incr.execute(backend.clone())?, RespFrame::Integer(5));
+        let incr = HIncrByCommand::try_from(cmd(&["hincrby", "k", "f", "-7"]))?;
+        assert_eq!(incr.execute(backend.clone())?, RespFrame::Integer(-2));
+        let incr = HIncrByFloatCommand::try_from(cmd(&["hincrbyfloat", "k", "f", "2.5"]))?;
+        assert_eq!(
+            incr.execute(backend.clone())?,
+            RespBulkString::new("0.5").into()
+        );
+        let incr = HIncrByCommand::try_from(cmd(&["hincrby", "k", "f", "1"]))?;
+        assert!(incr.execute(backend.clone()).is_err());
+        assert_eq!(backend.hstrlen("k", "f"), 3);
+
+        // a failed increment leaves nothing behind
+        assert!(HIncrByFloatCommand::try_from(cmd(&["hincrbyfloat", "h", "f", "inf"])).is_err());
+        assert!(backend.hincrbyfloat("h", "f", f64::MAX).is_ok());
+        assert!(backend.hincrbyfloat("h", "f", f64::MAX).is_err());
+        assert!(backend.hincrbyfloat("h2", "f", f64::INFINITY).is_err());
+        assert_eq!(backend.hlen("h2"), 0);
+        assert!(backend.hgetall("h2").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        backend.hset(
+            "k",
+            vec![
+                ("f1".to_string(), RespBulkString::new("v1").into()),
+                ("f2".to_string(), RespBulkString::new("v2").into()),
+            ],
+        )?;
+        assert_eq!(backend.hrandfield("k", None).len(), 1);
+        assert_eq!(backend.hrandfield("k", Some(5)).len(), 2);
+        assert_eq!(backend.hrandfield("k", Some(-5)).len(), 5);
+        assert!(backend.hrandfield("nope", Some(-5)).is_empty());
+
+        let rand = HRandFieldCommand::try_from(cmd(&["hrandfield", "k", "2", "WITHVALUES"]))?;
+        assert!(rand.with_values);
+        let RespFrame::Array(arr) = rand.execute(backend.clone())? else {
+            panic!("expected an array");
+        };
+        assert_eq!(arr.as_ref().map(|v| v.len()), Some(4));
+        Ok(())
+    }
 }
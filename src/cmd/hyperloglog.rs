@@ -0,0 +1,83 @@
+use crate::backend::Backend;
+use crate::cmd::set::parse_keys;
+use crate::cmd::{check_min_nargs, frame_to_string, into_args, CommandExecutor, ExecuteError};
+use crate::resp::array::RespArray;
+use crate::resp::frame::RespFrame;
+use crate::resp::simple_string::RespSimpleString;
+
+// PfAdd: key [element [element ...]]
+#[derive(Debug)]
+pub struct PfAddCommand {
+    key: String,
+    elements: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct PfCountCommand {
+    keys: Vec<String>,
+}
+
+// PfMerge: destkey [sourcekey [sourcekey ...]]
+#[derive(Debug)]
+pub struct PfMergeCommand {
+    destination: String,
+    sources: Vec<String>,
+}
+
+impl CommandExecutor for PfAddCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.pfadd(&self.key, self.elements)?;
+        Ok((ret as i64).into())
+    }
+}
+
+impl CommandExecutor for PfCountCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.pfcount(&self.keys)?;
+        Ok((ret as i64).into())
+    }
+}
+
+impl CommandExecutor for PfMergeCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        backend.pfmerge(&self.destination, &self.sources)?;
+        Ok(RespSimpleString::new("OK").into())
+    }
+}
+
+impl TryFrom<RespArray> for PfAddCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        Ok(PfAddCommand {
+            key,
+            elements: args.collect(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PfCountCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let keys = parse_keys(arr)?;
+        Ok(PfCountCommand { keys })
+    }
+}
+
+impl TryFrom<RespArray> for PfMergeCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let mut sources = parse_keys(arr)?;
+        let destination = sources.remove(0);
+        Ok(PfMergeCommand {
+            destination,
+            sources,
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use std::vec::IntoIter;
+
+use crate::backend::keyspace::KeyType;
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_i64, frame_to_string, into_args, CommandExecutor,
+    ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+#[derive(Debug)]
+pub struct KeysCommand {
+    pattern: String,
+}
+
+// Scan: cursor [MATCH pattern] [COUNT count] [TYPE type]
+#[derive(Debug, PartialEq)]
+pub struct ScanCommand {
+    cursor: u64,
+    opts: ScanOptions,
+}
+
+/// The options shared by `SCAN` and the `*SCAN` commands of each type.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ScanOptions {
+    pub pattern: Option<String>,
+    pub count: usize,
+    pub ty: Option<KeyType>,
+    pub novalues: bool,
+}
+
+impl CommandExecutor for KeysCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let keys = backend
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|key| RespBulkString::new(key).into())
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(keys).into())
+    }
+}
+
+impl CommandExecutor for ScanCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let opts = self.opts;
+        let (cursor, keys) =
+            backend.scan(self.cursor, opts.pattern.as_deref(), opts.count, opts.ty);
+        let keys = keys
+            .into_iter()
+            .map(|key| RespBulkString::new(key).into())
+            .collect();
+        Ok(scan_reply(cursor, keys))
+    }
+}
+
+impl TryFrom<RespArray> for KeysCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 1)?;
+        let pattern = frame_to_string(args.into_iter().next().expect("pattern has to exist"))?;
+        Ok(KeysCommand { pattern })
+    }
+}
+
+impl TryFrom<RespArray> for ScanCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let cursor = parse_cursor(args.next().expect("cursor has to exist"))?;
+        let opts = parse_scan_options(args, true, false)?;
+        Ok(ScanCommand { cursor, opts })
+    }
+}
+
+/// `[cursor, [item ...]]`
+pub(crate) fn scan_reply(cursor: u64, items: Vec<RespFrame>) -> RespFrame {
+    RespArray::new(vec![
+        RespBulkString::new(cursor.to_string()).into(),
+        RespArray::new(items).into(),
+    ])
+    .into()
+}
+
+// key cursor [MATCH pattern] [COUNT count], plus [NOVALUES] for HSCAN
+pub(crate) fn parse_key_scan(
+    arr: RespArray,
+    allow_novalues: bool,
+) -> Result<(String, u64, ScanOptions), ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, 2)?;
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let cursor = parse_cursor(args.next().expect("cursor has to exist"))?;
+    let opts = parse_scan_options(args, false, allow_novalues)?;
+    Ok((key, cursor, opts))
+}
+
+pub(crate) fn parse_cursor(frame: RespFrame) -> Result<u64, ExecuteError> {
+    frame_to_string(frame)?
+        .parse()
+        .map_err(|_| InvalidArgument("invalid cursor".to_string()))
+}
+
+// [MATCH pattern] [COUNT count], plus [TYPE type] for SCAN and [NOVALUES] for HSCAN
+fn parse_scan_options(
+    mut args: IntoIter<RespFrame>,
+    allow_type: bool,
+    allow_novalues: bool,
+) -> Result<ScanOptions, ExecuteError> {
+    let syntax_error = || InvalidArgument("syntax error".to_string());
+    let mut opts = ScanOptions {
+        pattern: None,
+        count: 10,
+        ty: None,
+        novalues: false,
+    };
+    while let Some(opt) = args.next() {
+        match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+            "match" => opts.pattern = Some(frame_to_string(args.next().ok_or_else(syntax_error)?)?),
+            "count" => {
+                let count = frame_to_i64(args.next().ok_or_else(syntax_error)?)?;
+                if count < 1 {
+                    return Err(syntax_error());
+                }
+                opts.count = count as usize;
+            }
+            "type" if allow_type => {
+                let name = frame_to_string(args.next().ok_or_else(syntax_error)?)?;
+                let ty = KeyType::parse(&name)
+                    .ok_or_else(|| InvalidArgument(format!("unknown type name '{}'", name)))?;
+                opts.ty = Some(ty);
+            }
+            "novalues" if allow_novalues => opts.novalues = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_scan_try_from() -> anyhow::Result<()> {
+        let scan = ScanCommand::try_from(cmd(&[
+            "scan", "42", "MATCH", "user:*", "COUNT", "100", "TYPE", "hash",
+        ]))?;
+        assert_eq!(
+            scan,
+            ScanCommand {
+                cursor: 42,
+                opts: ScanOptions {
+                    pattern: Some("user:*".to_string()),
+                    count: 100,
+                    ty: Some(KeyType::Hash),
+                    novalues: false,
+                },
+            }
+        );
+        assert!(ScanCommand::try_from(cmd(&["scan", "-1"])).is_err());
+        assert!(ScanCommand::try_from(cmd(&["scan", "0", "COUNT", "0"])).is_err());
+        assert!(ScanCommand::try_from(cmd(&["scan", "0", "NOVALUES"])).is_err());
+        assert!(ScanCommand::try_from(cmd(&["scan", "0", "TYPE", "nope"])).is_err());
+        Ok(())
+    }
+}
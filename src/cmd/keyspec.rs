@@ -0,0 +1,183 @@
+use crate::backend::frame_bytes;
+use crate::resp::array::RespArray;
+
+/// What a command does to the keyspace: the keys it names and whether it
+/// may change them.
+#[derive(Debug, Default, PartialEq)]
+pub struct KeyAccess {
+    pub write: bool,
+    pub keys: Vec<String>,
+}
+
+/// Where the keys of a command are among its arguments, the command name
+/// not counted. Ranges end at `last`, counted from the end when negative.
+#[derive(Debug, Clone, Copy)]
+enum KeySpec {
+    None,
+    Range(usize, isize, usize),
+    // a key count at the index, the keys right after it
+    NumKeys(usize),
+    // a destination key, then a key count and the keys
+    DestNumKeys,
+    // the first half of what follows `STREAMS`
+    Streams,
+}
+
+impl KeyAccess {
+    /// Looks the keys up the way Redis's command table does. Commands that
+    /// fail to parse may come back with fewer keys, they don't run anyway.
+    pub fn of(arr: &RespArray) -> Self {
+        let Some((name, args)) = arr.0.as_deref().and_then(|frames| frames.split_first()) else {
+            return KeyAccess::default();
+        };
+        let Some(name) = frame_bytes(name) else {
+            return KeyAccess::default();
+        };
+        let args = args
+            .iter()
+            .map(|arg| frame_bytes(arg).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let (write, spec) = spec(&name.to_ascii_lowercase());
+        let keys = spec
+            .positions(&args)
+            .into_iter()
+            .filter_map(|i| args.get(i))
+            .map(|key| String::from_utf8_lossy(key).into_owned())
+            .collect();
+        KeyAccess { write, keys }
+    }
+}
+
+impl KeySpec {
+    fn positions(self, args: &[&[u8]]) -> Vec<usize> {
+        let numkeys = |at: usize| {
+            let n = args
+                .get(at)
+                .and_then(|n| std::str::from_utf8(n).ok())
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or_default()
+                // the count is the client's, only trust it as far as the
+                // arguments go
+                .min(args.len().saturating_sub(at + 1));
+            (at + 1..at + 1 + n).collect::<Vec<_>>()
+        };
+        match self {
+            KeySpec::None => vec![],
+            KeySpec::Range(first, last, step) => {
+                let last = if last < 0 {
+                    args.len() as isize + last
+                } else {
+                    last
+                };
+                if last < first as isize {
+                    return vec![];
+                }
+                (first..=last as usize).step_by(step).collect()
+            }
+            KeySpec::NumKeys(at) => numkeys(at),
+            KeySpec::DestNumKeys => {
+                let mut positions = vec![0];
+                positions.extend(numkeys(1));
+                positions
+            }
+            KeySpec::Streams => {
+                let Some(at) = args.iter().position(|a| a.eq_ignore_ascii_case(b"streams")) else {
+                    return vec![];
+                };
+                let n = (args.len() - at - 1) / 2;
+                (at + 1..at + 1 + n).collect()
+            }
+        }
+    }
+}
+
+fn spec(name: &[u8]) -> (bool, KeySpec) {
+    const FIRST: KeySpec = KeySpec::Range(0, 0, 1);
+    const ALL: KeySpec = KeySpec::Range(0, -1, 1);
+    const TWO: KeySpec = KeySpec::Range(0, 1, 1);
+    // every argument but the trailing timeout
+    const BLOCKING: KeySpec = KeySpec::Range(0, -2, 1);
+    // the key after a subcommand
+    const SECOND: KeySpec = KeySpec::Range(1, 1, 1);
+    match name {
+        b"get" | b"hget" | b"hgetall" | b"hmget" | b"hexists" | b"hlen" | b"hkeys" | b"hvals"
+        | b"hstrlen" | b"hrandfield" | b"httl" | b"hpttl" | b"hexpiretime" | b"hpexpiretime"
+        | b"hscan" | b"sismember" | b"smembers" | b"scard" | b"smismember" | b"srandmember"
+        | b"sscan" | b"lrange" | b"lindex" | b"llen" | b"lpos" | b"zscore" | b"zmscore"
+        | b"zcard" | b"zcount" | b"zrank" | b"zrevrank" | b"zrange" | b"zlexcount"
+        | b"zrandmember" | b"zscan" | b"geopos" | b"geodist" | b"geohash" | b"geosearch"
+        | b"xrange" | b"xrevrange" | b"xlen" | b"xpending" | b"bitfield_ro" | b"ttl" | b"pttl"
+        | b"expiretime" | b"pexpiretime" => (false, FIRST),
+        b"set" | b"hset" | b"hdel" | b"hincrby" | b"hincrbyfloat" | b"hsetnx" | b"hexpire"
+        | b"hpexpire" | b"hexpireat" | b"hpexpireat" | b"hpersist" | b"hgetex" | b"hsetex"
+        | b"sadd" | b"srem" | b"spop" | b"lpush" | b"rpush" | b"lpushx" | b"rpushx" | b"lpop"
+        | b"rpop" | b"lset" | b"lrem" | b"ltrim" | b"linsert" | b"zadd" | b"zrem" | b"zincrby"
+        | b"zremrangebyscore" | b"zremrangebyrank" | b"zremrangebylex" | b"zpopmin"
+        | b"zpopmax" | b"geoadd" | b"xadd" | b"xdel" | b"xtrim" | b"xack" | b"xclaim"
+        | b"xautoclaim" | b"pfadd" | b"move" | b"bitfield" | b"expire" | b"pexpire"
+        | b"expireat" | b"pexpireat" | b"persist" => (true, FIRST),
+        b"sinter" | b"sunion" | b"sdiff" | b"pfcount" => (false, ALL),
+        b"sinterstore" | b"sunionstore" | b"sdiffstore" | b"pfmerge" => (true, ALL),
+        b"smove" | b"lmove" | b"blmove" | b"zrangestore" | b"geosearchstore" => (true, TWO),
+        b"blpop" | b"brpop" | b"bzpopmin" | b"bzpopmax" => (true, BLOCKING),
+        b"sintercard" | b"zunion" | b"zinter" | b"zdiff" => (false, KeySpec::NumKeys(0)),
+        b"lmpop" | b"zmpop" => (true, KeySpec::NumKeys(0)),
+        b"blmpop" | b"bzmpop" => (true, KeySpec::NumKeys(1)),
+        b"zunionstore" | b"zinterstore" | b"zdiffstore" => (true, KeySpec::DestNumKeys),
+        b"xread" => (false, KeySpec::Streams),
+        b"xreadgroup" => (true, KeySpec::Streams),
+        b"xinfo" => (false, SECOND),
+        b"xgroup" => (true, SECOND),
+        // no key of their own, but they change every key of a database
+        b"flushdb" | b"flushall" | b"swapdb" => (true, KeySpec::None),
+        _ => (false, KeySpec::None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    fn access(args: &[&str]) -> KeyAccess {
+        KeyAccess::of(&cmd(args))
+    }
+
+    #[test]
+    fn test_key_access() {
+        let keys = |args: &[&str]| access(args).keys;
+        assert_eq!(
+            access(&["SET", "k", "v"]),
+            KeyAccess {
+                write: true,
+                keys: vec!["k".to_string()]
+            }
+        );
+        assert!(!access(&["get", "k"]).write);
+        assert_eq!(keys(&["sinter", "a", "b"]), ["a", "b"]);
+        assert_eq!(keys(&["blpop", "a", "b", "0"]), ["a", "b"]);
+        assert_eq!(keys(&["blmpop", "0", "2", "a", "b", "LEFT"]), ["a", "b"]);
+        assert_eq!(keys(&["zunionstore", "d", "2", "a", "b"]), ["d", "a", "b"]);
+        assert_eq!(
+            keys(&["xread", "COUNT", "1", "STREAMS", "a", "b", "0", "$"]),
+            ["a", "b"]
+        );
+        assert_eq!(keys(&["xgroup", "CREATE", "s", "g", "$"]), ["s"]);
+        assert!(keys(&["xgroup", "HELP"]).is_empty());
+        assert!(keys(&["ping"]).is_empty());
+        assert_eq!(
+            access(&["FLUSHALL"]),
+            KeyAccess {
+                write: true,
+                keys: vec![]
+            }
+        );
+        // a count past the arguments, or past usize, stops at the arguments
+        assert_eq!(keys(&["sintercard", "100000000000", "a"]), ["a"]);
+        assert_eq!(
+            keys(&["zunionstore", "d", &usize::MAX.to_string(), "a"]),
+            ["d", "a"]
+        );
+        assert!(keys(&["zmpop", "5"]).is_empty());
+    }
+}
@@ -0,0 +1,867 @@
+use std::time::Duration;
+
+use crate::backend::blocking::{Blocked, Serve};
+use crate::backend::list::{ListEnd, LposOptions};
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_i64, frame_to_string, into_args, parse_key,
+    parse_key_count, parse_key_members, parse_timeout, pop_count, BlockingExecutor,
+    CommandExecutor, ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+use crate::resp::simple_error::RespSimpleError;
+use crate::resp::simple_string::RespSimpleString;
+
+// LPush: "*3\r\n$5\r\nlpush\r\n$3\r\nkey\r\n$5\r\nhello\r\n"
+#[derive(Debug)]
+pub struct LPushCommand {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPushCommand {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPushXCommand {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPushXCommand {
+    key: String,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LPopCommand {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RPopCommand {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LRangeCommand {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LIndexCommand {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSetCommand {
+    key: String,
+    index: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LLenCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct LRemCommand {
+    key: String,
+    count: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LTrimCommand {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LInsertCommand {
+    key: String,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LPosCommand {
+    key: String,
+    value: RespFrame,
+    opts: LposOptions,
+    with_count: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LMoveCommand {
+    source: String,
+    destination: String,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LMPopCommand {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+}
+
+// BLPop: key [key ...] timeout
+#[derive(Debug, PartialEq)]
+pub struct BLPopCommand {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BRPopCommand {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BLMoveCommand {
+    source: String,
+    destination: String,
+    from: ListEnd,
+    to: ListEnd,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BLMPopCommand {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for LPushCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .push(&self.key, ListEnd::Left, self.values, false)?
+            .into())
+    }
+}
+
+impl CommandExecutor for RPushCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .push(&self.key, ListEnd::Right, self.values, false)?
+            .into())
+    }
+}
+
+impl CommandExecutor for LPushXCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .push(&self.key, ListEnd::Left, self.values, true)?
+            .into())
+    }
+}
+
+impl CommandExecutor for RPushXCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .push(&self.key, ListEnd::Right, self.values, true)?
+            .into())
+    }
+}
+
+impl CommandExecutor for LPopCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(pop_reply(backend, &self.key, ListEnd::Left, self.count))
+    }
+}
+
+impl CommandExecutor for RPopCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(pop_reply(backend, &self.key, ListEnd::Right, self.count))
+    }
+}
+
+impl CommandExecutor for LRangeCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.lrange(&self.key, self.start, self.stop);
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for LIndexCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .lindex(&self.key, self.index)
+            .unwrap_or_else(|| RespBulkString::null().into()))
+    }
+}
+
+impl CommandExecutor for LSetCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        backend.lset(&self.key, self.index, self.value)?;
+        Ok(RespSimpleString::new("OK").into())
+    }
+}
+
+impl CommandExecutor for LLenCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.llen(&self.key).into())
+    }
+}
+
+impl CommandExecutor for LRemCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.lrem(&self.key, self.count, &self.value).into())
+    }
+}
+
+impl CommandExecutor for LTrimCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        backend.ltrim(&self.key, self.start, self.stop);
+        Ok(RespSimpleString::new("OK").into())
+    }
+}
+
+impl CommandExecutor for LInsertCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.linsert(&self.key, self.before, &self.pivot, self.value);
+        Ok(ret.into())
+    }
+}
+
+impl CommandExecutor for LPosCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let mut ret = backend.lpos(&self.key, &self.value, self.opts);
+        if self.with_count {
+            let ret = ret.into_iter().map(RespFrame::Integer).collect::<Vec<_>>();
+            return Ok(RespArray::new(ret).into());
+        }
+        Ok(ret
+            .pop()
+            .map_or_else(|| RespBulkString::null().into(), RespFrame::Integer))
+    }
+}
+
+impl CommandExecutor for LMoveCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .lmove(&self.source, &self.destination, self.from, self.to)?
+            .unwrap_or_else(|| RespBulkString::null().into()))
+    }
+}
+
+impl CommandExecutor for LMPopCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(lmpop_reply(backend.lmpop(&self.keys, self.end, self.count)))
+    }
+}
+
+impl BlockingExecutor for BLPopCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        Ok(bpop(
+            backend,
+            client_id,
+            self.keys,
+            ListEnd::Left,
+            self.timeout,
+        ))
+    }
+}
+
+impl BlockingExecutor for BRPopCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        Ok(bpop(
+            backend,
+            client_id,
+            self.keys,
+            ListEnd::Right,
+            self.timeout,
+        ))
+    }
+}
+
+impl BlockingExecutor for BLMoveCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        let BLMoveCommand {
+            source,
+            destination,
+            from,
+            to,
+            timeout,
+        } = self;
+        let pushed_to = destination.clone();
+        let serve: Serve = Box::new(move |backend, key| {
+            // the destination may have taken another type while we waited
+            backend
+                .lmove(key, &pushed_to, from, to)
+                .unwrap_or_else(|e| Some(RespSimpleError::new(e.to_string()).into()))
+        });
+        Ok(backend.block(
+            client_id,
+            vec![source],
+            Some(destination),
+            timeout,
+            RespBulkString::null().into(),
+            serve,
+        ))
+    }
+}
+
+impl BlockingExecutor for BLMPopCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        let (end, count) = (self.end, self.count);
+        let serve: Serve = Box::new(move |backend, key| {
+            let keys = [key.to_string()];
+            backend
+                .lmpop(&keys, end, count)
+                .map(|ret| lmpop_reply(Some(ret)))
+        });
+        Ok(backend.block(
+            client_id,
+            self.keys,
+            None,
+            self.timeout,
+            RespArray::null().into(),
+            serve,
+        ))
+    }
+}
+
+impl TryFrom<RespArray> for LPushCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_key_members(arr, 1)?;
+        Ok(LPushCommand { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPushCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_key_members(arr, 1)?;
+        Ok(RPushCommand { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPushXCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_key_members(arr, 1)?;
+        Ok(LPushXCommand { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPushXCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_key_members(arr, 1)?;
+        Ok(RPushXCommand { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(arr)?;
+        Ok(LPopCommand {
+            key,
+            count: pop_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for RPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(arr)?;
+        Ok(RPopCommand {
+            key,
+            count: pop_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LRangeCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = parse_key_range(arr)?;
+        Ok(LRangeCommand { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LIndexCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let index = frame_to_i64(args.next().expect("index has to exist"))?;
+        Ok(LIndexCommand { key, index })
+    }
+}
+
+impl TryFrom<RespArray> for LSetCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let index = frame_to_i64(args.next().expect("index has to exist"))?;
+        let value = args.next().expect("value has to exist");
+        Ok(LSetCommand { key, index, value })
+    }
+}
+
+impl TryFrom<RespArray> for LLenCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(LLenCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LRemCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let count = frame_to_i64(args.next().expect("count has to exist"))?;
+        let value = args.next().expect("value has to exist");
+        Ok(LRemCommand { key, count, value })
+    }
+}
+
+impl TryFrom<RespArray> for LTrimCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = parse_key_range(arr)?;
+        Ok(LTrimCommand { key, start, stop })
+    }
+}
+
+// LInsert: key BEFORE|AFTER pivot element
+impl TryFrom<RespArray> for LInsertCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 4)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let before = match frame_to_string(args.next().expect("where has to exist"))?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "before" => true,
+            "after" => false,
+            _ => return Err(InvalidArgument("syntax error".to_string())),
+        };
+        let pivot = args.next().expect("pivot has to exist");
+        let value = args.next().expect("element has to exist");
+        Ok(LInsertCommand {
+            key,
+            before,
+            pivot,
+            value,
+        })
+    }
+}
+
+// LPos: key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+impl TryFrom<RespArray> for LPosCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let value = args.next().expect("element has to exist");
+
+        let mut opts = LposOptions::default();
+        let mut with_count = false;
+        while let Some(opt) = args.next() {
+            let opt = frame_to_string(opt)?.to_ascii_lowercase();
+            let Some(arg) = args.next() else {
+                return Err(InvalidArgument("syntax error".to_string()));
+            };
+            let arg = frame_to_i64(arg)?;
+            match opt.as_str() {
+                "rank" if arg == 0 || arg == i64::MIN => {
+                    return Err(InvalidArgument(
+                        "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                    ))
+                }
+                "rank" => opts.rank = arg,
+                "count" if arg < 0 => {
+                    return Err(InvalidArgument("COUNT can't be negative".to_string()))
+                }
+                "count" => {
+                    opts.count = arg as usize;
+                    with_count = true;
+                }
+                "maxlen" if arg < 0 => {
+                    return Err(InvalidArgument("MAXLEN can't be negative".to_string()))
+                }
+                "maxlen" => opts.maxlen = arg as usize,
+                _ => return Err(InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(LPosCommand {
+            key,
+            value,
+            opts,
+            with_count,
+        })
+    }
+}
+
+// LMove: source destination LEFT|RIGHT LEFT|RIGHT
+impl TryFrom<RespArray> for LMoveCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 4)?;
+        let mut args = args.into_iter();
+        let source = frame_to_string(args.next().expect("source has to exist"))?;
+        let destination = frame_to_string(args.next().expect("destination has to exist"))?;
+        let from = parse_end(args.next().expect("wherefrom has to exist"))?;
+        let to = parse_end(args.next().expect("whereto has to exist"))?;
+        Ok(LMoveCommand {
+            source,
+            destination,
+            from,
+            to,
+        })
+    }
+}
+
+// LMPop: numkeys key [key ...] LEFT|RIGHT [COUNT count]
+impl TryFrom<RespArray> for LMPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        let (keys, end, count) = parse_mpop(args, parse_end)?;
+        Ok(LMPopCommand { keys, end, count })
+    }
+}
+
+impl TryFrom<RespArray> for BLPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_keys_timeout(arr)?;
+        Ok(BLPopCommand { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BRPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_keys_timeout(arr)?;
+        Ok(BRPopCommand { keys, timeout })
+    }
+}
+
+// BLMove: source destination LEFT|RIGHT LEFT|RIGHT timeout
+impl TryFrom<RespArray> for BLMoveCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let mut args = into_args(arr)?;
+        check_nargs(&args, 5)?;
+        let timeout = parse_timeout(args.pop().expect("timeout has to exist"))?;
+        let mut args = args.into_iter();
+        let source = frame_to_string(args.next().expect("source has to exist"))?;
+        let destination = frame_to_string(args.next().expect("destination has to exist"))?;
+        let from = parse_end(args.next().expect("wherefrom has to exist"))?;
+        let to = parse_end(args.next().expect("whereto has to exist"))?;
+        Ok(BLMoveCommand {
+            source,
+            destination,
+            from,
+            to,
+            timeout,
+        })
+    }
+}
+
+// BLMPop: timeout numkeys key [key ...] LEFT|RIGHT [COUNT count]
+impl TryFrom<RespArray> for BLMPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let timeout = parse_timeout(args.next().expect("timeout has to exist"))?;
+        let (keys, end, count) = parse_mpop(args.collect(), parse_end)?;
+        Ok(BLMPopCommand {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+/// Blocks until one of `keys` has an element to pop from `end`, replying
+/// `[key, element]`.
+fn bpop(
+    backend: Backend,
+    client_id: u64,
+    keys: Vec<String>,
+    end: ListEnd,
+    timeout: Option<Duration>,
+) -> Blocked {
+    let serve: Serve = Box::new(move |backend, key| {
+        let value = backend.pop(key, end, 1)?.pop()?;
+        Some(RespArray::new(vec![RespBulkString::new(key).into(), value]).into())
+    });
+    backend.block(
+        client_id,
+        keys,
+        None,
+        timeout,
+        RespArray::null().into(),
+        serve,
+    )
+}
+
+fn pop_reply(backend: Backend, key: &str, end: ListEnd, count: Option<usize>) -> RespFrame {
+    match (backend.pop(key, end, count.unwrap_or(1)), count) {
+        (Some(popped), Some(_)) => RespArray::new(popped).into(),
+        (Some(mut popped), None) => popped
+            .pop()
+            .unwrap_or_else(|| RespBulkString::null().into()),
+        (None, Some(_)) => RespArray::null().into(),
+        (None, None) => RespBulkString::null().into(),
+    }
+}
+
+/// `[key, [element ...]]`, or a null array when nothing was popped.
+pub(crate) fn lmpop_reply(ret: Option<(String, Vec<RespFrame>)>) -> RespFrame {
+    match ret {
+        Some((key, popped)) => RespArray::new(vec![
+            RespBulkString::new(key).into(),
+            RespArray::new(popped).into(),
+        ])
+        .into(),
+        None => RespArray::null().into(),
+    }
+}
+
+pub(crate) fn parse_end(frame: RespFrame) -> Result<ListEnd, ExecuteError> {
+    match frame_to_string(frame)?.to_ascii_lowercase().as_str() {
+        "left" => Ok(ListEnd::Left),
+        "right" => Ok(ListEnd::Right),
+        _ => Err(InvalidArgument("syntax error".to_string())),
+    }
+}
+
+/// Parses `numkeys key [key ...] <end> [COUNT count]`, with `<end>` read by
+/// `parse_end`: `LEFT|RIGHT` for lists, `MIN|MAX` for sorted sets.
+pub(crate) fn parse_mpop<E>(
+    args: Vec<RespFrame>,
+    parse_end: fn(RespFrame) -> Result<E, ExecuteError>,
+) -> Result<(Vec<String>, E, usize), ExecuteError> {
+    check_min_nargs(&args, 3)?;
+    let mut args = args.into_iter();
+    let numkeys = frame_to_i64(args.next().expect("numkeys has to exist"))?;
+    if numkeys <= 0 {
+        return Err(InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+    let keys = args
+        .by_ref()
+        .take(numkeys as usize)
+        .map(frame_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(end) = args.next() else {
+        return Err(InvalidArgument("syntax error".to_string()));
+    };
+    if keys.len() != numkeys as usize {
+        return Err(InvalidArgument("syntax error".to_string()));
+    }
+    let end = parse_end(end)?;
+
+    let opt = args.next().map(frame_to_string).transpose()?;
+    let count = match (opt, args.next(), args.next()) {
+        (None, _, _) => 1,
+        (Some(opt), Some(count), None) if opt.eq_ignore_ascii_case("count") => {
+            let count = frame_to_i64(count)?;
+            if count <= 0 {
+                return Err(InvalidArgument(
+                    "count should be greater than 0".to_string(),
+                ));
+            }
+            count as usize
+        }
+        _ => return Err(InvalidArgument("syntax error".to_string())),
+    };
+    Ok((keys, end, count))
+}
+
+// key [key ...] timeout
+pub(crate) fn parse_keys_timeout(
+    arr: RespArray,
+) -> Result<(Vec<String>, Option<Duration>), ExecuteError> {
+    let mut args = into_args(arr)?;
+    check_min_nargs(&args, 2)?;
+    let timeout = parse_timeout(args.pop().expect("timeout has to exist"))?;
+    let keys = args
+        .into_iter()
+        .map(frame_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((keys, timeout))
+}
+
+// key start stop
+fn parse_key_range(arr: RespArray) -> Result<(String, i64, i64), ExecuteError> {
+    let args = into_args(arr)?;
+    check_nargs(&args, 3)?;
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let start = frame_to_i64(args.next().expect("start has to exist"))?;
+    let stop = frame_to_i64(args.next().expect("stop has to exist"))?;
+    Ok((key, start, stop))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_lpop_reply() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let lpop = LPopCommand::try_from(cmd(&["lpop", "k"]))?;
+        assert_eq!(
+            lpop.execute(backend.clone())?,
+            RespBulkString::null().into()
+        );
+        let lpop = LPopCommand::try_from(cmd(&["lpop", "k", "2"]))?;
+        assert_eq!(lpop.execute(backend.clone())?, RespArray::null().into());
+        assert!(LPopCommand::try_from(cmd(&["lpop", "k", "-1"])).is_err());
+
+        let rpush = RPushCommand::try_from(cmd(&["rpush", "k", "a", "b", "c"]))?;
+        assert_eq!(rpush.execute(backend.clone())?, RespFrame::Integer(3));
+        let rpop = RPopCommand::try_from(cmd(&["rpop", "k", "2"]))?;
+        assert_eq!(
+            rpop.execute(backend.clone())?,
+            RespArray::new(vec![
+                RespBulkString::new("c").into(),
+                RespBulkString::new("b").into()
+            ])
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos_lmpop_try_from() -> anyhow::Result<()> {
+        let lpos = LPosCommand::try_from(cmd(&["lpos", "k", "a", "RANK", "-2", "COUNT", "0"]))?;
+        assert_eq!(
+            lpos.opts,
+            LposOptions {
+                rank: -2,
+                count: 0,
+                maxlen: 0
+            }
+        );
+        assert!(lpos.with_count);
+        assert!(LPosCommand::try_from(cmd(&["lpos", "k", "a", "RANK", "0"])).is_err());
+        assert!(LPosCommand::try_from(cmd(&["lpos", "k", "a", "COUNT"])).is_err());
+
+        let lmpop = LMPopCommand::try_from(cmd(&["lmpop", "2", "a", "b", "RIGHT", "COUNT", "3"]))?;
+        assert_eq!(
+            lmpop,
+            LMPopCommand {
+                keys: vec!["a".to_string(), "b".to_string()],
+                end: ListEnd::Right,
+                count: 3,
+            }
+        );
+        assert!(LMPopCommand::try_from(cmd(&["lmpop", "2", "a", "LEFT"])).is_err());
+        assert!(LMPopCommand::try_from(cmd(&["lmpop", "1", "a", "LEFT", "COUNT", "0"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_try_from() -> anyhow::Result<()> {
+        let blpop = BLPopCommand::try_from(cmd(&["blpop", "a", "b", "0.5"]))?;
+        assert_eq!(
+            blpop,
+            BLPopCommand {
+                keys: vec!["a".to_string(), "b".to_string()],
+                timeout: Some(Duration::from_millis(500)),
+            }
+        );
+        let blmpop = BLMPopCommand::try_from(cmd(&["blmpop", "0", "1", "a", "LEFT"]))?;
+        assert_eq!(blmpop.timeout, None);
+        assert!(BRPopCommand::try_from(cmd(&["brpop", "a", "-1"])).is_err());
+        assert!(BLMoveCommand::try_from(cmd(&["blmove", "a", "b", "left", "up", "0"])).is_err());
+
+        let backend = Backend::default();
+        let blpop = BLPopCommand::try_from(cmd(&["blpop", "a", "b", "0"]))?;
+        assert!(matches!(
+            BlockingExecutor::execute(blpop, backend.clone(), 1)?,
+            Blocked::Waiting(_)
+        ));
+        backend.push(
+            "c",
+            ListEnd::Left,
+            vec![RespBulkString::new("x").into()],
+            false,
+        )?;
+        let blpop = BLPopCommand::try_from(cmd(&["blpop", "a", "c", "0"]))?;
+        let Blocked::Served(frame) = BlockingExecutor::execute(blpop, backend, 2)? else {
+            panic!("expected to be served");
+        };
+        assert_eq!(
+            frame,
+            RespArray::new(vec![
+                RespBulkString::new("c").into(),
+                RespBulkString::new("x").into()
+            ])
+            .into()
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::backend::Backend;
+use crate::cmd::hexpire::ExpireTime;
 use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
-use crate::cmd::{into_args_iter, CommandExecutor, ExecuteError, RET_OK};
+use crate::cmd::{frame_to_string, into_args_iter, CommandExecutor, ExecuteError, RET_OK};
 use crate::resp::array::RespArray;
 use crate::resp::frame::RespFrame;
 use crate::resp::null::RespNull;
@@ -12,10 +13,14 @@ pub struct GetCommand {
 }
 
 // Set: "*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n"
+// key value [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+// PXAT unix-time-milliseconds | KEEPTTL]
 #[derive(Debug)]
 pub struct SetCommand {
     key: String,
     value: RespFrame,
+    expiry: Option<ExpireTime>,
+    keep_ttl: bool,
 }
 
 impl CommandExecutor for GetCommand {
@@ -29,7 +34,16 @@ impl CommandExecutor for GetCommand {
 
 impl CommandExecutor for SetCommand {
     fn execute(self, mut backend: Backend) -> anyhow::Result<RespFrame> {
+        let kept = match self.keep_ttl {
+            true => backend.expiretime(&self.key),
+            false => -1,
+        };
         backend.set(&self.key, self.value.clone());
+        if let Some(time) = self.expiry {
+            backend.expire(&self.key, time.at(), None);
+        } else if kept >= 0 {
+            backend.set_expire(&self.key, kept as u64);
+        }
         Ok(RET_OK.clone())
     }
 }
@@ -64,25 +78,54 @@ impl TryFrom<RespArray> for SetCommand {
         let Some(arr) = arr.0 else {
             return Err(InvalidCommand("command exists".to_string()));
         };
-        if arr.len() != 3 {
+        if arr.len() < 3 {
             return Err(InvalidArgument(format!(
-                "expected 2, got {}",
+                "expected at least 2, got {}",
                 arr.len() - 1
             )));
         }
         let mut args = into_args_iter(arr, 1);
-        match (args.next(), args.next()) {
+        let (key, value) = match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(frame)) => {
                 let key = String::from_utf8(key.as_ref().expect("key has to exist").to_vec())?;
-                Ok(SetCommand { key, value: frame })
+                (key, frame)
+            }
+            _ => return Err(InvalidCommand("set key should be a bulkstring".to_string())),
+        };
+        let mut expiry = None;
+        let mut keep_ttl = false;
+        while let Some(option) = args.next() {
+            let option = frame_to_string(option)?.to_ascii_lowercase();
+            if expiry.is_some() || keep_ttl {
+                return Err(InvalidArgument("syntax error".to_string()));
+            }
+            if option == "keepttl" {
+                keep_ttl = true;
+                continue;
+            }
+            expiry = ExpireTime::parse(&option, args.next())?;
+            match expiry {
+                None => return Err(InvalidArgument("syntax error".to_string())),
+                Some(ExpireTime::Relative(0) | ExpireTime::Absolute(0)) => {
+                    return Err(InvalidArgument(
+                        "invalid expire time in 'set' command".to_string(),
+                    ))
+                }
+                Some(_) => {}
             }
-            _ => Err(InvalidCommand("set key should be a bulkstring".to_string())),
         }
+        Ok(SetCommand {
+            key,
+            value,
+            expiry,
+            keep_ttl,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::cmd::cmd;
     use crate::resp::bulkstring::RespBulkString;
 
     use super::*;
@@ -111,6 +154,11 @@ mod tests {
             Into::<RespFrame>::into(RespBulkString::new("world")),
             set.value
         );
+
+        let set = SetCommand::try_from(cmd(&["set", "hello", "world", "PX", "100"]))?;
+        assert_eq!(set.expiry, Some(ExpireTime::Relative(100)));
+        let set = SetCommand::try_from(cmd(&["set", "hello", "world", "EX", "0"]));
+        assert!(set.is_err());
         Ok(())
     }
 }
@@ -1,20 +1,94 @@
 use std::string::FromUtf8Error;
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 use thiserror::Error;
 
-use crate::backend::Backend;
-use crate::cmd::echo::ECHOCommand;
-use crate::cmd::hmap::{HGetAllCommand, HGetCommand, HSetCommand, HmgetCommand};
+use crate::backend::blocking::Blocked;
+use crate::backend::pubsub::SubscriptionKind;
+use crate::backend::{frame_bytes, Backend};
+use crate::cmd::bitfield::{BitFieldCommand, BitFieldRoCommand};
+use crate::cmd::client::{ClientCommand, HelloCommand};
+use crate::cmd::config::ConfigCommand;
+use crate::cmd::db::{
+    DbSizeCommand, FlushAllCommand, FlushDbCommand, MoveCommand, SelectCommand, SwapDbCommand,
+};
+use crate::cmd::echo::{ECHOCommand, PingCommand};
+use crate::cmd::expire::{ExpireCommand, PersistCommand, TtlCommand};
+use crate::cmd::geo::{
+    GeoAddCommand, GeoDistCommand, GeoHashCommand, GeoPosCommand, GeoSearchCommand,
+    GeoSearchStoreCommand,
+};
+use crate::cmd::hexpire::{
+    HExpireCommand, HGetExCommand, HPersistCommand, HSetExCommand, HTtlCommand,
+};
+use crate::cmd::hmap::{
+    HDelCommand, HExistsCommand, HGetAllCommand, HGetCommand, HIncrByCommand, HIncrByFloatCommand,
+    HKeysCommand, HLenCommand, HRandFieldCommand, HScanCommand, HSetCommand, HSetNxCommand,
+    HStrLenCommand, HValsCommand, HmgetCommand,
+};
+use crate::cmd::hyperloglog::{PfAddCommand, PfCountCommand, PfMergeCommand};
+use crate::cmd::keyspace::{KeysCommand, ScanCommand};
+use crate::cmd::list::{
+    BLMPopCommand, BLMoveCommand, BLPopCommand, BRPopCommand, LIndexCommand, LInsertCommand,
+    LLenCommand, LMPopCommand, LMoveCommand, LPopCommand, LPosCommand, LPushCommand, LPushXCommand,
+    LRangeCommand, LRemCommand, LSetCommand, LTrimCommand, RPopCommand, RPushCommand,
+    RPushXCommand,
+};
 use crate::cmd::map::{GetCommand, SetCommand};
-use crate::cmd::set::{SaddCommand, SismemberCommand};
+use crate::cmd::pubsub::{PubSubCommand, PublishCommand, SubscribeCommand, UnsubscribeCommand};
+use crate::cmd::script::{EvalCommand, ScriptCommand};
+use crate::cmd::set::{
+    SCardCommand, SDiffCommand, SDiffStoreCommand, SInterCardCommand, SInterCommand,
+    SInterStoreCommand, SMembersCommand, SMisMemberCommand, SMoveCommand, SPopCommand,
+    SRandMemberCommand, SRemCommand, SScanCommand, SUnionCommand, SUnionStoreCommand, SaddCommand,
+    SismemberCommand,
+};
+use crate::cmd::stream::{
+    XAddCommand, XDelCommand, XLenCommand, XRangeCommand, XReadCommand, XRevRangeCommand,
+    XTrimCommand,
+};
+use crate::cmd::stream_group::{
+    XAckCommand, XAutoClaimCommand, XClaimCommand, XGroupCommand, XInfoCommand, XPendingCommand,
+    XReadGroupCommand,
+};
+use crate::cmd::transaction::{
+    DiscardCommand, ExecCommand, MultiCommand, UnwatchCommand, WatchCommand,
+};
+use crate::cmd::zset::{
+    BZMPopCommand, BZPopMaxCommand, BZPopMinCommand, ZAddCommand, ZCardCommand, ZCountCommand,
+    ZDiffCommand, ZDiffStoreCommand, ZIncrByCommand, ZInterCommand, ZInterStoreCommand,
+    ZLexCountCommand, ZMPopCommand, ZMScoreCommand, ZPopMaxCommand, ZPopMinCommand,
+    ZRandMemberCommand, ZRangeCommand, ZRangeStoreCommand, ZRankCommand, ZRemCommand,
+    ZRemRangeByLexCommand, ZRemRangeByRankCommand, ZRemRangeByScoreCommand, ZRevRankCommand,
+    ZScanCommand, ZScoreCommand, ZUnionCommand, ZUnionStoreCommand,
+};
+use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand, UnknownCommand};
+use crate::resp::array::RespArray;
 use crate::resp::frame::{DecodeErr, RespFrame};
 use crate::resp::simple_string::RespSimpleString;
 
+pub mod bitfield;
+pub mod client;
+pub mod config;
+pub mod db;
 pub mod echo;
+pub mod expire;
+pub mod geo;
+pub mod hexpire;
 pub mod hmap;
+pub mod hyperloglog;
+pub mod keyspace;
+pub mod keyspec;
+pub mod list;
 pub mod map;
+pub mod pubsub;
+pub mod script;
 pub mod set;
+pub mod stream;
+pub mod stream_group;
+pub mod transaction;
+pub mod zset;
 
 lazy_static! {
     static ref RET_OK: RespFrame = RespSimpleString::new("OK").into();
@@ -24,6 +98,11 @@ pub trait CommandExecutor {
     fn execute(self, backend: Backend) -> anyhow::Result<RespFrame>;
 }
 
+/// Commands that may park the client until one of their keys can serve it.
+pub trait BlockingExecutor {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked>;
+}
+
 #[derive(Debug)]
 pub enum Command {
     Get(GetCommand),
@@ -35,12 +114,330 @@ pub enum Command {
     Hmget(HmgetCommand),
     Sadd(SaddCommand),
     SisMember(SismemberCommand),
+    HDel(HDelCommand),
+    HExists(HExistsCommand),
+    HLen(HLenCommand),
+    HKeys(HKeysCommand),
+    HVals(HValsCommand),
+    HIncrBy(HIncrByCommand),
+    HIncrByFloat(HIncrByFloatCommand),
+    HSetNx(HSetNxCommand),
+    HStrLen(HStrLenCommand),
+    HRandField(HRandFieldCommand),
+    HExpire(HExpireCommand),
+    HTtl(HTtlCommand),
+    HPersist(HPersistCommand),
+    HGetEx(HGetExCommand),
+    HSetEx(HSetExCommand),
+    SRem(SRemCommand),
+    SMembers(SMembersCommand),
+    SCard(SCardCommand),
+    SMisMember(SMisMemberCommand),
+    SPop(SPopCommand),
+    SRandMember(SRandMemberCommand),
+    SMove(SMoveCommand),
+    SInter(SInterCommand),
+    SUnion(SUnionCommand),
+    SDiff(SDiffCommand),
+    SInterStore(SInterStoreCommand),
+    SUnionStore(SUnionStoreCommand),
+    SDiffStore(SDiffStoreCommand),
+    SInterCard(SInterCardCommand),
+    LPush(LPushCommand),
+    RPush(RPushCommand),
+    LPushX(LPushXCommand),
+    RPushX(RPushXCommand),
+    LPop(LPopCommand),
+    RPop(RPopCommand),
+    LRange(LRangeCommand),
+    LIndex(LIndexCommand),
+    LSet(LSetCommand),
+    LLen(LLenCommand),
+    LRem(LRemCommand),
+    LTrim(LTrimCommand),
+    LInsert(LInsertCommand),
+    LPos(LPosCommand),
+    LMove(LMoveCommand),
+    LMPop(LMPopCommand),
+    BLPop(BLPopCommand),
+    BRPop(BRPopCommand),
+    BLMove(BLMoveCommand),
+    BLMPop(BLMPopCommand),
+    Client(ClientCommand),
+    ZAdd(ZAddCommand),
+    ZRem(ZRemCommand),
+    ZScore(ZScoreCommand),
+    ZMScore(ZMScoreCommand),
+    ZIncrBy(ZIncrByCommand),
+    ZCard(ZCardCommand),
+    ZCount(ZCountCommand),
+    ZRank(ZRankCommand),
+    ZRevRank(ZRevRankCommand),
+    ZRange(ZRangeCommand),
+    ZPopMin(ZPopMinCommand),
+    ZPopMax(ZPopMaxCommand),
+    BZPopMin(BZPopMinCommand),
+    BZPopMax(BZPopMaxCommand),
+    ZMPop(ZMPopCommand),
+    BZMPop(BZMPopCommand),
+    ZUnion(ZUnionCommand),
+    ZInter(ZInterCommand),
+    ZDiff(ZDiffCommand),
+    ZUnionStore(ZUnionStoreCommand),
+    ZInterStore(ZInterStoreCommand),
+    ZDiffStore(ZDiffStoreCommand),
+    ZRangeStore(ZRangeStoreCommand),
+    ZRemRangeByScore(ZRemRangeByScoreCommand),
+    ZRemRangeByRank(ZRemRangeByRankCommand),
+    ZRemRangeByLex(ZRemRangeByLexCommand),
+    ZLexCount(ZLexCountCommand),
+    ZRandMember(ZRandMemberCommand),
+    BitField(BitFieldCommand),
+    BitFieldRo(BitFieldRoCommand),
+    GeoAdd(GeoAddCommand),
+    GeoPos(GeoPosCommand),
+    GeoDist(GeoDistCommand),
+    GeoHash(GeoHashCommand),
+    GeoSearch(GeoSearchCommand),
+    GeoSearchStore(GeoSearchStoreCommand),
+    XAdd(XAddCommand),
+    XRange(XRangeCommand),
+    XRevRange(XRevRangeCommand),
+    XLen(XLenCommand),
+    XDel(XDelCommand),
+    XTrim(XTrimCommand),
+    XRead(XReadCommand),
+    PfAdd(PfAddCommand),
+    PfCount(PfCountCommand),
+    PfMerge(PfMergeCommand),
+    XGroup(XGroupCommand),
+    XReadGroup(XReadGroupCommand),
+    XAck(XAckCommand),
+    XPending(XPendingCommand),
+    XClaim(XClaimCommand),
+    XAutoClaim(XAutoClaimCommand),
+    XInfo(XInfoCommand),
+    Keys(KeysCommand),
+    Scan(ScanCommand),
+    HScan(HScanCommand),
+    SScan(SScanCommand),
+    ZScan(ZScanCommand),
+    Select(SelectCommand),
+    SwapDb(SwapDbCommand),
+    Move(MoveCommand),
+    Expire(ExpireCommand),
+    Ttl(TtlCommand),
+    Persist(PersistCommand),
+    DbSize(DbSizeCommand),
+    FlushDb(FlushDbCommand),
+    FlushAll(FlushAllCommand),
+    Multi(MultiCommand),
+    Exec(ExecCommand),
+    Discard(DiscardCommand),
+    Watch(WatchCommand),
+    Unwatch(UnwatchCommand),
+    Subscribe(SubscribeCommand),
+    Unsubscribe(UnsubscribeCommand),
+    Publish(PublishCommand),
+    PubSub(PubSubCommand),
+    Ping(PingCommand),
+    Hello(HelloCommand),
+    Config(ConfigCommand),
+    Eval(EvalCommand),
+    Script(ScriptCommand),
+}
+
+impl TryFrom<RespArray> for Command {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let name = match arr.0.as_deref().and_then(|args| args.first()) {
+            Some(RespFrame::BulkString(s)) => match s.as_deref() {
+                Some(name) => name.to_ascii_lowercase(),
+                None => return Err(InvalidCommand("Invalid command format.".to_string())),
+            },
+            _ => return Err(InvalidCommand("Invalid command format.".to_string())),
+        };
+        Ok(match name.as_slice() {
+            b"multi" => Command::Multi(MultiCommand::try_from(arr)?),
+            b"exec" => Command::Exec(ExecCommand::try_from(arr)?),
+            b"discard" => Command::Discard(DiscardCommand::try_from(arr)?),
+            b"watch" => Command::Watch(WatchCommand::try_from(arr)?),
+            b"unwatch" => Command::Unwatch(UnwatchCommand::try_from(arr)?),
+            b"get" => Command::Get(GetCommand::try_from(arr)?),
+            b"set" => Command::Set(SetCommand::try_from(arr)?),
+            b"hget" => Command::HGet(HGetCommand::try_from(arr)?),
+            b"hset" => Command::HSet(HSetCommand::try_from(arr)?),
+            b"hgetall" => Command::HGetAll(HGetAllCommand::try_from(arr)?),
+            b"echo" => Command::ECHO(ECHOCommand::try_from(arr)?),
+            b"hmget" => Command::Hmget(HmgetCommand::try_from(arr)?),
+            b"sadd" => Command::Sadd(SaddCommand::try_from(arr)?),
+            b"sismember" => Command::SisMember(SismemberCommand::try_from(arr)?),
+            b"hdel" => Command::HDel(HDelCommand::try_from(arr)?),
+            b"hexists" => Command::HExists(HExistsCommand::try_from(arr)?),
+            b"hlen" => Command::HLen(HLenCommand::try_from(arr)?),
+            b"hkeys" => Command::HKeys(HKeysCommand::try_from(arr)?),
+            b"hvals" => Command::HVals(HValsCommand::try_from(arr)?),
+            b"hincrby" => Command::HIncrBy(HIncrByCommand::try_from(arr)?),
+            b"hincrbyfloat" => Command::HIncrByFloat(HIncrByFloatCommand::try_from(arr)?),
+            b"hsetnx" => Command::HSetNx(HSetNxCommand::try_from(arr)?),
+            b"hstrlen" => Command::HStrLen(HStrLenCommand::try_from(arr)?),
+            b"hrandfield" => Command::HRandField(HRandFieldCommand::try_from(arr)?),
+            b"hexpire" | b"hpexpire" | b"hexpireat" | b"hpexpireat" => {
+                Command::HExpire(HExpireCommand::try_from(arr)?)
+            }
+            b"httl" | b"hpttl" | b"hexpiretime" | b"hpexpiretime" => {
+                Command::HTtl(HTtlCommand::try_from(arr)?)
+            }
+            b"hpersist" => Command::HPersist(HPersistCommand::try_from(arr)?),
+            b"hgetex" => Command::HGetEx(HGetExCommand::try_from(arr)?),
+            b"hsetex" => Command::HSetEx(HSetExCommand::try_from(arr)?),
+            b"srem" => Command::SRem(SRemCommand::try_from(arr)?),
+            b"smembers" => Command::SMembers(SMembersCommand::try_from(arr)?),
+            b"scard" => Command::SCard(SCardCommand::try_from(arr)?),
+            b"smismember" => Command::SMisMember(SMisMemberCommand::try_from(arr)?),
+            b"spop" => Command::SPop(SPopCommand::try_from(arr)?),
+            b"srandmember" => Command::SRandMember(SRandMemberCommand::try_from(arr)?),
+            b"smove" => Command::SMove(SMoveCommand::try_from(arr)?),
+            b"sinter" => Command::SInter(SInterCommand::try_from(arr)?),
+            b"sunion" => Command::SUnion(SUnionCommand::try_from(arr)?),
+            b"sdiff" => Command::SDiff(SDiffCommand::try_from(arr)?),
+            b"sinterstore" => Command::SInterStore(SInterStoreCommand::try_from(arr)?),
+            b"sunionstore" => Command::SUnionStore(SUnionStoreCommand::try_from(arr)?),
+            b"sdiffstore" => Command::SDiffStore(SDiffStoreCommand::try_from(arr)?),
+            b"sintercard" => Command::SInterCard(SInterCardCommand::try_from(arr)?),
+            b"lpush" => Command::LPush(LPushCommand::try_from(arr)?),
+            b"rpush" => Command::RPush(RPushCommand::try_from(arr)?),
+            b"lpushx" => Command::LPushX(LPushXCommand::try_from(arr)?),
+            b"rpushx" => Command::RPushX(RPushXCommand::try_from(arr)?),
+            b"lpop" => Command::LPop(LPopCommand::try_from(arr)?),
+            b"rpop" => Command::RPop(RPopCommand::try_from(arr)?),
+            b"lrange" => Command::LRange(LRangeCommand::try_from(arr)?),
+            b"lindex" => Command::LIndex(LIndexCommand::try_from(arr)?),
+            b"lset" => Command::LSet(LSetCommand::try_from(arr)?),
+            b"llen" => Command::LLen(LLenCommand::try_from(arr)?),
+            b"lrem" => Command::LRem(LRemCommand::try_from(arr)?),
+            b"ltrim" => Command::LTrim(LTrimCommand::try_from(arr)?),
+            b"linsert" => Command::LInsert(LInsertCommand::try_from(arr)?),
+            b"lpos" => Command::LPos(LPosCommand::try_from(arr)?),
+            b"lmove" => Command::LMove(LMoveCommand::try_from(arr)?),
+            b"lmpop" => Command::LMPop(LMPopCommand::try_from(arr)?),
+            b"blpop" => Command::BLPop(BLPopCommand::try_from(arr)?),
+            b"brpop" => Command::BRPop(BRPopCommand::try_from(arr)?),
+            b"blmove" => Command::BLMove(BLMoveCommand::try_from(arr)?),
+            b"blmpop" => Command::BLMPop(BLMPopCommand::try_from(arr)?),
+            b"client" => Command::Client(ClientCommand::try_from(arr)?),
+            b"zadd" => Command::ZAdd(ZAddCommand::try_from(arr)?),
+            b"zrem" => Command::ZRem(ZRemCommand::try_from(arr)?),
+            b"zscore" => Command::ZScore(ZScoreCommand::try_from(arr)?),
+            b"zmscore" => Command::ZMScore(ZMScoreCommand::try_from(arr)?),
+            b"zincrby" => Command::ZIncrBy(ZIncrByCommand::try_from(arr)?),
+            b"zcard" => Command::ZCard(ZCardCommand::try_from(arr)?),
+            b"zcount" => Command::ZCount(ZCountCommand::try_from(arr)?),
+            b"zrank" => Command::ZRank(ZRankCommand::try_from(arr)?),
+            b"zrevrank" => Command::ZRevRank(ZRevRankCommand::try_from(arr)?),
+            b"zrange" => Command::ZRange(ZRangeCommand::try_from(arr)?),
+            b"zunion" => Command::ZUnion(ZUnionCommand::try_from(arr)?),
+            b"zinter" => Command::ZInter(ZInterCommand::try_from(arr)?),
+            b"zdiff" => Command::ZDiff(ZDiffCommand::try_from(arr)?),
+            b"zunionstore" => Command::ZUnionStore(ZUnionStoreCommand::try_from(arr)?),
+            b"zinterstore" => Command::ZInterStore(ZInterStoreCommand::try_from(arr)?),
+            b"zdiffstore" => Command::ZDiffStore(ZDiffStoreCommand::try_from(arr)?),
+            b"zrangestore" => Command::ZRangeStore(ZRangeStoreCommand::try_from(arr)?),
+            b"zremrangebyscore" => {
+                Command::ZRemRangeByScore(ZRemRangeByScoreCommand::try_from(arr)?)
+            }
+            b"zremrangebyrank" => Command::ZRemRangeByRank(ZRemRangeByRankCommand::try_from(arr)?),
+            b"zremrangebylex" => Command::ZRemRangeByLex(ZRemRangeByLexCommand::try_from(arr)?),
+            b"zlexcount" => Command::ZLexCount(ZLexCountCommand::try_from(arr)?),
+            b"zrandmember" => Command::ZRandMember(ZRandMemberCommand::try_from(arr)?),
+            b"zpopmin" => Command::ZPopMin(ZPopMinCommand::try_from(arr)?),
+            b"zpopmax" => Command::ZPopMax(ZPopMaxCommand::try_from(arr)?),
+            b"bzpopmin" => Command::BZPopMin(BZPopMinCommand::try_from(arr)?),
+            b"bzpopmax" => Command::BZPopMax(BZPopMaxCommand::try_from(arr)?),
+            b"zmpop" => Command::ZMPop(ZMPopCommand::try_from(arr)?),
+            b"bzmpop" => Command::BZMPop(BZMPopCommand::try_from(arr)?),
+            b"geoadd" => Command::GeoAdd(GeoAddCommand::try_from(arr)?),
+            b"geopos" => Command::GeoPos(GeoPosCommand::try_from(arr)?),
+            b"geodist" => Command::GeoDist(GeoDistCommand::try_from(arr)?),
+            b"geohash" => Command::GeoHash(GeoHashCommand::try_from(arr)?),
+            b"geosearch" => Command::GeoSearch(GeoSearchCommand::try_from(arr)?),
+            b"geosearchstore" => Command::GeoSearchStore(GeoSearchStoreCommand::try_from(arr)?),
+            b"xadd" => Command::XAdd(XAddCommand::try_from(arr)?),
+            b"xrange" => Command::XRange(XRangeCommand::try_from(arr)?),
+            b"xrevrange" => Command::XRevRange(XRevRangeCommand::try_from(arr)?),
+            b"xlen" => Command::XLen(XLenCommand::try_from(arr)?),
+            b"xdel" => Command::XDel(XDelCommand::try_from(arr)?),
+            b"xtrim" => Command::XTrim(XTrimCommand::try_from(arr)?),
+            b"xread" => Command::XRead(XReadCommand::try_from(arr)?),
+            b"xgroup" => Command::XGroup(XGroupCommand::try_from(arr)?),
+            b"xreadgroup" => Command::XReadGroup(XReadGroupCommand::try_from(arr)?),
+            b"xack" => Command::XAck(XAckCommand::try_from(arr)?),
+            b"xpending" => Command::XPending(XPendingCommand::try_from(arr)?),
+            b"xclaim" => Command::XClaim(XClaimCommand::try_from(arr)?),
+            b"xautoclaim" => Command::XAutoClaim(XAutoClaimCommand::try_from(arr)?),
+            b"xinfo" => Command::XInfo(XInfoCommand::try_from(arr)?),
+            b"pfadd" => Command::PfAdd(PfAddCommand::try_from(arr)?),
+            b"pfcount" => Command::PfCount(PfCountCommand::try_from(arr)?),
+            b"pfmerge" => Command::PfMerge(PfMergeCommand::try_from(arr)?),
+            b"keys" => Command::Keys(KeysCommand::try_from(arr)?),
+            b"scan" => Command::Scan(ScanCommand::try_from(arr)?),
+            b"hscan" => Command::HScan(HScanCommand::try_from(arr)?),
+            b"sscan" => Command::SScan(SScanCommand::try_from(arr)?),
+            b"zscan" => Command::ZScan(ZScanCommand::try_from(arr)?),
+            b"select" => Command::Select(SelectCommand::try_from(arr)?),
+            b"swapdb" => Command::SwapDb(SwapDbCommand::try_from(arr)?),
+            b"move" => Command::Move(MoveCommand::try_from(arr)?),
+            b"expire" | b"pexpire" | b"expireat" | b"pexpireat" => {
+                Command::Expire(ExpireCommand::try_from(arr)?)
+            }
+            b"ttl" | b"pttl" | b"expiretime" | b"pexpiretime" => {
+                Command::Ttl(TtlCommand::try_from(arr)?)
+            }
+            b"persist" => Command::Persist(PersistCommand::try_from(arr)?),
+            b"dbsize" => Command::DbSize(DbSizeCommand::try_from(arr)?),
+            b"flushdb" => Command::FlushDb(FlushDbCommand::try_from(arr)?),
+            b"flushall" => Command::FlushAll(FlushAllCommand::try_from(arr)?),
+            b"bitfield" => Command::BitField(BitFieldCommand::try_from(arr)?),
+            b"bitfield_ro" => Command::BitFieldRo(BitFieldRoCommand::try_from(arr)?),
+            b"subscribe" => {
+                Command::Subscribe(SubscribeCommand::parse(arr, SubscriptionKind::Channel)?)
+            }
+            b"psubscribe" => {
+                Command::Subscribe(SubscribeCommand::parse(arr, SubscriptionKind::Pattern)?)
+            }
+            b"unsubscribe" => {
+                Command::Unsubscribe(UnsubscribeCommand::parse(arr, SubscriptionKind::Channel)?)
+            }
+            b"punsubscribe" => {
+                Command::Unsubscribe(UnsubscribeCommand::parse(arr, SubscriptionKind::Pattern)?)
+            }
+            b"ssubscribe" => {
+                Command::Subscribe(SubscribeCommand::parse(arr, SubscriptionKind::Shard)?)
+            }
+            b"sunsubscribe" => {
+                Command::Unsubscribe(UnsubscribeCommand::parse(arr, SubscriptionKind::Shard)?)
+            }
+            b"publish" => Command::Publish(PublishCommand::parse(arr, false)?),
+            b"spublish" => Command::Publish(PublishCommand::parse(arr, true)?),
+            b"pubsub" => Command::PubSub(PubSubCommand::try_from(arr)?),
+            b"ping" => Command::Ping(PingCommand::try_from(arr)?),
+            b"hello" => Command::Hello(HelloCommand::try_from(arr)?),
+            b"config" => Command::Config(ConfigCommand::try_from(arr)?),
+            b"eval" => Command::Eval(EvalCommand::parse(arr, false)?),
+            b"evalsha" => Command::Eval(EvalCommand::parse(arr, true)?),
+            b"script" => Command::Script(ScriptCommand::try_from(arr)?),
+            _ => return Err(UnknownCommand(String::from_utf8_lossy(&name).into_owned())),
+        })
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum ExecuteError {
     #[error("Invalid command: {0}")]
     InvalidCommand(String),
+    #[error("unknown command '{0}'")]
+    UnknownCommand(String),
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
     #[error("{0}")]
@@ -52,3 +449,150 @@ pub enum ExecuteError {
 pub fn into_args_iter(val: Vec<RespFrame>, start: usize) -> impl Iterator<Item = RespFrame> {
     val.into_iter().skip(start)
 }
+
+/// Strips the command name and returns the remaining arguments.
+pub fn into_args(arr: RespArray) -> Result<Vec<RespFrame>, ExecuteError> {
+    let Some(arr) = arr.0 else {
+        return Err(InvalidCommand("command exists".to_string()));
+    };
+    Ok(into_args_iter(arr, 1).collect())
+}
+
+/// Parses commands whose only argument is a key.
+pub fn parse_key(arr: RespArray) -> Result<String, ExecuteError> {
+    let args = into_args(arr)?;
+    check_nargs(&args, 1)?;
+    frame_to_string(args.into_iter().next().expect("key has to exist"))
+}
+
+pub fn check_nargs(args: &[RespFrame], n: usize) -> Result<(), ExecuteError> {
+    if args.len() != n {
+        return Err(InvalidArgument(format!(
+            "expected {}, got {}",
+            n,
+            args.len()
+        )));
+    }
+    Ok(())
+}
+
+pub fn check_min_nargs(args: &[RespFrame], n: usize) -> Result<(), ExecuteError> {
+    if args.len() < n {
+        return Err(InvalidArgument(format!(
+            "expected at least {}, got {}",
+            n,
+            args.len()
+        )));
+    }
+    Ok(())
+}
+
+pub fn frame_to_string(frame: RespFrame) -> Result<String, ExecuteError> {
+    match frame {
+        RespFrame::BulkString(s) => match s.as_ref() {
+            Some(s) => Ok(String::from_utf8(s.to_vec())?),
+            None => Err(InvalidArgument("null bulkstring".to_string())),
+        },
+        RespFrame::SimpleString(s) => Ok(s.to_string()),
+        _ => Err(InvalidArgument(
+            "argument should be a bulkstring".to_string(),
+        )),
+    }
+}
+
+pub fn frame_to_i64(frame: RespFrame) -> Result<i64, ExecuteError> {
+    match frame {
+        RespFrame::Integer(i) => Ok(i),
+        frame => frame_to_string(frame)?
+            .parse::<i64>()
+            .map_err(|_| InvalidArgument("value is not an integer or out of range".to_string())),
+    }
+}
+
+pub fn frame_to_f64(frame: RespFrame) -> Result<f64, ExecuteError> {
+    match frame {
+        RespFrame::Integer(i) => Ok(i as f64),
+        RespFrame::Double(d) => Ok(d.get()),
+        frame => frame_to_string(frame)?
+            .parse::<f64>()
+            .ok()
+            .filter(|f| !f.is_nan())
+            .ok_or_else(|| InvalidArgument("value is not a valid float".to_string())),
+    }
+}
+
+/// Blocking timeout in seconds, `None` for 0 which waits forever.
+pub fn parse_timeout(frame: RespFrame) -> Result<Option<Duration>, ExecuteError> {
+    let timeout = frame_to_f64(frame)
+        .ok()
+        .filter(|t| t.is_finite())
+        .ok_or_else(|| InvalidArgument("timeout is not a float or out of range".to_string()))?;
+    if timeout < 0.0 {
+        return Err(InvalidArgument("timeout is negative".to_string()));
+    }
+    Ok((timeout > 0.0).then(|| Duration::from_secs_f64(timeout)))
+}
+
+/// The lowercase name of a command, empty if it has none.
+pub fn command_name(arr: &RespArray) -> String {
+    arr.as_deref()
+        .and_then(|frames| frames.first())
+        .and_then(frame_bytes)
+        .map(|name| String::from_utf8_lossy(name).to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+// key member [member ...], with at least `min` members
+pub fn parse_key_members(
+    arr: RespArray,
+    min: usize,
+) -> Result<(String, Vec<RespFrame>), ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, min + 1)?;
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    Ok((key, args.collect()))
+}
+
+// like `parse_key_members`, for members that are names
+pub fn parse_key_names(arr: RespArray, min: usize) -> Result<(String, Vec<String>), ExecuteError> {
+    let (key, members) = parse_key_members(arr, min)?;
+    let members = members
+        .into_iter()
+        .map(frame_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((key, members))
+}
+
+// key [count]
+pub fn parse_key_count(arr: RespArray) -> Result<(String, Option<i64>), ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, 1)?;
+    if args.len() > 2 {
+        return Err(InvalidArgument("syntax error".to_string()));
+    }
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let count = args.next().map(frame_to_i64).transpose()?;
+    Ok((key, count))
+}
+
+/// The count of the pop commands, which can't be negative.
+pub fn pop_count(count: Option<i64>) -> Result<Option<usize>, ExecuteError> {
+    match count {
+        Some(count) if count < 0 => Err(InvalidArgument(
+            "value is out of range, must be positive".to_string(),
+        )),
+        count => Ok(count.map(|c| c as usize)),
+    }
+}
+
+/// A command as a client sends it.
+#[cfg(test)]
+pub(crate) fn cmd(args: &[&str]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|s| crate::resp::bulkstring::RespBulkString::new(s).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
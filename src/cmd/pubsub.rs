@@ -0,0 +1,278 @@
+use crate::backend::pubsub::{PushSender, SubscriptionKind};
+use crate::backend::slot::key_slot;
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_string, into_args, CommandExecutor, ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+// Subscribe: channel [channel ...]
+// PSubscribe: pattern [pattern ...]
+// SSubscribe: shardchannel [shardchannel ...]
+#[derive(Debug, PartialEq)]
+pub struct SubscribeCommand {
+    kind: SubscriptionKind,
+    names: Vec<String>,
+}
+
+// Unsubscribe: [channel [channel ...]]
+// PUnsubscribe: [pattern [pattern ...]]
+// SUnsubscribe: [shardchannel [shardchannel ...]]
+#[derive(Debug, PartialEq)]
+pub struct UnsubscribeCommand {
+    kind: SubscriptionKind,
+    names: Vec<String>,
+}
+
+// Publish: channel message
+// SPublish: shardchannel message
+#[derive(Debug)]
+pub struct PublishCommand {
+    channel: String,
+    message: RespFrame,
+    shard: bool,
+}
+
+// PubSub: CHANNELS [pattern] | NUMSUB [channel ...] | NUMPAT
+//       | SHARDCHANNELS [pattern] | SHARDNUMSUB [shardchannel ...]
+#[derive(Debug, PartialEq)]
+pub enum PubSubCommand {
+    Channels {
+        kind: SubscriptionKind,
+        pattern: Option<String>,
+    },
+    NumSub {
+        kind: SubscriptionKind,
+        channels: Vec<String>,
+    },
+    NumPat,
+}
+
+// the names confirmations go by, subscribing and unsubscribing
+fn confirmation_names(kind: SubscriptionKind) -> (&'static str, &'static str) {
+    match kind {
+        SubscriptionKind::Channel => ("subscribe", "unsubscribe"),
+        SubscriptionKind::Pattern => ("psubscribe", "punsubscribe"),
+        SubscriptionKind::Shard => ("ssubscribe", "sunsubscribe"),
+    }
+}
+
+impl SubscribeCommand {
+    /// Replies with one confirmation per name, each going out the way a
+    /// message would. `sender` is where the client's messages go.
+    pub fn execute(
+        self,
+        backend: Backend,
+        client_id: u64,
+        sender: &PushSender,
+    ) -> anyhow::Result<Vec<Vec<RespFrame>>> {
+        // with a cluster, a node could only serve the shard channels of one slot
+        if self.kind == SubscriptionKind::Shard {
+            let mut slots = self.names.iter().map(|name| key_slot(name.as_bytes()));
+            let first = slots.next();
+            if slots.any(|slot| Some(slot) != first) {
+                anyhow::bail!("CROSSSLOT Keys in request don't hash to the same slot");
+            }
+        }
+        let (kind, _) = confirmation_names(self.kind);
+        Ok(backend
+            .subscribe(client_id, sender, self.kind, self.names)
+            .into_iter()
+            .map(|(name, count)| {
+                vec![
+                    RespBulkString::new(kind).into(),
+                    RespBulkString::new(name).into(),
+                    RespFrame::Integer(count as i64),
+                ]
+            })
+            .collect())
+    }
+}
+
+impl UnsubscribeCommand {
+    pub fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Vec<Vec<RespFrame>>> {
+        let (_, kind) = confirmation_names(self.kind);
+        Ok(backend
+            .unsubscribe(client_id, self.kind, self.names)
+            .into_iter()
+            .map(|(name, count)| {
+                let name = name.map_or_else(RespBulkString::null, RespBulkString::new);
+                vec![
+                    RespBulkString::new(kind).into(),
+                    name.into(),
+                    RespFrame::Integer(count as i64),
+                ]
+            })
+            .collect())
+    }
+}
+
+impl CommandExecutor for PublishCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let receivers = if self.shard {
+            backend.spublish(&self.channel, self.message)
+        } else {
+            backend.publish(&self.channel, self.message)
+        };
+        Ok(RespFrame::Integer(receivers as i64))
+    }
+}
+
+impl CommandExecutor for PubSubCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        match self {
+            PubSubCommand::Channels { kind, pattern } => {
+                let channels = backend
+                    .pubsub_channels(kind, pattern.as_deref())
+                    .into_iter()
+                    .map(|channel| RespBulkString::new(channel).into())
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(channels).into())
+            }
+            PubSubCommand::NumSub { kind, channels } => {
+                let counts = backend
+                    .pubsub_numsub(kind, channels)
+                    .into_iter()
+                    .flat_map(|(channel, n)| {
+                        [
+                            RespBulkString::new(channel).into(),
+                            RespFrame::Integer(n as i64),
+                        ]
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(counts).into())
+            }
+            PubSubCommand::NumPat => Ok(RespFrame::Integer(backend.pubsub_numpat() as i64)),
+        }
+    }
+}
+
+// whether a PUBSUB subcommand is about shard channels
+fn channels_kind(sub: &str) -> SubscriptionKind {
+    if sub.to_ascii_lowercase().starts_with("shard") {
+        SubscriptionKind::Shard
+    } else {
+        SubscriptionKind::Channel
+    }
+}
+
+fn parse_names(args: Vec<RespFrame>) -> Result<Vec<String>, ExecuteError> {
+    args.into_iter().map(frame_to_string).collect()
+}
+
+impl SubscribeCommand {
+    /// Parses `SUBSCRIBE`, `PSUBSCRIBE` or `SSUBSCRIBE`, depending on `kind`.
+    pub fn parse(arr: RespArray, kind: SubscriptionKind) -> Result<Self, ExecuteError> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        Ok(SubscribeCommand {
+            kind,
+            names: parse_names(args)?,
+        })
+    }
+}
+
+impl UnsubscribeCommand {
+    /// Parses `UNSUBSCRIBE`, `PUNSUBSCRIBE` or `SUNSUBSCRIBE`, depending on
+    /// `kind`.
+    pub fn parse(arr: RespArray, kind: SubscriptionKind) -> Result<Self, ExecuteError> {
+        Ok(UnsubscribeCommand {
+            kind,
+            names: parse_names(into_args(arr)?)?,
+        })
+    }
+}
+
+impl PublishCommand {
+    /// Parses `PUBLISH`, or `SPUBLISH` when `shard` is set.
+    pub fn parse(arr: RespArray, shard: bool) -> Result<Self, ExecuteError> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let channel = frame_to_string(args.next().expect("channel has to exist"))?;
+        let message = args.next().expect("message has to exist");
+        Ok(PublishCommand {
+            channel,
+            message,
+            shard,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PubSubCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let sub = frame_to_string(args.next().expect("subcommand has to exist"))?;
+        let args = args.collect::<Vec<_>>();
+        match (sub.to_ascii_lowercase().as_str(), args.len()) {
+            ("channels" | "shardchannels", 0 | 1) => Ok(PubSubCommand::Channels {
+                kind: channels_kind(&sub),
+                pattern: args.into_iter().next().map(frame_to_string).transpose()?,
+            }),
+            ("numsub" | "shardnumsub", _) => Ok(PubSubCommand::NumSub {
+                kind: channels_kind(&sub),
+                channels: parse_names(args)?,
+            }),
+            ("numpat", 0) => Ok(PubSubCommand::NumPat),
+            _ => Err(InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_pubsub_try_from() -> anyhow::Result<()> {
+        assert_eq!(
+            SubscribeCommand::parse(cmd(&["psubscribe", "n*"]), SubscriptionKind::Pattern)?,
+            SubscribeCommand {
+                kind: SubscriptionKind::Pattern,
+                names: vec!["n*".to_string()]
+            }
+        );
+        assert!(SubscribeCommand::parse(cmd(&["subscribe"]), SubscriptionKind::Channel).is_err());
+        assert!(
+            UnsubscribeCommand::parse(cmd(&["unsubscribe"]), SubscriptionKind::Channel)?
+                .names
+                .is_empty()
+        );
+        assert_eq!(
+            PubSubCommand::try_from(cmd(&["pubsub", "NUMSUB", "a", "b"]))?,
+            PubSubCommand::NumSub {
+                kind: SubscriptionKind::Channel,
+                channels: vec!["a".to_string(), "b".to_string()]
+            }
+        );
+        assert_eq!(
+            PubSubCommand::try_from(cmd(&["pubsub", "channels"]))?,
+            PubSubCommand::Channels {
+                kind: SubscriptionKind::Channel,
+                pattern: None
+            }
+        );
+        assert!(PubSubCommand::try_from(cmd(&["pubsub", "numpat", "x"])).is_err());
+        assert_eq!(
+            PubSubCommand::try_from(cmd(&["pubsub", "SHARDCHANNELS", "a*"]))?,
+            PubSubCommand::Channels {
+                kind: SubscriptionKind::Shard,
+                pattern: Some("a*".to_string())
+            }
+        );
+        assert!(PublishCommand::parse(cmd(&["publish", "ch"]), false).is_err());
+        assert!(PublishCommand::parse(cmd(&["spublish", "ch", "hi"]), true)?.shard);
+        Ok(())
+    }
+}
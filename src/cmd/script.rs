@@ -0,0 +1,416 @@
+use anyhow::bail;
+use mlua::{HookTriggers, Lua, LuaOptions, MultiValue, StdLib, Table, Value};
+use tracing::info;
+
+use crate::backend::script::sha1_hex;
+use crate::backend::{frame_bytes, Backend};
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, frame_to_i64, frame_to_string, into_args, CommandExecutor, ExecuteError,
+    RET_OK,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+use crate::resp::simple_error::RespSimpleError;
+use crate::resp::simple_string::RespSimpleString;
+
+// Eval: script numkeys [key ...] [arg ...]
+// EvalSha: sha1 numkeys [key ...] [arg ...]
+#[derive(Debug, PartialEq)]
+pub struct EvalCommand {
+    script: ScriptSource,
+    keys: Vec<RespFrame>,
+    args: Vec<RespFrame>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ScriptSource {
+    Body(String),
+    Sha(String),
+}
+
+// Script: LOAD script | EXISTS sha1 [sha1 ...] | FLUSH [ASYNC|SYNC] | KILL
+#[derive(Debug, PartialEq)]
+pub enum ScriptCommand {
+    Load(String),
+    Exists(Vec<String>),
+    Flush,
+    Kill,
+}
+
+// instructions run between two looks at whether the script was killed
+const KILL_CHECK_INSTRUCTIONS: u32 = 10_000;
+
+// what of the `redis` library is easier said in Lua: `redis.call` raises
+// the errors `redis.pcall` returns
+const PRELUDE: &str = r#"
+redis.call = function(...)
+    local reply = redis.pcall(...)
+    if type(reply) == "table" and reply.err then
+        error(reply, 0)
+    end
+    return reply
+end
+redis.status_reply = function(s) return { ok = s } end
+redis.error_reply = function(s) return { err = s } end
+redis.LOG_DEBUG, redis.LOG_VERBOSE, redis.LOG_NOTICE, redis.LOG_WARNING = 0, 1, 2, 3
+loadfile, dofile = nil, nil
+"#;
+
+impl EvalCommand {
+    /// Runs the script, `call` running each command it calls through
+    /// `redis.call` or `redis.pcall`. Nothing else runs meanwhile, the
+    /// caller sees to that.
+    pub fn execute(
+        self,
+        backend: Backend,
+        mut call: impl FnMut(RespArray) -> RespFrame,
+    ) -> anyhow::Result<RespFrame> {
+        let (sha, body) = match self.script {
+            ScriptSource::Body(body) => (backend.script_load(body.clone()), body),
+            ScriptSource::Sha(sha) => match backend.script_body(&sha) {
+                Some(body) => (sha.to_ascii_lowercase(), body),
+                None => bail!("NOSCRIPT No matching script. Please use EVAL."),
+            },
+        };
+        // a fresh interpreter for every script, so none sees what another left
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::new(),
+        )?;
+        let script = match lua.load(&body).set_name("@user_script").into_function() {
+            Ok(script) => script,
+            Err(e) => bail!(
+                "Error compiling script (new function): {}",
+                error_message(&e)
+            ),
+        };
+        let globals = lua.globals();
+        globals.set("KEYS", strings_table(&lua, &self.keys)?)?;
+        globals.set("ARGV", strings_table(&lua, &self.args)?)?;
+        let redis = lua.create_table()?;
+        redis.set(
+            "sha1hex",
+            lua.create_function(|_, s: mlua::String| Ok(sha1_hex(s.as_bytes())))?,
+        )?;
+        redis.set(
+            "log",
+            lua.create_function(|_, (level, msg): (i64, mlua::String)| {
+                info!("script log {}: {}", level, msg.to_string_lossy());
+                Ok(())
+            })?,
+        )?;
+        globals.set("redis", redis.clone())?;
+
+        let _running = backend.start_script();
+        let killed = backend.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+            move |_, _| {
+                if killed.script_killed() {
+                    return Err(mlua::Error::RuntimeError(
+                        "ERR Script killed by user with SCRIPT KILL...".to_string(),
+                    ));
+                }
+                Ok(())
+            },
+        );
+        let result = lua.scope(|scope| {
+            let pcall = scope.create_function_mut(|lua, args: MultiValue| {
+                let reply = call(to_command(lua, args)?);
+                to_lua(lua, reply)
+            })?;
+            redis.set("pcall", pcall)?;
+            lua.load(PRELUDE).exec()?;
+            // errors come back as they were raised, tables included
+            let (ok, value) = lua
+                .load("return pcall(...)")
+                .call::<_, (bool, Value)>(script)?;
+            Ok(if ok {
+                Ok(from_lua(value))
+            } else {
+                Err(value_message(value))
+            })
+        });
+        match result {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(msg)) => bail!("{} script: {}", msg, sha),
+            Err(e) => bail!("{} script: {}", error_message(&e), sha),
+        }
+    }
+}
+
+impl CommandExecutor for ScriptCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        match self {
+            ScriptCommand::Load(body) => Ok(RespBulkString::new(backend.script_load(body)).into()),
+            ScriptCommand::Exists(shas) => {
+                let exists = backend
+                    .script_exists(&shas)
+                    .into_iter()
+                    .map(|exists| RespFrame::Integer(exists as i64))
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(exists).into())
+            }
+            ScriptCommand::Flush => {
+                backend.script_flush();
+                Ok(RET_OK.clone())
+            }
+            ScriptCommand::Kill => {
+                backend.kill_script()?;
+                Ok(RET_OK.clone())
+            }
+        }
+    }
+}
+
+fn strings_table<'lua>(lua: &'lua Lua, frames: &[RespFrame]) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    for (i, frame) in frames.iter().enumerate() {
+        let s = lua.create_string(frame_bytes(frame).unwrap_or_default())?;
+        table.raw_set(i + 1, s)?;
+    }
+    Ok(table)
+}
+
+// the command `redis.call` or `redis.pcall` was asked to run
+fn to_command(lua: &Lua, args: MultiValue) -> mlua::Result<RespArray> {
+    if args.is_empty() {
+        return Err(mlua::Error::RuntimeError(
+            "Please specify at least one argument for this redis lib call".to_string(),
+        ));
+    }
+    let frames = args
+        .into_iter()
+        .map(|arg| {
+            let s = match arg {
+                Value::String(s) => Some(s),
+                Value::Integer(_) | Value::Number(_) => lua.coerce_string(arg)?,
+                _ => None,
+            };
+            s.map(|s| RespBulkString::new(s.as_bytes()).into())
+                .ok_or_else(|| {
+                    mlua::Error::RuntimeError(
+                        "Lua redis lib command arguments must be strings or integers".to_string(),
+                    )
+                })
+        })
+        .collect::<mlua::Result<Vec<RespFrame>>>()?;
+    Ok(RespArray::new(frames))
+}
+
+fn sequence<'lua>(
+    lua: &'lua Lua,
+    items: impl IntoIterator<Item = RespFrame>,
+) -> mlua::Result<Value<'lua>> {
+    let table = lua.create_table()?;
+    for (i, item) in items.into_iter().enumerate() {
+        table.raw_set(i + 1, to_lua(lua, item)?)?;
+    }
+    Ok(Value::Table(table))
+}
+
+/// A reply as a script sees it, following Redis's RESP2 conversion: nulls
+/// are false, status and error replies tables with an `ok` or `err` field.
+fn to_lua(lua: &Lua, frame: RespFrame) -> mlua::Result<Value<'_>> {
+    let field = |name: &str, s: &str| -> mlua::Result<Value<'_>> {
+        let table = lua.create_table()?;
+        table.raw_set(name, s)?;
+        Ok(Value::Table(table))
+    };
+    match frame {
+        RespFrame::Integer(n) => Ok(Value::Integer(n as mlua::Integer)),
+        RespFrame::BulkString(s) => match s.as_deref() {
+            Some(s) => Ok(Value::String(lua.create_string(s)?)),
+            None => Ok(Value::Boolean(false)),
+        },
+        RespFrame::SimpleString(s) => field("ok", &s),
+        RespFrame::Error(e) => field("err", &e),
+        RespFrame::Null(_) => Ok(Value::Boolean(false)),
+        RespFrame::Boolean(true) => Ok(Value::Integer(1)),
+        RespFrame::Boolean(false) => Ok(Value::Boolean(false)),
+        RespFrame::Double(d) => Ok(Value::String(lua.create_string(d.get().to_string())?)),
+        RespFrame::Array(arr) => match arr.0 {
+            Some(items) => sequence(lua, items),
+            None => Ok(Value::Boolean(false)),
+        },
+        RespFrame::Map(map) => sequence(
+            lua,
+            map.iter()
+                .flat_map(|(key, value)| [key.clone(), value.clone()]),
+        ),
+        RespFrame::Set(set) => sequence(lua, set.iter().cloned()),
+        RespFrame::Push(push) => sequence(lua, push.iter().cloned()),
+    }
+}
+
+/// What a script returned as a reply: numbers are truncated to integers,
+/// true is 1, false and nil are null, and a table is an array up to its
+/// first nil unless it has an `ok` or `err` field.
+fn from_lua(value: Value) -> RespFrame {
+    match value {
+        Value::Boolean(true) => RespFrame::Integer(1),
+        Value::Integer(n) => RespFrame::Integer(n),
+        Value::Number(n) => RespFrame::Integer(n as i64),
+        Value::String(s) => RespBulkString::new(s.as_bytes()).into(),
+        Value::Table(table) => {
+            if let Ok(Value::String(s)) = table.raw_get::<_, Value>("err") {
+                return RespSimpleError::new(s.to_string_lossy()).into();
+            }
+            if let Ok(Value::String(s)) = table.raw_get::<_, Value>("ok") {
+                return RespSimpleString::new(s.to_string_lossy()).into();
+            }
+            let items = (1..)
+                .map_while(|i| match table.raw_get::<_, Value>(i) {
+                    Ok(Value::Nil) | Err(_) => None,
+                    Ok(item) => Some(from_lua(item)),
+                })
+                .collect::<Vec<_>>();
+            RespArray::new(items).into()
+        }
+        _ => RespBulkString::null().into(),
+    }
+}
+
+// the message of an error raised in Lua, whatever was raised
+fn value_message(value: Value) -> String {
+    match value {
+        Value::String(s) => s.to_string_lossy().into_owned(),
+        Value::Table(table) => match table.raw_get::<_, Value>("err") {
+            Ok(Value::String(s)) => s.to_string_lossy().into_owned(),
+            _ => "user_script: the script raised a table without an err field".to_string(),
+        },
+        Value::Error(e) => error_message(&e),
+        value => format!("user_script: the script raised a {}", value.type_name()),
+    }
+}
+
+fn error_message(e: &mlua::Error) -> String {
+    match e {
+        mlua::Error::RuntimeError(msg) | mlua::Error::SyntaxError { message: msg, .. } => {
+            msg.clone()
+        }
+        mlua::Error::CallbackError { cause, .. } => error_message(cause),
+        e => e.to_string(),
+    }
+}
+
+impl EvalCommand {
+    /// Parses `EVAL`, or `EVALSHA` when `sha` is set.
+    pub fn parse(arr: RespArray, sha: bool) -> Result<Self, ExecuteError> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let script = frame_to_string(args.next().expect("script has to exist"))?;
+        let numkeys = frame_to_i64(args.next().expect("numkeys has to exist"))?;
+        let mut keys = args.collect::<Vec<_>>();
+        if numkeys < 0 {
+            return Err(InvalidArgument(
+                "Number of keys can't be negative".to_string(),
+            ));
+        }
+        if numkeys as usize > keys.len() {
+            return Err(InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let args = keys.split_off(numkeys as usize);
+        let script = if sha {
+            ScriptSource::Sha(script)
+        } else {
+            ScriptSource::Body(script)
+        };
+        Ok(EvalCommand { script, keys, args })
+    }
+}
+
+impl TryFrom<RespArray> for ScriptCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let sub = frame_to_string(args.next().expect("subcommand has to exist"))?;
+        let args = args.map(frame_to_string).collect::<Result<Vec<_>, _>>()?;
+        match (sub.to_ascii_lowercase().as_str(), args.len()) {
+            ("load", 1) => Ok(ScriptCommand::Load(
+                args.into_iter().next().expect("script has to exist"),
+            )),
+            ("exists", 1..) => Ok(ScriptCommand::Exists(args)),
+            ("flush", 0) => Ok(ScriptCommand::Flush),
+            ("flush", 1) if ["async", "sync"].contains(&args[0].to_ascii_lowercase().as_str()) => {
+                Ok(ScriptCommand::Flush)
+            }
+            ("kill", 0) => Ok(ScriptCommand::Kill),
+            _ => Err(InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    fn eval(backend: &Backend, args: &[&str]) -> anyhow::Result<RespFrame> {
+        // a dispatcher knowing only SET, to see what scripts send and get
+        EvalCommand::parse(cmd(args), false)?.execute(backend.clone(), |arr| {
+            match arr.0.as_deref() {
+                Some([name, ..]) if frame_bytes(name) == Some(b"set".as_slice()) => {
+                    RespSimpleString::new("OK").into()
+                }
+                _ => RespSimpleError::new("ERR unknown command").into(),
+            }
+        })
+    }
+
+    #[test]
+    fn test_eval() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        assert_eq!(
+            eval(
+                &backend,
+                &[
+                    "eval",
+                    "return {KEYS[1], ARGV[1], 3.9, true, nil, 'x'}",
+                    "1",
+                    "k",
+                    "a"
+                ]
+            )?,
+            RespArray::new(vec![
+                RespBulkString::new("k").into(),
+                RespBulkString::new("a").into(),
+                RespFrame::Integer(3),
+                RespFrame::Integer(1),
+            ])
+            .into()
+        );
+        assert_eq!(
+            eval(&backend, &["eval", "return redis.call('set', 'k', 1)", "0"])?,
+            RespSimpleString::new("OK").into()
+        );
+        assert_eq!(
+            eval(&backend, &["eval", "return redis.pcall('nope').err", "0"])?,
+            RespBulkString::new("ERR unknown command").into()
+        );
+        let e = eval(&backend, &["eval", "return redis.call('nope')", "0"]).unwrap_err();
+        assert!(e.to_string().starts_with("ERR unknown command script: "));
+        assert!(eval(&backend, &["eval", "return (", "0"]).is_err());
+        assert!(EvalCommand::parse(cmd(&["eval", "return 1", "2", "k"]), false).is_err());
+
+        let sha = sha1_hex(b"return redis.call('set', 'k', 1)");
+        assert_eq!(backend.script_exists(&[sha]), [true]);
+        assert_eq!(
+            ScriptCommand::try_from(cmd(&["script", "FLUSH", "async"]))?,
+            ScriptCommand::Flush
+        );
+        assert!(ScriptCommand::try_from(cmd(&["script", "kill", "now"])).is_err());
+        Ok(())
+    }
+}
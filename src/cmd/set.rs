@@ -1,13 +1,19 @@
+use crate::backend::set::SetOp;
 use crate::backend::Backend;
+use crate::cmd::keyspace::{parse_key_scan, scan_reply, ScanOptions};
 use crate::cmd::ExecuteError::{InvalidArgument, InvalidCommand};
-use crate::cmd::{into_args_iter, CommandExecutor, ExecuteError};
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_i64, frame_to_string, into_args, into_args_iter,
+    parse_key, parse_key_count, parse_key_members, pop_count, CommandExecutor, ExecuteError,
+};
 use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
 use crate::resp::frame::RespFrame;
 
 #[derive(Debug)]
 pub struct SaddCommand {
     key: String,
-    member: RespFrame,
+    members: Vec<RespFrame>,
 }
 
 #[derive(Debug)]
@@ -16,9 +22,97 @@ pub struct SismemberCommand {
     member: RespFrame,
 }
 
+#[derive(Debug)]
+pub struct SRemCommand {
+    key: String,
+    members: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct SMembersCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SCardCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SMisMemberCommand {
+    key: String,
+    members: Vec<RespFrame>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SPopCommand {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SRandMemberCommand {
+    key: String,
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SMoveCommand {
+    source: String,
+    destination: String,
+    member: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct SInterCommand {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnionCommand {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SDiffCommand {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SInterStoreCommand {
+    destination: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnionStoreCommand {
+    destination: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SDiffStoreCommand {
+    destination: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SInterCardCommand {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+// SScan: key cursor [MATCH pattern] [COUNT count]
+#[derive(Debug, PartialEq)]
+pub struct SScanCommand {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+}
+
 impl CommandExecutor for SaddCommand {
     fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
-        let ret = backend.sadd(&self.key, self.member);
+        let ret = backend.sadd(&self.key, self.members)?;
         Ok(ret.into())
     }
 }
@@ -30,9 +124,134 @@ impl CommandExecutor for SismemberCommand {
     }
 }
 
+impl CommandExecutor for SRemCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.srem(&self.key, &self.members).into())
+    }
+}
+
+impl CommandExecutor for SMembersCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespArray::new(backend.smembers(&self.key)).into())
+    }
+}
+
+impl CommandExecutor for SCardCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.scard(&self.key).into())
+    }
+}
+
+impl CommandExecutor for SMisMemberCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend
+            .smismember(&self.key, &self.members)
+            .into_iter()
+            .map(RespFrame::Integer)
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for SPopCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        match self.count {
+            Some(count) => Ok(RespArray::new(backend.spop(&self.key, count)).into()),
+            None => Ok(backend
+                .spop(&self.key, 1)
+                .pop()
+                .unwrap_or_else(|| RespBulkString::null().into())),
+        }
+    }
+}
+
+impl CommandExecutor for SRandMemberCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let mut ret = backend.srandmember(&self.key, self.count);
+        match self.count {
+            Some(_) => Ok(RespArray::new(ret).into()),
+            None => Ok(ret.pop().unwrap_or_else(|| RespBulkString::null().into())),
+        }
+    }
+}
+
+impl CommandExecutor for SMoveCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.smove(&self.source, &self.destination, self.member)?;
+        Ok(ret.into())
+    }
+}
+
+impl CommandExecutor for SInterCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespArray::new(backend.sinter(&self.keys)).into())
+    }
+}
+
+impl CommandExecutor for SUnionCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespArray::new(backend.sunion(&self.keys)).into())
+    }
+}
+
+impl CommandExecutor for SDiffCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(RespArray::new(backend.sdiff(&self.keys)).into())
+    }
+}
+
+impl CommandExecutor for SInterStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .sstore(SetOp::Inter, &self.destination, &self.keys)
+            .into())
+    }
+}
+
+impl CommandExecutor for SUnionStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .sstore(SetOp::Union, &self.destination, &self.keys)
+            .into())
+    }
+}
+
+impl CommandExecutor for SDiffStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .sstore(SetOp::Diff, &self.destination, &self.keys)
+            .into())
+    }
+}
+
+impl CommandExecutor for SInterCardCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.sintercard(&self.keys, self.limit).into())
+    }
+}
+
+impl CommandExecutor for SScanCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let opts = self.opts;
+        let (cursor, members) =
+            backend.sscan(&self.key, self.cursor, opts.pattern.as_deref(), opts.count);
+        Ok(scan_reply(cursor, members))
+    }
+}
+
+// SAdd: "*4\r\n$4\r\nsadd\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n"
 impl TryFrom<RespArray> for SaddCommand {
     type Error = ExecuteError;
 
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(arr, 1)?;
+        Ok(SaddCommand { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SismemberCommand {
+    type Error = ExecuteError;
+
     fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
         let Some(arr) = arr.0 else {
             return Err(InvalidCommand("command exists".to_string()));
@@ -47,35 +266,263 @@ impl TryFrom<RespArray> for SaddCommand {
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(frame)) => {
                 let key = String::from_utf8(key.as_ref().expect("key has to exist").to_vec())?;
-                Ok(SaddCommand { key, member: frame })
+                Ok(SismemberCommand { key, member: frame })
             }
+            _ => Err(InvalidCommand("set key should be a bulkstring".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SRemCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(arr, 1)?;
+        Ok(SRemCommand { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembersCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(SMembersCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SCardCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(SCardCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SMisMemberCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(arr, 1)?;
+        Ok(SMisMemberCommand { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(arr)?;
+        Ok(SPopCommand {
+            key,
+            count: pop_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMemberCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(arr)?;
+        Ok(SRandMemberCommand { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SMoveCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(source), Some(destination), Some(member)) => Ok(SMoveCommand {
+                source: frame_to_string(source)?,
+                destination: frame_to_string(destination)?,
+                member,
+            }),
             _ => Err(InvalidCommand(
-                "sadd key should be a bulkstring".to_string(),
+                "smove source destination member".to_string(),
             )),
         }
     }
 }
 
-impl TryFrom<RespArray> for SismemberCommand {
+impl TryFrom<RespArray> for SInterCommand {
     type Error = ExecuteError;
 
     fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
-        let Some(arr) = arr.0 else {
-            return Err(InvalidCommand("command exists".to_string()));
-        };
-        if arr.len() != 3 {
-            return Err(InvalidArgument(format!(
-                "expected 2, got {}",
-                arr.len() - 1
-            )));
+        Ok(SInterCommand {
+            keys: parse_keys(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SUnionCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(SUnionCommand {
+            keys: parse_keys(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SDiffCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(SDiffCommand {
+            keys: parse_keys(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = parse_destination_keys(arr)?;
+        Ok(SInterStoreCommand { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SUnionStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = parse_destination_keys(arr)?;
+        Ok(SUnionStoreCommand { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SDiffStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = parse_destination_keys(arr)?;
+        Ok(SDiffStoreCommand { destination, keys })
+    }
+}
+
+// SInterCard: numkeys key [key ...] [LIMIT limit]
+impl TryFrom<RespArray> for SInterCardCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let numkeys = frame_to_i64(args.next().expect("numkeys has to exist"))?;
+        if numkeys <= 0 {
+            return Err(InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
         }
-        let mut args = into_args_iter(arr, 1);
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(frame)) => {
-                let key = String::from_utf8(key.as_ref().expect("key has to exist").to_vec())?;
-                Ok(SismemberCommand { key, member: frame })
-            }
-            _ => Err(InvalidCommand("set key should be a bulkstring".to_string())),
+        let keys = args
+            .by_ref()
+            .take(numkeys as usize)
+            .map(frame_to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() != numkeys as usize {
+            return Err(InvalidArgument(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
         }
+        let opt = args.next().map(frame_to_string).transpose()?;
+        let limit = match (opt, args.next(), args.next()) {
+            (None, _, _) => 0,
+            (Some(opt), Some(limit), None) if opt.eq_ignore_ascii_case("limit") => {
+                let limit = frame_to_i64(limit)?;
+                if limit < 0 {
+                    return Err(InvalidArgument("LIMIT can't be negative".to_string()));
+                }
+                limit as usize
+            }
+            _ => return Err(InvalidArgument("syntax error".to_string())),
+        };
+        Ok(SInterCardCommand { keys, limit })
+    }
+}
+
+impl TryFrom<RespArray> for SScanCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, cursor, opts) = parse_key_scan(arr, false)?;
+        Ok(SScanCommand { key, cursor, opts })
+    }
+}
+
+// key [key ...]
+pub(crate) fn parse_keys(arr: RespArray) -> Result<Vec<String>, ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, 1)?;
+    args.into_iter().map(frame_to_string).collect()
+}
+
+// destination key [key ...]
+fn parse_destination_keys(arr: RespArray) -> Result<(String, Vec<String>), ExecuteError> {
+    let mut keys = parse_keys(arr)?;
+    if keys.len() < 2 {
+        return Err(InvalidArgument("expected at least 2, got 1".to_string()));
+    }
+    let destination = keys.remove(0);
+    Ok((destination, keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_sadd_srem() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let sadd = SaddCommand::try_from(cmd(&["sadd", "k", "a", "b", "a"]))?;
+        assert_eq!(sadd.execute(backend.clone())?, RespFrame::Integer(2));
+        let srem = SRemCommand::try_from(cmd(&["srem", "k", "a", "c"]))?;
+        assert_eq!(srem.execute(backend.clone())?, RespFrame::Integer(1));
+        let smismember = SMisMemberCommand::try_from(cmd(&["smismember", "k", "a", "b"]))?;
+        assert_eq!(
+            smismember.execute(backend.clone())?,
+            RespArray::new(vec![RespFrame::Integer(0), RespFrame::Integer(1)]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_try_from() -> anyhow::Result<()> {
+        let spop = SPopCommand::try_from(cmd(&["spop", "k", "3"]))?;
+        assert_eq!(spop.count, Some(3));
+        assert!(SPopCommand::try_from(cmd(&["spop", "k", "-3"])).is_err());
+        let srandmember = SRandMemberCommand::try_from(cmd(&["srandmember", "k", "-3"]))?;
+        assert_eq!(srandmember.count, Some(-3));
+
+        let backend = Backend::default();
+        let spop = SPopCommand::try_from(cmd(&["spop", "k"]))?;
+        assert_eq!(spop.execute(backend)?, RespBulkString::null().into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_try_from() -> anyhow::Result<()> {
+        let sintercard =
+            SInterCardCommand::try_from(cmd(&["sintercard", "2", "a", "b", "LIMIT", "5"]))?;
+        assert_eq!(
+            sintercard,
+            SInterCardCommand {
+                keys: vec!["a".to_string(), "b".to_string()],
+                limit: 5,
+            }
+        );
+        assert!(SInterCardCommand::try_from(cmd(&["sintercard", "3", "a", "b"])).is_err());
+        assert!(SInterCardCommand::try_from(cmd(&["sintercard", "1", "a", "b"])).is_err());
+        assert!(SInterCardCommand::try_from(cmd(&["sintercard", "0", "a"])).is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,625 @@
+use std::iter::Peekable;
+use std::time::Duration;
+use std::vec::IntoIter;
+
+use crate::backend::blocking::{Blocked, Serve};
+use crate::backend::stream::{StreamId, StreamTrim, TrimBy, XAddId, XReadId};
+use crate::backend::Backend;
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_i64, frame_to_string, into_args, parse_key,
+    BlockingExecutor, CommandExecutor, ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+
+// XAdd: key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold [LIMIT count]] *|id field value [field value ...]
+#[derive(Debug, PartialEq)]
+pub struct XAddCommand {
+    key: String,
+    nomkstream: bool,
+    trim: Option<StreamTrim>,
+    id: XAddId,
+    fields: Vec<RespFrame>,
+}
+
+// XRange: key start end [COUNT count]
+#[derive(Debug, PartialEq)]
+pub struct XRangeCommand {
+    key: String,
+    start: StreamId,
+    end: StreamId,
+    count: Option<usize>,
+}
+
+// XRevRange: key end start [COUNT count]
+#[derive(Debug, PartialEq)]
+pub struct XRevRangeCommand {
+    key: String,
+    start: StreamId,
+    end: StreamId,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XLenCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XDelCommand {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+// XTrim: key MAXLEN|MINID [=|~] threshold [LIMIT count]
+#[derive(Debug, PartialEq)]
+pub struct XTrimCommand {
+    key: String,
+    trim: StreamTrim,
+}
+
+// XRead: [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+#[derive(Debug, PartialEq)]
+pub struct XReadCommand {
+    count: Option<usize>,
+    block: Option<u64>,
+    keys: Vec<String>,
+    ids: Vec<XReadId>,
+}
+
+impl CommandExecutor for XAddCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.xadd(&self.key, self.id, self.fields, self.nomkstream, self.trim)?;
+        Ok(match ret {
+            Some(id) => RespBulkString::new(id.to_string()).into(),
+            None => RespBulkString::null().into(),
+        })
+    }
+}
+
+impl CommandExecutor for XRangeCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.xrange(&self.key, self.start, self.end, self.count, false);
+        Ok(entries_reply(ret))
+    }
+}
+
+impl CommandExecutor for XRevRangeCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.xrange(&self.key, self.start, self.end, self.count, true);
+        Ok(entries_reply(ret))
+    }
+}
+
+impl CommandExecutor for XLenCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.xlen(&self.key).into())
+    }
+}
+
+impl CommandExecutor for XDelCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.xdel(&self.key, &self.ids).into())
+    }
+}
+
+impl CommandExecutor for XTrimCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.xtrim(&self.key, self.trim).into())
+    }
+}
+
+impl BlockingExecutor for XReadCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        let after = self
+            .keys
+            .iter()
+            .zip(self.ids)
+            .map(|(key, id)| backend.xread_after(key, id))
+            .collect::<Vec<_>>();
+        let ret = self
+            .keys
+            .iter()
+            .zip(&after)
+            .filter_map(|(key, after)| {
+                let entries = backend.xread(key, *after, self.count);
+                (!entries.is_empty()).then(|| key_entries_frame(key, entries_reply(entries)))
+            })
+            .collect::<Vec<RespFrame>>();
+        if !ret.is_empty() {
+            return Ok(Blocked::Served(RespArray::new(ret).into()));
+        }
+        let Some(block) = self.block else {
+            return Ok(Blocked::Served(RespArray::null().into()));
+        };
+
+        let (keys, count) = (self.keys.clone(), self.count);
+        let serve: Serve = Box::new(move |backend, key| {
+            let i = keys.iter().position(|k| k == key)?;
+            let entries = backend.xread(key, after[i], count);
+            (!entries.is_empty()).then(|| {
+                RespArray::new(vec![key_entries_frame(key, entries_reply(entries))]).into()
+            })
+        });
+        Ok(backend.block(
+            client_id,
+            self.keys,
+            None,
+            block_timeout(block),
+            RespArray::null().into(),
+            serve,
+        ))
+    }
+}
+
+impl TryFrom<RespArray> for XAddCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter().peekable();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+
+        let (mut nomkstream, mut trim) = (false, None);
+        let id = loop {
+            let Some(opt) = args.next() else {
+                return Err(InvalidArgument("syntax error".to_string()));
+            };
+            let opt = frame_to_string(opt)?;
+            match opt.to_ascii_lowercase().as_str() {
+                "nomkstream" => nomkstream = true,
+                "maxlen" | "minid" => trim = Some(parse_trim(&opt, &mut args)?),
+                _ => break parse_xadd_id(&opt)?,
+            }
+        };
+
+        let fields: Vec<RespFrame> = args.collect();
+        if fields.is_empty() || !fields.len().is_multiple_of(2) {
+            return Err(InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string(),
+            ));
+        }
+        Ok(XAddCommand {
+            key,
+            nomkstream,
+            trim,
+            id,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRangeCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, end, count) = parse_range(arr, false)?;
+        Ok(XRangeCommand {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRevRangeCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, end, count) = parse_range(arr, true)?;
+        Ok(XRevRangeCommand {
+            key,
+            start,
+            end,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLenCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let key = parse_key(arr)?;
+        Ok(XLenCommand { key })
+    }
+}
+
+// XDel: key id [id ...]
+impl TryFrom<RespArray> for XDelCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let ids = args
+            .map(|id| parse_stream_id(id, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XDelCommand { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XTrimCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        let mut args = args.into_iter().peekable();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let strategy = frame_to_string(args.next().expect("strategy has to exist"))?;
+        if !matches!(strategy.to_ascii_lowercase().as_str(), "maxlen" | "minid") {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        let trim = parse_trim(&strategy, &mut args)?;
+        if args.next().is_some() {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XTrimCommand { key, trim })
+    }
+}
+
+impl TryFrom<RespArray> for XReadCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let syntax_error = || InvalidArgument("syntax error".to_string());
+
+        let (mut count, mut block) = (None, None);
+        loop {
+            let opt = frame_to_string(args.next().ok_or_else(syntax_error)?)?;
+            match opt.to_ascii_lowercase().as_str() {
+                "count" => {
+                    let n = frame_to_i64(args.next().ok_or_else(syntax_error)?)?;
+                    count = Some(n.max(0) as usize).filter(|&n| n > 0);
+                }
+                "block" => block = Some(parse_block(args.next().ok_or_else(syntax_error)?)?),
+                "streams" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let (keys, ids) = parse_streams(args.collect(), "xread")?;
+        let ids = ids
+            .into_iter()
+            .map(|id| match id.as_str() {
+                "$" => Ok(XReadId::New),
+                "+" => Ok(XReadId::Last),
+                _ => StreamId::parse(&id, 0)
+                    .map(XReadId::After)
+                    .ok_or_else(invalid_id),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XReadCommand {
+            count,
+            block,
+            keys,
+            ids,
+        })
+    }
+}
+
+/// `[[id, [field, value, ...]] ...]`
+pub(crate) fn entries_reply(entries: Vec<(StreamId, Vec<RespFrame>)>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| entry_frame(id, fields))
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+pub(crate) fn entry_frame(id: StreamId, fields: Vec<RespFrame>) -> RespFrame {
+    RespArray::new(vec![
+        RespBulkString::new(id.to_string()).into(),
+        RespArray::new(fields).into(),
+    ])
+    .into()
+}
+
+/// `[key, entries]`, one stream of an `XREAD` or `XREADGROUP` reply.
+pub(crate) fn key_entries_frame(key: &str, entries: RespFrame) -> RespFrame {
+    RespArray::new(vec![RespBulkString::new(key).into(), entries]).into()
+}
+
+/// Splits `key [key ...] id [id ...]` after `STREAMS` in halves.
+pub(crate) fn parse_streams(
+    args: Vec<RespFrame>,
+    command: &str,
+) -> Result<(Vec<String>, Vec<String>), ExecuteError> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(InvalidArgument(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '{}' must be specified.",
+            command,
+            if command == "xreadgroup" { ">" } else { "$" }
+        )));
+    }
+    let mut args = args
+        .into_iter()
+        .map(frame_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let ids = args.split_off(args.len() / 2);
+    Ok((args, ids))
+}
+
+/// The milliseconds of `BLOCK`, 0 blocking forever.
+pub(crate) fn parse_block(frame: RespFrame) -> Result<u64, ExecuteError> {
+    let ms = frame_to_i64(frame)?;
+    if ms < 0 {
+        return Err(InvalidArgument("timeout is negative".to_string()));
+    }
+    Ok(ms as u64)
+}
+
+pub(crate) fn block_timeout(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// An explicit id, `<ms>` alone standing for `<ms>-<missing_seq>`.
+pub(crate) fn parse_stream_id(
+    frame: RespFrame,
+    missing_seq: u64,
+) -> Result<StreamId, ExecuteError> {
+    let id = frame_to_string(frame)?;
+    StreamId::parse(&id, missing_seq).ok_or_else(invalid_id)
+}
+
+fn parse_xadd_id(id: &str) -> Result<XAddId, ExecuteError> {
+    if id == "*" {
+        return Ok(XAddId::Auto);
+    }
+    match id.strip_suffix("-*") {
+        Some(ms) => Ok(XAddId::Partial(ms.parse().map_err(|_| invalid_id())?)),
+        None => Ok(XAddId::Explicit(
+            StreamId::parse(id, 0).ok_or_else(invalid_id)?,
+        )),
+    }
+}
+
+fn invalid_id() -> ExecuteError {
+    InvalidArgument("Invalid stream ID specified as stream command argument".to_string())
+}
+
+// [=|~] threshold [LIMIT count], after MAXLEN or MINID
+fn parse_trim(
+    strategy: &str,
+    args: &mut Peekable<IntoIter<RespFrame>>,
+) -> Result<StreamTrim, ExecuteError> {
+    let syntax_error = || InvalidArgument("syntax error".to_string());
+    let mut threshold = args.next().ok_or_else(syntax_error)?;
+    let mut approx = false;
+    if let RespFrame::BulkString(s) = &threshold {
+        match s.as_deref() {
+            Some(b"~") | Some(b"=") => {
+                approx = s.as_deref() == Some(b"~");
+                threshold = args.next().ok_or_else(syntax_error)?;
+            }
+            _ => {}
+        }
+    }
+    let by = if strategy.eq_ignore_ascii_case("maxlen") {
+        let maxlen = frame_to_i64(threshold)?;
+        if maxlen < 0 {
+            return Err(InvalidArgument(
+                "The MAXLEN argument must be >= 0.".to_string(),
+            ));
+        }
+        TrimBy::MaxLen(maxlen as usize)
+    } else {
+        TrimBy::MinId(parse_stream_id(threshold, 0)?)
+    };
+
+    let mut limit = None;
+    let is_limit = matches!(
+        args.peek(),
+        Some(RespFrame::BulkString(s)) if s.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(b"limit"))
+    );
+    if is_limit {
+        args.next();
+        let count = frame_to_i64(args.next().ok_or_else(syntax_error)?)?;
+        if count < 0 {
+            return Err(InvalidArgument(
+                "The LIMIT argument must be >= 0.".to_string(),
+            ));
+        }
+        if !approx {
+            return Err(InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        limit = Some(count as usize);
+    }
+    Ok(StreamTrim { by, approx, limit })
+}
+
+// key start end [COUNT count], with end and start swapped when `rev`
+fn parse_range(
+    arr: RespArray,
+    rev: bool,
+) -> Result<(String, StreamId, StreamId, Option<usize>), ExecuteError> {
+    let args = into_args(arr)?;
+    if args.len() != 5 {
+        check_nargs(&args, 3)?;
+    }
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let (first, second) = (
+        args.next().expect("start has to exist"),
+        args.next().expect("end has to exist"),
+    );
+    let (start, end) = if rev {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    let start = parse_range_bound(start, true)?;
+    let end = parse_range_bound(end, false)?;
+    let opt = args.next().map(frame_to_string).transpose()?;
+    let count = match (opt, args.next()) {
+        (Some(opt), Some(count)) if opt.eq_ignore_ascii_case("count") => {
+            Some(frame_to_i64(count)?.max(0) as usize)
+        }
+        (None, None) => None,
+        _ => return Err(InvalidArgument("syntax error".to_string())),
+    };
+    Ok((key, start, end, count))
+}
+
+// `-`, `+`, `<ms>[-<seq>]` or an exclusive `(<ms>[-<seq>]`
+fn parse_range_bound(frame: RespFrame, start: bool) -> Result<StreamId, ExecuteError> {
+    let bound = frame_to_string(frame)?;
+    let missing_seq = if start { 0 } else { u64::MAX };
+    match bound.as_str() {
+        "-" => Ok(StreamId::MIN),
+        "+" => Ok(StreamId::MAX),
+        _ => match bound.strip_prefix('(') {
+            Some(id) => {
+                let id = StreamId::parse(id, missing_seq).ok_or_else(invalid_id)?;
+                let id = if start { id.next() } else { id.prev() };
+                id.ok_or_else(|| {
+                    let which = if start { "start" } else { "end" };
+                    InvalidArgument(format!("invalid {} ID for the interval", which))
+                })
+            }
+            None => StreamId::parse(&bound, missing_seq).ok_or_else(invalid_id),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_xadd_try_from() -> anyhow::Result<()> {
+        let xadd = XAddCommand::try_from(cmd(&[
+            "xadd",
+            "s",
+            "NOMKSTREAM",
+            "MAXLEN",
+            "~",
+            "10",
+            "LIMIT",
+            "5",
+            "7-*",
+            "f",
+            "v",
+        ]))?;
+        assert_eq!(
+            xadd,
+            XAddCommand {
+                key: "s".to_string(),
+                nomkstream: true,
+                trim: Some(StreamTrim {
+                    by: TrimBy::MaxLen(10),
+                    approx: true,
+                    limit: Some(5),
+                }),
+                id: XAddId::Partial(7),
+                fields: vec![
+                    RespBulkString::new("f").into(),
+                    RespBulkString::new("v").into()
+                ],
+            }
+        );
+        let xadd = XAddCommand::try_from(cmd(&["xadd", "s", "MINID", "3", "*", "f", "v"]))?;
+        assert_eq!(xadd.id, XAddId::Auto);
+        assert!(XAddCommand::try_from(cmd(&["xadd", "s", "*", "f"])).is_err());
+        assert!(XAddCommand::try_from(cmd(&["xadd", "s", "1-x", "f", "v"])).is_err());
+        assert!(XAddCommand::try_from(cmd(&[
+            "xadd", "s", "MAXLEN", "1", "LIMIT", "1", "*", "f", "v"
+        ]))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_try_from() -> anyhow::Result<()> {
+        let xrange = XRangeCommand::try_from(cmd(&["xrange", "s", "(1-5", "7", "COUNT", "2"]))?;
+        assert_eq!(
+            xrange,
+            XRangeCommand {
+                key: "s".to_string(),
+                start: StreamId::new(1, 6),
+                end: StreamId::new(7, u64::MAX),
+                count: Some(2),
+            }
+        );
+        let xrevrange = XRevRangeCommand::try_from(cmd(&["xrevrange", "s", "+", "-"]))?;
+        assert_eq!(
+            (xrevrange.start, xrevrange.end),
+            (StreamId::MIN, StreamId::MAX)
+        );
+        assert!(XRangeCommand::try_from(cmd(&["xrange", "s", "(0-0", "+"])).is_ok());
+        assert!(XRangeCommand::try_from(cmd(&["xrange", "s", "-", "(0-0"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xread_try_from() -> anyhow::Result<()> {
+        let xread = XReadCommand::try_from(cmd(&[
+            "xread", "COUNT", "2", "BLOCK", "0", "STREAMS", "a", "b", "c", "$", "+", "5",
+        ]))?;
+        assert_eq!(
+            xread,
+            XReadCommand {
+                count: Some(2),
+                block: Some(0),
+                keys: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+                ids: vec![
+                    XReadId::New,
+                    XReadId::Last,
+                    XReadId::After(StreamId::new(5, 0))
+                ],
+            }
+        );
+        assert!(XReadCommand::try_from(cmd(&["xread", "STREAMS", "a", "b", "0"])).is_err());
+        assert!(
+            XReadCommand::try_from(cmd(&["xread", "BLOCK", "-1", "STREAMS", "a", "0"])).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xread_block() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        let fields = vec![
+            RespBulkString::new("f").into(),
+            RespBulkString::new("v").into(),
+        ];
+        let xadd = |ms| {
+            let id = XAddId::Explicit(StreamId::new(ms, 0));
+            backend.xadd("s", id, fields.clone(), false, None)
+        };
+        xadd(1)?;
+        let xread = XReadCommand::try_from(cmd(&["xread", "BLOCK", "0", "STREAMS", "s", "$"]))?;
+        let Blocked::Waiting(mut waiting) = BlockingExecutor::execute(xread, backend.clone(), 1)?
+        else {
+            panic!("expected to wait");
+        };
+        let xread = XReadCommand::try_from(cmd(&["xread", "BLOCK", "0", "STREAMS", "s", "+"]))?;
+        assert!(matches!(
+            BlockingExecutor::execute(xread, backend.clone(), 2)?,
+            Blocked::Served(_)
+        ));
+
+        xadd(2)?;
+        backend.serve_blocked();
+        let reply = waiting.rx.try_recv()?;
+        let expected = RespArray::new(vec![key_entries_frame(
+            "s",
+            entries_reply(vec![(StreamId::new(2, 0), fields.clone())]),
+        )]);
+        assert_eq!(reply, expected.into());
+        Ok(())
+    }
+}
@@ -0,0 +1,905 @@
+use crate::backend::blocking::{Blocked, Serve};
+use crate::backend::stream::{GroupEntry, StreamId, XClaimOptions};
+use crate::backend::{now_ms, Backend};
+use crate::cmd::stream::{
+    block_timeout, entry_frame, key_entries_frame, parse_block, parse_stream_id, parse_streams,
+};
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, frame_to_i64, frame_to_string, into_args, BlockingExecutor, CommandExecutor,
+    ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::frame::RespFrame;
+use crate::resp::simple_error::RespSimpleError;
+use crate::resp::simple_string::RespSimpleString;
+
+// XGroup: CREATE key group id|$ [MKSTREAM] [ENTRIESREAD n] | SETID key group id|$ [ENTRIESREAD n]
+//       | DESTROY key group | CREATECONSUMER key group consumer | DELCONSUMER key group consumer
+#[derive(Debug, PartialEq)]
+pub enum XGroupCommand {
+    Create {
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    },
+    Destroy {
+        key: String,
+        group: String,
+    },
+    CreateConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+    DelConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+}
+
+// XReadGroup: GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK]
+//             STREAMS key [key ...] id [id ...]
+#[derive(Debug, PartialEq)]
+pub struct XReadGroupCommand {
+    group: String,
+    consumer: String,
+    count: Option<usize>,
+    block: Option<u64>,
+    noack: bool,
+    keys: Vec<String>,
+    // `None` for `>`
+    ids: Vec<Option<StreamId>>,
+}
+
+#[derive(Debug)]
+pub struct XAckCommand {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+// XPending: key group [[IDLE min-idle-time] start end count [consumer]]
+#[derive(Debug, PartialEq)]
+pub struct XPendingCommand {
+    key: String,
+    group: String,
+    range: Option<XPendingRange>,
+}
+
+#[derive(Debug, PartialEq)]
+struct XPendingRange {
+    min_idle: u64,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+    consumer: Option<String>,
+}
+
+// XClaim: key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds]
+//         [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]
+#[derive(Debug, PartialEq)]
+pub struct XClaimCommand {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    opts: XClaimOptions,
+}
+
+// XAutoClaim: key group consumer min-idle-time start [COUNT count] [JUSTID]
+#[derive(Debug, PartialEq)]
+pub struct XAutoClaimCommand {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    just_id: bool,
+}
+
+// XInfo: STREAM key | GROUPS key | CONSUMERS key group
+#[derive(Debug, PartialEq)]
+pub enum XInfoCommand {
+    Stream { key: String },
+    Groups { key: String },
+    Consumers { key: String, group: String },
+}
+
+impl CommandExecutor for XGroupCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ok = || RespSimpleString::new("OK").into();
+        match self {
+            XGroupCommand::Create {
+                key,
+                group,
+                id,
+                mkstream,
+                entries_read,
+            } => {
+                backend.xgroup_create(&key, &group, id, mkstream, entries_read)?;
+                Ok(ok())
+            }
+            XGroupCommand::SetId {
+                key,
+                group,
+                id,
+                entries_read,
+            } => {
+                backend.xgroup_setid(&key, &group, id, entries_read)?;
+                Ok(ok())
+            }
+            XGroupCommand::Destroy { key, group } => {
+                Ok((backend.xgroup_destroy(&key, &group)? as i64).into())
+            }
+            XGroupCommand::CreateConsumer {
+                key,
+                group,
+                consumer,
+            } => Ok((backend.xgroup_createconsumer(&key, &group, &consumer)? as i64).into()),
+            XGroupCommand::DelConsumer {
+                key,
+                group,
+                consumer,
+            } => Ok((backend.xgroup_delconsumer(&key, &group, &consumer)? as i64).into()),
+        }
+    }
+}
+
+impl BlockingExecutor for XReadGroupCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        let mut ret = vec![];
+        for (key, id) in self.keys.iter().zip(&self.ids) {
+            let entries = backend.xreadgroup(
+                key,
+                &self.group,
+                &self.consumer,
+                *id,
+                self.count,
+                self.noack,
+            )?;
+            // streams without new entries are left out, history is always replied
+            if id.is_some() || !entries.is_empty() {
+                ret.push(stream_entries_frame(key, entries));
+            }
+        }
+        if !ret.is_empty() {
+            return Ok(Blocked::Served(RespArray::new(ret).into()));
+        }
+        let Some(block) = self.block else {
+            return Ok(Blocked::Served(RespArray::null().into()));
+        };
+
+        let XReadGroupCommand {
+            group,
+            consumer,
+            count,
+            noack,
+            ..
+        } = self;
+        let serve: Serve = Box::new(move |backend, key| {
+            match backend.xreadgroup(key, &group, &consumer, None, count, noack) {
+                Ok(entries) if entries.is_empty() => None,
+                Ok(entries) => {
+                    Some(RespArray::new(vec![stream_entries_frame(key, entries)]).into())
+                }
+                // the group went away while waiting
+                Err(e) => Some(RespSimpleError::new(e.to_string()).into()),
+            }
+        });
+        Ok(backend.block(
+            client_id,
+            self.keys,
+            None,
+            block_timeout(block),
+            RespArray::null().into(),
+            serve,
+        ))
+    }
+}
+
+impl CommandExecutor for XAckCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.xack(&self.key, &self.group, &self.ids).into())
+    }
+}
+
+impl CommandExecutor for XPendingCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let Some(range) = self.range else {
+            let summary = backend.xpending_summary(&self.key, &self.group)?;
+            if summary.count == 0 {
+                return Ok(RespArray::new(vec![
+                    RespFrame::Integer(0),
+                    RespBulkString::null().into(),
+                    RespBulkString::null().into(),
+                    RespArray::null().into(),
+                ])
+                .into());
+            }
+            let consumers = summary
+                .consumers
+                .into_iter()
+                .map(|(name, count)| {
+                    RespArray::new(vec![
+                        RespBulkString::new(name).into(),
+                        RespBulkString::new(count.to_string()).into(),
+                    ])
+                    .into()
+                })
+                .collect::<Vec<RespFrame>>();
+            let id_frame = |id: Option<StreamId>| match id {
+                Some(id) => RespBulkString::new(id.to_string()).into(),
+                None => RespBulkString::null().into(),
+            };
+            return Ok(RespArray::new(vec![
+                RespFrame::Integer(summary.count as i64),
+                id_frame(summary.min),
+                id_frame(summary.max),
+                RespArray::new(consumers).into(),
+            ])
+            .into());
+        };
+
+        let ret = backend
+            .xpending(
+                &self.key,
+                &self.group,
+                range.min_idle,
+                range.start,
+                range.end,
+                range.count,
+                range.consumer.as_deref(),
+            )?
+            .into_iter()
+            .map(|info| {
+                RespArray::new(vec![
+                    RespBulkString::new(info.id.to_string()).into(),
+                    RespBulkString::new(info.consumer).into(),
+                    RespFrame::Integer(info.idle as i64),
+                    RespFrame::Integer(info.deliveries as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for XClaimCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let claimed = backend.xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            self.opts,
+        )?;
+        Ok(claimed_frame(claimed, self.opts.just_id))
+    }
+}
+
+impl CommandExecutor for XAutoClaimCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let (next, claimed, deleted) = backend.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.just_id,
+        )?;
+        let deleted = deleted
+            .into_iter()
+            .map(|id| RespBulkString::new(id.to_string()).into())
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(vec![
+            RespBulkString::new(next.to_string()).into(),
+            claimed_frame(claimed, self.just_id),
+            RespArray::new(deleted).into(),
+        ])
+        .into())
+    }
+}
+
+impl CommandExecutor for XInfoCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let id_frame = |id: StreamId| RespBulkString::new(id.to_string()).into();
+        let opt_int = |n: Option<u64>| match n {
+            Some(n) => RespFrame::Integer(n as i64),
+            None => RespBulkString::null().into(),
+        };
+        match self {
+            XInfoCommand::Stream { key } => {
+                let info = backend.xinfo_stream(&key)?;
+                let entry = |entry: Option<(StreamId, Vec<RespFrame>)>| match entry {
+                    Some((id, fields)) => entry_frame(id, fields),
+                    None => RespBulkString::null().into(),
+                };
+                Ok(fields_frame(vec![
+                    ("length", RespFrame::Integer(info.length as i64)),
+                    ("last-generated-id", id_frame(info.last_id)),
+                    ("max-deleted-entry-id", id_frame(info.max_deleted_id)),
+                    (
+                        "entries-added",
+                        RespFrame::Integer(info.entries_added as i64),
+                    ),
+                    ("recorded-first-entry-id", id_frame(info.first_id)),
+                    ("groups", RespFrame::Integer(info.groups as i64)),
+                    ("first-entry", entry(info.first_entry)),
+                    ("last-entry", entry(info.last_entry)),
+                ]))
+            }
+            XInfoCommand::Groups { key } => {
+                let groups = backend
+                    .xinfo_groups(&key)?
+                    .into_iter()
+                    .map(|group| {
+                        fields_frame(vec![
+                            ("name", RespBulkString::new(group.name).into()),
+                            ("consumers", RespFrame::Integer(group.consumers as i64)),
+                            ("pending", RespFrame::Integer(group.pending as i64)),
+                            ("last-delivered-id", id_frame(group.last_id)),
+                            ("entries-read", opt_int(group.entries_read)),
+                            ("lag", opt_int(group.lag)),
+                        ])
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(groups).into())
+            }
+            XInfoCommand::Consumers { key, group } => {
+                let consumers = backend
+                    .xinfo_consumers(&key, &group)?
+                    .into_iter()
+                    .map(|consumer| {
+                        let inactive = consumer.inactive.map_or(-1, |ms| ms as i64);
+                        fields_frame(vec![
+                            ("name", RespBulkString::new(consumer.name).into()),
+                            ("pending", RespFrame::Integer(consumer.pending as i64)),
+                            ("idle", RespFrame::Integer(consumer.idle as i64)),
+                            ("inactive", RespFrame::Integer(inactive)),
+                        ])
+                    })
+                    .collect::<Vec<RespFrame>>();
+                Ok(RespArray::new(consumers).into())
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XGroupCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let sub = frame_to_string(args.next().expect("subcommand has to exist"))?;
+        let args = args.map(frame_to_string).collect::<Result<Vec<_>, _>>()?;
+        let unknown = || {
+            InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                sub
+            ))
+        };
+        let sub_lower = sub.to_ascii_lowercase();
+        match (sub_lower.as_str(), args.as_slice()) {
+            ("create", [key, group, id, opts @ ..]) => {
+                let (mkstream, entries_read) = parse_group_opts(opts, true)?;
+                Ok(XGroupCommand::Create {
+                    key: key.clone(),
+                    group: group.clone(),
+                    id: parse_group_id(id)?,
+                    mkstream,
+                    entries_read,
+                })
+            }
+            ("setid", [key, group, id, opts @ ..]) => {
+                let (_, entries_read) = parse_group_opts(opts, false)?;
+                Ok(XGroupCommand::SetId {
+                    key: key.clone(),
+                    group: group.clone(),
+                    id: parse_group_id(id)?,
+                    entries_read,
+                })
+            }
+            ("destroy", [key, group]) => Ok(XGroupCommand::Destroy {
+                key: key.clone(),
+                group: group.clone(),
+            }),
+            ("createconsumer", [key, group, consumer]) => Ok(XGroupCommand::CreateConsumer {
+                key: key.clone(),
+                group: group.clone(),
+                consumer: consumer.clone(),
+            }),
+            ("delconsumer", [key, group, consumer]) => Ok(XGroupCommand::DelConsumer {
+                key: key.clone(),
+                group: group.clone(),
+                consumer: consumer.clone(),
+            }),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroupCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 6)?;
+        let mut args = args.into_iter();
+        let opt = frame_to_string(args.next().expect("GROUP has to exist"))?;
+        if !opt.eq_ignore_ascii_case("group") {
+            return Err(InvalidArgument(
+                "Missing GROUP option for XREADGROUP".to_string(),
+            ));
+        }
+        let group = frame_to_string(args.next().expect("group has to exist"))?;
+        let consumer = frame_to_string(args.next().expect("consumer has to exist"))?;
+
+        let (mut count, mut block, mut noack) = (None, None, false);
+        loop {
+            let Some(opt) = args.next() else {
+                return Err(InvalidArgument("syntax error".to_string()));
+            };
+            match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+                "count" => {
+                    let n = args.next().ok_or_else(syntax_error)?;
+                    count = Some(frame_to_i64(n)?.max(0) as usize).filter(|&n| n > 0);
+                }
+                "block" => block = Some(parse_block(args.next().ok_or_else(syntax_error)?)?),
+                "noack" => noack = true,
+                "streams" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let (keys, ids) = parse_streams(args.collect(), "xreadgroup")?;
+        let ids = ids
+            .into_iter()
+            .map(|id| match id.as_str() {
+                ">" => Ok(None),
+                _ => StreamId::parse(&id, 0).map(Some).ok_or_else(|| {
+                    InvalidArgument(
+                        "Invalid stream ID specified as stream command argument".to_string(),
+                    )
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XReadGroupCommand {
+            group,
+            consumer,
+            count,
+            block,
+            noack,
+            keys,
+            ids,
+        })
+    }
+}
+
+// XAck: key group id [id ...]
+impl TryFrom<RespArray> for XAckCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let group = frame_to_string(args.next().expect("group has to exist"))?;
+        let ids = args
+            .map(|id| parse_stream_id(id, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XAckCommand { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPendingCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let mut args = args.into_iter().peekable();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let group = frame_to_string(args.next().expect("group has to exist"))?;
+        if args.peek().is_none() {
+            return Ok(XPendingCommand {
+                key,
+                group,
+                range: None,
+            });
+        }
+
+        let mut args = args.collect::<Vec<_>>().into_iter();
+        let mut first = frame_to_string(args.next().expect("checked above"))?;
+        let mut min_idle = 0;
+        if first.eq_ignore_ascii_case("idle") {
+            min_idle = parse_ms(args.next().ok_or_else(syntax_error)?)?;
+            first = frame_to_string(args.next().ok_or_else(syntax_error)?)?;
+        }
+        let start = parse_pending_bound(&first, true)?;
+        let end = parse_pending_bound(
+            &frame_to_string(args.next().ok_or_else(syntax_error)?)?,
+            false,
+        )?;
+        let count = frame_to_i64(args.next().ok_or_else(syntax_error)?)?.max(0) as usize;
+        let consumer = args.next().map(frame_to_string).transpose()?;
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XPendingCommand {
+            key,
+            group,
+            range: Some(XPendingRange {
+                min_idle,
+                start,
+                end,
+                count,
+                consumer,
+            }),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaimCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 5)?;
+        let mut args = args.into_iter().peekable();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let group = frame_to_string(args.next().expect("group has to exist"))?;
+        let consumer = frame_to_string(args.next().expect("consumer has to exist"))?;
+        let min_idle = parse_ms(args.next().expect("min-idle-time has to exist"))?;
+
+        // ids run until the first option
+        let mut ids = vec![];
+        while let Some(frame) = args.peek() {
+            let Some(id) = frame_to_string(frame.clone())
+                .ok()
+                .and_then(|id| StreamId::parse(&id, 0))
+            else {
+                break;
+            };
+            ids.push(id);
+            args.next();
+        }
+        if ids.is_empty() {
+            return Err(InvalidArgument(
+                "Invalid stream ID specified as stream command argument".to_string(),
+            ));
+        }
+
+        let mut opts = XClaimOptions::default();
+        while let Some(opt) = args.next() {
+            match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+                "idle" => {
+                    let idle = parse_ms(args.next().ok_or_else(syntax_error)?)?;
+                    opts.delivered_at = Some(now_ms().saturating_sub(idle));
+                }
+                "time" => {
+                    opts.delivered_at = Some(parse_ms(args.next().ok_or_else(syntax_error)?)?)
+                }
+                "retrycount" => {
+                    opts.retry_count = Some(parse_ms(args.next().ok_or_else(syntax_error)?)?)
+                }
+                "force" => opts.force = true,
+                "justid" => opts.just_id = true,
+                "lastid" => {
+                    opts.last_id = Some(parse_stream_id(args.next().ok_or_else(syntax_error)?, 0)?)
+                }
+                other => {
+                    return Err(InvalidArgument(format!(
+                        "Unrecognized XCLAIM option '{}'",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(XClaimCommand {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            opts,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAutoClaimCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 5)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let group = frame_to_string(args.next().expect("group has to exist"))?;
+        let consumer = frame_to_string(args.next().expect("consumer has to exist"))?;
+        let min_idle = parse_ms(args.next().expect("min-idle-time has to exist"))?;
+        let start = parse_pending_bound(
+            &frame_to_string(args.next().expect("start has to exist"))?,
+            true,
+        )?;
+        let (mut count, mut just_id) = (100, false);
+        while let Some(opt) = args.next() {
+            match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+                "count" => {
+                    let n = frame_to_i64(args.next().ok_or_else(syntax_error)?)?;
+                    if n <= 0 {
+                        return Err(InvalidArgument("COUNT must be > 0".to_string()));
+                    }
+                    count = n as usize;
+                }
+                "justid" => just_id = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(XAutoClaimCommand {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            just_id,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 2)?;
+        let args = args
+            .into_iter()
+            .map(frame_to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        match args.as_slice() {
+            [sub, key] if sub.eq_ignore_ascii_case("stream") => {
+                Ok(XInfoCommand::Stream { key: key.clone() })
+            }
+            [sub, key] if sub.eq_ignore_ascii_case("groups") => {
+                Ok(XInfoCommand::Groups { key: key.clone() })
+            }
+            [sub, key, group] if sub.eq_ignore_ascii_case("consumers") => {
+                Ok(XInfoCommand::Consumers {
+                    key: key.clone(),
+                    group: group.clone(),
+                })
+            }
+            [sub, ..] => Err(InvalidArgument(format!(
+                "unknown subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+            [] => unreachable!("checked above"),
+        }
+    }
+}
+
+// `[key, [entry ...]]`, with a nil in place of the fields of deleted entries
+fn stream_entries_frame(key: &str, entries: Vec<GroupEntry>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| match fields {
+            Some(fields) => entry_frame(id, fields),
+            None => RespArray::new(vec![
+                RespBulkString::new(id.to_string()).into(),
+                RespArray::null().into(),
+            ])
+            .into(),
+        })
+        .collect::<Vec<RespFrame>>();
+    key_entries_frame(key, RespArray::new(entries).into())
+}
+
+fn claimed_frame(claimed: Vec<(StreamId, Vec<RespFrame>)>, just_id: bool) -> RespFrame {
+    let claimed = claimed
+        .into_iter()
+        .map(|(id, fields)| match just_id {
+            true => RespBulkString::new(id.to_string()).into(),
+            false => entry_frame(id, fields),
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(claimed).into()
+}
+
+// `field, value, ...` as a flat array
+fn fields_frame(fields: Vec<(&str, RespFrame)>) -> RespFrame {
+    let mut ret = Vec::with_capacity(fields.len() * 2);
+    for (name, value) in fields {
+        ret.push(RespBulkString::new(name).into());
+        ret.push(value);
+    }
+    RespArray::new(ret).into()
+}
+
+// `$` for the last entry of the stream, otherwise an explicit id
+fn parse_group_id(id: &str) -> Result<Option<StreamId>, ExecuteError> {
+    if id == "$" {
+        return Ok(None);
+    }
+    StreamId::parse(id, 0).map(Some).ok_or_else(|| {
+        InvalidArgument("Invalid stream ID specified as stream command argument".to_string())
+    })
+}
+
+// [MKSTREAM] [ENTRIESREAD n]
+fn parse_group_opts(
+    opts: &[String],
+    allow_mkstream: bool,
+) -> Result<(bool, Option<u64>), ExecuteError> {
+    let (mut mkstream, mut entries_read) = (false, None);
+    let mut opts = opts.iter();
+    while let Some(opt) = opts.next() {
+        match opt.to_ascii_lowercase().as_str() {
+            "mkstream" if allow_mkstream => mkstream = true,
+            "entriesread" => {
+                let n = opts.next().ok_or_else(syntax_error)?;
+                let n = n.parse::<i64>().map_err(|_| {
+                    InvalidArgument("value is not an integer or out of range".to_string())
+                })?;
+                if n < 0 && n != -1 {
+                    return Err(InvalidArgument(
+                        "value for ENTRIESREAD must be positive or -1".to_string(),
+                    ));
+                }
+                entries_read = (n >= 0).then_some(n as u64);
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((mkstream, entries_read))
+}
+
+// `-`, `+`, an id or an exclusive `(id`
+fn parse_pending_bound(bound: &str, start: bool) -> Result<StreamId, ExecuteError> {
+    let invalid =
+        || InvalidArgument("Invalid stream ID specified as stream command argument".to_string());
+    let missing_seq = if start { 0 } else { u64::MAX };
+    match bound {
+        "-" => Ok(StreamId::MIN),
+        "+" => Ok(StreamId::MAX),
+        _ => match bound.strip_prefix('(') {
+            Some(id) => {
+                let id = StreamId::parse(id, missing_seq).ok_or_else(invalid)?;
+                let id = if start { id.next() } else { id.prev() };
+                id.ok_or_else(|| {
+                    let which = if start { "start" } else { "end" };
+                    InvalidArgument(format!("invalid {} ID for the interval", which))
+                })
+            }
+            None => StreamId::parse(bound, missing_seq).ok_or_else(invalid),
+        },
+    }
+}
+
+// a non-negative number of milliseconds
+fn parse_ms(frame: RespFrame) -> Result<u64, ExecuteError> {
+    let ms = frame_to_i64(frame)?;
+    if ms < 0 {
+        return Err(InvalidArgument(
+            "value is out of range, must be positive".to_string(),
+        ));
+    }
+    Ok(ms as u64)
+}
+
+fn syntax_error() -> ExecuteError {
+    InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_xgroup_try_from() -> anyhow::Result<()> {
+        let xgroup = XGroupCommand::try_from(cmd(&[
+            "xgroup",
+            "CREATE",
+            "s",
+            "g",
+            "$",
+            "MKSTREAM",
+            "ENTRIESREAD",
+            "3",
+        ]))?;
+        assert_eq!(
+            xgroup,
+            XGroupCommand::Create {
+                key: "s".to_string(),
+                group: "g".to_string(),
+                id: None,
+                mkstream: true,
+                entries_read: Some(3),
+            }
+        );
+        assert!(
+            XGroupCommand::try_from(cmd(&["xgroup", "setid", "s", "g", "0", "mkstream"])).is_err()
+        );
+        assert!(XGroupCommand::try_from(cmd(&["xgroup", "destroy", "s"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xreadgroup_try_from() -> anyhow::Result<()> {
+        let xreadgroup = XReadGroupCommand::try_from(cmd(&[
+            "xreadgroup",
+            "GROUP",
+            "g",
+            "c",
+            "COUNT",
+            "2",
+            "BLOCK",
+            "100",
+            "NOACK",
+            "STREAMS",
+            "a",
+            "b",
+            ">",
+            "0",
+        ]))?;
+        assert_eq!(
+            xreadgroup,
+            XReadGroupCommand {
+                group: "g".to_string(),
+                consumer: "c".to_string(),
+                count: Some(2),
+                block: Some(100),
+                noack: true,
+                keys: vec!["a".to_string(), "b".to_string()],
+                ids: vec![None, Some(StreamId::MIN)],
+            }
+        );
+        let unbalanced = cmd(&["xreadgroup", "GROUP", "g", "c", "STREAMS", "a", "b", ">"]);
+        assert!(XReadGroupCommand::try_from(unbalanced).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xclaim_try_from() -> anyhow::Result<()> {
+        let xclaim = XClaimCommand::try_from(cmd(&[
+            "xclaim",
+            "s",
+            "g",
+            "c",
+            "10",
+            "1-1",
+            "2",
+            "RETRYCOUNT",
+            "5",
+            "JUSTID",
+        ]))?;
+        assert_eq!(xclaim.ids, vec![StreamId::new(1, 1), StreamId::new(2, 0)]);
+        assert_eq!(xclaim.opts.retry_count, Some(5));
+        assert!(xclaim.opts.just_id);
+        let xpending =
+            XPendingCommand::try_from(cmd(&["xpending", "s", "g", "IDLE", "5", "-", "+", "10"]))?;
+        let range = xpending.range.unwrap();
+        assert_eq!((range.min_idle, range.count), (5, 10));
+        Ok(())
+    }
+}
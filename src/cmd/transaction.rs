@@ -0,0 +1,124 @@
+use crate::backend::Backend;
+use crate::cmd::keyspec::KeyAccess;
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_string, into_args, Command, ExecuteError, RET_OK,
+};
+use crate::resp::array::RespArray;
+use crate::resp::frame::RespFrame;
+
+// Multi: "*1\r\n$5\r\nmulti\r\n"
+#[derive(Debug)]
+pub struct MultiCommand;
+
+#[derive(Debug)]
+pub struct ExecCommand;
+
+#[derive(Debug)]
+pub struct DiscardCommand;
+
+// Watch: key [key ...]
+#[derive(Debug, PartialEq)]
+pub struct WatchCommand {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct UnwatchCommand;
+
+/// The commands a connection queued since `MULTI`. Any of them failing to
+/// parse aborts the whole transaction.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    pub commands: Vec<(Command, KeyAccess)>,
+    pub aborted: bool,
+}
+
+impl WatchCommand {
+    /// `client_id` is the id of the connection running the command.
+    pub fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<RespFrame> {
+        backend.watch(client_id, self.keys);
+        Ok(RET_OK.clone())
+    }
+}
+
+impl UnwatchCommand {
+    pub fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<RespFrame> {
+        backend.unwatch(client_id);
+        Ok(RET_OK.clone())
+    }
+}
+
+impl TryFrom<RespArray> for MultiCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        check_nargs(&into_args(arr)?, 0)?;
+        Ok(MultiCommand)
+    }
+}
+
+impl TryFrom<RespArray> for ExecCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        check_nargs(&into_args(arr)?, 0)?;
+        Ok(ExecCommand)
+    }
+}
+
+impl TryFrom<RespArray> for DiscardCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        check_nargs(&into_args(arr)?, 0)?;
+        Ok(DiscardCommand)
+    }
+}
+
+impl TryFrom<RespArray> for WatchCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let keys = args
+            .into_iter()
+            .map(frame_to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(WatchCommand { keys })
+    }
+}
+
+impl TryFrom<RespArray> for UnwatchCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        check_nargs(&into_args(arr)?, 0)?;
+        Ok(UnwatchCommand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_queue_time_parse() {
+        // commands are parsed before they are queued, so bad ones are caught
+        assert!(matches!(
+            Command::try_from(cmd(&["MULTI"])),
+            Ok(Command::Multi(_))
+        ));
+        assert!(matches!(
+            Command::try_from(cmd(&["set", "k", "v"])),
+            Ok(Command::Set(_))
+        ));
+        assert!(Command::try_from(cmd(&["exec", "now"])).is_err());
+        assert!(Command::try_from(cmd(&["zadd", "k", "one", "m"])).is_err());
+        assert!(matches!(
+            Command::try_from(cmd(&["nope"])),
+            Err(ExecuteError::UnknownCommand(name)) if name == "nope"
+        ));
+    }
+}
@@ -0,0 +1,1343 @@
+use std::time::Duration;
+
+use crate::backend::blocking::{Blocked, Serve};
+use crate::backend::zset::{
+    Aggregate, LexBound, ScoreBound, ZAddFlags, ZRangeBy, ZRangeSpec, ZSetEnd, ZSetOp,
+};
+use crate::backend::Backend;
+use crate::cmd::keyspace::{parse_key_scan, scan_reply, ScanOptions};
+use crate::cmd::list::{parse_keys_timeout, parse_mpop};
+use crate::cmd::ExecuteError::InvalidArgument;
+use crate::cmd::{
+    check_min_nargs, check_nargs, frame_to_f64, frame_to_i64, frame_to_string, into_args,
+    parse_key, parse_key_count, parse_key_names, parse_timeout, pop_count, BlockingExecutor,
+    CommandExecutor, ExecuteError,
+};
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
+use crate::resp::double::RespDouble;
+use crate::resp::frame::RespFrame;
+
+// ZAdd: "*4\r\n$4\r\nzadd\r\n$3\r\nkey\r\n$1\r\n1\r\n$3\r\none\r\n"
+#[derive(Debug, PartialEq)]
+pub struct ZAddCommand {
+    key: String,
+    flags: ZAddFlags,
+    incr: bool,
+    pairs: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct ZRemCommand {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZScoreCommand {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZMScoreCommand {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZIncrByCommand {
+    key: String,
+    incr: f64,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZCardCommand {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ZCountCommand {
+    key: String,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZRankCommand {
+    key: String,
+    member: String,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRevRankCommand {
+    key: String,
+    member: String,
+    with_score: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZRangeCommand {
+    key: String,
+    spec: ZRangeSpec,
+    with_scores: bool,
+}
+
+// ZUnion: numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX] [WITHSCORES]
+#[derive(Debug, PartialEq)]
+pub struct ZUnionCommand {
+    keys: Vec<String>,
+    weights: Option<Vec<f64>>,
+    aggregate: Aggregate,
+    with_scores: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZInterCommand {
+    keys: Vec<String>,
+    weights: Option<Vec<f64>>,
+    aggregate: Aggregate,
+    with_scores: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZDiffCommand {
+    keys: Vec<String>,
+    with_scores: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZUnionStoreCommand {
+    destination: String,
+    keys: Vec<String>,
+    weights: Option<Vec<f64>>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZInterStoreCommand {
+    destination: String,
+    keys: Vec<String>,
+    weights: Option<Vec<f64>>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZDiffStoreCommand {
+    destination: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZRangeStoreCommand {
+    destination: String,
+    source: String,
+    spec: ZRangeSpec,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByScoreCommand {
+    key: String,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByRankCommand {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByLexCommand {
+    key: String,
+    min: LexBound,
+    max: LexBound,
+}
+
+#[derive(Debug)]
+pub struct ZLexCountCommand {
+    key: String,
+    min: LexBound,
+    max: LexBound,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZRandMemberCommand {
+    key: String,
+    count: Option<i64>,
+    with_scores: bool,
+}
+
+// ZPopMin: key [count]
+#[derive(Debug, PartialEq)]
+pub struct ZPopMinCommand {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZPopMaxCommand {
+    key: String,
+    count: Option<usize>,
+}
+
+// BZPopMin: key [key ...] timeout
+#[derive(Debug, PartialEq)]
+pub struct BZPopMinCommand {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BZPopMaxCommand {
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ZMPopCommand {
+    keys: Vec<String>,
+    end: ZSetEnd,
+    count: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BZMPopCommand {
+    keys: Vec<String>,
+    end: ZSetEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+// ZScan: key cursor [MATCH pattern] [COUNT count]
+#[derive(Debug, PartialEq)]
+pub struct ZScanCommand {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+}
+
+impl CommandExecutor for ZAddCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        if self.incr {
+            let (incr, member) = self.pairs.into_iter().next().expect("one pair with INCR");
+            let ret = backend.zincrby(&self.key, self.flags, incr, member)?;
+            return Ok(ret.map_or_else(|| RespBulkString::null().into(), score_frame));
+        }
+        Ok(backend.zadd(&self.key, self.flags, self.pairs)?.into())
+    }
+}
+
+impl CommandExecutor for ZRemCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.zrem(&self.key, &self.members).into())
+    }
+}
+
+impl CommandExecutor for ZScoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend
+            .zscore(&self.key, &self.member)
+            .map_or_else(|| RespBulkString::null().into(), score_frame))
+    }
+}
+
+impl CommandExecutor for ZMScoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend
+            .zmscore(&self.key, &self.members)
+            .into_iter()
+            .map(|s| s.map_or_else(|| RespBulkString::null().into(), score_frame))
+            .collect::<Vec<_>>();
+        Ok(RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for ZIncrByCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zincrby(&self.key, ZAddFlags::default(), self.incr, self.member)?;
+        Ok(score_frame(ret.expect("ZINCRBY has no flags to skip it")))
+    }
+}
+
+impl CommandExecutor for ZCardCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.zcard(&self.key).into())
+    }
+}
+
+impl CommandExecutor for ZCountCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.zcount(&self.key, self.min, self.max).into())
+    }
+}
+
+impl CommandExecutor for ZRankCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zrank(&self.key, &self.member, false);
+        Ok(rank_reply(ret, self.with_score))
+    }
+}
+
+impl CommandExecutor for ZRevRankCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zrank(&self.key, &self.member, true);
+        Ok(rank_reply(ret, self.with_score))
+    }
+}
+
+impl CommandExecutor for ZRangeCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zrange(&self.key, &self.spec);
+        Ok(scores_reply(ret, self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZUnionCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let weights = self.weights.as_deref();
+        let ret = backend.zsetop(ZSetOp::Union, &self.keys, weights, self.aggregate);
+        Ok(scores_reply(ret, self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZInterCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let weights = self.weights.as_deref();
+        let ret = backend.zsetop(ZSetOp::Inter, &self.keys, weights, self.aggregate);
+        Ok(scores_reply(ret, self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZDiffCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zsetop(ZSetOp::Diff, &self.keys, None, Aggregate::Sum);
+        Ok(scores_reply(ret, self.with_scores))
+    }
+}
+
+impl CommandExecutor for ZUnionStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let weights = self.weights.as_deref();
+        let ret = backend.zsetop_store(
+            &self.destination,
+            ZSetOp::Union,
+            &self.keys,
+            weights,
+            self.aggregate,
+        );
+        Ok(ret.into())
+    }
+}
+
+impl CommandExecutor for ZInterStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let weights = self.weights.as_deref();
+        let ret = backend.zsetop_store(
+            &self.destination,
+            ZSetOp::Inter,
+            &self.keys,
+            weights,
+            self.aggregate,
+        );
+        Ok(ret.into())
+    }
+}
+
+impl CommandExecutor for ZDiffStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zsetop_store(
+            &self.destination,
+            ZSetOp::Diff,
+            &self.keys,
+            None,
+            Aggregate::Sum,
+        );
+        Ok(ret.into())
+    }
+}
+
+impl CommandExecutor for ZRangeStoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zrangestore(&self.destination, &self.source, &self.spec);
+        Ok(ret.into())
+    }
+}
+
+impl CommandExecutor for ZRemRangeByScoreCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let by = ZRangeBy::Score(self.min, self.max);
+        Ok(backend.zremrange(&self.key, by).into())
+    }
+}
+
+impl CommandExecutor for ZRemRangeByRankCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let by = ZRangeBy::Rank(self.start, self.stop);
+        Ok(backend.zremrange(&self.key, by).into())
+    }
+}
+
+impl CommandExecutor for ZRemRangeByLexCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let by = ZRangeBy::Lex(self.min, self.max);
+        Ok(backend.zremrange(&self.key, by).into())
+    }
+}
+
+impl CommandExecutor for ZLexCountCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        Ok(backend.zlexcount(&self.key, &self.min, &self.max).into())
+    }
+}
+
+impl CommandExecutor for ZRandMemberCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zrandmember(&self.key, self.count);
+        match self.count {
+            Some(_) => Ok(scores_reply(ret, self.with_scores)),
+            None => Ok(ret.into_iter().next().map_or_else(
+                || RespBulkString::null().into(),
+                |(member, _)| RespBulkString::new(member).into(),
+            )),
+        }
+    }
+}
+
+impl CommandExecutor for ZPopMinCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zpop(&self.key, ZSetEnd::Min, self.count.unwrap_or(1));
+        Ok(scores_reply(ret, true))
+    }
+}
+
+impl CommandExecutor for ZPopMaxCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zpop(&self.key, ZSetEnd::Max, self.count.unwrap_or(1));
+        Ok(scores_reply(ret, true))
+    }
+}
+
+impl BlockingExecutor for BZPopMinCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        Ok(bzpop(
+            backend,
+            client_id,
+            self.keys,
+            ZSetEnd::Min,
+            self.timeout,
+        ))
+    }
+}
+
+impl BlockingExecutor for BZPopMaxCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        Ok(bzpop(
+            backend,
+            client_id,
+            self.keys,
+            ZSetEnd::Max,
+            self.timeout,
+        ))
+    }
+}
+
+impl CommandExecutor for ZMPopCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let ret = backend.zmpop(&self.keys, self.end, self.count);
+        Ok(zmpop_reply(ret))
+    }
+}
+
+impl CommandExecutor for ZScanCommand {
+    fn execute(self, backend: Backend) -> anyhow::Result<RespFrame> {
+        let opts = self.opts;
+        let (cursor, members) =
+            backend.zscan(&self.key, self.cursor, opts.pattern.as_deref(), opts.count);
+        // scores come back as bulk strings, as ZSCAN has always replied
+        let mut items = Vec::with_capacity(members.len() * 2);
+        for (member, score) in members {
+            items.push(RespBulkString::new(member).into());
+            items.push(RespBulkString::new(score.to_string()).into());
+        }
+        Ok(scan_reply(cursor, items))
+    }
+}
+
+impl BlockingExecutor for BZMPopCommand {
+    fn execute(self, backend: Backend, client_id: u64) -> anyhow::Result<Blocked> {
+        let (end, count) = (self.end, self.count);
+        let serve: Serve = Box::new(move |backend, key| {
+            let keys = [key.to_string()];
+            backend
+                .zmpop(&keys, end, count)
+                .map(|ret| zmpop_reply(Some(ret)))
+        });
+        Ok(backend.block(
+            client_id,
+            self.keys,
+            None,
+            self.timeout,
+            RespArray::null().into(),
+            serve,
+        ))
+    }
+}
+
+// ZAdd: key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]
+impl TryFrom<RespArray> for ZAddCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        let mut args = args.into_iter().peekable();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+
+        let mut flags = ZAddFlags::default();
+        let mut incr = false;
+        while let Some(frame) = args.peek() {
+            let opt = match frame {
+                RespFrame::BulkString(s) => s.as_deref().unwrap_or_default().to_ascii_lowercase(),
+                _ => break,
+            };
+            match opt.as_slice() {
+                b"nx" => flags.nx = true,
+                b"xx" => flags.xx = true,
+                b"gt" => flags.gt = true,
+                b"lt" => flags.lt = true,
+                b"ch" => flags.ch = true,
+                b"incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if flags.nx && flags.xx {
+            return Err(InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (flags.gt && flags.lt) || (flags.nx && (flags.gt || flags.lt)) {
+            return Err(InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        if incr && args.len() != 2 {
+            return Err(InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut args = args.into_iter();
+        let mut pairs = vec![];
+        while let (Some(score), Some(member)) = (args.next(), args.next()) {
+            pairs.push((frame_to_f64(score)?, frame_to_string(member)?));
+        }
+        Ok(ZAddCommand {
+            key,
+            flags,
+            incr,
+            pairs,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_names(arr, 1)?;
+        Ok(ZRemCommand { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZScoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 2)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let member = frame_to_string(args.next().expect("member has to exist"))?;
+        Ok(ZScoreCommand { key, member })
+    }
+}
+
+impl TryFrom<RespArray> for ZMScoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_names(arr, 1)?;
+        Ok(ZMScoreCommand { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrByCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let incr = frame_to_f64(args.next().expect("increment has to exist"))?;
+        let member = frame_to_string(args.next().expect("member has to exist"))?;
+        Ok(ZIncrByCommand { key, incr, member })
+    }
+}
+
+impl TryFrom<RespArray> for ZCardCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        Ok(ZCardCommand {
+            key: parse_key(arr)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCountCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let min = parse_score_bound(args.next().expect("min has to exist"))?;
+        let max = parse_score_bound(args.next().expect("max has to exist"))?;
+        Ok(ZCountCommand { key, min, max })
+    }
+}
+
+impl TryFrom<RespArray> for ZRankCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = parse_rank(arr)?;
+        Ok(ZRankCommand {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRevRankCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = parse_rank(arr)?;
+        Ok(ZRevRankCommand {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+// ZRange: key start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+impl TryFrom<RespArray> for ZRangeCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 3)?;
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let (spec, with_scores) = parse_zrange(args)?;
+        Ok(ZRangeCommand {
+            key,
+            spec,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let opts = parse_zsetop(into_args(arr)?, true, true)?;
+        Ok(ZUnionCommand {
+            keys: opts.keys,
+            weights: opts.weights,
+            aggregate: opts.aggregate,
+            with_scores: opts.with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let opts = parse_zsetop(into_args(arr)?, true, true)?;
+        Ok(ZInterCommand {
+            keys: opts.keys,
+            weights: opts.weights,
+            aggregate: opts.aggregate,
+            with_scores: opts.with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZDiffCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let opts = parse_zsetop(into_args(arr)?, false, true)?;
+        Ok(ZDiffCommand {
+            keys: opts.keys,
+            with_scores: opts.with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (destination, args) = split_destination(arr)?;
+        let opts = parse_zsetop(args, true, false)?;
+        Ok(ZUnionStoreCommand {
+            destination,
+            keys: opts.keys,
+            weights: opts.weights,
+            aggregate: opts.aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (destination, args) = split_destination(arr)?;
+        let opts = parse_zsetop(args, true, false)?;
+        Ok(ZInterStoreCommand {
+            destination,
+            keys: opts.keys,
+            weights: opts.weights,
+            aggregate: opts.aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZDiffStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (destination, args) = split_destination(arr)?;
+        let opts = parse_zsetop(args, false, false)?;
+        Ok(ZDiffStoreCommand {
+            destination,
+            keys: opts.keys,
+        })
+    }
+}
+
+// ZRangeStore: dst src min max [BYSCORE|BYLEX] [REV] [LIMIT offset count]
+impl TryFrom<RespArray> for ZRangeStoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 4)?;
+        let mut args = args.into_iter();
+        let destination = frame_to_string(args.next().expect("dst has to exist"))?;
+        let source = frame_to_string(args.next().expect("src has to exist"))?;
+        let (spec, with_scores) = parse_zrange(args)?;
+        if with_scores {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        Ok(ZRangeStoreCommand {
+            destination,
+            source,
+            spec,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByScoreCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, min, max) = parse_key_min_max(arr)?;
+        Ok(ZRemRangeByScoreCommand {
+            key,
+            min: parse_score_bound(min)?,
+            max: parse_score_bound(max)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByRankCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = parse_key_min_max(arr)?;
+        Ok(ZRemRangeByRankCommand {
+            key,
+            start: frame_to_i64(start)?,
+            stop: frame_to_i64(stop)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByLexCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, min, max) = parse_key_min_max(arr)?;
+        Ok(ZRemRangeByLexCommand {
+            key,
+            min: parse_lex_bound(min)?,
+            max: parse_lex_bound(max)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZLexCountCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, min, max) = parse_key_min_max(arr)?;
+        Ok(ZLexCountCommand {
+            key,
+            min: parse_lex_bound(min)?,
+            max: parse_lex_bound(max)?,
+        })
+    }
+}
+
+// ZRandMember: key [count [WITHSCORES]]
+impl TryFrom<RespArray> for ZRandMemberCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        if args.len() > 3 {
+            return Err(InvalidArgument("syntax error".to_string()));
+        }
+        let mut args = args.into_iter();
+        let key = frame_to_string(args.next().expect("key has to exist"))?;
+        let count = args.next().map(frame_to_i64).transpose()?;
+        let with_scores = match args.next().map(frame_to_string).transpose()? {
+            None => false,
+            Some(opt) if opt.eq_ignore_ascii_case("withscores") => true,
+            Some(_) => return Err(InvalidArgument("syntax error".to_string())),
+        };
+        Ok(ZRandMemberCommand {
+            key,
+            count,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMinCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(arr)?;
+        Ok(ZPopMinCommand {
+            key,
+            count: pop_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMaxCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(arr)?;
+        Ok(ZPopMaxCommand {
+            key,
+            count: pop_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMinCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_keys_timeout(arr)?;
+        Ok(BZPopMinCommand { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMaxCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_keys_timeout(arr)?;
+        Ok(BZPopMaxCommand { keys, timeout })
+    }
+}
+
+// ZMPop: numkeys key [key ...] MIN|MAX [COUNT count]
+impl TryFrom<RespArray> for ZMPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (keys, end, count) = parse_mpop(into_args(arr)?, parse_zset_end)?;
+        Ok(ZMPopCommand { keys, end, count })
+    }
+}
+
+// BZMPop: timeout numkeys key [key ...] MIN|MAX [COUNT count]
+impl TryFrom<RespArray> for BZMPopCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let args = into_args(arr)?;
+        check_min_nargs(&args, 1)?;
+        let mut args = args.into_iter();
+        let timeout = parse_timeout(args.next().expect("timeout has to exist"))?;
+        let (keys, end, count) = parse_mpop(args.collect(), parse_zset_end)?;
+        Ok(BZMPopCommand {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZScanCommand {
+    type Error = ExecuteError;
+
+    fn try_from(arr: RespArray) -> Result<Self, Self::Error> {
+        let (key, cursor, opts) = parse_key_scan(arr, false)?;
+        Ok(ZScanCommand { key, cursor, opts })
+    }
+}
+
+/// Parses `start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`.
+pub(crate) fn parse_zrange(
+    mut args: impl Iterator<Item = RespFrame>,
+) -> Result<(ZRangeSpec, bool), ExecuteError> {
+    let start = args.next().expect("start has to exist");
+    let stop = args.next().expect("stop has to exist");
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+    while let Some(opt) = args.next() {
+        match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+            "byscore" => by_score = true,
+            "bylex" => by_lex = true,
+            "rev" => rev = true,
+            "withscores" => with_scores = true,
+            "limit" => match (args.next(), args.next()) {
+                (Some(offset), Some(count)) => {
+                    limit = Some((frame_to_i64(offset)?, frame_to_i64(count)?));
+                }
+                _ => return Err(InvalidArgument("syntax error".to_string())),
+            },
+            _ => return Err(InvalidArgument("syntax error".to_string())),
+        }
+    }
+    if by_score && by_lex {
+        return Err(InvalidArgument("syntax error".to_string()));
+    }
+    if limit.is_some() && !by_score && !by_lex {
+        return Err(InvalidArgument(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_string(),
+        ));
+    }
+    if with_scores && by_lex {
+        return Err(InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+
+    // with REV the score and lex bounds come as `max min`
+    let (min, max) = if rev && (by_score || by_lex) {
+        (stop, start)
+    } else {
+        (start, stop)
+    };
+    let by = if by_score {
+        ZRangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?)
+    } else if by_lex {
+        ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?)
+    } else {
+        ZRangeBy::Rank(frame_to_i64(min)?, frame_to_i64(max)?)
+    };
+    Ok((ZRangeSpec { by, rev, limit }, with_scores))
+}
+
+/// `1.5`, `(1.5`, `-inf` or `+inf`.
+pub(crate) fn parse_score_bound(frame: RespFrame) -> Result<ScoreBound, ExecuteError> {
+    let s = frame_to_string(frame)?;
+    let (value, exclusive) = match s.strip_prefix('(') {
+        Some(value) => (value, true),
+        None => (s.as_str(), false),
+    };
+    match value.parse::<f64>() {
+        Ok(value) if !value.is_nan() => Ok(ScoreBound { value, exclusive }),
+        _ => Err(InvalidArgument("min or max is not a float".to_string())),
+    }
+}
+
+/// `-`, `+`, `[member` or `(member`.
+pub(crate) fn parse_lex_bound(frame: RespFrame) -> Result<LexBound, ExecuteError> {
+    let s = frame_to_string(frame)?;
+    match s.as_str() {
+        "-" => Ok(LexBound::Min),
+        "+" => Ok(LexBound::Max),
+        _ => match (s.strip_prefix('['), s.strip_prefix('(')) {
+            (Some(member), _) => Ok(LexBound::Inclusive(member.to_string())),
+            (_, Some(member)) => Ok(LexBound::Exclusive(member.to_string())),
+            _ => Err(InvalidArgument(
+                "min or max not valid string range item".to_string(),
+            )),
+        },
+    }
+}
+
+/// A score as RESP3 has it, RESP2 clients are sent it as a bulk string.
+pub(crate) fn score_frame(score: f64) -> RespFrame {
+    RespDouble::new(score).into()
+}
+
+/// Members, each followed by its score when `with_scores`.
+pub(crate) fn scores_reply(ret: Vec<(String, f64)>, with_scores: bool) -> RespFrame {
+    let mut vec = Vec::with_capacity(ret.len() * 2);
+    for (member, score) in ret {
+        vec.push(RespBulkString::new(member).into());
+        if with_scores {
+            vec.push(score_frame(score));
+        }
+    }
+    RespArray::new(vec).into()
+}
+
+fn rank_reply(ret: Option<(usize, f64)>, with_score: bool) -> RespFrame {
+    match ret {
+        Some((rank, score)) if with_score => {
+            RespArray::new(vec![RespFrame::Integer(rank as i64), score_frame(score)]).into()
+        }
+        Some((rank, _)) => RespFrame::Integer(rank as i64),
+        None if with_score => RespArray::null().into(),
+        None => RespBulkString::null().into(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct ZSetOpArgs {
+    keys: Vec<String>,
+    weights: Option<Vec<f64>>,
+    aggregate: Aggregate,
+    with_scores: bool,
+}
+
+// numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX] [WITHSCORES]
+fn parse_zsetop(
+    args: Vec<RespFrame>,
+    allow_weights: bool,
+    allow_with_scores: bool,
+) -> Result<ZSetOpArgs, ExecuteError> {
+    check_min_nargs(&args, 2)?;
+    let mut args = args.into_iter();
+    let numkeys = frame_to_i64(args.next().expect("numkeys has to exist"))?;
+    if numkeys <= 0 {
+        return Err(InvalidArgument(
+            "at least 1 input key is needed for this command".to_string(),
+        ));
+    }
+    let numkeys = numkeys as usize;
+    let keys = args
+        .by_ref()
+        .take(numkeys)
+        .map(frame_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.len() != numkeys {
+        return Err(InvalidArgument("syntax error".to_string()));
+    }
+
+    let mut ret = ZSetOpArgs {
+        keys,
+        ..Default::default()
+    };
+    while let Some(opt) = args.next() {
+        match frame_to_string(opt)?.to_ascii_lowercase().as_str() {
+            "weights" if allow_weights => {
+                let weights = args
+                    .by_ref()
+                    .take(numkeys)
+                    .map(|w| {
+                        frame_to_f64(w)
+                            .map_err(|_| InvalidArgument("weight value is not a float".to_string()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if weights.len() != numkeys {
+                    return Err(InvalidArgument("syntax error".to_string()));
+                }
+                ret.weights = Some(weights);
+            }
+            "aggregate" if allow_weights => {
+                let Some(aggregate) = args.next() else {
+                    return Err(InvalidArgument("syntax error".to_string()));
+                };
+                ret.aggregate = match frame_to_string(aggregate)?.to_ascii_lowercase().as_str() {
+                    "sum" => Aggregate::Sum,
+                    "min" => Aggregate::Min,
+                    "max" => Aggregate::Max,
+                    _ => return Err(InvalidArgument("syntax error".to_string())),
+                };
+            }
+            "withscores" if allow_with_scores => ret.with_scores = true,
+            _ => return Err(InvalidArgument("syntax error".to_string())),
+        }
+    }
+    Ok(ret)
+}
+
+// destination numkeys key [key ...] ...
+fn split_destination(arr: RespArray) -> Result<(String, Vec<RespFrame>), ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, 1)?;
+    let mut args = args.into_iter();
+    let destination = frame_to_string(args.next().expect("destination has to exist"))?;
+    Ok((destination, args.collect()))
+}
+
+// key min max
+fn parse_key_min_max(arr: RespArray) -> Result<(String, RespFrame, RespFrame), ExecuteError> {
+    let args = into_args(arr)?;
+    check_nargs(&args, 3)?;
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let min = args.next().expect("min has to exist");
+    let max = args.next().expect("max has to exist");
+    Ok((key, min, max))
+}
+
+/// Blocks until one of `keys` has a member to pop from `end`, replying
+/// `[key, member, score]`.
+fn bzpop(
+    backend: Backend,
+    client_id: u64,
+    keys: Vec<String>,
+    end: ZSetEnd,
+    timeout: Option<Duration>,
+) -> Blocked {
+    let serve: Serve = Box::new(move |backend, key| {
+        let (member, score) = backend.zpop(key, end, 1).pop()?;
+        Some(
+            RespArray::new(vec![
+                RespBulkString::new(key).into(),
+                RespBulkString::new(member).into(),
+                score_frame(score),
+            ])
+            .into(),
+        )
+    });
+    backend.block(
+        client_id,
+        keys,
+        None,
+        timeout,
+        RespArray::null().into(),
+        serve,
+    )
+}
+
+/// `[key, [[member, score] ...]]`, or a null array when nothing was popped.
+fn zmpop_reply(ret: Option<(String, Vec<(String, f64)>)>) -> RespFrame {
+    match ret {
+        Some((key, popped)) => {
+            let popped = popped
+                .into_iter()
+                .map(|(member, score)| {
+                    RespArray::new(vec![RespBulkString::new(member).into(), score_frame(score)])
+                        .into()
+                })
+                .collect::<Vec<RespFrame>>();
+            RespArray::new(vec![
+                RespBulkString::new(key).into(),
+                RespArray::new(popped).into(),
+            ])
+            .into()
+        }
+        None => RespArray::null().into(),
+    }
+}
+
+fn parse_zset_end(frame: RespFrame) -> Result<ZSetEnd, ExecuteError> {
+    match frame_to_string(frame)?.to_ascii_lowercase().as_str() {
+        "min" => Ok(ZSetEnd::Min),
+        "max" => Ok(ZSetEnd::Max),
+        _ => Err(InvalidArgument("syntax error".to_string())),
+    }
+}
+
+// key member [WITHSCORE]
+fn parse_rank(arr: RespArray) -> Result<(String, String, bool), ExecuteError> {
+    let args = into_args(arr)?;
+    check_min_nargs(&args, 2)?;
+    let mut args = args.into_iter();
+    let key = frame_to_string(args.next().expect("key has to exist"))?;
+    let member = frame_to_string(args.next().expect("member has to exist"))?;
+    let with_score = match (args.next().map(frame_to_string).transpose()?, args.next()) {
+        (None, _) => false,
+        (Some(opt), None) if opt.eq_ignore_ascii_case("withscore") => true,
+        _ => return Err(InvalidArgument("syntax error".to_string())),
+    };
+    Ok((key, member, with_score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::cmd;
+
+    #[test]
+    fn test_zadd_try_from() -> anyhow::Result<()> {
+        let zadd = ZAddCommand::try_from(cmd(&["zadd", "z", "XX", "ch", "1", "a", "-inf", "b"]))?;
+        assert_eq!(
+            zadd,
+            ZAddCommand {
+                key: "z".to_string(),
+                flags: ZAddFlags {
+                    xx: true,
+                    ch: true,
+                    ..Default::default()
+                },
+                incr: false,
+                pairs: vec![(1.0, "a".to_string()), (f64::NEG_INFINITY, "b".to_string())],
+            }
+        );
+        assert!(ZAddCommand::try_from(cmd(&["zadd", "z", "nx", "xx", "1", "a"])).is_err());
+        assert!(ZAddCommand::try_from(cmd(&["zadd", "z", "nx", "gt", "1", "a"])).is_err());
+        assert!(ZAddCommand::try_from(cmd(&["zadd", "z", "incr", "1", "a", "2", "b"])).is_err());
+        assert!(ZAddCommand::try_from(cmd(&["zadd", "z", "1", "a", "2"])).is_err());
+        assert!(ZAddCommand::try_from(cmd(&["zadd", "z", "nan", "a"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_try_from() -> anyhow::Result<()> {
+        let zrange = ZRangeCommand::try_from(cmd(&[
+            "zrange",
+            "z",
+            "+inf",
+            "(1",
+            "BYSCORE",
+            "REV",
+            "LIMIT",
+            "0",
+            "2",
+            "WITHSCORES",
+        ]))?;
+        assert_eq!(
+            zrange.spec,
+            ZRangeSpec {
+                by: ZRangeBy::Score(
+                    ScoreBound {
+                        value: 1.0,
+                        exclusive: true
+                    },
+                    ScoreBound {
+                        value: f64::INFINITY,
+                        exclusive: false
+                    }
+                ),
+                rev: true,
+                limit: Some((0, 2)),
+            }
+        );
+        assert!(zrange.with_scores);
+        assert!(
+            ZRangeCommand::try_from(cmd(&["zrange", "z", "0", "1", "LIMIT", "0", "1"])).is_err()
+        );
+        assert!(
+            ZRangeCommand::try_from(cmd(&["zrange", "z", "-", "+", "BYLEX", "WITHSCORES"]))
+                .is_err()
+        );
+        assert!(ZRangeCommand::try_from(cmd(&["zrange", "z", "a", "+", "BYLEX"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrank_execute() -> anyhow::Result<()> {
+        let backend = Backend::default();
+        ZAddCommand::try_from(cmd(&["zadd", "z", "1", "a", "2", "b"]))?.execute(backend.clone())?;
+        let zrevrank = ZRevRankCommand::try_from(cmd(&["zrevrank", "z", "a", "withscore"]))?;
+        assert_eq!(
+            zrevrank.execute(backend.clone())?,
+            RespArray::new(vec![RespFrame::Integer(1), score_frame(1.0)]).into()
+        );
+        let zrank = ZRankCommand::try_from(cmd(&["zrank", "z", "c"]))?;
+        assert_eq!(zrank.execute(backend)?, RespBulkString::null().into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zsetop_try_from() -> anyhow::Result<()> {
+        let zunion = ZUnionCommand::try_from(cmd(&[
+            "zunion",
+            "2",
+            "a",
+            "b",
+            "WEIGHTS",
+            "2",
+            "0.5",
+            "AGGREGATE",
+            "max",
+            "WITHSCORES",
+        ]))?;
+        assert_eq!(
+            zunion,
+            ZUnionCommand {
+                keys: vec!["a".to_string(), "b".to_string()],
+                weights: Some(vec![2.0, 0.5]),
+                aggregate: Aggregate::Max,
+                with_scores: true,
+            }
+        );
+        let zdiffstore = ZDiffStoreCommand::try_from(cmd(&["zdiffstore", "d", "1", "a"]))?;
+        assert_eq!(zdiffstore.destination, "d");
+        assert!(ZInterCommand::try_from(cmd(&["zinter", "2", "a", "b", "WEIGHTS", "1"])).is_err());
+        assert!(ZDiffCommand::try_from(cmd(&["zdiff", "1", "a", "WEIGHTS", "1"])).is_err());
+        assert!(
+            ZInterStoreCommand::try_from(cmd(&["zinterstore", "d", "1", "a", "WITHSCORES"]))
+                .is_err()
+        );
+        assert!(ZUnionCommand::try_from(cmd(&["zunion", "0", "a"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zmpop_try_from() -> anyhow::Result<()> {
+        let zmpop = ZMPopCommand::try_from(cmd(&["zmpop", "2", "a", "b", "max", "COUNT", "3"]))?;
+        assert_eq!(
+            zmpop,
+            ZMPopCommand {
+                keys: vec!["a".to_string(), "b".to_string()],
+                end: ZSetEnd::Max,
+                count: 3,
+            }
+        );
+        let bzmpop = BZMPopCommand::try_from(cmd(&["bzmpop", "0.5", "1", "a", "MIN"]))?;
+        assert_eq!(bzmpop.timeout, Some(Duration::from_millis(500)));
+        assert_eq!(bzmpop.count, 1);
+        assert!(ZMPopCommand::try_from(cmd(&["zmpop", "1", "a", "left"])).is_err());
+        assert!(BZPopMinCommand::try_from(cmd(&["bzpopmin", "a", "-1"])).is_err());
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use tokio::net::TcpListener;
 use tracing::{info, warn};
 
@@ -13,6 +15,16 @@ async fn main() -> anyhow::Result<()> {
     let listener = TcpListener::bind(addr).await?;
 
     let backend = Backend::default();
+
+    let backend_cloned = backend.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+            backend_cloned.active_expire_cycle();
+        }
+    });
+
     loop {
         let (stream, addr) = listener.accept().await?;
         info!("Accepted connection from {}", addr);
@@ -1,18 +1,29 @@
-use anyhow::bail;
+use std::collections::VecDeque;
+use std::future::{self, Future};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
 use futures::SinkExt;
 use tokio::net::TcpStream;
+use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 use tokio_util::codec::Framed;
 use tracing::info;
 
+use crate::backend::blocking::{Blocked, Waiting};
+use crate::backend::pubsub::PushSender;
 use crate::backend::Backend;
-use crate::cmd::echo::ECHOCommand;
-use crate::cmd::hmap::{HGetAllCommand, HGetCommand, HSetCommand, HmgetCommand};
-use crate::cmd::map::{GetCommand, SetCommand};
-use crate::cmd::set::{SaddCommand, SismemberCommand};
-use crate::cmd::CommandExecutor;
+use crate::cmd::keyspec::KeyAccess;
+use crate::cmd::script::ScriptCommand;
+use crate::cmd::transaction::Transaction;
+use crate::cmd::ExecuteError::UnknownCommand;
+use crate::cmd::{command_name, BlockingExecutor, Command, CommandExecutor};
 use crate::network::codec::RespCodec;
+use crate::resp::array::RespArray;
+use crate::resp::bulkstring::RespBulkString;
 use crate::resp::frame::RespFrame;
+use crate::resp::push::RespPush;
+use crate::resp::simple_error::RespSimpleError;
 use crate::resp::simple_string::RespSimpleString;
 
 mod codec;
@@ -23,105 +34,631 @@ pub struct RedisRequest {
     backend: Backend,
 }
 
+/// What a connection keeps between its commands.
 #[derive(Debug)]
-pub struct RedisResponse {
-    frame: RespFrame,
+struct Session {
+    backend: Backend,
+    client_id: u64,
+    // the database commands run against, see `SELECT`
+    db: usize,
+    // set between `MULTI` and `EXEC`
+    transaction: Option<Transaction>,
+    // the RESP version spoken, see `HELLO`
+    protocol: u8,
+    // where messages for the connection go, such as pub/sub ones
+    pushes: PushSender,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // however the connection ended, it watches nothing anymore
+        self.backend.unwatch(self.client_id);
+        self.backend.unsubscribe_all(self.client_id);
+        self.backend.untrack(self.client_id);
+    }
+}
+
+#[derive(Debug)]
+pub enum RedisResponse {
+    Reply(RespFrame),
+    // several replies in a row, such as one per channel subscribed to
+    Replies(Vec<RespFrame>),
+    // the client is parked until a key can serve it
+    Blocked(Waiting),
+}
+
+impl From<Blocked> for RedisResponse {
+    fn from(blocked: Blocked) -> Self {
+        match blocked {
+            Blocked::Served(frame) => RedisResponse::Reply(frame),
+            Blocked::Waiting(waiting) => RedisResponse::Blocked(waiting),
+        }
+    }
 }
 
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> anyhow::Result<()> {
     let mut resp = Framed::new(stream, RespCodec);
+    let client_id = backend.next_client_id();
+    let (pushes, mut push_rx) = mpsc::unbounded_channel();
+    let mut session = Session {
+        backend: backend.clone(),
+        client_id,
+        db: 0,
+        transaction: None,
+        protocol: 2,
+        pushes,
+    };
+    // commands that came in while the client was blocked
+    let mut pipelined = VecDeque::new();
     loop {
-        match resp.next().await {
+        let frame = match pipelined.pop_front() {
+            Some(frame) => Some(Ok(frame)),
+            None => tokio::select! {
+                frame = resp.next() => frame,
+                Some(items) = push_rx.recv() => {
+                    resp.send(push_frame(items, session.protocol)).await?;
+                    continue;
+                }
+            },
+        };
+        match frame {
             Some(Ok(frame)) => {
                 info!("Received frame: {:?}", frame);
                 let req = RedisRequest {
                     frame,
-                    backend: backend.clone(),
+                    backend: backend.select(session.db)?,
+                };
+                let response = match request_handler(req, &mut session).await {
+                    Ok(response) => response,
+                    Err(e) => RedisResponse::Reply(error_reply(e)),
                 };
-                let response = request_handler(req).await?;
-                info!("Sending response: {:?}", response);
-                resp.send(response.frame).await?;
+                // wake the clients blocked on keys this command pushed to
+                backend.serve_blocked();
+                let frames = match response {
+                    RedisResponse::Reply(frame) => vec![frame],
+                    RedisResponse::Replies(frames) => frames,
+                    RedisResponse::Blocked(waiting) => {
+                        let reply =
+                            wait_unblocked(&mut resp, &backend, client_id, waiting, &mut pipelined)
+                                .await?;
+                        vec![reply]
+                    }
+                };
+                for frame in frames {
+                    let frame = reply_frame(frame, session.protocol);
+                    info!("Sending response: {:?}", frame);
+                    resp.send(frame).await?;
+                }
             }
             Some(Err(e)) => {
                 bail!(e.to_string());
             }
-            None => continue,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Waits until a blocked client is served, unblocked or times out. The
+/// connection is read meanwhile so a client hanging up stops waiting, the
+/// commands it pipelined are kept in `pipelined` for after the reply.
+async fn wait_unblocked(
+    resp: &mut Framed<TcpStream, RespCodec>,
+    backend: &Backend,
+    client_id: u64,
+    waiting: Waiting,
+    pipelined: &mut VecDeque<RespFrame>,
+) -> anyhow::Result<RespFrame> {
+    let Waiting { mut rx, timeout } = waiting;
+    let timeout = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => future::pending().await,
+        }
+    };
+    tokio::pin!(timeout);
+    loop {
+        tokio::select! {
+            reply = &mut rx => return Ok(reply?),
+            _ = &mut timeout => {
+                // sends the timeout reply, unless it was served meanwhile
+                backend.unblock(client_id, false);
+                return Ok(rx.await?);
+            }
+            frame = resp.next() => match frame {
+                Some(Ok(frame)) => pipelined.push_back(frame),
+                _ => {
+                    backend.unblock(client_id, false);
+                    bail!("connection closed while blocked");
+                }
+            },
         }
     }
 }
 
-async fn request_handler(req: RedisRequest) -> anyhow::Result<RedisResponse> {
+async fn request_handler(
+    req: RedisRequest,
+    session: &mut Session,
+) -> anyhow::Result<RedisResponse> {
     let (frame, backend) = (req.frame, req.backend);
 
     let RespFrame::Array(cmd) = frame else {
         bail!("Invalid command format.");
     };
 
-    let Some(content) = &cmd.0 else {
-        bail!("Invalid command format.");
+    // a RESP2 subscriber reads nothing but messages, so it may only run
+    // what keeps replies in that shape
+    if session.protocol == 2 && backend.subscriptions(session.client_id) > 0 {
+        let name = command_name(&cmd);
+        if !matches!(
+            name.as_str(),
+            "subscribe"
+                | "unsubscribe"
+                | "psubscribe"
+                | "punsubscribe"
+                | "ssubscribe"
+                | "sunsubscribe"
+                | "ping"
+        ) {
+            bail!(
+                "Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                name
+            );
+        }
+    }
+
+    let access = KeyAccess::of(&cmd);
+    let command = match Command::try_from(cmd) {
+        Ok(command) => command,
+        Err(e) => {
+            // a command that can't be queued dooms the whole transaction
+            if let Some(transaction) = &mut session.transaction {
+                transaction.aborted = true;
+                return Err(e.into());
+            }
+            if let UnknownCommand(name) = e {
+                let s = format!("unimplemented command: {}", name);
+                info!(s);
+                return Ok(RedisResponse::Reply(RespSimpleString::new(s).into()));
+            }
+            return Err(e.into());
+        }
     };
 
-    let RespFrame::BulkString(s) = &content[0] else {
-        bail!("Invalid command format.");
+    match command {
+        // refused, but unlike a command failing to parse, the transaction
+        // goes on
+        Command::Multi(_) | Command::Watch(_) if session.transaction.is_some() => match command {
+            Command::Multi(_) => bail!("ERR MULTI calls can not be nested"),
+            _ => bail!("ERR WATCH inside MULTI is not allowed"),
+        },
+        Command::Multi(_) => {
+            session.transaction = Some(Transaction::default());
+            Ok(RedisResponse::Reply(RespSimpleString::new("OK").into()))
+        }
+        Command::Discard(_) => {
+            if session.transaction.take().is_none() {
+                bail!("DISCARD without MULTI");
+            }
+            backend.unwatch(session.client_id);
+            Ok(RedisResponse::Reply(RespSimpleString::new("OK").into()))
+        }
+        Command::Exec(_) => {
+            let Some(transaction) = session.transaction.take() else {
+                bail!("EXEC without MULTI");
+            };
+            let _guard = match wait_lock(&backend, backend.lock_exclusive()).await {
+                Ok(guard) => guard,
+                Err(e) => {
+                    // the transaction is gone, and so is what it watched
+                    backend.unwatch(session.client_id);
+                    return Err(e);
+                }
+            };
+            let dirty = backend.unwatch(session.client_id);
+            if transaction.aborted {
+                bail!("EXECABORT Transaction discarded because of previous errors.");
+            }
+            // a watched key changed, so the transaction doesn't run
+            if dirty {
+                return Ok(RedisResponse::Reply(RespArray::null().into()));
+            }
+            Ok(RedisResponse::Reply(exec(transaction, &backend, session)?))
+        }
+        // stops a script holding every other command off
+        Command::Script(ScriptCommand::Kill) if session.transaction.is_none() => {
+            run(command, &access, &backend, session)
+        }
+        command => match &mut session.transaction {
+            Some(transaction) => {
+                transaction.commands.push((command, access));
+                Ok(RedisResponse::Reply(RespSimpleString::new("QUEUED").into()))
+            }
+            // a script runs alone, the way a transaction does, and so do
+            // commands swapping out whole databases or moving keys between
+            // them, which no other command's handle on a database would see
+            None if matches!(
+                command,
+                Command::Eval(_)
+                    | Command::FlushDb(_)
+                    | Command::FlushAll(_)
+                    | Command::SwapDb(_)
+                    | Command::Move(_)
+            ) =>
+            {
+                let _guard = wait_lock(&backend, backend.lock_exclusive()).await?;
+                run(command, &access, &backend.select(session.db)?, session)
+            }
+            None => {
+                let _guard = wait_lock(&backend, backend.lock_shared()).await?;
+                // the database as it is once nothing swaps it out anymore
+                run(command, &access, &backend.select(session.db)?, session)
+            }
+        },
+    }
+}
+
+/// Runs the queued commands, the caller holding every other client off. A
+/// command failing doesn't stop the rest, its error is its reply; blocking
+/// commands don't block and reply as if they timed out.
+fn exec(
+    transaction: Transaction,
+    backend: &Backend,
+    session: &mut Session,
+) -> anyhow::Result<RespFrame> {
+    let mut replies = Vec::with_capacity(transaction.commands.len());
+    for (command, access) in transaction.commands {
+        // a queued SELECT changes the database of the commands after it
+        let backend = backend.select(session.db)?;
+        let response = run(command, &access, &backend, session);
+        replies.push(reply_now(response, &backend, session.client_id)?);
+    }
+    Ok(RespArray::new(replies).into())
+}
+
+/// The reply of a command run by `EXEC` or a script, where a failing
+/// command replies with its error and a blocking one as if it timed out.
+fn reply_now(
+    response: anyhow::Result<RedisResponse>,
+    backend: &Backend,
+    client_id: u64,
+) -> anyhow::Result<RespFrame> {
+    Ok(match response {
+        Ok(RedisResponse::Reply(frame)) => frame,
+        Ok(RedisResponse::Replies(frames)) => RespArray::new(frames).into(),
+        Ok(RedisResponse::Blocked(mut waiting)) => {
+            backend.unblock(client_id, false);
+            waiting.rx.try_recv()?
+        }
+        Err(e) => error_reply(e),
+    })
+}
+
+// what a script can't call: commands about the connection, or that would
+// run something else inside it
+const NOT_IN_SCRIPTS: [&str; 17] = [
+    "multi",
+    "exec",
+    "discard",
+    "watch",
+    "unwatch",
+    "eval",
+    "evalsha",
+    "script",
+    "subscribe",
+    "unsubscribe",
+    "psubscribe",
+    "punsubscribe",
+    "ssubscribe",
+    "sunsubscribe",
+    "client",
+    "hello",
+    "config",
+];
+
+/// Runs a command a script called through `redis.call` or `redis.pcall`,
+/// the script holding every other command off.
+fn script_call(cmd: RespArray, backend: &Backend, session: &mut Session) -> RespFrame {
+    let name = command_name(&cmd);
+    if NOT_IN_SCRIPTS.contains(&name.as_str()) {
+        return error_reply(anyhow!("This Redis command is not allowed from script"));
+    }
+    let access = KeyAccess::of(&cmd);
+    let command = match Command::try_from(cmd) {
+        Ok(command) => command,
+        Err(UnknownCommand(_)) => {
+            return error_reply(anyhow!("Unknown Redis command called from script"))
+        }
+        Err(e) => return error_reply(e.into()),
     };
+    if access.write {
+        backend.script_wrote();
+    }
+    backend
+        .select(session.db)
+        .and_then(|backend| {
+            let response = run(command, &access, &backend, session);
+            reply_now(response, &backend, session.client_id)
+        })
+        .unwrap_or_else(error_reply)
+}
 
-    let s = s.as_ref().expect("command name must exist.");
-
-    let response = match s.to_ascii_lowercase().as_slice() {
-        b"get" => {
-            info!("get command");
-            let get = GetCommand::try_from(cmd)?;
-            get.execute(backend)?
-        }
-        b"set" => {
-            info!("set command");
-            let set = SetCommand::try_from(cmd)?;
-            set.execute(backend)?
-        }
-        b"hget" => {
-            info!("hget command");
-            let hget = HGetCommand::try_from(cmd)?;
-            hget.execute(backend)?
-        }
-        b"hset" => {
-            info!("hset command");
-            let hset = HSetCommand::try_from(cmd)?;
-            hset.execute(backend)?
-        }
-        b"hgetall" => {
-            info!("hgetall command");
-            let hgetall = HGetAllCommand::try_from(cmd)?;
-            hgetall.execute(backend)?
-        }
-        b"echo" => {
-            info!("echo command");
-            let echo = ECHOCommand::try_from(cmd)?;
-            echo.execute(backend)?
-        }
-        b"hmget" => {
-            info!("hmget command");
-            let echo = HmgetCommand::try_from(cmd)?;
-            echo.execute(backend)?
-        }
-        b"sadd" => {
-            info!("sadd command");
-            let sadd = SaddCommand::try_from(cmd)?;
-            sadd.execute(backend)?
-        }
-        b"sismember" => {
-            info!("sismember command");
-            let sismember = SismemberCommand::try_from(cmd)?;
-            sismember.execute(backend)?
-        }
-        _ => {
-            let s = format!(
-                "unimplemented command: {}",
-                String::from_utf8_lossy(s.as_slice())
-            );
-            info!(s);
-            RespSimpleString::new(s).into()
+/// How often a command waiting for its turn looks whether a script has
+/// run past `busy-reply-threshold`.
+const BUSY_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Waits for `lock` without holding up the thread, so the connection that
+/// would kill a long script still gets to. Once a script has run past
+/// `busy-reply-threshold`, the command is turned away instead.
+async fn wait_lock<G>(backend: &Backend, lock: impl Future<Output = G>) -> anyhow::Result<G> {
+    // polled throughout, so the command keeps its place in the queue
+    tokio::pin!(lock);
+    loop {
+        tokio::select! {
+            guard = &mut lock => return Ok(guard),
+            _ = tokio::time::sleep(BUSY_CHECK_INTERVAL) => {
+                if backend.script_busy() {
+                    bail!("BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.");
+                }
+            }
         }
+    }
+}
+
+/// Runs a command with its keys locked and those whose TTL has passed
+/// expired, then tells whoever watches or caches them they may have changed.
+fn run(
+    command: Command,
+    access: &KeyAccess,
+    backend: &Backend,
+    session: &mut Session,
+) -> anyhow::Result<RedisResponse> {
+    let client_id = session.client_id;
+    let caching = backend.take_caching(client_id);
+    // keys read are tracked before they are, so a change racing the read
+    // is always followed by an invalidation
+    if !access.write {
+        backend.track_keys(client_id, &access.keys, caching);
+    }
+    let response = {
+        let _keys = backend.lock_keys(&access.keys);
+        for key in &access.keys {
+            backend.expire_if_due(key);
+        }
+        execute(command, backend.clone(), session)?
     };
+    if access.write {
+        for key in &access.keys {
+            backend.touched_by(key, Some(client_id));
+        }
+    }
+    Ok(response)
+}
 
-    Ok(RedisResponse { frame: response })
+fn execute(
+    command: Command,
+    backend: Backend,
+    session: &mut Session,
+) -> anyhow::Result<RedisResponse> {
+    info!("Executing {:?}", command);
+    let client_id = session.client_id;
+    let response = match command {
+        Command::Get(get) => get.execute(backend)?,
+        Command::Set(set) => set.execute(backend)?,
+        Command::HGet(hget) => hget.execute(backend)?,
+        Command::HSet(hset) => hset.execute(backend)?,
+        Command::HGetAll(hgetall) => hgetall.execute(backend)?,
+        Command::ECHO(echo) => echo.execute(backend)?,
+        Command::Hmget(hmget) => hmget.execute(backend)?,
+        Command::Sadd(sadd) => sadd.execute(backend)?,
+        Command::SisMember(sismember) => sismember.execute(backend)?,
+        Command::HDel(hdel) => hdel.execute(backend)?,
+        Command::HExists(hexists) => hexists.execute(backend)?,
+        Command::HLen(hlen) => hlen.execute(backend)?,
+        Command::HKeys(hkeys) => hkeys.execute(backend)?,
+        Command::HVals(hvals) => hvals.execute(backend)?,
+        Command::HIncrBy(hincrby) => hincrby.execute(backend)?,
+        Command::HIncrByFloat(hincrbyfloat) => hincrbyfloat.execute(backend)?,
+        Command::HSetNx(hsetnx) => hsetnx.execute(backend)?,
+        Command::HStrLen(hstrlen) => hstrlen.execute(backend)?,
+        Command::HRandField(hrandfield) => hrandfield.execute(backend)?,
+        Command::HExpire(hexpire) => hexpire.execute(backend)?,
+        Command::HTtl(httl) => httl.execute(backend)?,
+        Command::HPersist(hpersist) => hpersist.execute(backend)?,
+        Command::HGetEx(hgetex) => hgetex.execute(backend)?,
+        Command::HSetEx(hsetex) => hsetex.execute(backend)?,
+        Command::SRem(srem) => srem.execute(backend)?,
+        Command::SMembers(smembers) => smembers.execute(backend)?,
+        Command::SCard(scard) => scard.execute(backend)?,
+        Command::SMisMember(smismember) => smismember.execute(backend)?,
+        Command::SPop(spop) => spop.execute(backend)?,
+        Command::SRandMember(srandmember) => srandmember.execute(backend)?,
+        Command::SMove(smove) => smove.execute(backend)?,
+        Command::SInter(sinter) => sinter.execute(backend)?,
+        Command::SUnion(sunion) => sunion.execute(backend)?,
+        Command::SDiff(sdiff) => sdiff.execute(backend)?,
+        Command::SInterStore(sinterstore) => sinterstore.execute(backend)?,
+        Command::SUnionStore(sunionstore) => sunionstore.execute(backend)?,
+        Command::SDiffStore(sdiffstore) => sdiffstore.execute(backend)?,
+        Command::SInterCard(sintercard) => sintercard.execute(backend)?,
+        Command::LPush(lpush) => lpush.execute(backend)?,
+        Command::RPush(rpush) => rpush.execute(backend)?,
+        Command::LPushX(lpushx) => lpushx.execute(backend)?,
+        Command::RPushX(rpushx) => rpushx.execute(backend)?,
+        Command::LPop(lpop) => lpop.execute(backend)?,
+        Command::RPop(rpop) => rpop.execute(backend)?,
+        Command::LRange(lrange) => lrange.execute(backend)?,
+        Command::LIndex(lindex) => lindex.execute(backend)?,
+        Command::LSet(lset) => lset.execute(backend)?,
+        Command::LLen(llen) => llen.execute(backend)?,
+        Command::LRem(lrem) => lrem.execute(backend)?,
+        Command::LTrim(ltrim) => ltrim.execute(backend)?,
+        Command::LInsert(linsert) => linsert.execute(backend)?,
+        Command::LPos(lpos) => lpos.execute(backend)?,
+        Command::LMove(lmove) => lmove.execute(backend)?,
+        Command::LMPop(lmpop) => lmpop.execute(backend)?,
+        Command::BLPop(blpop) => return Ok(blpop.execute(backend, client_id)?.into()),
+        Command::BRPop(brpop) => return Ok(brpop.execute(backend, client_id)?.into()),
+        Command::BLMove(blmove) => return Ok(blmove.execute(backend, client_id)?.into()),
+        Command::BLMPop(blmpop) => return Ok(blmpop.execute(backend, client_id)?.into()),
+        Command::Client(client) => {
+            client.execute(backend, client_id, &session.pushes, session.protocol)?
+        }
+        Command::Watch(watch) => watch.execute(backend, client_id)?,
+        Command::Unwatch(unwatch) => unwatch.execute(backend, client_id)?,
+        Command::ZAdd(zadd) => zadd.execute(backend)?,
+        Command::ZRem(zrem) => zrem.execute(backend)?,
+        Command::ZScore(zscore) => zscore.execute(backend)?,
+        Command::ZMScore(zmscore) => zmscore.execute(backend)?,
+        Command::ZIncrBy(zincrby) => zincrby.execute(backend)?,
+        Command::ZCard(zcard) => zcard.execute(backend)?,
+        Command::ZCount(zcount) => zcount.execute(backend)?,
+        Command::ZRank(zrank) => zrank.execute(backend)?,
+        Command::ZRevRank(zrevrank) => zrevrank.execute(backend)?,
+        Command::ZRange(zrange) => zrange.execute(backend)?,
+        Command::ZUnion(zunion) => zunion.execute(backend)?,
+        Command::ZInter(zinter) => zinter.execute(backend)?,
+        Command::ZDiff(zdiff) => zdiff.execute(backend)?,
+        Command::ZUnionStore(zunionstore) => zunionstore.execute(backend)?,
+        Command::ZInterStore(zinterstore) => zinterstore.execute(backend)?,
+        Command::ZDiffStore(zdiffstore) => zdiffstore.execute(backend)?,
+        Command::ZRangeStore(zrangestore) => zrangestore.execute(backend)?,
+        Command::ZRemRangeByScore(zremrangebyscore) => zremrangebyscore.execute(backend)?,
+        Command::ZRemRangeByRank(zremrangebyrank) => zremrangebyrank.execute(backend)?,
+        Command::ZRemRangeByLex(zremrangebylex) => zremrangebylex.execute(backend)?,
+        Command::ZLexCount(zlexcount) => zlexcount.execute(backend)?,
+        Command::ZRandMember(zrandmember) => zrandmember.execute(backend)?,
+        Command::ZPopMin(zpopmin) => zpopmin.execute(backend)?,
+        Command::ZPopMax(zpopmax) => zpopmax.execute(backend)?,
+        Command::BZPopMin(bzpopmin) => return Ok(bzpopmin.execute(backend, client_id)?.into()),
+        Command::BZPopMax(bzpopmax) => return Ok(bzpopmax.execute(backend, client_id)?.into()),
+        Command::ZMPop(zmpop) => zmpop.execute(backend)?,
+        Command::BZMPop(bzmpop) => return Ok(bzmpop.execute(backend, client_id)?.into()),
+        Command::GeoAdd(geoadd) => geoadd.execute(backend)?,
+        Command::GeoPos(geopos) => geopos.execute(backend)?,
+        Command::GeoDist(geodist) => geodist.execute(backend)?,
+        Command::GeoHash(geohash) => geohash.execute(backend)?,
+        Command::GeoSearch(geosearch) => geosearch.execute(backend)?,
+        Command::GeoSearchStore(geosearchstore) => geosearchstore.execute(backend)?,
+        Command::XAdd(xadd) => xadd.execute(backend)?,
+        Command::XRange(xrange) => xrange.execute(backend)?,
+        Command::XRevRange(xrevrange) => xrevrange.execute(backend)?,
+        Command::XLen(xlen) => xlen.execute(backend)?,
+        Command::XDel(xdel) => xdel.execute(backend)?,
+        Command::XTrim(xtrim) => xtrim.execute(backend)?,
+        Command::XRead(xread) => return Ok(xread.execute(backend, client_id)?.into()),
+        Command::XGroup(xgroup) => xgroup.execute(backend)?,
+        Command::XReadGroup(xreadgroup) => {
+            return Ok(xreadgroup.execute(backend, client_id)?.into())
+        }
+        Command::XAck(xack) => xack.execute(backend)?,
+        Command::XPending(xpending) => xpending.execute(backend)?,
+        Command::XClaim(xclaim) => xclaim.execute(backend)?,
+        Command::XAutoClaim(xautoclaim) => xautoclaim.execute(backend)?,
+        Command::XInfo(xinfo) => xinfo.execute(backend)?,
+        Command::PfAdd(pfadd) => pfadd.execute(backend)?,
+        Command::PfCount(pfcount) => pfcount.execute(backend)?,
+        Command::PfMerge(pfmerge) => pfmerge.execute(backend)?,
+        Command::Keys(keys) => keys.execute(backend)?,
+        Command::Scan(scan) => scan.execute(backend)?,
+        Command::HScan(hscan) => hscan.execute(backend)?,
+        Command::SScan(sscan) => sscan.execute(backend)?,
+        Command::ZScan(zscan) => zscan.execute(backend)?,
+        Command::Select(select) => select.execute(backend, &mut session.db)?,
+        Command::SwapDb(swapdb) => swapdb.execute(backend)?,
+        Command::Move(move_cmd) => move_cmd.execute(backend)?,
+        Command::Expire(expire) => expire.execute(backend)?,
+        Command::Ttl(ttl) => ttl.execute(backend)?,
+        Command::Persist(persist) => persist.execute(backend)?,
+        Command::DbSize(dbsize) => dbsize.execute(backend)?,
+        Command::FlushDb(flushdb) => flushdb.execute(backend)?,
+        Command::FlushAll(flushall) => flushall.execute(backend)?,
+        Command::BitField(bitfield) => bitfield.execute(backend)?,
+        Command::BitFieldRo(bitfield_ro) => bitfield_ro.execute(backend)?,
+        Command::Subscribe(subscribe) => {
+            let confirmations = subscribe.execute(backend, client_id, &session.pushes)?;
+            return Ok(replies(confirmations, session.protocol));
+        }
+        Command::Unsubscribe(unsubscribe) => {
+            let confirmations = unsubscribe.execute(backend, client_id)?;
+            return Ok(replies(confirmations, session.protocol));
+        }
+        Command::Publish(publish) => publish.execute(backend)?,
+        Command::PubSub(pubsub) => pubsub.execute(backend)?,
+        Command::Ping(ping) => {
+            let subscribed = session.protocol == 2 && backend.subscriptions(client_id) > 0;
+            ping.execute(backend, subscribed)?
+        }
+        Command::Hello(hello) => hello.execute(backend, client_id, &mut session.protocol)?,
+        Command::Config(config) => config.execute(backend)?,
+        Command::Eval(eval) => {
+            // a SELECT in the script doesn't outlive it
+            let db = session.db;
+            // other connections move off this thread meanwhile, so one of
+            // them can kill a script that runs too long
+            let reply = tokio::task::block_in_place(|| {
+                eval.execute(backend.clone(), |cmd| script_call(cmd, &backend, session))
+            });
+            session.db = db;
+            reply?
+        }
+        Command::Script(script) => script.execute(backend)?,
+        Command::Multi(_) | Command::Exec(_) | Command::Discard(_) => {
+            unreachable!("transactions are handled by request_handler")
+        }
+    };
+
+    Ok(RedisResponse::Reply(response))
+}
+
+/// Something the client didn't ask for, a push in RESP3 and a plain array
+/// in RESP2.
+fn push_frame(items: Vec<RespFrame>, protocol: u8) -> RespFrame {
+    if protocol == 3 {
+        RespPush::new(items).into()
+    } else {
+        RespArray::new(items).into()
+    }
+}
+
+/// A reply the way the client's RESP version can read it: RESP2 has no
+/// doubles, so scores go out as bulk strings, as nested as they were.
+fn reply_frame(frame: RespFrame, protocol: u8) -> RespFrame {
+    if protocol == 3 {
+        return frame;
+    }
+    match frame {
+        RespFrame::Double(d) => RespBulkString::new(d.get().to_string()).into(),
+        RespFrame::Array(RespArray(Some(items))) => RespArray::new(
+            items
+                .into_iter()
+                .map(|item| reply_frame(item, protocol))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        frame => frame,
+    }
+}
+
+/// Subscription confirmations, which go out the way messages do.
+fn replies(confirmations: Vec<Vec<RespFrame>>, protocol: u8) -> RedisResponse {
+    RedisResponse::Replies(
+        confirmations
+            .into_iter()
+            .map(|items| push_frame(items, protocol))
+            .collect(),
+    )
+}
+
+/// The reply for a failed command. Errors carrying their own code, such as
+/// `WRONGTYPE`, keep it; anything else is a generic `ERR`.
+fn error_reply(e: anyhow::Error) -> RespFrame {
+    let msg = e.to_string();
+    let has_code = msg
+        .split_once(' ')
+        .is_some_and(|(code, _)| code.len() > 2 && code.bytes().all(|b| b.is_ascii_uppercase()));
+    if has_code {
+        RespSimpleError::new(msg).into()
+    } else {
+        RespSimpleError::new(format!("ERR {}", msg)).into()
+    }
 }
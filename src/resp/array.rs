@@ -6,7 +6,7 @@ use crate::resp::bulkstring::RespBulkString;
 use crate::resp::frame::{DecodeErr, Decoded, EncodeErr, RespDecode, RespEncode, RespFrame};
 use crate::resp::split_r_n;
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 pub struct RespArray(pub(crate) Option<Vec<RespFrame>>);
 
 impl Deref for RespArray {
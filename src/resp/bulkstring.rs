@@ -1,10 +1,10 @@
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
 use crate::resp::frame::DecodeErr::InvalidLength;
 use crate::resp::frame::{DecodeErr, Decoded, EncodeErr, RespDecode, RespEncode};
 use crate::resp::split_r_n;
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 pub struct RespBulkString(Option<Vec<u8>>);
 
 impl Deref for RespBulkString {
@@ -15,6 +15,12 @@ impl Deref for RespBulkString {
     }
 }
 
+impl DerefMut for RespBulkString {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 impl RespBulkString {
     pub fn new(s: impl AsRef<[u8]>) -> Self {
         Self(Some(s.as_ref().to_vec()))
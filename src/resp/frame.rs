@@ -9,6 +9,7 @@ use crate::resp::double::RespDouble;
 use crate::resp::frame::DecodeErr::InvalidFrameType;
 use crate::resp::map::RespMap;
 use crate::resp::null::RespNull;
+use crate::resp::push::RespPush;
 use crate::resp::set::RespSet;
 use crate::resp::simple_error::RespSimpleError;
 use crate::resp::simple_string::RespSimpleString;
@@ -29,7 +30,7 @@ where
 }
 
 #[enum_dispatch(RespEncode)]
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 pub enum RespFrame {
     SimpleString(RespSimpleString),
     Error(RespSimpleError),
@@ -41,6 +42,7 @@ pub enum RespFrame {
     Double(RespDouble),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 // ------------------------------------------------
 // RespEncode has been implemented by enum_dispatch
@@ -98,6 +100,12 @@ impl RespDecode for RespFrame {
                 let frame = decoded.0.map(|x| x.into());
                 Ok(Decoded(frame, decoded.1))
             }
+            Some(b'>') => {
+                // push
+                let decoded = RespPush::decode(buf)?;
+                let frame = decoded.0.map(|x| x.into());
+                Ok(Decoded(frame, decoded.1))
+            }
             Some(b'-') => {
                 // simple error
                 let decoded = RespSimpleError::decode(buf)?;
@@ -346,6 +354,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_push_encode_decode() -> anyhow::Result<()> {
+        let push = RespPush::new(vec![
+            RespBulkString::new("message").into(),
+            RespBulkString::new("news").into(),
+            RespBulkString::new("hi").into(),
+        ]);
+        let encoded = b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n";
+        assert_eq!(RespFrame::from(push.clone()).encode()?, encoded);
+        let decoded = RespFrame::decode(&encoded)?;
+        assert_eq!(decoded.1, encoded.len());
+        assert_eq!(decoded.0, Some(push.into()));
+        Ok(())
+    }
+
     #[test]
     fn test_set_decode() -> anyhow::Result<()> {
         let mut set = RespSet::new();
@@ -4,7 +4,7 @@ use std::ops::{Deref, DerefMut};
 use crate::resp::frame::{DecodeErr, Decoded, EncodeErr, RespDecode, RespEncode, RespFrame};
 use crate::resp::split_r_n;
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone)]
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 pub struct RespMap(BTreeMap<RespFrame, RespFrame>);
 
 impl Deref for RespMap {
@@ -9,6 +9,7 @@ pub mod frame;
 pub mod integer;
 pub mod map;
 pub mod null;
+pub mod push;
 pub mod set;
 pub mod simple_error;
 pub mod simple_string;
@@ -0,0 +1,55 @@
+use std::ops::Deref;
+
+use crate::resp::frame::{DecodeErr, Decoded, EncodeErr, RespDecode, RespEncode, RespFrame};
+use crate::resp::split_r_n;
+
+/// Out-of-band data such as pub/sub messages, RESP3 only.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
+pub struct RespPush(Vec<RespFrame>);
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// ><number-of-elements>\r\n<element-1>...<element-n>
+impl RespEncode for RespPush {
+    fn encode(self) -> Result<Vec<u8>, EncodeErr> {
+        let n_elems = self.len();
+        let mut ret = Vec::with_capacity(4096);
+        ret.extend_from_slice(&format!(">{}\r\n", n_elems).into_bytes());
+        for elem in self.0 {
+            let encoded = elem.encode()?;
+            ret.extend_from_slice(&encoded);
+        }
+        Ok(ret)
+    }
+}
+
+impl RespDecode for RespPush {
+    fn decode(buf: &impl AsRef<[u8]>) -> anyhow::Result<Decoded<Self>, DecodeErr> {
+        let (pre, rest) = split_r_n(buf)?;
+        let n_elem = pre[1..].parse::<usize>()?; // num of elements in push
+        let mut ret = Vec::with_capacity(n_elem);
+        let mut total_length = pre.len() + 2;
+
+        let mut remainder = rest;
+        for _ in 0..n_elem {
+            let decoded = RespFrame::decode(&remainder)?;
+            ret.push(decoded.0.unwrap());
+            total_length += decoded.1;
+            remainder = remainder.split_off(decoded.1);
+        }
+
+        Ok(Decoded(Some(RespPush::new(ret)), total_length))
+    }
+}
+
+impl RespPush {
+    pub fn new(items: impl Into<Vec<RespFrame>>) -> Self {
+        Self(items.into())
+    }
+}